@@ -0,0 +1,307 @@
+// Throughput and latency of the spot engine.
+// - `place`: a bid placed below every other bid of a deep book, then cancelled.
+// - `sweep`: a market buy sweeping a number of ask levels.
+// - `cancel_heavy`: steady flow on a full book where every new order replaces the oldest one.
+// - `iceberg`: a limit buy partially filling a level of iceberg asks, then replenishing the public
+//   quantity of the maker it stopped at.
+// After the criterion groups, a mixed flow is timed command by command and the p50/p99 latency of
+// each command type is printed.
+
+use std::collections::VecDeque;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput};
+use off_grid_primitives::spot::command::{LimitOrder, MarketOrder};
+use off_grid_primitives::spot::event;
+use off_grid_primitives::spot::fixed_point::{Price, Quantity, Rounding};
+use off_grid_primitives::spot::market_order::MarketOrderSize;
+use off_grid_primitives::spot::orders::OrderId;
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::Pair;
+
+const SCALE_8: u64 = 1_0000_0000;
+const MID: u64 = 10_000 * SCALE_8;
+const TICK: u64 = SCALE_8 / 100;
+const GTC: TimeInForce = TimeInForce::GoodTillCanceled;
+
+fn new_pair() -> Pair {
+    let mut pair = Pair::new();
+    pair.pair_id = [1].into();
+    pair.base_asset_id = [2].into();
+    pair.quote_asset_id = [3].into();
+    pair
+}
+
+fn limit(pair: &mut Pair, is_bid: bool, price: u64, amnt: u64, iqty: u64, timestamp: i64, time_in_force: TimeInForce) {
+    if is_bid {
+        pair.limit_buy(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [7].into(),
+            coid: None,
+            price,
+            amnt,
+            iqty,
+            timestamp,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force,
+            post_only: false,
+        }).expect("limit buy");
+    } else {
+        pair.limit_sell(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [7].into(),
+            coid: None,
+            price,
+            amnt,
+            iqty,
+            timestamp,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force,
+            post_only: false,
+        }).expect("limit sell");
+    }
+}
+
+/// Book with `levels` bid and ask levels of `per_level` orders around `MID`, one tick apart
+fn deep_book(levels: u64, per_level: u64) -> Pair {
+    let mut pair = new_pair();
+    // from the far end to the spread, so every level is inserted at the head of its list
+    for i in (1..=levels).rev() {
+        for _ in 0..per_level {
+            limit(&mut pair, false, MID + i * TICK, SCALE_8, 0, 0, GTC);
+            limit(&mut pair, true, MID - i * TICK, 10_000 * SCALE_8, 0, 0, GTC);
+        }
+    }
+    let _ = event::drain_events();
+    pair
+}
+
+/// Id of the order placed last at a price
+fn last_order(pair: &Pair, price: u64) -> OrderId {
+    pair.orderbook.l3.tail(price).expect("placed bid")
+}
+
+fn bench_place(c: &mut Criterion) {
+    let mut group = c.benchmark_group("place");
+    for levels in [100, 1_000, 10_000] {
+        let mut pair = deep_book(levels, 1);
+        let price = MID - (levels + 1) * TICK;
+        group.bench_with_input(BenchmarkId::new("deepest_bid_and_cancel", levels), &levels, |b, _| {
+            b.iter(|| {
+                limit(&mut pair, true, black_box(price), 10_000 * SCALE_8, 0, 1, GTC);
+                let id = last_order(&pair, price);
+                pair.cancel_order([1], [1], id, [7]).expect("cancel");
+                event::drain_events()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_sweep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sweep");
+    for levels in [10, 100, 1_000] {
+        let book = deep_book(levels, 2);
+        group.throughput(Throughput::Elements(levels));
+        group.bench_with_input(BenchmarkId::new("market_buy_levels", levels), &levels, |b, &levels| {
+            b.iter_batched(
+                || book.clone(),
+                |mut pair| {
+                    let size = MarketOrderSize::Base(2 * levels * SCALE_8);
+                    pair.market_buy(MarketOrder {
+                        cid: [1].into(),
+                        pair_id: pair.pair_id,
+                        existing_order_id: None,
+                        owner: [8].into(),
+                        coid: None,
+                        size,
+                        public_amount: 0,
+                        timestamp: 1,
+                        expires_at: i64::MAX,
+                        maker_fee_bps: 5,
+                        taker_fee_bps: 10,
+                        time_in_force: TimeInForce::ImmediateOrCancel,
+                    })
+                        .expect("market buy");
+                    event::drain_events()
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_cancel_heavy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cancel_heavy");
+    let levels = 1_000;
+    let mut pair = deep_book(levels, 1);
+    let mut resting: VecDeque<OrderId> = VecDeque::new();
+    let mut step = 0u64;
+    group.throughput(Throughput::Elements(2));
+    group.bench_function("replace_oldest_of_1000_levels", |b| {
+        b.iter(|| {
+            // a new bid deeper in the book, then the oldest placed bid leaves once 1000 are resting
+            step += 1;
+            let price = MID - (levels + 1 + step % 500) * TICK;
+            limit(&mut pair, true, price, 10_000 * SCALE_8, 0, step as i64, GTC);
+            resting.push_back(last_order(&pair, price));
+            if resting.len() > 1_000 {
+                let id = resting.pop_front().expect("oldest");
+                pair.cancel_order([1], [1], id, [7]).expect("cancel");
+            }
+            event::drain_events()
+        })
+    });
+    group.finish();
+}
+
+fn bench_iceberg(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iceberg");
+    let mut book = new_pair();
+    let price = MID + TICK;
+    for _ in 0..100 {
+        // one base unit shown of ten
+        limit(&mut book, false, price, 10 * SCALE_8, 9 * SCALE_8, 0, GTC);
+    }
+    let _ = event::drain_events();
+    // buys nine and a half makers, leaving the tenth with five units of which one is shown
+    let quote = Price(price).notional(Quantity(95 * SCALE_8), Rounding::Ceil).expect("notional").0;
+    group.bench_function("partial_fill_and_replenish", |b| {
+        b.iter_batched(
+            || book.clone(),
+            |mut pair| {
+                limit(&mut pair, true, price, quote, 0, 1, TimeInForce::ImmediateOrCancel);
+                let head = pair.orderbook.l3.head(price).expect("partially filled maker");
+                pair.orderbook.set_iceberg_quantity([1], [1], false, head, 0).expect("replenish");
+                event::drain_events()
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+/// splitmix64, the flow is the same on every run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    sorted[(sorted.len() * p / 100).min(sorted.len() - 1)]
+}
+
+/// Times a mixed flow on a 1000 level book command by command and prints p50/p99 per command type
+fn latency_report(commands: usize) {
+    let mut pair = deep_book(1_000, 1);
+    let mut rng = Rng(42);
+    let mut resting: Vec<OrderId> = Vec::new();
+    let names = ["limit (resting)", "limit (crossing)", "market", "cancel"];
+    let mut samples: [Vec<Duration>; 4] = Default::default();
+
+    for step in 0..commands {
+        let kind = match rng.next() % 10 {
+            0..=3 => 0,
+            4..=5 => 1,
+            6 => 2,
+            _ => 3,
+        };
+        let offset = 1 + rng.next() % 1_000;
+        let start = Instant::now();
+        match kind {
+            0 => {
+                let price = MID - offset * TICK;
+                limit(&mut pair, true, price, 10_000 * SCALE_8, 0, step as i64, GTC);
+                let elapsed = start.elapsed();
+                resting.push(last_order(&pair, price));
+                samples[kind].push(elapsed);
+                let _ = event::drain_events();
+                continue;
+            }
+            1 => {
+                limit(&mut pair, false, MID - offset * TICK, SCALE_8, 0, step as i64, TimeInForce::ImmediateOrCancel);
+            }
+            2 => {
+                let size = MarketOrderSize::Quote(1_000 * SCALE_8);
+                let _ = pair.market_buy(MarketOrder {
+                    cid: [1].into(),
+                    pair_id: pair.pair_id,
+                    existing_order_id: None,
+                    owner: [8].into(),
+                    coid: None,
+                    size,
+                    public_amount: 0,
+                    timestamp: step as i64,
+                    expires_at: i64::MAX,
+                    maker_fee_bps: 5,
+                    taker_fee_bps: 10,
+                    time_in_force: TimeInForce::ImmediateOrCancel,
+                });
+            }
+            _ => {
+                if resting.is_empty() {
+                    continue;
+                }
+                let id = resting.swap_remove(rng.next() as usize % resting.len());
+                // filled or swept orders are gone already
+                let _ = pair.cancel_order([1], [1], id, [7]);
+            }
+        }
+        samples[kind].push(start.elapsed());
+        let _ = event::drain_events();
+        if pair.orderbook.l2.ask_head().is_none() {
+            // refill the asks swept by market orders
+            for i in (1..=1_000).rev() {
+                limit(&mut pair, false, MID + i * TICK, SCALE_8, 0, step as i64, GTC);
+            }
+            let _ = event::drain_events();
+        }
+    }
+
+    println!("\nlatency per command over {commands} commands on a 1000 level book");
+    println!("{:<18} {:>8} {:>12} {:>12} {:>12}", "command", "count", "p50", "p99", "max");
+    for (name, samples) in names.iter().zip(samples.iter_mut()) {
+        if samples.is_empty() {
+            continue;
+        }
+        samples.sort();
+        println!(
+            "{:<18} {:>8} {:>12?} {:>12?} {:>12?}",
+            name,
+            samples.len(),
+            percentile(samples, 50),
+            percentile(samples, 99),
+            samples[samples.len() - 1]
+        );
+    }
+}
+
+fn main() {
+    let mut criterion = Criterion::default().configure_from_args();
+    bench_place(&mut criterion);
+    bench_sweep(&mut criterion);
+    bench_cancel_heavy(&mut criterion);
+    bench_iceberg(&mut criterion);
+    criterion.final_summary();
+
+    // `cargo test --benches` runs every bench once, keep the report short there
+    let quick = std::env::args().any(|arg| arg == "--test");
+    latency_report(if quick { 1_000 } else { 50_000 });
+}
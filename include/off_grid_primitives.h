@@ -0,0 +1,62 @@
+/* Generated by `off_grid_primitives::spot::ffi::header`, do not edit. */
+#ifndef OFF_GRID_PRIMITIVES_H
+#define OFF_GRID_PRIMITIVES_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define OG_ABI_VERSION 1u
+
+typedef enum og_status {
+    OG_OK = 0,
+    OG_NULL_POINTER = 1,
+    OG_POSTCARD = 2,
+    OG_REJECTED = 3,
+    OG_PANIC = 4
+} og_status;
+
+/* Opaque engine handle, calls on one handle must not run concurrently */
+typedef struct og_engine og_engine;
+
+/* Bytes owned by the caller, released with og_buffer_free */
+typedef struct og_buffer {
+    uint8_t *data;
+    size_t len;
+} og_buffer;
+
+/* Returns OG_ABI_VERSION of the library */
+uint32_t og_abi_version(void);
+
+/* Creates an engine without pairs, destroyed with og_engine_free, NULL if it panicked */
+og_engine *og_engine_new(void);
+
+/* Destroys an engine, NULL is ignored */
+void og_engine_free(og_engine *engine);
+
+/* Executes a postcard SpotCommand, its events are kept until og_engine_drain_events */
+og_status og_engine_submit(og_engine *engine, const uint8_t *command, size_t len);
+
+/* Moves the events of the commands submitted since the last drain into out as a postcard EventQueue */
+og_status og_engine_drain_events(og_engine *engine, og_buffer *out);
+
+/* Writes the versioned snapshot of the engine into out, the events not drained are not part of it */
+og_status og_engine_snapshot(const og_engine *engine, og_buffer *out);
+
+/* Creates an engine from a snapshot of og_engine_snapshot of this or an older version into out */
+og_status og_engine_restore(const uint8_t *snapshot, size_t len, og_engine **out);
+
+/* Writes the UTF-8 message of the last rejected command into out, empty if no command was rejected */
+og_status og_engine_last_error(const og_engine *engine, og_buffer *out);
+
+/* Releases a buffer returned by the engine, empty buffers are ignored */
+void og_buffer_free(og_buffer buffer);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* OFF_GRID_PRIMITIVES_H */
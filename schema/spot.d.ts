@@ -0,0 +1,1174 @@
+// Generated from the Rust types by `off_grid_primitives::spot::schema`, do not edit.
+
+/**
+ * bytes of the AccountId
+ */
+export type AccountId = number[];
+
+/**
+ * bytes of the AssetId
+ */
+export type AssetId = number[];
+
+/**
+ * Best bid and ask of a pair.
+ */
+export type BestBidAsk = {
+  ask: DepthLevel | null;
+  bid: DepthLevel | null;
+};
+
+/**
+ * OHLCV bar of an interval.
+ */
+export type Candle = {
+  /**
+   * base volume in 8 decimals
+   */
+  base_volume: number;
+  /**
+   * last trade price in 8 decimals
+   */
+  close: number;
+  /**
+   * highest trade price in 8 decimals
+   */
+  high: number;
+  /**
+   * lowest trade price in 8 decimals
+   */
+  low: number;
+  /**
+   * first trade price in 8 decimals
+   */
+  open: number;
+  /**
+   * start of the interval in milliseconds
+   */
+  open_time: number;
+  /**
+   * quote volume in 8 decimals
+   */
+  quote_volume: number;
+  /**
+   * number of trades
+   */
+  trades: number;
+};
+
+/**
+ * bytes of the ClientId
+ */
+export type ClientId = number[];
+
+/**
+ * L2 depth of a pair, best prices first.
+ */
+export type Depth = {
+  asks: DepthLevel[];
+  bids: DepthLevel[];
+};
+
+/**
+ * Public quantity resting at a price.
+ */
+export type DepthLevel = {
+  /**
+   * price in 8 decimals
+   */
+  price: number;
+  /**
+   * public quantity in 8 decimals
+   */
+  qty: number;
+};
+
+export type L1 = {
+  /**
+   * Head of the ask list
+   */
+  ask_head: number | null;
+  /**
+   * Head of the bid list
+   */
+  bid_head: number | null;
+  /**
+   * Slippage limit for limit buy orders in 8 decimals
+   */
+  limit_buy_slippage_limit: number | null;
+  /**
+   * Slippage limit for limit sell orders in 8 decimals
+   */
+  limit_sell_slippage_limit: number | null;
+  /**
+   * Last match price
+   */
+  lmp: number | null;
+  /**
+   * Protection band of market buy orders in basis points above `lmp`, `None` disables the protection
+   */
+  market_buy_protection_bps: number | null;
+  /**
+   * Slippage limit for market buy orders in 8 decimals
+   */
+  market_buy_slippage_limit: number | null;
+  /**
+   * Protection band of market sell orders in basis points below `lmp`, `None` disables the protection
+   */
+  market_sell_protection_bps: number | null;
+  /**
+   * Slippage limit for market sell orders in 8 decimals
+   */
+  market_sell_slippage_limit: number | null;
+};
+
+/**
+ * Change of a single scaled level in the depth feed.
+ * - `pqty` and `cqty` are the new quantities of the level, a level with both at zero is removed.
+ * - `seq` increases by one for every delta of the pair and scale.
+ */
+export type L2Delta = {
+  /**
+   * base asset id
+   */
+  base_asset_id: AssetId;
+  /**
+   * current quantity in 8 decimals
+   */
+  cqty: number;
+  /**
+   * is bid level
+   */
+  is_bid: boolean;
+  /**
+   * pair id
+   */
+  pair_id: PairId;
+  /**
+   * public quantity in 8 decimals
+   */
+  pqty: number;
+  /**
+   * bucket price in 8 decimals
+   */
+  price: number;
+  /**
+   * quote asset id
+   */
+  quote_asset_id: AssetId;
+  /**
+   * price scale in 8 decimals
+   */
+  scale: number;
+  /**
+   * sequence number
+   */
+  seq: number;
+};
+
+/**
+ * Full L2 depth of a pair in one scale.
+ * - `seq` is the sequence number of the last delta reflected in the snapshot.
+ */
+export type L2Snapshot = {
+  /**
+   * ask levels sorted by price ascending
+   */
+  asks: Level[];
+  /**
+   * base asset id
+   */
+  base_asset_id: AssetId;
+  /**
+   * bid levels sorted by price descending
+   */
+  bids: Level[];
+  /**
+   * pair id
+   */
+  pair_id: PairId;
+  /**
+   * quote asset id
+   */
+  quote_asset_id: AssetId;
+  /**
+   * price scale in 8 decimals
+   */
+  scale: number;
+  /**
+   * sequence number
+   */
+  seq: number;
+};
+
+export type Level = {
+  /**
+   * current quantity in 8 decimals
+   */
+  cqty: number;
+  /**
+   * public quantity in 8 decimals
+   */
+  pqty: number;
+  /**
+   * price in 8 decimals
+   */
+  price: number;
+};
+
+/**
+ * Represents an order stored in the order book.
+ */
+export type Order = {
+  /**
+   * whole amount of the order in 8 decimals without iceberg protection
+   */
+  amnt: number;
+  /**
+   * gateway client id
+   */
+  cid: ClientId;
+  /**
+   * user-supplied client order id, unique per owner
+   */
+  coid: number[] | null;
+  /**
+   * current quantity of the order in 8 decimals without iceberg protection
+   */
+  cqty: number;
+  /**
+   * expires at timestamp in milliseconds
+   */
+  expires_at: number;
+  /**
+   * fee basis points of the order (maker or taker)
+   */
+  fee_bps: number;
+  /**
+   * order id
+   */
+  id: string;
+  /**
+   * iceberg quantity of the order in 8 decimals to hide the order from the public
+   */
+  iqty: number;
+  /**
+   * is bid order
+   */
+  is_bid: boolean;
+  /**
+   * owner of the order
+   */
+  owner: AccountId;
+  /**
+   * public quantity of the order in 8 decimals with iceberg protection
+   */
+  pqty: number;
+  /**
+   * price of the order in 8 decimals
+   */
+  price: number;
+  /**
+   * timestamp of the order in milliseconds
+   */
+  timestamp: number;
+};
+
+/**
+ * Read-only view of a resting order.
+ */
+export type OrderView = {
+  /**
+   * whole amount in 8 decimals
+   */
+  amnt: number;
+  /**
+   * gateway client id
+   */
+  cid: ClientId;
+  /**
+   * user-supplied client order id
+   */
+  coid: number[] | null;
+  /**
+   * current quantity in 8 decimals
+   */
+  cqty: number;
+  /**
+   * expires at timestamp in milliseconds, i64 is chosen because of js type compatibility
+   */
+  expires_at: number;
+  /**
+   * fee basis points of the order
+   */
+  fee_bps: number;
+  /**
+   * order id
+   */
+  id: string;
+  /**
+   * iceberg quantity in 8 decimals
+   */
+  iqty: number;
+  /**
+   * is bid order
+   */
+  is_bid: boolean;
+  /**
+   * owner of the order
+   */
+  owner: AccountId;
+  /**
+   * public quantity in 8 decimals
+   */
+  pqty: number;
+  /**
+   * price in 8 decimals
+   */
+  price: number;
+  /**
+   * timestamp in milliseconds, i64 is chosen because of js type compatibility
+   */
+  timestamp: number;
+};
+
+/**
+ * A page of open orders.
+ * - `next_cursor` is the cursor to pass to fetch the next page, `None` on the last page.
+ */
+export type OrdersPage = {
+  next_cursor: string | null;
+  orders: OrderView[];
+};
+
+/**
+ * bytes of the PairId
+ */
+export type PairId = number[];
+
+export type SpotEvent =
+  /**
+   * New market price event in Spot
+   */
+  | {
+    SpotNewMarketPrice: {
+      /**
+       * base asset id
+       */
+      base_asset_id: AssetId;
+      /**
+       * client id
+       * multiple client ids can be provided for the same event
+       */
+      client_ids: ClientId[];
+      /**
+       * whether the price is the make price of an order resting after matching, otherwise the last matched price
+       */
+      is_make_price: boolean;
+      /**
+       * pair id
+       */
+      pair_id: PairId;
+      /**
+       * price
+       */
+      price: number;
+      /**
+       * quote asset id
+       */
+      quote_asset_id: AssetId;
+      /**
+       * timestamp in milliseconds
+       * i64 is chosen because of js type compatibility
+       */
+      timestamp: number;
+    };
+  }
+  /**
+   * Spot pair rebate happened when maker fee bps is lower than 0
+   */
+  | {
+    SpotPairRebate: {
+      /**
+       * account id
+       */
+      account_id: AccountId;
+      /**
+       * base asset id
+       */
+      base_asset_id: AssetId;
+      /**
+       * asset volume
+       */
+      base_volume: number;
+      /**
+       * fee bps
+       */
+      fee_bps: number;
+      /**
+       * is bid
+       */
+      is_bid: boolean;
+      /**
+       * happened on maker/taker side
+       */
+      is_maker: boolean;
+      /**
+       * pair id
+       */
+      pair_id: PairId;
+      /**
+       * price
+       */
+      price: number;
+      /**
+       * quote asset id
+       */
+      quote_asset_id: AssetId;
+      /**
+       * quote volume
+       */
+      quote_volume: number;
+      /**
+       * timestamp
+       */
+      timestamp: number;
+    };
+  }
+  /**
+   * Spot pair client account changed
+   */
+  | {
+    SpotPairClientAccountChanged: {
+      /**
+       * admin account id
+       */
+      admin_account_id: AccountId | null;
+      /**
+       * client id
+       */
+      cid: ClientId | null;
+      /**
+       * fee account id
+       */
+      fee_account_id: AccountId | null;
+      /**
+       * pair id
+       */
+      pair_id: PairId;
+      /**
+       * timestamp
+       * i64 is chosen because of js type compatibility
+       */
+      timestamp: number;
+    };
+  }
+  /**
+   * Pair added to the matching engine
+   */
+  | {
+    SpotPairAdded: {
+      /**
+       * client id
+       */
+      cid: ClientId;
+      /**
+       * pair id
+       */
+      pair_id: PairId;
+      /**
+       * timestamp
+       * i64 is chosen because of js type compatibility
+       */
+      timestamp: number;
+    };
+  }
+  /**
+   * Transfer event from an account to another account
+   */
+  | {
+    Transfer: {
+      /**
+       * amount
+       */
+      amnt: number;
+      /**
+       * asset id
+       */
+      asset: AssetId;
+      /**
+       * client id
+       */
+      cid: ClientId;
+      /**
+       * from account id
+       */
+      from: AccountId;
+      /**
+       * timestamp
+       */
+      timestamp: number;
+      /**
+       * to account id
+       */
+      to: AccountId;
+    };
+  }
+  /**
+   * Spot order block changed in the orderbook
+   */
+  | {
+    SpotPriceLevelChanged: {
+      /**
+       * current quantity
+       */
+      cqty: number;
+      /**
+       * is bid
+       */
+      is_bid: boolean;
+      /**
+       * pair id
+       */
+      pair_id: PairId;
+      /**
+       * public quantity
+       */
+      pqty: number;
+      /**
+       * price
+       */
+      price: number;
+      /**
+       * timestamp
+       */
+      timestamp: number;
+    };
+  }
+  /**
+   * Spot order placed in the orderbook being a maker
+   */
+  | {
+    SpotOrderPlaced: {
+      /**
+       * whole amount
+       */
+      amnt: number;
+      /**
+       * base asset id
+       */
+      base_asset_id: AssetId;
+      /**
+       * client id
+       */
+      cid: ClientId;
+      /**
+       * client order id given by the owner
+       */
+      coid: number[] | null;
+      /**
+       * current quantity
+       */
+      cqty: number;
+      /**
+       * expires at timestamp, i64 is chosen because of js type compatibility
+       */
+      expires_at: number;
+      /**
+       * fee bps the order is placed with
+       */
+      fee_bps: number;
+      /**
+       * iceberg quantity
+       */
+      iqty: number;
+      /**
+       * maker order is bid
+       */
+      is_bid: boolean;
+      /**
+       * maker account id
+       */
+      maker_account_id: AccountId;
+      /**
+       * order id
+       */
+      order_id: number[];
+      /**
+       * pair id
+       */
+      pair_id: PairId;
+      /**
+       * public quantity
+       */
+      pqty: number;
+      /**
+       * price
+       */
+      price: number;
+      /**
+       * quote asset id
+       */
+      quote_asset_id: AssetId;
+      /**
+       * timestamp, i64 is chosen because of js type compatibility
+       */
+      timestamp: number;
+    };
+  }
+  /**
+   * Spot order partially filled in the orderbook being a taker for taker spot order history
+   */
+  | {
+    SpotOrderPartiallyFilled: {
+      /**
+       * whole amount
+       */
+      amnt: number;
+      /**
+       * base asset id
+       */
+      base_asset_id: AssetId;
+      /**
+       * base fee
+       */
+      base_fee: number;
+      /**
+       * base volume
+       */
+      base_volume: number;
+      /**
+       * current quantity
+       */
+      cqty: number;
+      /**
+       * expires at timestamp, i64 is chosen because of js type compatibility
+       */
+      expires_at: number;
+      /**
+       * iceberg quantity
+       */
+      iqty: number;
+      /**
+       * whether the event is for a taker order
+       */
+      is_taker_event: boolean;
+      /**
+       * taker account id
+       */
+      maker_account_id: AccountId;
+      /**
+       * maker client id
+       */
+      maker_cid: ClientId;
+      /**
+       * maker fee bps
+       */
+      maker_fee_bps: number;
+      /**
+       * maker order id
+       */
+      maker_order_id: number[];
+      /**
+       * maker order is bid
+       */
+      maker_order_is_bid: boolean;
+      /**
+       * pair id
+       */
+      pair_id: PairId;
+      /**
+       * public quantity
+       */
+      pqty: number;
+      /**
+       * price
+       */
+      price: number;
+      /**
+       * quote asset id
+       */
+      quote_asset_id: AssetId;
+      /**
+       * quote fee
+       */
+      quote_fee: number;
+      /**
+       * quote volume
+       */
+      quote_volume: number;
+      /**
+       * maker account id
+       */
+      taker_account_id: AccountId;
+      /**
+       * taker client id
+       */
+      taker_cid: ClientId;
+      /**
+       * taker fee bps
+       */
+      taker_fee_bps: number;
+      /**
+       * taker order id
+       */
+      taker_order_id: number[];
+      /**
+       * taker order is bid
+       */
+      taker_order_is_bid: boolean;
+      /**
+       * timestamp, i64 is chosen because of js type compatibility
+       */
+      timestamp: number;
+    };
+  }
+  /**
+   * Spot order fully filled in the orderbook being a taker for taker spot order history
+   */
+  | {
+    SpotOrderFullyFilled: {
+      /**
+       * whole amount
+       */
+      amnt: number;
+      /**
+       * base asset id
+       */
+      base_asset_id: AssetId;
+      /**
+       * base fee
+       */
+      base_fee: number;
+      /**
+       * base volume
+       */
+      base_volume: number;
+      /**
+       * current quantity
+       */
+      cqty: number;
+      /**
+       * expires at timestamp, i64 is chosen because of js type compatibility
+       */
+      expires_at: number;
+      /**
+       * iceberg quantity
+       */
+      iqty: number;
+      /**
+       * whether the event is for a taker order
+       */
+      is_taker_event: boolean;
+      /**
+       * maker account id
+       */
+      maker_account_id: AccountId;
+      /**
+       * maker client id
+       */
+      maker_cid: ClientId;
+      /**
+       * maker fee bps
+       */
+      maker_fee_bps: number;
+      /**
+       * maker order id
+       */
+      maker_order_id: number[];
+      /**
+       * maker order is bid
+       */
+      maker_order_is_bid: boolean;
+      /**
+       * pair id
+       */
+      pair_id: PairId;
+      /**
+       * public quantity
+       */
+      pqty: number;
+      /**
+       * price
+       */
+      price: number;
+      /**
+       * quote asset id
+       */
+      quote_asset_id: AssetId;
+      /**
+       * quote fee
+       */
+      quote_fee: number;
+      /**
+       * quote volume
+       */
+      quote_volume: number;
+      /**
+       * taker account id
+       */
+      taker_account_id: AccountId;
+      /**
+       * taker client id
+       */
+      taker_cid: ClientId;
+      /**
+       * taker fee bps
+       */
+      taker_fee_bps: number;
+      /**
+       * taker order id
+       */
+      taker_order_id: number[];
+      /**
+       * taker order is bid
+       */
+      taker_order_is_bid: boolean;
+      /**
+       * timestamp, i64 is chosen because of js type compatibility
+       */
+      timestamp: number;
+    };
+  }
+  /**
+   * Spot order cancelled in the orderbook regardless of being a maker or taker
+   */
+  | {
+    SpotOrderCancelled: {
+      /**
+       * whole amount
+       */
+      amnt: number;
+      /**
+       * client id
+       */
+      cid: ClientId;
+      /**
+       * current quantity
+       */
+      cqty: number;
+      /**
+       * expires at timestamp, i64 is chosen because of js type compatibility
+       */
+      expires_at: number;
+      /**
+       * iceberg quantity
+       */
+      iqty: number;
+      /**
+       * is bid
+       */
+      is_bid: boolean;
+      /**
+       * maker account id
+       */
+      maker_account_id: AccountId;
+      /**
+       * order id
+       */
+      order_id: number[];
+      /**
+       * pair id, `None` for events of schema version 1 which did not carry it
+       */
+      pair_id: PairId | null;
+      /**
+       * public quantity
+       */
+      pqty: number;
+      /**
+       * price
+       */
+      price: number;
+      /**
+       * timestamp, i64 is chosen because of js type compatibility
+       */
+      timestamp: number;
+    };
+  }
+  /**
+   * Spot order expired in the orderbook regardless of being a maker
+   */
+  | {
+    SpotOrderExpired: {
+      /**
+       * whole amount
+       */
+      amnt: number;
+      /**
+       * client id
+       */
+      cid: ClientId;
+      /**
+       * current quantity
+       */
+      cqty: number;
+      /**
+       * expires at timestamp, i64 is chosen because of js type compatibility
+       */
+      expires_at: number;
+      /**
+       * iceberg quantity
+       */
+      iqty: number;
+      /**
+       * is bid
+       */
+      is_bid: boolean;
+      /**
+       * maker account id
+       */
+      maker_account_id: AccountId;
+      /**
+       * order id
+       */
+      order_id: number[];
+      /**
+       * pair id, `None` for events of schema version 1 which did not carry it
+       */
+      pair_id: PairId | null;
+      /**
+       * public quantity
+       */
+      pqty: number;
+      /**
+       * price
+       */
+      price: number;
+      /**
+       * timestamp, i64 is chosen because of js type compatibility
+       */
+      timestamp: number;
+    };
+  }
+  | {
+    SpotOrderIcebergQuantityChanged: {
+      /**
+       * whole initial amount
+       */
+      amnt: number;
+      /**
+       * client id
+       */
+      cid: ClientId;
+      /**
+       * current quantity
+       */
+      cqty: number;
+      /**
+       * expires at timestamp, i64 is chosen because of js type compatibility
+       */
+      expires_at: number;
+      /**
+       * iceberg quantity
+       */
+      iqty: number;
+      /**
+       * order id
+       */
+      order_id: number[];
+      /**
+       * pair id, `None` for events of schema version 1 which did not carry it
+       */
+      pair_id: PairId | null;
+      /**
+       * public quantity
+       */
+      pqty: number;
+      /**
+       * timestamp, i64 is chosen because of js type compatibility
+       */
+      timestamp: number;
+    };
+  }
+  /**
+   * Spot trade, emitted once per match between a taker and a maker order
+   */
+  | {
+    SpotTrade: {
+      /**
+       * base asset id
+       */
+      base_asset_id: AssetId;
+      /**
+       * base volume
+       */
+      base_volume: number;
+      /**
+       * maker order id
+       */
+      maker_order_id: number[];
+      /**
+       * pair id
+       */
+      pair_id: PairId;
+      /**
+       * execution price, the maker's price
+       */
+      price: number;
+      /**
+       * quote asset id
+       */
+      quote_asset_id: AssetId;
+      /**
+       * quote volume
+       */
+      quote_volume: number;
+      /**
+       * aggressor side, true when the taker is buying
+       */
+      taker_is_bid: boolean;
+      /**
+       * taker order id
+       */
+      taker_order_id: number[];
+      /**
+       * timestamp, i64 is chosen because of js type compatibility
+       */
+      timestamp: number;
+      /**
+       * trade id, increasing by one per match on the pair
+       */
+      trade_id: number;
+    };
+  }
+  /**
+   * Spot market order stopped at its protection price, the rest of the order is cancelled
+   */
+  | {
+    SpotMarketOrderProtected: {
+      /**
+       * base asset id
+       */
+      base_asset_id: AssetId;
+      /**
+       * client id
+       */
+      cid: ClientId;
+      /**
+       * is bid
+       */
+      is_bid: boolean;
+      /**
+       * whether `unfilled` is in quote terms, otherwise in base terms
+       */
+      is_quote_size: boolean;
+      /**
+       * maker account id
+       */
+      maker_account_id: AccountId;
+      /**
+       * order id
+       */
+      order_id: number[];
+      /**
+       * pair id
+       */
+      pair_id: PairId;
+      /**
+       * price the order did not match beyond
+       */
+      protection_price: number;
+      /**
+       * quote asset id
+       */
+      quote_asset_id: AssetId;
+      /**
+       * timestamp, i64 is chosen because of js type compatibility
+       */
+      timestamp: number;
+      /**
+       * part of the order size left unfilled
+       */
+      unfilled: number;
+    };
+  };
+
+/**
+ * Ticker statistics of a pair over the rolling window.
+ * - `open`, `high` and `low` are `None` when there was no trade in the window.
+ * - `change_bps` is the change from `open` to `last` in basis points.
+ */
+export type Ticker = {
+  /**
+   * base volume of the window in 8 decimals
+   */
+  base_volume: number;
+  /**
+   * price change from open to last in basis points
+   */
+  change_bps: number;
+  /**
+   * highest trade price of the window in 8 decimals
+   */
+  high: number | null;
+  /**
+   * last trade price in 8 decimals
+   */
+  last: number | null;
+  /**
+   * lowest trade price of the window in 8 decimals
+   */
+  low: number | null;
+  /**
+   * first trade price of the window in 8 decimals
+   */
+  open: number | null;
+  /**
+   * quote volume of the window in 8 decimals
+   */
+  quote_volume: number;
+  /**
+   * number of trades in the window
+   */
+  trades: number;
+};
+
+/**
+ * Canonical record of a single match between a taker and a maker order.
+ */
+export type Trade = {
+  /**
+   * base asset id
+   */
+  base_asset_id: AssetId;
+  /**
+   * base volume in 8 decimals
+   */
+  base_volume: number;
+  /**
+   * maker order id
+   */
+  maker_order_id: number[];
+  /**
+   * pair id
+   */
+  pair_id: PairId;
+  /**
+   * execution price in 8 decimals, the maker's price
+   */
+  price: number;
+  /**
+   * quote asset id
+   */
+  quote_asset_id: AssetId;
+  /**
+   * quote volume in 8 decimals
+   */
+  quote_volume: number;
+  /**
+   * aggressor side, true when the taker is buying
+   */
+  taker_is_bid: boolean;
+  /**
+   * taker order id
+   */
+  taker_order_id: number[];
+  /**
+   * timestamp in milliseconds, i64 is chosen because of js type compatibility
+   */
+  timestamp: number;
+  /**
+   * trade id, increasing by one per match on the pair
+   */
+  trade_id: number;
+};
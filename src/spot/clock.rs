@@ -0,0 +1,16 @@
+//! Wall clock of the events emitted without a command timestamp.
+//! Browsers (`wasm32-unknown-unknown`) have no `SystemTime::now`, the time comes from JS `Date.now()` there.
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use web_time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Returns the current time in milliseconds since the unix epoch
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+use super::ids::{AccountId, ClientId, PairId};
+use super::market_order::MarketOrderSize;
+use super::orders::{ClientOrderId, OrderId, OrderRef};
+use super::time_in_force::TimeInForce;
+
+/// Limit order entry, the argument of `MatchingEngine::limit_buy`/`limit_sell`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LimitOrder {
+    /// gateway client id
+    pub cid: ClientId,
+    /// pair id
+    pub pair_id: PairId,
+    /// order id to update with the command if it exists
+    pub existing_order_id: Option<OrderId>,
+    /// owner of the order
+    pub owner: AccountId,
+    /// client order id of the order, unique per owner
+    pub coid: Option<ClientOrderId>,
+    /// price in 8 decimals
+    pub price: u64,
+    /// whole amount in 8 decimals, quote for buys and base for sells
+    pub amnt: u64,
+    /// iceberg quantity in 8 decimals
+    pub iqty: u64,
+    /// timestamp in milliseconds
+    pub timestamp: i64,
+    /// expires at timestamp in milliseconds
+    pub expires_at: i64,
+    pub maker_fee_bps: i16,
+    pub taker_fee_bps: i16,
+    pub time_in_force: TimeInForce,
+    /// the order must not take liquidity, it is rejected with `PostOnlyWouldTake` if it would match on arrival
+    #[serde(default)]
+    pub post_only: bool,
+}
+
+/// Market order entry, the argument of `MatchingEngine::market_buy`/`market_sell`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketOrder {
+    /// gateway client id
+    pub cid: ClientId,
+    /// pair id
+    pub pair_id: PairId,
+    /// order id to update with the command if it exists
+    pub existing_order_id: Option<OrderId>,
+    /// owner of the order
+    pub owner: AccountId,
+    /// client order id of the order, unique per owner
+    pub coid: Option<ClientOrderId>,
+    /// exact base or quote size of the order
+    pub size: MarketOrderSize,
+    /// public amount in 8 decimals
+    pub public_amount: u64,
+    /// timestamp in milliseconds
+    pub timestamp: i64,
+    /// expires at timestamp in milliseconds
+    pub expires_at: i64,
+    pub maker_fee_bps: i16,
+    pub taker_fee_bps: i16,
+    pub time_in_force: TimeInForce,
+}
+
+/// Command mutating a single pair of a `MatchingEngine`, applied by `MatchingEngine::execute`.
+/// - in untrusted input, see `intern::untrusted`, only `AddPair` and `AddPairClient` introduce pair and client ids,
+///   the other commands fail to deserialize with ids not registered yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpotCommand {
+    /// Adds a pair, or a client of an existing pair
+    AddPair {
+        #[serde(deserialize_with = "ClientId::deserialize_registered")]
+        cid: ClientId,
+        admin_account_id: AccountId,
+        fee_account_id: AccountId,
+        #[serde(deserialize_with = "PairId::deserialize_registered")]
+        pair_id: PairId,
+        timestamp: i64,
+    },
+    /// Adds a client and its accounts to a pair
+    AddPairClient {
+        #[serde(deserialize_with = "ClientId::deserialize_registered")]
+        cid: ClientId,
+        pair_id: PairId,
+        admin_account_id: AccountId,
+        fee_account_id: AccountId,
+        timestamp: i64,
+    },
+    LimitBuy(LimitOrder),
+    LimitSell(LimitOrder),
+    MarketBuy(MarketOrder),
+    MarketSell(MarketOrder),
+    /// Cancels an order by id or by the owner's client order id
+    CancelOrder {
+        cid: ClientId,
+        pair_id: PairId,
+        order: OrderRef,
+        owner: AccountId,
+    },
+    /// Sets the dust limit of a pair
+    SetDust { pair_id: PairId, dust: u64 },
+}
+
+impl SpotCommand {
+    /// Returns the pair the command applies to
+    pub fn pair_id(&self) -> PairId {
+        match self {
+            SpotCommand::AddPair { pair_id, .. }
+            | SpotCommand::AddPairClient { pair_id, .. }
+            | SpotCommand::CancelOrder { pair_id, .. }
+            | SpotCommand::SetDust { pair_id, .. } => *pair_id,
+            SpotCommand::LimitBuy(order) | SpotCommand::LimitSell(order) => order.pair_id,
+            SpotCommand::MarketBuy(order) | SpotCommand::MarketSell(order) => order.pair_id,
+        }
+    }
+}
@@ -1,50 +1,57 @@
 // core_events/src/lib.rs
 use once_cell::sync::OnceCell;
-use std::sync::{mpsc, Mutex};
-use std::thread;
+use std::cell::RefCell;
+use std::io;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering::SeqCst};
+use std::sync::mpsc;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::thread::{self, JoinHandle};
 use std::fmt;
 use serde::{Serialize, Deserialize};
 
+use super::event_bus::{self, Backpressure, EventBus, EventBusError, EventProducer};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use super::event_bus::{EventConsumer, EventWaker};
+use super::ids::{AccountId, AssetId, ClientId, PairId};
+use super::orders::ClientOrderId;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum SpotEvent {
     /// New market price event in Spot
     SpotNewMarketPrice {
         /// client id
         /// multiple client ids can be provided for the same event
-        client_ids: Vec<Vec<u8>>,
+        client_ids: Vec<ClientId>,
         /// pair id
-        #[serde(with = "serde_bytes")]
-        pair_id: Vec<u8>,
+        pair_id: PairId,
         /// base asset id
-        #[serde(with = "serde_bytes")]
-        base_asset_id: Vec<u8>,
+        base_asset_id: AssetId,
         /// quote asset id
-        #[serde(with = "serde_bytes")]
-        quote_asset_id: Vec<u8>,
+        quote_asset_id: AssetId,
         /// price
         price: u64,
         /// timestamp in milliseconds
         /// i64 is chosen because of js type compatibility
         timestamp: i64,
+        /// whether the price is the make price of an order resting after matching, otherwise the last matched price
+        is_make_price: bool,
     },
     /// Spot pair rebate happened when maker fee bps is lower than 0
     SpotPairRebate {
         /// pair id
-        #[serde(with = "serde_bytes")]
-        pair_id: Vec<u8>,
+        pair_id: PairId,
         /// base asset id
-        #[serde(with = "serde_bytes")]
-        base_asset_id: Vec<u8>,
+        base_asset_id: AssetId,
         /// quote asset id
-        #[serde(with = "serde_bytes")]
-        quote_asset_id: Vec<u8>,
+        quote_asset_id: AssetId,
         /// price 
         price: u64,
         /// is bid
         is_bid: bool,
         /// account id
-        #[serde(with = "serde_bytes")]
-        account_id: Vec<u8>,
+        account_id: AccountId,
         /// happened on maker/taker side
         is_maker: bool,
         /// fee bps
@@ -59,17 +66,13 @@ pub enum SpotEvent {
     /// Spot pair client account changed
     SpotPairClientAccountChanged {
         /// pair id
-        #[serde(with = "serde_bytes")]
-        pair_id: Vec<u8>,
+        pair_id: PairId,
         /// client id
-        #[serde(with = "serde_bytes")]
-        cid: Option<Vec<u8>>,
+        cid: Option<ClientId>,
         /// admin account id
-        #[serde(with = "serde_bytes")]
-        admin_account_id: Option<Vec<u8>>,
+        admin_account_id: Option<AccountId>,
         /// fee account id
-        #[serde(with = "serde_bytes")]
-        fee_account_id: Option<Vec<u8>>,
+        fee_account_id: Option<AccountId>,
         /// timestamp
         /// i64 is chosen because of js type compatibility
         timestamp: i64,
@@ -77,11 +80,9 @@ pub enum SpotEvent {
     /// Pair added to the matching engine
     SpotPairAdded {
         /// client id
-        #[serde(with = "serde_bytes")]
-        cid: Vec<u8>,
+        cid: ClientId,
         /// pair id
-        #[serde(with = "serde_bytes")]
-        pair_id: Vec<u8>,
+        pair_id: PairId,
         /// timestamp
         /// i64 is chosen because of js type compatibility
         timestamp: i64,
@@ -89,17 +90,13 @@ pub enum SpotEvent {
     /// Transfer event from an account to another account
     Transfer {
         /// client id 
-        #[serde(with = "serde_bytes")]
-        cid: Vec<u8>,
+        cid: ClientId,
         /// from account id
-        #[serde(with = "serde_bytes")]
-        from: Vec<u8>,
+        from: AccountId,
         /// to account id
-        #[serde(with = "serde_bytes")]
-        to: Vec<u8>,
+        to: AccountId,
         /// asset id
-        #[serde(with = "serde_bytes")]
-        asset: Vec<u8>,
+        asset: AssetId,
         /// amount
         amnt: u64,
         /// timestamp
@@ -108,8 +105,7 @@ pub enum SpotEvent {
     /// Spot order block changed in the orderbook
     SpotPriceLevelChanged {
         /// pair id
-        #[serde(with = "serde_bytes")]
-        pair_id: Vec<u8>,
+        pair_id: PairId,
         /// is bid
         is_bid: bool,
         /// price
@@ -124,23 +120,19 @@ pub enum SpotEvent {
     /// Spot order placed in the orderbook being a maker
     SpotOrderPlaced { 
         /// client id
-        #[serde(with = "serde_bytes")]
-        cid: Vec<u8>,
+        cid: ClientId,
         /// pair id
-        #[serde(with = "serde_bytes")]
-        pair_id: Vec<u8>,
+        pair_id: PairId,
         /// base asset id
-        #[serde(with = "serde_bytes")]
-        base_asset_id: Vec<u8>,
+        base_asset_id: AssetId,
         /// quote asset id
-        #[serde(with = "serde_bytes")]
-        quote_asset_id: Vec<u8>,
+        quote_asset_id: AssetId,
         /// order id
         #[serde(with = "serde_bytes")]
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
         order_id: Vec<u8>, 
         /// maker account id
-        #[serde(with = "serde_bytes")]
-        maker_account_id: Vec<u8>, 
+        maker_account_id: AccountId,
         /// maker order is bid
         is_bid: bool, 
         /// price
@@ -156,30 +148,33 @@ pub enum SpotEvent {
         /// timestamp, i64 is chosen because of js type compatibility
         timestamp: i64, 
         /// expires at timestamp, i64 is chosen because of js type compatibility
-        expires_at: i64 
+        expires_at: i64,
+        /// client order id given by the owner
+        #[cfg_attr(feature = "schema", schemars(with = "Option<Vec<u8>>"))]
+        coid: Option<ClientOrderId>,
+        /// fee bps the order is placed with
+        fee_bps: i16
     },
     /// Spot order partially filled in the orderbook being a taker for taker spot order history
     SpotOrderPartiallyFilled { 
         /// whether the event is for a taker order
         is_taker_event: bool,
         /// taker client id
-        #[serde(with = "serde_bytes")]
-        taker_cid: Vec<u8>,
+        taker_cid: ClientId,
         /// maker client id
-        #[serde(with = "serde_bytes")]
-        maker_cid: Vec<u8>,
+        maker_cid: ClientId,
         /// taker order id
         #[serde(with = "serde_bytes")]
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
         taker_order_id: Vec<u8>,
         /// maker order id
         #[serde(with = "serde_bytes")]
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
         maker_order_id: Vec<u8>,
         /// maker account id
-        #[serde(with = "serde_bytes")]
-        taker_account_id: Vec<u8>, 
+        taker_account_id: AccountId,
         /// taker account id
-        #[serde(with = "serde_bytes")]
-        maker_account_id: Vec<u8>, 
+        maker_account_id: AccountId,
         /// taker order is bid
         taker_order_is_bid: bool, 
         /// maker order is bid
@@ -187,14 +182,11 @@ pub enum SpotEvent {
         /// price
         price: u64, 
         /// pair id
-        #[serde(with = "serde_bytes")]
-        pair_id: Vec<u8>,
+        pair_id: PairId,
         /// base asset id
-        #[serde(with = "serde_bytes")]
-        base_asset_id: Vec<u8>,
+        base_asset_id: AssetId,
         /// quote asset id
-        #[serde(with = "serde_bytes")]
-        quote_asset_id: Vec<u8>,
+        quote_asset_id: AssetId,
         /// base volume
         base_volume: u64,
         /// quote volume
@@ -225,23 +217,21 @@ pub enum SpotEvent {
         /// whether the event is for a taker order
         is_taker_event: bool,
         /// taker client id
-        #[serde(with = "serde_bytes")]
-        taker_cid: Vec<u8>,
+        taker_cid: ClientId,
         /// maker client id
-        #[serde(with = "serde_bytes")]
-        maker_cid: Vec<u8>,
+        maker_cid: ClientId,
         /// taker order id
         #[serde(with = "serde_bytes")]
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
         taker_order_id: Vec<u8>,
         /// maker order id
         #[serde(with = "serde_bytes")]
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
         maker_order_id: Vec<u8>,
         /// maker account id
-        #[serde(with = "serde_bytes")]
-        maker_account_id: Vec<u8>, 
+        maker_account_id: AccountId,
         /// taker account id
-        #[serde(with = "serde_bytes")]
-        taker_account_id: Vec<u8>, 
+        taker_account_id: AccountId,
         /// taker order is bid
         taker_order_is_bid: bool, 
         /// maker order is bid
@@ -249,14 +239,11 @@ pub enum SpotEvent {
         /// price
         price: u64, 
         /// pair id
-        #[serde(with = "serde_bytes")]
-        pair_id: Vec<u8>,
+        pair_id: PairId,
         /// base asset id
-        #[serde(with = "serde_bytes")]
-        base_asset_id: Vec<u8>,
+        base_asset_id: AssetId,
         /// quote asset id
-        #[serde(with = "serde_bytes")]
-        quote_asset_id: Vec<u8>,
+        quote_asset_id: AssetId,
         /// base volume 
         base_volume: u64,
         /// quote volume
@@ -285,14 +272,13 @@ pub enum SpotEvent {
     /// Spot order cancelled in the orderbook regardless of being a maker or taker
     SpotOrderCancelled { 
         /// client id
-        #[serde(with = "serde_bytes")]
-        cid: Vec<u8>,
+        cid: ClientId,
         /// order id
         #[serde(with = "serde_bytes")]
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
         order_id: Vec<u8>, 
         /// maker account id
-        #[serde(with = "serde_bytes")]
-        maker_account_id: Vec<u8>, 
+        maker_account_id: AccountId,
         /// is bid
         is_bid: bool, 
         /// price
@@ -308,19 +294,20 @@ pub enum SpotEvent {
         /// timestamp, i64 is chosen because of js type compatibility
         timestamp: i64, 
         /// expires at timestamp, i64 is chosen because of js type compatibility
-        expires_at: i64 
+        expires_at: i64,
+        /// pair id, `None` for events of schema version 1 which did not carry it
+        pair_id: Option<PairId>
     },
     /// Spot order expired in the orderbook regardless of being a maker
     SpotOrderExpired { 
         /// client id
-        #[serde(with = "serde_bytes")]
-        cid: Vec<u8>,
+        cid: ClientId,
         /// order id
         #[serde(with = "serde_bytes")]
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
         order_id: Vec<u8>, 
         /// maker account id
-        #[serde(with = "serde_bytes")]
-        maker_account_id: Vec<u8>, 
+        maker_account_id: AccountId,
         /// is bid
         is_bid: bool, 
         /// price
@@ -336,14 +323,16 @@ pub enum SpotEvent {
         /// timestamp, i64 is chosen because of js type compatibility
         timestamp: i64, 
         /// expires at timestamp, i64 is chosen because of js type compatibility
-        expires_at: i64 
+        expires_at: i64,
+        /// pair id, `None` for events of schema version 1 which did not carry it
+        pair_id: Option<PairId>
     },
     SpotOrderIcebergQuantityChanged { 
         /// client id
-        #[serde(with = "serde_bytes")]
-        cid: Vec<u8>,
+        cid: ClientId,
         /// order id
         #[serde(with = "serde_bytes")]
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
         order_id: Vec<u8>, 
         /// whole initial amount
         amnt: u64,
@@ -356,7 +345,65 @@ pub enum SpotEvent {
         /// timestamp, i64 is chosen because of js type compatibility
         timestamp: i64, 
         /// expires at timestamp, i64 is chosen because of js type compatibility
-        expires_at: i64 
+        expires_at: i64,
+        /// pair id, `None` for events of schema version 1 which did not carry it
+        pair_id: Option<PairId>
+    },
+    /// Spot trade, emitted once per match between a taker and a maker order
+    SpotTrade {
+        /// trade id, increasing by one per match on the pair
+        trade_id: u64,
+        /// pair id
+        pair_id: PairId,
+        /// base asset id
+        base_asset_id: AssetId,
+        /// quote asset id
+        quote_asset_id: AssetId,
+        /// taker order id
+        #[serde(with = "serde_bytes")]
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
+        taker_order_id: Vec<u8>,
+        /// maker order id
+        #[serde(with = "serde_bytes")]
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
+        maker_order_id: Vec<u8>,
+        /// aggressor side, true when the taker is buying
+        taker_is_bid: bool,
+        /// execution price, the maker's price
+        price: u64,
+        /// base volume
+        base_volume: u64,
+        /// quote volume
+        quote_volume: u64,
+        /// timestamp, i64 is chosen because of js type compatibility
+        timestamp: i64,
+    },
+    /// Spot market order stopped at its protection price, the rest of the order is cancelled
+    SpotMarketOrderProtected {
+        /// client id
+        cid: ClientId,
+        /// pair id
+        pair_id: PairId,
+        /// base asset id
+        base_asset_id: AssetId,
+        /// quote asset id
+        quote_asset_id: AssetId,
+        /// order id
+        #[serde(with = "serde_bytes")]
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
+        order_id: Vec<u8>,
+        /// maker account id
+        maker_account_id: AccountId,
+        /// is bid
+        is_bid: bool,
+        /// price the order did not match beyond
+        protection_price: u64,
+        /// whether `unfilled` is in quote terms, otherwise in base terms
+        is_quote_size: bool,
+        /// part of the order size left unfilled
+        unfilled: u64,
+        /// timestamp, i64 is chosen because of js type compatibility
+        timestamp: i64,
     }
 }
 
@@ -464,107 +511,281 @@ impl std::ops::DerefMut for EventQueue {
 
 pub trait EventBackend: Send + 'static {
     fn handle_event(&mut self, event: SpotEvent);
+
+    /// Handles a batch read from the event bus, one event at a time unless the backend can do better
+    fn handle_batch(&mut self, events: std::vec::Drain<'_, SpotEvent>) {
+        for event in events {
+            self.handle_event(event);
+        }
+    }
+
+    /// Makes the handled events durable, returning the first error met since the last flush
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
-// Sender into the dispatcher
-static DISPATCH_TX: OnceCell<mpsc::Sender<SpotEvent>> = OnceCell::new();
+impl EventBackend for mpsc::Sender<SpotEvent> {
+    fn handle_event(&mut self, event: SpotEvent) {
+        // Ignore send errors (the receiver might have shut down)
+        let _ = self.send(event);
+    }
+}
 
-// List of per-backend senders
-static BACKEND_TXS: OnceCell<Mutex<Vec<mpsc::Sender<SpotEvent>>>> = OnceCell::new();
+/// Number of events of the process-wide event bus
+pub const DEFAULT_EVENT_BUS_CAPACITY: usize = 1 << 16;
 
-// In-memory event queue that stores events before they are published
-static EVENT_QUEUE: OnceCell<Mutex<Vec<SpotEvent>>> = OnceCell::new();
+/// Number of events a backend reads from the event bus at once
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+const BACKEND_BATCH: usize = 256;
 
-fn backend_txs() -> &'static Mutex<Vec<mpsc::Sender<SpotEvent>>> {
-    BACKEND_TXS.get_or_init(|| Mutex::new(Vec::new()))
+// Producer of the process-wide event bus while no thread owns it (null otherwise), and the handle to subscribe with
+static EVENT_BUS: OnceCell<(AtomicPtr<EventProducer>, EventBus)> = OnceCell::new();
+
+thread_local! {
+    // Events emitted by the current thread and not drained yet
+    static EVENT_QUEUE: RefCell<Vec<SpotEvent>> = const { RefCell::new(Vec::new()) };
+    // Producer of the process-wide event bus if the current thread owns it
+    static EVENT_PRODUCER: OwnedProducer = const { OwnedProducer(RefCell::new(None)) };
 }
 
-fn event_queue() -> &'static Mutex<Vec<SpotEvent>> {
-    EVENT_QUEUE.get_or_init(|| Mutex::new(Vec::new()))
+/// The producer of the process-wide event bus owned by a thread, handed back when the thread exits
+struct OwnedProducer(RefCell<Option<Box<EventProducer>>>);
+
+impl Drop for OwnedProducer {
+    fn drop(&mut self) {
+        if let Some(producer) = self.0.get_mut().take() {
+            hand_back(producer);
+        }
+    }
 }
 
-/// Call once at process startup to create the dispatcher thread.
+/// Takes the producer of the process-wide event bus if no thread owns it
+fn take_producer() -> Option<Box<EventProducer>> {
+    let (free, _) = EVENT_BUS.get()?;
+    let producer = free.swap(ptr::null_mut(), SeqCst);
+    // SAFETY: the pointer comes from `Box::into_raw`, the swap hands it to the calling thread only
+    (!producer.is_null()).then(|| unsafe { Box::from_raw(producer) })
+}
+
+fn hand_back(producer: Box<EventProducer>) {
+    if let Some((free, _)) = EVENT_BUS.get() {
+        free.store(Box::into_raw(producer), SeqCst);
+    }
+}
+
+/// Call once at process startup to create the process-wide event bus,
+/// holding `DEFAULT_EVENT_BUS_CAPACITY` events and blocking publishers behind the slowest backend.
 pub fn init_event_bus() {
-    let (tx, rx) = mpsc::channel::<SpotEvent>();
-    DISPATCH_TX.set(tx).ok(); // ignore if already set
-
-    // Dispatcher thread: fan out every event to all registered backends.
-    thread::spawn(move || {
-        while let Ok(event) = rx.recv() {
-            // clone once per backend
-            let backends = backend_txs().lock().unwrap();
-            for backend_tx in backends.iter() {
-                // Ignore send errors (backend might have shut down)
-                let _ = backend_tx.send(event.clone());
-            }
-        }
+    init_event_bus_with(DEFAULT_EVENT_BUS_CAPACITY, Backpressure::Block);
+}
+
+/// Creates the process-wide event bus with its capacity and backpressure policy, unless it exists already.
+pub fn init_event_bus_with(capacity: usize, backpressure: Backpressure) {
+    EVENT_BUS.get_or_init(|| {
+        let (producer, bus) = event_bus::bounded(capacity, backpressure);
+        (AtomicPtr::new(Box::into_raw(Box::new(producer))), bus)
     });
 }
 
+/// Returns the process-wide event bus if it was initialized
+pub fn event_bus() -> Option<&'static EventBus> {
+    EVENT_BUS.get().map(|(_, bus)| bus)
+}
+
 /// Called from anywhere (engine, core logic) to emit an event.
-/// This stores the event in the event queue. Use `publish_events()` to actually send them.
+/// This stores the event in the queue of the calling thread. Use `publish_events()` to actually send them.
 pub fn emit_event(event: SpotEvent) {
-    let mut queue = event_queue().lock().unwrap();
-    queue.push(event);
-}
-
-/// Publishes all events from the global queue to the event bus (if initialized).
-/// After publishing, the queue is drained and cleared.
-pub fn publish_events() {
-    // Drain all events from the queue
-    let events: Vec<SpotEvent> = {
-        let mut queue = event_queue().lock().unwrap();
-        let drained = queue.clone();
-        queue.clear();
-        drained
-    };
-
-    // Send each event to the dispatcher if it's initialized
-    if let Some(tx) = DISPATCH_TX.get() {
-        for event in events {
-            // ignore error if dispatcher is down
-            let _ = tx.send(event);
+    EVENT_QUEUE.with(|queue| queue.borrow_mut().push(event));
+}
+
+/// Runs `f` with the events of the current thread in a queue of its own.
+/// `emit_event`, `drain_events` and `clear_events` called by `f` use that queue, and the events emitted
+/// before are left untouched. Events left in the queue are dropped when `f` returns.
+pub fn isolated<R>(f: impl FnOnce() -> R) -> R {
+    // restores the outer queue of nested calls, even if `f` unwinds
+    struct Restore(Vec<SpotEvent>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            EVENT_QUEUE.with(|queue| *queue.borrow_mut() = std::mem::take(&mut self.0));
         }
     }
+    let _restore = Restore(EVENT_QUEUE.with(|queue| std::mem::take(&mut *queue.borrow_mut())));
+    f()
+}
+
+/// Publishes the events of the calling thread to the event bus (if initialized).
+/// The queue is drained, events are moved to the bus without copying the queue.
+pub fn publish_events() -> Result<(), EventBusError> {
+    publish_event_queue(drain_events())
 }
 
-/// Publishes an EventQueue to the event bus (if initialized).
+/// Publishes an EventQueue to the event bus (if initialized) as one batch.
 /// This is useful when you have an EventQueue returned from an operation.
-pub fn publish_event_queue(events: EventQueue) {
-    // Send each event to the dispatcher if it's initialized
-    if let Some(tx) = DISPATCH_TX.get() {
-        for event in events.into_vec() {
-            // ignore error if dispatcher is down
-            let _ = tx.send(event);
-        }
+/// The bus has a single producer: the calling thread takes it unless another thread owns it, which fails with
+/// `EventBusError::ProducerOwned`, and owns it until `release_event_producer` or until the thread exits.
+pub fn publish_event_queue(events: EventQueue) -> Result<(), EventBusError> {
+    if events.is_empty() || EVENT_BUS.get().is_none() {
+        return Ok(());
     }
+    EVENT_PRODUCER.with(|owned| {
+        let mut owned = owned.0.borrow_mut();
+        if owned.is_none() {
+            *owned = take_producer();
+        }
+        owned.as_mut().ok_or(EventBusError::ProducerOwned)?.publish_all(events.into_vec())
+    })
 }
 
-/// Register a backend; returns an `mpsc::Receiver<SpotEvent>` that you
-/// can consume from a dedicated thread.
-pub fn register_backend() -> mpsc::Receiver<SpotEvent> {
+/// Hands the producer of the process-wide event bus back if the calling thread owns it,
+/// so that another thread can publish.
+pub fn release_event_producer() {
+    EVENT_PRODUCER.with(|owned| {
+        if let Some(producer) = owned.0.borrow_mut().take() {
+            hand_back(producer);
+        }
+    });
+}
+
+/// Register a backend; returns the handle of its thread and an `mpsc::Receiver<SpotEvent>` that you
+/// can consume from a dedicated thread. The backend stops when the handle is shut down or dropped.
+/// The event bus is initialized with the defaults if it was not already.
+/// Not available on `wasm32-unknown-unknown`, which has no threads.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub fn register_backend() -> (EventBackendHandle, mpsc::Receiver<SpotEvent>) {
     let (tx, rx) = mpsc::channel::<SpotEvent>();
+    (register_event_backend(Box::new(tx)), rx)
+}
+
+/// Runs a backend on a thread of its own, fed with every event published to the process-wide event bus
+/// from now on. The event bus is initialized with the defaults if it was not already.
+/// The backend stops with `EventBackendHandle::shutdown`, or when the handle is dropped.
+/// Not available on `wasm32-unknown-unknown`, which has no threads.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub fn register_event_backend(backend: Box<dyn EventBackend>) -> EventBackendHandle {
+    init_event_bus();
+    let consumer = event_bus().expect("event bus initialized").subscribe();
+    let waker = consumer.waker();
+    let (requests, rx) = mpsc::channel();
+    let thread = thread::Builder::new()
+        .name("spot-event-backend".to_string())
+        .spawn(move || run_backend(backend, consumer, rx))
+        .expect("spawn event backend");
+    EventBackendHandle { requests, waker, thread: Some(thread) }
+}
+
+/// Request to a backend thread, answered once the events published before it are handled and flushed
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+struct BackendRequest {
+    /// sequence number of the first event published after the request
+    upto: u64,
+    stop: bool,
+    reply: mpsc::Sender<io::Result<()>>,
+}
+
+/// Handle of a backend registered with `register_event_backend`.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub struct EventBackendHandle {
+    requests: mpsc::Sender<BackendRequest>,
+    /// wakes the backend thread parked on the event bus to read a request
+    waker: EventWaker,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl EventBackendHandle {
+    /// Waits until the backend handled every event published so far and flushed them
+    pub fn flush(&self) -> io::Result<()> {
+        self.request(false)
+    }
+
+    /// Handles and flushes every event published so far, then stops the backend thread
+    pub fn shutdown(mut self) -> io::Result<()> {
+        self.stop()
+    }
 
-    {
-        let mut list = backend_txs().lock().unwrap();
-        list.push(tx);
+    fn request(&self, stop: bool) -> io::Result<()> {
+        let upto = event_bus().map_or(0, EventBus::sequence);
+        let (reply, replied) = mpsc::channel();
+        let stopped = || io::Error::other("event backend stopped");
+        self.requests.send(BackendRequest { upto, stop, reply }).map_err(|_| stopped())?;
+        self.waker.wake();
+        replied.recv().map_err(|_| stopped())?
     }
 
-    rx
+    fn stop(&mut self) -> io::Result<()> {
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+        let result = self.request(true);
+        thread.join().map_err(|_| io::Error::other("event backend panicked"))?;
+        result
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl Drop for EventBackendHandle {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// Feeds a backend from the event bus, answering the requests whose events are handled
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn run_backend(mut backend: Box<dyn EventBackend>, mut consumer: EventConsumer, requests: mpsc::Receiver<BackendRequest>) {
+    let mut events = Vec::with_capacity(BACKEND_BATCH);
+    let mut pending: Vec<BackendRequest> = Vec::new();
+    loop {
+        loop {
+            match requests.try_recv() {
+                Ok(request) => pending.push(request),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    let _ = backend.flush();
+                    return;
+                }
+            }
+        }
+        let read = consumer.try_recv_batch(&mut events, BACKEND_BATCH);
+        if read > 0 {
+            backend.handle_batch(events.drain(..));
+        }
+        // a request with events left to read waits for the next batches
+        let (ready, waiting): (Vec<_>, Vec<_>) = pending.into_iter().partition(|request| consumer.sequence() >= request.upto);
+        pending = waiting;
+        if !ready.is_empty() {
+            let result = backend.flush();
+            let stop = ready.iter().any(|request| request.stop);
+            for request in ready {
+                let _ = request.reply.send(clone_result(&result));
+            }
+            if stop {
+                return;
+            }
+        }
+        if read == 0 {
+            // parked until an event is published or the handle sends a request
+            consumer.wait();
+        }
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn clone_result(result: &io::Result<()>) -> io::Result<()> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) => Err(io::Error::new(err.kind(), err.to_string())),
+    }
 }
 
-/// Drains all events from the event queue and returns them.
+/// Drains all events emitted by the calling thread and returns them.
 /// This clears the queue after draining.
 /// Useful for retrieving events after operations complete.
 pub fn drain_events() -> EventQueue {
-    let mut queue = event_queue().lock().unwrap();
-    let drained = queue.clone();
-    queue.clear();
-    EventQueue(drained)
+    EventQueue(EVENT_QUEUE.with(|queue| std::mem::take(&mut *queue.borrow_mut())))
 }
 
-/// Clears all events from the event queue without returning them.
+/// Clears all events emitted by the calling thread without returning them.
 pub fn clear_events() {
-    let mut queue = event_queue().lock().unwrap();
-    queue.clear();
+    EVENT_QUEUE.with(|queue| queue.borrow_mut().clear());
 }
@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use super::event::{EventBackend, SpotEvent};
+use super::wire::{self, WireError};
+
+/// Backend keeping the events in memory, for tests.
+/// Clones share the events, so a clone kept before registering the backend reads what it recorded.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingBackend {
+    events: Arc<Mutex<Vec<SpotEvent>>>,
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of the recorded events
+    pub fn events(&self) -> Vec<SpotEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Returns the recorded events, leaving the recorder empty
+    pub fn take(&self) -> Vec<SpotEvent> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl EventBackend for RecordingBackend {
+    fn handle_event(&mut self, event: SpotEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+
+    fn handle_batch(&mut self, events: std::vec::Drain<'_, SpotEvent>) {
+        self.events.lock().unwrap().extend(events);
+    }
+}
+
+/// Buffered writer of a file backend keeping the first error until the next flush
+struct EventWriter<W: Write> {
+    writer: BufWriter<W>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> EventWriter<W> {
+    fn new(writer: W) -> Self {
+        Self { writer: BufWriter::new(writer), error: None }
+    }
+
+    /// Writes the bytes of an event, skipped after an error so a file does not get a hole in the middle
+    fn write(&mut self, bytes: io::Result<Vec<u8>>) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(err) = bytes.and_then(|bytes| self.writer.write_all(&bytes)) {
+            self.error = Some(err);
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => self.writer.flush(),
+        }
+    }
+}
+
+/// Backend writing every event as one line of JSON, with the `json` feature.
+#[cfg(feature = "json")]
+pub struct JsonLinesBackend<W: Write + Send + 'static = File> {
+    writer: EventWriter<W>,
+}
+
+#[cfg(feature = "json")]
+impl JsonLinesBackend<File> {
+    /// Creates the file, truncating it if it exists
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(File::create(path)?))
+    }
+}
+
+#[cfg(feature = "json")]
+impl<W: Write + Send + 'static> JsonLinesBackend<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer: EventWriter::new(writer) }
+    }
+}
+
+#[cfg(feature = "json")]
+impl<W: Write + Send + 'static> EventBackend for JsonLinesBackend<W> {
+    fn handle_event(&mut self, event: SpotEvent) {
+        let line = serde_json::to_vec(&event).map(|mut line| {
+            line.push(b'\n');
+            line
+        });
+        self.writer.write(line.map_err(io::Error::from));
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads the events written by a `JsonLinesBackend`, skipping blank lines
+#[cfg(feature = "json")]
+pub fn read_json_lines(reader: impl io::BufRead) -> io::Result<Vec<SpotEvent>> {
+    let mut events = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            events.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(events)
+}
+
+/// Backend writing every event in the versioned wire format of `spot::wire`,
+/// COBS framed so every frame ends with the only zero byte in it.
+pub struct PostcardBackend<W: Write + Send + 'static = File> {
+    writer: EventWriter<W>,
+}
+
+impl PostcardBackend<File> {
+    /// Creates the file, truncating it if it exists
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(File::create(path)?))
+    }
+}
+
+impl<W: Write + Send + 'static> PostcardBackend<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer: EventWriter::new(writer) }
+    }
+}
+
+impl<W: Write + Send + 'static> EventBackend for PostcardBackend<W> {
+    fn handle_event(&mut self, event: SpotEvent) {
+        let frame = wire::encode_cobs(&event).map_err(io::Error::other);
+        self.writer.write(frame);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads the events written by a `PostcardBackend` of this or an older version, skipping event types
+/// added by newer versions
+pub fn read_postcard(mut reader: impl Read) -> io::Result<Vec<SpotEvent>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let mut events = Vec::new();
+    for frame in bytes.split_inclusive_mut(|byte| *byte == 0) {
+        if frame.last() != Some(&0) {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated postcard frame"));
+        }
+        match wire::decode_cobs(frame) {
+            Ok(event) => events.push(event),
+            // events of newer versions of the crate are skipped
+            Err(WireError::UnknownEventType { .. }) => {}
+            Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        }
+    }
+    Ok(events)
+}
@@ -0,0 +1,482 @@
+use crossbeam_epoch::{self as epoch, Atomic, Guard, Owned, Shared};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering::SeqCst};
+use std::sync::Arc;
+use std::thread::{self, Thread};
+
+use super::event::{EventBackend, SpotEvent};
+
+/// What a producer does when the slowest consumer is a whole ring behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backpressure {
+    /// Waits until the slowest consumer reads
+    #[default]
+    Block,
+    /// Overwrites the oldest events, consumers behind skip them and count them in `EventConsumer::dropped`
+    DropOldest,
+    /// Fails with `EventBusError::Full` without publishing anything
+    Error,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum EventBusError {
+    #[error("event bus is full")]
+    Full,
+    #[error("event bus is closed")]
+    Closed,
+    #[error("event bus producer is owned by another thread")]
+    ProducerOwned,
+}
+
+/// Cursor of a consumer that no longer holds the producer back
+const DETACHED: u64 = u64::MAX;
+
+/// Slot of the ring, `stamp` is `2 * seq + 1` while the event of sequence `seq` is written and `2 * seq + 2` once it is
+struct Slot {
+    stamp: AtomicU64,
+    event: Atomic<SpotEvent>,
+}
+
+/// Thread parked until the other side of the ring wakes it
+#[derive(Default)]
+struct Waiter {
+    thread: Atomic<Thread>,
+}
+
+impl Waiter {
+    /// Spins, then parks the calling thread until `ready` returns true
+    fn wait_until(&self, mut ready: impl FnMut() -> bool) {
+        let mut backoff = Backoff::default();
+        while !ready() {
+            if !backoff.is_completed() {
+                backoff.snooze();
+                continue;
+            }
+            self.register(Some(thread::current()));
+            // checked again once registered: a wake in between finds the thread and unparks it, so park returns at once
+            if ready() {
+                break;
+            }
+            thread::park();
+        }
+        self.register(None);
+    }
+
+    /// Unparks the registered thread, if any
+    fn wake(&self) {
+        let guard = epoch::pin();
+        if self.thread.load(SeqCst, &guard).is_null() {
+            return;
+        }
+        let thread = self.thread.swap(Shared::null(), SeqCst, &guard);
+        // SAFETY: a swapped out thread is destroyed only once every guard pinned before is dropped
+        if let Some(waiting) = unsafe { thread.as_ref() } {
+            waiting.unpark();
+            unsafe { guard.defer_destroy(thread) };
+        }
+    }
+
+    fn register(&self, thread: Option<Thread>) {
+        let guard = epoch::pin();
+        let thread = thread.map_or(Shared::null(), |thread| Owned::new(thread).into_shared(&guard));
+        let replaced = self.thread.swap(thread, SeqCst, &guard);
+        if !replaced.is_null() {
+            // SAFETY: see `wake`
+            unsafe { guard.defer_destroy(replaced) };
+        }
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        // SAFETY: the waiter is gone, no thread loads from it any more
+        unsafe {
+            let thread = self.thread.load(SeqCst, epoch::unprotected());
+            if !thread.is_null() {
+                drop(thread.into_owned());
+            }
+        }
+    }
+}
+
+/// Next sequence of a consumer, shared with the producer, and the consumer waiting for events
+#[derive(Default)]
+struct Cursor {
+    next: AtomicU64,
+    waiter: Waiter,
+    /// set by an `EventWaker`, cleared once the waiting consumer returns
+    notified: AtomicBool,
+}
+
+struct Ring {
+    slots: Box<[Slot]>,
+    capacity: u64,
+    backpressure: Backpressure,
+    /// sequence of the next event, the events of `[head - capacity, head)` are readable
+    head: AtomicU64,
+    /// set once the producer is dropped
+    closed: AtomicBool,
+    /// every consumer, copied on write so the producer reads it without locking
+    cursors: Atomic<Vec<Arc<Cursor>>>,
+    /// the producer blocked behind the slowest consumer
+    producer: Waiter,
+}
+
+impl Ring {
+    fn slot(&self, seq: u64) -> &Slot {
+        &self.slots[(seq % self.capacity) as usize]
+    }
+
+    /// Returns a copy of the event of a sequence, or `None` if it was overwritten
+    fn read(&self, seq: u64, guard: &Guard) -> Option<SpotEvent> {
+        let slot = self.slot(seq);
+        let stamp = 2 * seq + 2;
+        if slot.stamp.load(SeqCst) != stamp {
+            return None;
+        }
+        let event = slot.event.load(SeqCst, guard);
+        // SAFETY: a replaced event is destroyed only once every guard pinned before the replacement is dropped
+        let event = unsafe { event.as_ref() }?.clone();
+        // the stamp did not move, so the copy is of the event of `seq` and not of one written since
+        (slot.stamp.load(SeqCst) == stamp).then_some(event)
+    }
+
+    /// Returns the next sequence of the slowest consumer, `DETACHED` without consumers
+    fn slowest(&self) -> u64 {
+        let guard = epoch::pin();
+        let cursors = self.cursors.load(SeqCst, &guard);
+        // SAFETY: the list is never null and a replaced list outlives the guard
+        unsafe { cursors.deref() }.iter().map(|cursor| cursor.next.load(SeqCst)).min().unwrap_or(DETACHED)
+    }
+
+    /// Unparks the consumers waiting for events
+    fn wake_consumers(&self) {
+        let guard = epoch::pin();
+        let cursors = self.cursors.load(SeqCst, &guard);
+        // SAFETY: see `slowest`
+        unsafe { cursors.deref() }.iter().for_each(|cursor| cursor.waiter.wake());
+    }
+
+    fn update_cursors(&self, update: impl Fn(&mut Vec<Arc<Cursor>>)) {
+        let guard = epoch::pin();
+        loop {
+            let current = self.cursors.load(SeqCst, &guard);
+            // SAFETY: see `slowest`
+            let mut cursors = unsafe { current.deref() }.clone();
+            update(&mut cursors);
+            if self.cursors.compare_exchange(current, Owned::new(cursors), SeqCst, SeqCst, &guard).is_ok() {
+                // SAFETY: the list is unreachable from the ring now, readers pinned before hold it until they unpin
+                unsafe { guard.defer_destroy(current) };
+                return;
+            }
+        }
+    }
+
+    fn subscribe(self: &Arc<Self>) -> EventConsumer {
+        let next = self.head.load(SeqCst);
+        let cursor = Arc::new(Cursor { next: AtomicU64::new(next), ..Cursor::default() });
+        self.update_cursors(|cursors| cursors.push(cursor.clone()));
+        EventConsumer { ring: self.clone(), cursor, next, dropped: 0 }
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        // SAFETY: the last handle of the ring is gone, no thread loads from it any more
+        unsafe {
+            let guard = epoch::unprotected();
+            for slot in self.slots.iter() {
+                let event = slot.event.load(SeqCst, guard);
+                if !event.is_null() {
+                    drop(event.into_owned());
+                }
+            }
+            drop(self.cursors.load(SeqCst, guard).into_owned());
+        }
+    }
+}
+
+/// Creates a bounded single producer, multi consumer event bus of `capacity` events.
+/// - the producer writes events into a ring of slots without locking, each consumer reads them at its own cursor.
+/// - a consumer sees the events published after it subscribed, in publishing order.
+/// - `backpressure` decides what happens when the slowest consumer is `capacity` events behind.
+///
+/// # Examples
+///
+/// ```
+/// use off_grid_primitives::spot::event::SpotEvent;
+/// use off_grid_primitives::spot::event_bus::{self, Backpressure};
+///
+/// let (mut producer, bus) = event_bus::bounded(1024, Backpressure::Block);
+/// let mut consumer = bus.subscribe();
+/// producer
+///     .publish(SpotEvent::SpotPairAdded { cid: "gateway".into(), pair_id: "BTC-USD".into(), timestamp: 0 })
+///     .unwrap();
+/// let mut events = Vec::new();
+/// assert_eq!(consumer.try_recv_batch(&mut events, 64), 1);
+/// ```
+pub fn bounded(capacity: usize, backpressure: Backpressure) -> (EventProducer, EventBus) {
+    assert!(capacity > 0, "an event bus needs at least one slot");
+    let slots = (0..capacity).map(|_| Slot { stamp: AtomicU64::new(0), event: Atomic::null() }).collect();
+    let ring = Arc::new(Ring {
+        slots,
+        capacity: capacity as u64,
+        backpressure,
+        head: AtomicU64::new(0),
+        closed: AtomicBool::new(false),
+        cursors: Atomic::new(Vec::new()),
+        producer: Waiter::default(),
+    });
+    (EventProducer { ring: ring.clone(), head: 0 }, EventBus { ring })
+}
+
+/// Handle of an event bus to subscribe consumers with.
+#[derive(Clone)]
+pub struct EventBus {
+    ring: Arc<Ring>,
+}
+
+impl EventBus {
+    /// Adds a consumer reading the events published from now on
+    pub fn subscribe(&self) -> EventConsumer {
+        self.ring.subscribe()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity as usize
+    }
+
+    pub fn backpressure(&self) -> Backpressure {
+        self.ring.backpressure
+    }
+
+    /// Returns true once the producer is dropped
+    pub fn is_closed(&self) -> bool {
+        self.ring.closed.load(SeqCst)
+    }
+
+    /// Sequence number of the next event to be published
+    pub fn sequence(&self) -> u64 {
+        self.ring.head.load(SeqCst)
+    }
+}
+
+/// The single producer of an event bus, dropping it closes the bus.
+pub struct EventProducer {
+    ring: Arc<Ring>,
+    head: u64,
+}
+
+impl EventProducer {
+    /// Publishes an event, returning its sequence number
+    pub fn publish(&mut self, event: SpotEvent) -> Result<u64, EventBusError> {
+        self.reserve(1)?;
+        let seq = self.write(event, &epoch::pin());
+        self.ring.wake_consumers();
+        Ok(seq)
+    }
+
+    /// Publishes events in order. With `Backpressure::Error` either all of them are published or none.
+    pub fn publish_all(&mut self, events: Vec<SpotEvent>) -> Result<(), EventBusError> {
+        if self.ring.backpressure == Backpressure::Error {
+            self.reserve(events.len() as u64)?;
+        }
+        let guard = epoch::pin();
+        for event in events {
+            if self.ring.backpressure == Backpressure::Block {
+                self.reserve(1)?;
+            }
+            self.write(event, &guard);
+        }
+        self.ring.wake_consumers();
+        Ok(())
+    }
+
+    /// Sequence number of the next event
+    pub fn sequence(&self) -> u64 {
+        self.head
+    }
+
+    /// Adds a consumer reading the events published from now on
+    pub fn subscribe(&self) -> EventConsumer {
+        self.ring.subscribe()
+    }
+
+    /// Makes room for `n` events without overwriting an event a consumer has not read yet
+    fn reserve(&self, n: u64) -> Result<(), EventBusError> {
+        let fits = || self.head + n <= self.ring.slowest().saturating_add(self.ring.capacity);
+        match self.ring.backpressure {
+            Backpressure::DropOldest => Ok(()),
+            Backpressure::Error if fits() => Ok(()),
+            Backpressure::Error => Err(EventBusError::Full),
+            Backpressure::Block => {
+                if !fits() {
+                    // the consumers read what was published so far while the producer waits for them
+                    self.ring.wake_consumers();
+                    self.ring.producer.wait_until(fits);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn write(&mut self, event: SpotEvent, guard: &Guard) -> u64 {
+        let seq = self.head;
+        let slot = self.ring.slot(seq);
+        slot.stamp.store(2 * seq + 1, SeqCst);
+        let replaced = slot.event.swap(Owned::new(event), SeqCst, guard);
+        if !replaced.is_null() {
+            // SAFETY: the event is unreachable from the slot now, consumers copying it are pinned
+            unsafe { guard.defer_destroy(replaced) };
+        }
+        slot.stamp.store(2 * seq + 2, SeqCst);
+        self.head = seq + 1;
+        self.ring.head.store(self.head, SeqCst);
+        seq
+    }
+}
+
+impl Drop for EventProducer {
+    fn drop(&mut self) {
+        self.ring.closed.store(true, SeqCst);
+        self.ring.wake_consumers();
+    }
+}
+
+/// A consumer of an event bus reading events at its own cursor, in batches.
+pub struct EventConsumer {
+    ring: Arc<Ring>,
+    /// next sequence shared with the producer, updated after every batch
+    cursor: Arc<Cursor>,
+    next: u64,
+    dropped: u64,
+}
+
+impl EventConsumer {
+    /// Sequence number of the next event to read
+    pub fn sequence(&self) -> u64 {
+        self.next
+    }
+
+    /// Number of events overwritten before this consumer read them, only with `Backpressure::DropOldest`
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Appends up to `max` published events to `events` without waiting, returning how many were read
+    pub fn try_recv_batch(&mut self, events: &mut Vec<SpotEvent>, max: usize) -> usize {
+        let guard = epoch::pin();
+        let mut read = 0;
+        while read < max {
+            let head = self.ring.head.load(SeqCst);
+            let oldest = head.saturating_sub(self.ring.capacity);
+            if self.next < oldest {
+                self.dropped += oldest - self.next;
+                self.next = oldest;
+            }
+            if self.next == head {
+                break;
+            }
+            match self.ring.read(self.next, &guard) {
+                Some(event) => {
+                    events.push(event);
+                    read += 1;
+                }
+                None => self.dropped += 1,
+            }
+            self.next += 1;
+        }
+        self.cursor.next.store(self.next, SeqCst);
+        if read > 0 {
+            self.ring.producer.wake();
+        }
+        read
+    }
+
+    /// Appends up to `max` events to `events`, waiting for at least one.
+    /// Fails with `EventBusError::Closed` once the producer is dropped and every event is read.
+    pub fn recv_batch(&mut self, events: &mut Vec<SpotEvent>, max: usize) -> Result<usize, EventBusError> {
+        loop {
+            // checked before reading, so the events published before closing are read first
+            let closed = self.ring.closed.load(SeqCst);
+            let read = self.try_recv_batch(events, max);
+            if read > 0 {
+                return Ok(read);
+            }
+            if closed {
+                return Err(EventBusError::Closed);
+            }
+            self.wait();
+        }
+    }
+
+    /// Parks the calling thread until an event is published, the bus is closed or an `EventWaker` of the consumer wakes it
+    pub fn wait(&self) {
+        let (ring, cursor) = (&self.ring, &self.cursor);
+        cursor.waiter.wait_until(|| {
+            ring.head.load(SeqCst) > self.next || ring.closed.load(SeqCst) || cursor.notified.swap(false, SeqCst)
+        });
+    }
+
+    /// Returns a handle that wakes this consumer from `wait`, from any thread
+    pub fn waker(&self) -> EventWaker {
+        EventWaker { cursor: self.cursor.clone() }
+    }
+
+    /// Returns the next event if there is one, without waiting
+    pub fn try_recv(&mut self) -> Option<SpotEvent> {
+        let mut events = Vec::with_capacity(1);
+        self.try_recv_batch(&mut events, 1);
+        events.pop()
+    }
+
+    /// Feeds a backend with batches of up to `batch` events until the bus is closed
+    pub fn run<B: EventBackend + ?Sized>(&mut self, backend: &mut B, batch: usize) {
+        let mut events = Vec::with_capacity(batch);
+        while self.recv_batch(&mut events, batch).is_ok() {
+            backend.handle_batch(events.drain(..));
+        }
+    }
+}
+
+impl Drop for EventConsumer {
+    fn drop(&mut self) {
+        self.cursor.next.store(DETACHED, SeqCst);
+        self.ring.update_cursors(|cursors| cursors.retain(|cursor| !Arc::ptr_eq(cursor, &self.cursor)));
+        self.ring.producer.wake();
+    }
+}
+
+/// Wakes a consumer waiting for events, so that its thread can handle something else than events.
+#[derive(Clone)]
+pub struct EventWaker {
+    cursor: Arc<Cursor>,
+}
+
+impl EventWaker {
+    /// Returns the consumer from its current or next `wait`
+    pub fn wake(&self) {
+        self.cursor.notified.store(true, SeqCst);
+        self.cursor.waiter.wake();
+    }
+}
+
+/// Waits between polls of the ring before parking: spins first, then yields
+#[derive(Default)]
+struct Backoff {
+    step: u32,
+}
+
+impl Backoff {
+    fn snooze(&mut self) {
+        match self.step {
+            0..=6 => (0..1 << self.step).for_each(|_| std::hint::spin_loop()),
+            _ => thread::yield_now(),
+        }
+        self.step = self.step.saturating_add(1);
+    }
+
+    /// Returns true once polling again is not worth it and the thread should park
+    fn is_completed(&self) -> bool {
+        self.step > 10
+    }
+}
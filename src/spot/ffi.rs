@@ -0,0 +1,365 @@
+//! C ABI of the spot engine, with the `ffi` feature, for embedding it in C and C++ gateways.
+//!
+//! - `og_engine` is an opaque handle owning a `MatchingEngine` and the events of the commands submitted to it.
+//!   A handle is not thread safe, calls on one handle must not run concurrently.
+//! - commands are postcard `SpotCommand`s, events are drained as a postcard `EventQueue` and snapshots are
+//!   versioned `spot::snapshot`s of the `MatchingEngine`. Commands run in an isolated event queue, nothing is
+//!   published to the event bus. Commands and snapshots are decoded as untrusted input, see `intern::untrusted`.
+//! - functions return an `og_status`, buffers returned by the engine are owned by the caller and released with
+//!   `og_buffer_free`. Panics are caught and reported as `OG_PANIC`, they never unwind into C.
+//!
+//! The header of the ABI is rendered by `header` and checked in as `include/off_grid_primitives.h`. Its prototypes
+//! are rendered from the signatures of the exported functions, which are checked at compile time.
+
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use super::command::SpotCommand;
+use super::event::{self, EventQueue, SpotEvent};
+use super::intern::{self, InternBudget};
+use super::matching_engine::MatchingEngine;
+use super::snapshot;
+
+/// Version of the ABI, bumped on every change of the header
+pub const ABI_VERSION: u32 = 1;
+
+/// Status returned by the functions of the ABI
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OgStatus {
+    Ok = 0,
+    /// a pointer argument is null
+    NullPointer = 1,
+    /// a command, snapshot or event queue failed to decode or encode
+    Postcard = 2,
+    /// the command was rejected by the engine, see `og_engine_last_error`
+    Rejected = 3,
+    /// the engine panicked, the handle should be destroyed
+    Panic = 4,
+}
+
+/// Bytes owned by the caller, released with `og_buffer_free`
+#[repr(C)]
+#[derive(Debug)]
+pub struct OgBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl OgBuffer {
+    fn empty() -> Self {
+        Self { data: ptr::null_mut(), len: 0 }
+    }
+
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let mut bytes = bytes.into_boxed_slice();
+        let buffer = Self { data: bytes.as_mut_ptr(), len: bytes.len() };
+        std::mem::forget(bytes);
+        buffer
+    }
+}
+
+/// Engine behind an `og_engine *`
+pub struct OgEngine {
+    engine: MatchingEngine,
+    /// events of the submitted commands not drained yet
+    events: Vec<SpotEvent>,
+    /// message of the last rejected command
+    last_error: Option<String>,
+    /// new ids the commands of the handle may intern
+    budget: InternBudget,
+}
+
+impl OgEngine {
+    fn new(engine: MatchingEngine) -> Self {
+        Self { engine, events: Vec::new(), last_error: None, budget: InternBudget::default() }
+    }
+}
+
+fn guarded(f: impl FnOnce() -> OgStatus) -> OgStatus {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(OgStatus::Panic)
+}
+
+/// Returns `ABI_VERSION`
+#[no_mangle]
+pub extern "C" fn og_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+/// Creates an engine without pairs, destroyed with `og_engine_free`, null if it panicked
+#[no_mangle]
+pub extern "C" fn og_engine_new() -> *mut OgEngine {
+    panic::catch_unwind(|| Box::into_raw(Box::new(OgEngine::new(MatchingEngine::new())))).unwrap_or(ptr::null_mut())
+}
+
+/// Destroys an engine, null is ignored
+///
+/// # Safety
+/// `engine` is null or a handle of `og_engine_new`/`og_engine_restore` not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn og_engine_free(engine: *mut OgEngine) {
+    if !engine.is_null() {
+        // a panic dropping the engine leaks what is left of it
+        let _ = guarded(|| {
+            drop(Box::from_raw(engine));
+            OgStatus::Ok
+        });
+    }
+}
+
+/// Executes a postcard `SpotCommand`, its events are kept until `og_engine_drain_events`
+///
+/// # Safety
+/// `engine` is a live handle and `command` points to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn og_engine_submit(engine: *mut OgEngine, command: *const u8, len: usize) -> OgStatus {
+    let (Some(engine), false) = (engine.as_mut(), command.is_null()) else {
+        return OgStatus::NullPointer;
+    };
+    let command = std::slice::from_raw_parts(command, len);
+    guarded(|| {
+        let Ok(command) = intern::untrusted(&mut engine.budget, || postcard::from_bytes::<SpotCommand>(command)) else {
+            return OgStatus::Postcard;
+        };
+        match event::isolated(|| engine.engine.execute(command)) {
+            Ok(events) => {
+                engine.events.extend(events.into_vec());
+                OgStatus::Ok
+            }
+            Err(err) => {
+                engine.last_error = Some(err.to_string());
+                OgStatus::Rejected
+            }
+        }
+    })
+}
+
+/// Moves the events of the commands submitted since the last drain into `out` as a postcard `EventQueue`
+///
+/// # Safety
+/// `engine` is a live handle and `out` points to a writable `og_buffer`.
+#[no_mangle]
+pub unsafe extern "C" fn og_engine_drain_events(engine: *mut OgEngine, out: *mut OgBuffer) -> OgStatus {
+    let (Some(engine), Some(out)) = (engine.as_mut(), out.as_mut()) else {
+        return OgStatus::NullPointer;
+    };
+    *out = OgBuffer::empty();
+    guarded(|| match postcard::to_allocvec(&EventQueue::from_vec(std::mem::take(&mut engine.events))) {
+        Ok(bytes) => {
+            *out = OgBuffer::from_vec(bytes);
+            OgStatus::Ok
+        }
+        Err(_) => OgStatus::Postcard,
+    })
+}
+
+/// Writes the versioned snapshot of the engine into `out`, the events not drained are not part of it
+///
+/// # Safety
+/// `engine` is a live handle and `out` points to a writable `og_buffer`.
+#[no_mangle]
+pub unsafe extern "C" fn og_engine_snapshot(engine: *const OgEngine, out: *mut OgBuffer) -> OgStatus {
+    let (Some(engine), Some(out)) = (engine.as_ref(), out.as_mut()) else {
+        return OgStatus::NullPointer;
+    };
+    *out = OgBuffer::empty();
+    guarded(|| match snapshot::encode(&engine.engine) {
+        Ok(bytes) => {
+            *out = OgBuffer::from_vec(bytes);
+            OgStatus::Ok
+        }
+        Err(_) => OgStatus::Postcard,
+    })
+}
+
+/// Creates an engine from a snapshot of `og_engine_snapshot` of this or an older version into `out`.
+/// The snapshot is decoded as untrusted input, see `intern::untrusted_snapshot`, its new ids are bounded by its length.
+///
+/// # Safety
+/// `snapshot` points to `len` readable bytes and `out` to a writable `og_engine *`.
+#[no_mangle]
+pub unsafe extern "C" fn og_engine_restore(snapshot: *const u8, len: usize, out: *mut *mut OgEngine) -> OgStatus {
+    let (false, Some(out)) = (snapshot.is_null(), out.as_mut()) else {
+        return OgStatus::NullPointer;
+    };
+    *out = ptr::null_mut();
+    let snapshot = std::slice::from_raw_parts(snapshot, len);
+    guarded(|| match intern::untrusted_snapshot(&mut InternBudget::new(snapshot.len()), || snapshot::decode::<MatchingEngine>(snapshot)) {
+        Ok(engine) => {
+            *out = Box::into_raw(Box::new(OgEngine::new(engine)));
+            OgStatus::Ok
+        }
+        Err(_) => OgStatus::Postcard,
+    })
+}
+
+/// Writes the UTF-8 message of the last rejected command into `out`, empty if no command was rejected
+///
+/// # Safety
+/// `engine` is a live handle and `out` points to a writable `og_buffer`.
+#[no_mangle]
+pub unsafe extern "C" fn og_engine_last_error(engine: *const OgEngine, out: *mut OgBuffer) -> OgStatus {
+    let (Some(engine), Some(out)) = (engine.as_ref(), out.as_mut()) else {
+        return OgStatus::NullPointer;
+    };
+    *out = OgBuffer::from_vec(engine.last_error.clone().unwrap_or_default().into_bytes());
+    OgStatus::Ok
+}
+
+/// Releases a buffer returned by the engine, empty buffers are ignored
+///
+/// # Safety
+/// `buffer` was returned by a function of this ABI and is released once.
+#[no_mangle]
+pub unsafe extern "C" fn og_buffer_free(buffer: OgBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len)));
+    }
+}
+
+/// C type of a parameter or the result of a function of the ABI
+trait CType {
+    fn c_type() -> String;
+}
+
+macro_rules! c_types {
+    ($($rust:ty => $c:literal),* $(,)?) => {
+        $(impl CType for $rust {
+            fn c_type() -> String {
+                $c.to_string()
+            }
+        })*
+    };
+}
+
+c_types! {
+    () => "void",
+    u8 => "uint8_t",
+    u32 => "uint32_t",
+    usize => "size_t",
+    OgStatus => "og_status",
+    OgBuffer => "og_buffer",
+    OgEngine => "og_engine",
+}
+
+impl<T: CType> CType for *const T {
+    fn c_type() -> String {
+        pointer(format!("const {}", T::c_type()))
+    }
+}
+
+impl<T: CType> CType for *mut T {
+    fn c_type() -> String {
+        pointer(T::c_type())
+    }
+}
+
+fn pointer(pointee: String) -> String {
+    if pointee.ends_with('*') {
+        pointee + "*"
+    } else {
+        pointee + " *"
+    }
+}
+
+/// Renders a C prototype, `og_status og_engine_submit(og_engine *engine, ...);`
+fn prototype(result: String, name: &str, params: &[(&str, String)]) -> String {
+    let param = |(name, ty): &(&str, String)| if ty.ends_with('*') { format!("{ty}{name}") } else { format!("{ty} {name}") };
+    let params = if params.is_empty() { "void".to_string() } else { params.iter().map(param).collect::<Vec<_>>().join(", ") };
+    format!("{}({params});", param(&(name, result)))
+}
+
+/// Declares the functions of the ABI in the order of the header. Each signature is checked against the exported
+/// function and rendered as the C prototype of the header.
+macro_rules! declarations {
+    ($($doc:literal $name:ident($($param:ident: $ty:ty),*) $(-> $result:ty)?;)*) => {
+        const _: () = {
+            $(let _: unsafe extern "C" fn($($ty),*) $(-> $result)? = $name;)*
+        };
+
+        /// C declarations of the ABI with their comments
+        fn declarations() -> Vec<(&'static str, String)> {
+            vec![$((
+                $doc,
+                prototype(declarations!(@result $($result)?), stringify!($name), &[$((stringify!($param), <$ty>::c_type())),*]),
+            )),*]
+        }
+    };
+    (@result) => { <()>::c_type() };
+    (@result $result:ty) => { <$result>::c_type() };
+}
+
+declarations! {
+    "Returns OG_ABI_VERSION of the library"
+    og_abi_version() -> u32;
+    "Creates an engine without pairs, destroyed with og_engine_free, NULL if it panicked"
+    og_engine_new() -> *mut OgEngine;
+    "Destroys an engine, NULL is ignored"
+    og_engine_free(engine: *mut OgEngine);
+    "Executes a postcard SpotCommand, its events are kept until og_engine_drain_events"
+    og_engine_submit(engine: *mut OgEngine, command: *const u8, len: usize) -> OgStatus;
+    "Moves the events of the commands submitted since the last drain into out as a postcard EventQueue"
+    og_engine_drain_events(engine: *mut OgEngine, out: *mut OgBuffer) -> OgStatus;
+    "Writes the versioned snapshot of the engine into out, the events not drained are not part of it"
+    og_engine_snapshot(engine: *const OgEngine, out: *mut OgBuffer) -> OgStatus;
+    "Creates an engine from a snapshot of og_engine_snapshot of this or an older version into out"
+    og_engine_restore(snapshot: *const u8, len: usize, out: *mut *mut OgEngine) -> OgStatus;
+    "Writes the UTF-8 message of the last rejected command into out, empty if no command was rejected"
+    og_engine_last_error(engine: *const OgEngine, out: *mut OgBuffer) -> OgStatus;
+    "Releases a buffer returned by the engine, empty buffers are ignored"
+    og_buffer_free(buffer: OgBuffer);
+}
+
+/// Returns the C header of the ABI, the content of `include/off_grid_primitives.h`
+pub fn header() -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "/* Generated by `off_grid_primitives::spot::ffi::header`, do not edit. */");
+    out.push_str(
+        "#ifndef OFF_GRID_PRIMITIVES_H
+#define OFF_GRID_PRIMITIVES_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern \"C\" {
+#endif
+
+",
+    );
+    let _ = writeln!(out, "#define OG_ABI_VERSION {ABI_VERSION}u\n");
+    out.push_str(
+        "typedef enum og_status {
+    OG_OK = 0,
+    OG_NULL_POINTER = 1,
+    OG_POSTCARD = 2,
+    OG_REJECTED = 3,
+    OG_PANIC = 4
+} og_status;
+
+/* Opaque engine handle, calls on one handle must not run concurrently */
+typedef struct og_engine og_engine;
+
+/* Bytes owned by the caller, released with og_buffer_free */
+typedef struct og_buffer {
+    uint8_t *data;
+    size_t len;
+} og_buffer;
+",
+    );
+    for (doc, declaration) in declarations() {
+        let _ = write!(out, "\n/* {doc} */\n{declaration}\n");
+    }
+    out.push_str(
+        "
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* OFF_GRID_PRIMITIVES_H */
+",
+    );
+    out
+}
+
@@ -0,0 +1,839 @@
+//! FIX 4.4 codec of order entry and execution reports.
+//!
+//! - `FixMessage` parses and encodes tag=value messages, checking BeginString, BodyLength and CheckSum.
+//!   Session fields (CompIDs, MsgSeqNum, SendingTime, ...) are kept as any other field, logons, sequencing
+//!   and resends belong to the session layer of the gateway.
+//! - `FixRequest::parse` maps NewOrderSingle (D), OrderCancelRequest (F) and OrderCancelReplaceRequest (G)
+//!   to `SpotCommand`s. The engine has no amend, a replace is the cancel of the original order followed by
+//!   the new order, which loses the time priority of the original.
+//! - `ExecutionReporter` renders the events of the engine as ExecutionReports (8), and the rejections of the
+//!   gateway as ExecutionReports or OrderCancelRejects (9).
+//!
+//! Fields of the requests:
+//!
+//! | tag | field | mapping |
+//! |---|---|---|
+//! | 1 | Account | `owner`, interned within the bounds of `intern::intern_bounded` and the `intern_budget` of the session |
+//! | 11 | ClOrdID | `coid` of the order |
+//! | 18 | ExecInst | `6` (participate don't initiate) is `post_only`, for GTC limit orders, rejected by the engine if they would take liquidity |
+//! | 37 | OrderID | `OrderRef::Id` of the order to cancel |
+//! | 38 | OrderQty | base quantity, converted to the quote amount of buys at the limit price. Executions are reported up to the OrderQty, an order the engine fills up executes all of it |
+//! | 40 | OrdType | `1` market, `2` limit |
+//! | 41 | OrigClOrdID | `OrderRef::ClientOrderId` of the order to cancel, when OrderID is not given |
+//! | 44 | Price | limit price |
+//! | 54 | Side | `1` buy, `2` sell |
+//! | 55 | Symbol | `pair_id`, a pair id known to the process |
+//! | 59 | TimeInForce | `0` Day, the default of limit orders, GTD until the `session_end` of the `OrderEntry`, `1` GTC, `3` IOC, the default of market orders, `4` FOK, `6` GTD until ExpireTime (126) |
+//! | 60 | TransactTime | `timestamp` |
+//! | 111 | MaxFloor | public quantity of iceberg limit orders |
+//! | 152 | CashOrderQty | quote size of market orders, instead of OrderQty |
+//!
+//! Prices and quantities are decimals of up to 8 decimals, the fixed point of the engine, and timestamps are
+//! UTCTimestamps of millisecond precision. Fields and values outside the tables fail with `FixError::Unsupported`
+//! rather than being ignored.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::command::{LimitOrder, MarketOrder, SpotCommand};
+use super::event::SpotEvent;
+use super::fixed_point::{mul_div, FixedPointError, Notional, Price, Quantity, Rounding, SCALE_8};
+use super::ids::{AccountId, ClientId, PairId};
+use super::intern::{self, InternBudget};
+use super::market_order::MarketOrderSize;
+use super::orders::{ClientOrderId, OrderId, OrderRef};
+use super::time_in_force::TimeInForce;
+
+/// BeginString (8) of the messages
+pub const BEGIN_STRING: &str = "FIX.4.4";
+
+/// Field delimiter
+pub const SOH: u8 = 0x01;
+
+/// Tags of the fields used by the codec
+pub mod tag {
+    pub const ACCOUNT: u32 = 1;
+    pub const AVG_PX: u32 = 6;
+    pub const BEGIN_STRING: u32 = 8;
+    pub const BODY_LENGTH: u32 = 9;
+    pub const CHECK_SUM: u32 = 10;
+    pub const CL_ORD_ID: u32 = 11;
+    pub const CUM_QTY: u32 = 14;
+    pub const EXEC_ID: u32 = 17;
+    pub const EXEC_INST: u32 = 18;
+    pub const LAST_PX: u32 = 31;
+    pub const LAST_QTY: u32 = 32;
+    pub const MSG_TYPE: u32 = 35;
+    pub const ORDER_ID: u32 = 37;
+    pub const ORDER_QTY: u32 = 38;
+    pub const ORD_STATUS: u32 = 39;
+    pub const ORD_TYPE: u32 = 40;
+    pub const ORIG_CL_ORD_ID: u32 = 41;
+    pub const PRICE: u32 = 44;
+    pub const SIDE: u32 = 54;
+    pub const SYMBOL: u32 = 55;
+    pub const TEXT: u32 = 58;
+    pub const TIME_IN_FORCE: u32 = 59;
+    pub const TRANSACT_TIME: u32 = 60;
+    pub const CXL_REJ_REASON: u32 = 102;
+    pub const ORD_REJ_REASON: u32 = 103;
+    pub const MAX_FLOOR: u32 = 111;
+    pub const EXPIRE_TIME: u32 = 126;
+    pub const EXEC_TYPE: u32 = 150;
+    pub const LEAVES_QTY: u32 = 151;
+    pub const CASH_ORDER_QTY: u32 = 152;
+    pub const CXL_REJ_RESPONSE_TO: u32 = 434;
+}
+
+/// MsgType (35) values of the codec
+pub mod msg_type {
+    pub const EXECUTION_REPORT: &str = "8";
+    pub const ORDER_CANCEL_REJECT: &str = "9";
+    pub const NEW_ORDER_SINGLE: &str = "D";
+    pub const ORDER_CANCEL_REQUEST: &str = "F";
+    pub const ORDER_CANCEL_REPLACE_REQUEST: &str = "G";
+}
+
+#[derive(Debug, Clone, thiserror::Error, PartialEq, Eq)]
+pub enum FixError {
+    #[error("malformed message: {0}")]
+    Malformed(&'static str),
+    #[error("unsupported begin string {0}")]
+    BeginString(String),
+    #[error("body length {declared} does not match the {actual} bytes of the body")]
+    BodyLength { declared: usize, actual: usize },
+    #[error("checksum {declared} does not match the computed {actual}")]
+    CheckSum { declared: u8, actual: u8 },
+    #[error("unsupported message type {0}")]
+    MsgType(String),
+    #[error("missing tag {0}")]
+    MissingTag(u32),
+    #[error("invalid value {value:?} of tag {tag}")]
+    InvalidValue { tag: u32, value: String },
+    #[error("unsupported value {value:?} of tag {tag}")]
+    Unsupported { tag: u32, value: String },
+    #[error("fixed point error: {0}")]
+    FixedPoint(#[from] FixedPointError),
+}
+
+fn invalid(tag: u32, value: &str) -> FixError {
+    FixError::InvalidValue { tag, value: value.to_string() }
+}
+
+fn unsupported(tag: u32, value: &str) -> FixError {
+    FixError::Unsupported { tag, value: value.to_string() }
+}
+
+/// A FIX message without its BeginString, BodyLength and CheckSum, which are checked by `parse` and written by
+/// `encode`. MsgType is the first field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixMessage {
+    fields: Vec<(u32, String)>,
+}
+
+impl FixMessage {
+    pub fn new(msg_type: &str) -> Self {
+        Self { fields: vec![(tag::MSG_TYPE, msg_type.to_string())] }
+    }
+
+    /// Appends a field
+    pub fn push(&mut self, tag: u32, value: impl ToString) -> &mut Self {
+        self.fields.push((tag, value.to_string()));
+        self
+    }
+
+    /// Appends a field, for building messages in one expression
+    pub fn with(mut self, tag: u32, value: impl ToString) -> Self {
+        self.push(tag, value);
+        self
+    }
+
+    pub fn msg_type(&self) -> &str {
+        self.get(tag::MSG_TYPE).unwrap_or_default()
+    }
+
+    /// Value of the first field with the tag
+    pub fn get(&self, tag: u32) -> Option<&str> {
+        self.fields.iter().find(|(t, _)| *t == tag).map(|(_, value)| value.as_str())
+    }
+
+    /// Fields in their order in the message
+    pub fn fields(&self) -> &[(u32, String)] {
+        &self.fields
+    }
+
+    /// Parses a message delimited by `SOH`, from its BeginString to the `SOH` after its CheckSum
+    pub fn parse(bytes: &[u8]) -> Result<Self, FixError> {
+        // (tag, value, offset of the field)
+        let mut raw = Vec::new();
+        let mut start = 0;
+        while start < bytes.len() {
+            let end = bytes[start..]
+                .iter()
+                .position(|byte| *byte == SOH)
+                .map(|len| start + len)
+                .ok_or(FixError::Malformed("field without delimiter"))?;
+            let field = std::str::from_utf8(&bytes[start..end]).map_err(|_| FixError::Malformed("field is not UTF-8"))?;
+            let (tag, value) = field.split_once('=').ok_or(FixError::Malformed("field without '='"))?;
+            let tag: u32 = tag.parse().map_err(|_| FixError::Malformed("tag is not a number"))?;
+            raw.push((tag, value, start));
+            start = end + 1;
+        }
+
+        let [(tag::BEGIN_STRING, begin_string, _), (tag::BODY_LENGTH, body_length, _), (tag::MSG_TYPE, _, body_start), .., (tag::CHECK_SUM, check_sum, trailer_start)] =
+            raw.as_slice()
+        else {
+            return Err(FixError::Malformed("message does not start with 8, 9 and 35 and end with 10"));
+        };
+        if *begin_string != BEGIN_STRING {
+            return Err(FixError::BeginString(begin_string.to_string()));
+        }
+        let declared = body_length.parse().map_err(|_| invalid(tag::BODY_LENGTH, body_length))?;
+        let actual = trailer_start - body_start;
+        if declared != actual {
+            return Err(FixError::BodyLength { declared, actual });
+        }
+        let declared = check_sum.parse().map_err(|_| invalid(tag::CHECK_SUM, check_sum))?;
+        let actual = check_sum_of(&bytes[..*trailer_start]);
+        if declared != actual {
+            return Err(FixError::CheckSum { declared, actual });
+        }
+
+        let fields = raw[2..raw.len() - 1].iter().map(|(tag, value, _)| (*tag, value.to_string())).collect();
+        Ok(Self { fields })
+    }
+
+    /// Encodes the message with its BeginString, BodyLength and CheckSum
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = String::new();
+        for (tag, value) in &self.fields {
+            let _ = write!(body, "{tag}={value}\x01");
+        }
+        let mut out = format!("8={BEGIN_STRING}\x019={}\x01{body}", body.len()).into_bytes();
+        let check_sum = check_sum_of(&out);
+        out.extend_from_slice(format!("10={check_sum:03}\x01").as_bytes());
+        out
+    }
+
+    fn required(&self, tag: u32) -> Result<&str, FixError> {
+        self.get(tag).ok_or(FixError::MissingTag(tag))
+    }
+
+    /// Pair of the Symbol, which must be a pair id known to the process
+    fn pair_id(&self) -> Result<PairId, FixError> {
+        let symbol = self.required(tag::SYMBOL)?;
+        PairId::lookup(symbol.as_bytes()).ok_or_else(|| invalid(tag::SYMBOL, symbol))
+    }
+
+    /// Owner of the Account, interned within the bounds of `intern::intern_bounded` and the budget of the request
+    fn account(&self) -> Result<AccountId, FixError> {
+        let account = self.required(tag::ACCOUNT)?;
+        AccountId::new_bounded(account.as_bytes()).ok_or_else(|| invalid(tag::ACCOUNT, account))
+    }
+
+    fn decimal(&self, tag: u32) -> Result<Option<u64>, FixError> {
+        self.get(tag).map(|value| parse_decimal(value).ok_or_else(|| invalid(tag, value))).transpose()
+    }
+
+    fn required_decimal(&self, tag: u32) -> Result<u64, FixError> {
+        self.decimal(tag)?.ok_or(FixError::MissingTag(tag))
+    }
+
+    fn required_timestamp(&self, tag: u32) -> Result<i64, FixError> {
+        let value = self.required(tag)?;
+        parse_utc_timestamp(value).ok_or_else(|| invalid(tag, value))
+    }
+}
+
+fn check_sum_of(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// Parses a decimal of up to 8 decimals into the fixed point of the engine
+pub fn parse_decimal(value: &str) -> Option<u64> {
+    let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if int.is_empty() || frac.len() > 8 || !digits(int) || !digits(frac) {
+        return None;
+    }
+    let frac = if frac.is_empty() { 0 } else { frac.parse::<u64>().ok()? * 10u64.pow(8 - frac.len() as u32) };
+    int.parse::<u64>().ok()?.checked_mul(SCALE_8)?.checked_add(frac)
+}
+
+/// Formats a fixed point value as a decimal without trailing zeros
+pub fn format_decimal(value: u64) -> String {
+    let (int, frac) = (value / SCALE_8, value % SCALE_8);
+    if frac == 0 {
+        return int.to_string();
+    }
+    let frac = format!("{frac:08}");
+    format!("{int}.{}", frac.trim_end_matches('0'))
+}
+
+/// Parses a UTCTimestamp, `YYYYMMDD-HH:MM:SS` with optional fractional seconds, into milliseconds since the epoch
+pub fn parse_utc_timestamp(value: &str) -> Option<i64> {
+    let (seconds, frac) = value.split_once('.').unwrap_or((value, ""));
+    let b = seconds.as_bytes();
+    if b.len() != 17 || b[8] != b'-' || b[11] != b':' || b[14] != b':' || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let part = &seconds[range];
+        part.bytes().all(|b| b.is_ascii_digit()).then(|| part.parse().ok()).flatten()
+    };
+    let (year, month, day) = (number(0..4)?, number(4..6)?, number(6..8)?);
+    let (hour, minute, second) = (number(9..11)?, number(12..14)?, number(15..17)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let millis = format!("{frac:0<3}")[..3].parse::<i64>().ok()?;
+    let days = days_from_civil(year, month, day);
+    Some(((days * 24 + hour) * 60 + minute) * 60_000 + second * 1000 + millis)
+}
+
+/// Formats milliseconds since the epoch as a UTCTimestamp of millisecond precision
+pub fn format_utc_timestamp(millis: i64) -> String {
+    let (days, millis) = (millis.div_euclid(86_400_000), millis.rem_euclid(86_400_000));
+    let (year, month, day) = civil_from_days(days);
+    let seconds = millis / 1000;
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}:{:02}:{:02}.{:03}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        millis % 1000
+    )
+}
+
+// Days between 1970-01-01 and a date of the proleptic Gregorian calendar, and back
+// (http://howardhinnant.github.io/date_algorithms.html)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Engine fields FIX does not carry, set by the gateway for the orders of a session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderEntry {
+    /// gateway client id
+    pub cid: ClientId,
+    pub maker_fee_bps: i16,
+    pub taker_fee_bps: i16,
+    /// end of the trading day of the session in milliseconds, Day orders expire at it. `None` rejects Day orders,
+    /// and limit orders without TimeInForce.
+    pub session_end: Option<i64>,
+    /// new accounts the requests of the session may intern, see `intern::untrusted`
+    pub intern_budget: InternBudget,
+}
+
+/// A NewOrderSingle, or the new order of an OrderCancelReplaceRequest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewOrder {
+    pub cl_ord_id: String,
+    /// `LimitBuy`, `LimitSell`, `MarketBuy` or `MarketSell`
+    pub command: SpotCommand,
+    /// OrderQty of the request, the base quantity of the order unless it is sized by CashOrderQty
+    pub order_qty: Option<u64>,
+}
+
+impl NewOrder {
+    fn parse(message: &FixMessage, entry: &OrderEntry) -> Result<Self, FixError> {
+        let cl_ord_id = message.required(tag::CL_ORD_ID)?.to_string();
+        let owner = message.account()?;
+        let pair_id = message.pair_id()?;
+        let is_bid = parse_side(message)?;
+        let is_market = match message.required(tag::ORD_TYPE)? {
+            "1" => true,
+            "2" => false,
+            value => return Err(unsupported(tag::ORD_TYPE, value)),
+        };
+        let timestamp = message.required_timestamp(tag::TRANSACT_TIME)?;
+        let (time_in_force, expires_at) = match message.get(tag::TIME_IN_FORCE) {
+            None if is_market => (TimeInForce::ImmediateOrCancel, i64::MAX),
+            // Day is the default of FIX
+            None => (TimeInForce::GoodTillCanceled, entry.session_end.ok_or(FixError::MissingTag(tag::TIME_IN_FORCE))?),
+            Some(value @ "0") => (TimeInForce::GoodTillCanceled, entry.session_end.ok_or_else(|| unsupported(tag::TIME_IN_FORCE, value))?),
+            Some("1") => (TimeInForce::GoodTillCanceled, i64::MAX),
+            Some("3") => (TimeInForce::ImmediateOrCancel, i64::MAX),
+            Some("4") => (TimeInForce::FillOrKill, i64::MAX),
+            Some("6") => (TimeInForce::GoodTillCanceled, message.required_timestamp(tag::EXPIRE_TIME)?),
+            Some(value) => return Err(unsupported(tag::TIME_IN_FORCE, value)),
+        };
+        let post_only = match message.get(tag::EXEC_INST) {
+            None => false,
+            Some(value) if value.split(' ').any(|inst| inst != "6") => return Err(unsupported(tag::EXEC_INST, value)),
+            // resting is the point of a post-only order
+            Some(value) if is_market || time_in_force != TimeInForce::GoodTillCanceled => {
+                return Err(invalid(tag::EXEC_INST, value))
+            }
+            Some(_) => true,
+        };
+        let coid = Some(ClientOrderId::from(cl_ord_id.as_str()));
+        let (maker_fee_bps, taker_fee_bps) = (entry.maker_fee_bps, entry.taker_fee_bps);
+
+        if is_market {
+            if let Some(value) = message.get(tag::MAX_FLOOR) {
+                return Err(unsupported(tag::MAX_FLOOR, value));
+            }
+            let order_qty = message.decimal(tag::ORDER_QTY)?;
+            let size = match (order_qty, message.decimal(tag::CASH_ORDER_QTY)?) {
+                (Some(qty), None) => MarketOrderSize::Base(qty),
+                (None, Some(cash)) => MarketOrderSize::Quote(cash),
+                (None, None) => return Err(FixError::MissingTag(tag::ORDER_QTY)),
+                (Some(_), Some(_)) => return Err(invalid(tag::CASH_ORDER_QTY, message.required(tag::CASH_ORDER_QTY)?)),
+            };
+            let order = MarketOrder {
+                cid: entry.cid,
+                pair_id,
+                existing_order_id: None,
+                owner,
+                coid,
+                size,
+                public_amount: 0,
+                timestamp,
+                expires_at,
+                maker_fee_bps,
+                taker_fee_bps,
+                time_in_force,
+            };
+            let command = if is_bid { SpotCommand::MarketBuy(order) } else { SpotCommand::MarketSell(order) };
+            return Ok(Self { cl_ord_id, command, order_qty });
+        }
+
+        let price = message.required_decimal(tag::PRICE)?;
+        let qty = message.required_decimal(tag::ORDER_QTY)?;
+        let public = message.decimal(tag::MAX_FLOOR)?.unwrap_or(qty);
+        if public == 0 || public > qty {
+            return Err(invalid(tag::MAX_FLOOR, message.required(tag::MAX_FLOOR)?));
+        }
+        // buys are sized in quote, the whole quantity is bought at the limit price
+        let (amnt, public) = if is_bid {
+            let amnt = Price(price).notional(Quantity(qty), Rounding::Ceil)?.0;
+            (amnt, Price(price).notional(Quantity(public), Rounding::Ceil)?.0.min(amnt))
+        } else {
+            (qty, public)
+        };
+        let order = LimitOrder {
+            cid: entry.cid,
+            pair_id,
+            existing_order_id: None,
+            owner,
+            coid,
+            price,
+            amnt,
+            iqty: amnt - public,
+            timestamp,
+            expires_at,
+            maker_fee_bps,
+            taker_fee_bps,
+            time_in_force,
+            post_only,
+        };
+        let command = if is_bid { SpotCommand::LimitBuy(order) } else { SpotCommand::LimitSell(order) };
+        Ok(Self { cl_ord_id, command, order_qty: Some(qty) })
+    }
+}
+
+/// An OrderCancelRequest, or the cancel of the original order of an OrderCancelReplaceRequest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CancelRequest {
+    pub cl_ord_id: String,
+    pub orig_cl_ord_id: Option<String>,
+    /// `CancelOrder`
+    pub command: SpotCommand,
+}
+
+impl CancelRequest {
+    fn parse(message: &FixMessage, entry: &OrderEntry) -> Result<Self, FixError> {
+        let cl_ord_id = message.required(tag::CL_ORD_ID)?.to_string();
+        let orig_cl_ord_id = message.get(tag::ORIG_CL_ORD_ID).map(str::to_string);
+        let order = match (message.get(tag::ORDER_ID), &orig_cl_ord_id) {
+            (Some(id), _) => OrderRef::Id(OrderId::from_string(id).map_err(|_| invalid(tag::ORDER_ID, id))?),
+            (None, Some(orig)) => OrderRef::ClientOrderId(orig.as_bytes().to_vec()),
+            (None, None) => return Err(FixError::MissingTag(tag::ORIG_CL_ORD_ID)),
+        };
+        parse_side(message)?;
+        let command = SpotCommand::CancelOrder {
+            cid: entry.cid,
+            pair_id: message.pair_id()?,
+            order,
+            owner: message.account()?,
+        };
+        Ok(Self { cl_ord_id, orig_cl_ord_id, command })
+    }
+}
+
+fn parse_side(message: &FixMessage) -> Result<bool, FixError> {
+    match message.required(tag::SIDE)? {
+        "1" => Ok(true),
+        "2" => Ok(false),
+        value => Err(unsupported(tag::SIDE, value)),
+    }
+}
+
+/// Order entry request of a FIX message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FixRequest {
+    /// NewOrderSingle
+    NewOrder(NewOrder),
+    /// OrderCancelRequest
+    Cancel(CancelRequest),
+    /// OrderCancelReplaceRequest, the cancel of the original order and the new order, both with the ClOrdID
+    /// of the request
+    Replace { cancel: CancelRequest, order: NewOrder },
+}
+
+impl FixRequest {
+    /// Parses a request of the session of `entry`, as untrusted input charged to its `intern_budget`
+    pub fn parse(message: &FixMessage, entry: &mut OrderEntry) -> Result<Self, FixError> {
+        let mut budget = entry.intern_budget;
+        let request = intern::untrusted(&mut budget, || Self::parse_request(message, entry));
+        entry.intern_budget = budget;
+        request
+    }
+
+    fn parse_request(message: &FixMessage, entry: &OrderEntry) -> Result<Self, FixError> {
+        match message.msg_type() {
+            msg_type::NEW_ORDER_SINGLE => Ok(Self::NewOrder(NewOrder::parse(message, entry)?)),
+            msg_type::ORDER_CANCEL_REQUEST => Ok(Self::Cancel(CancelRequest::parse(message, entry)?)),
+            msg_type::ORDER_CANCEL_REPLACE_REQUEST => Ok(Self::Replace {
+                cancel: CancelRequest::parse(message, entry)?,
+                order: NewOrder::parse(message, entry)?,
+            }),
+            other => Err(FixError::MsgType(other.to_string())),
+        }
+    }
+
+    /// Commands of the request in the order they are executed, the new order of a replace is only executed
+    /// if the cancel succeeded
+    pub fn commands(&self) -> Vec<&SpotCommand> {
+        match self {
+            FixRequest::NewOrder(order) => vec![&order.command],
+            FixRequest::Cancel(cancel) => vec![&cancel.command],
+            FixRequest::Replace { cancel, order } => vec![&cancel.command, &order.command],
+        }
+    }
+}
+
+/// Order of an ExecutionReport, as carried by the events
+struct ReportedOrder<'a> {
+    order_id: &'a [u8],
+    account: AccountId,
+    /// `None` for the removal of an order decoded from a schema version 1 event, reported without a symbol
+    pair_id: Option<PairId>,
+    is_bid: bool,
+    price: u64,
+    amnt: u64,
+    cqty: u64,
+    timestamp: i64,
+}
+
+/// What the reporter keeps of an order between its reports
+#[derive(Debug, Clone, Default)]
+struct TrackedOrder {
+    cl_ord_id: Option<String>,
+    is_market: bool,
+    order_qty: Option<u64>,
+    cash_order_qty: Option<u64>,
+    /// executed base quantity
+    cum_qty: u64,
+    /// executed quote amount
+    cum_quote: u64,
+}
+
+/// Renders the events of the engine as ExecutionReports.
+///
+/// Orders are tracked from their `SpotOrderPlaced` to their last fill, cancel or expiry, for the ClOrdID,
+/// cumulative quantity and average price the other events do not carry. Orders placed before the reporter
+/// are reported without ClOrdID and with the quantities executed since. Reports are rendered for every
+/// account, the gateway routes them to the session of their Account (1).
+#[derive(Debug, Default)]
+pub struct ExecutionReporter {
+    orders: HashMap<Vec<u8>, TrackedOrder>,
+    exec_id: u64,
+}
+
+impl ExecutionReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renders the events of the commands of `request`, or of commands outside of FIX with `None`.
+    /// - `SpotOrderPlaced` is a New report, or a Replaced report for the new order of a replace.
+    /// - `SpotOrderPartiallyFilled`/`SpotOrderFullyFilled` are Trade reports of the taker or maker order.
+    /// - `SpotOrderCancelled`/`SpotOrderExpired` are Canceled/Expired reports. The cancel of a replace is
+    ///   reported by the Replaced report of the new order, unless the new order was not placed.
+    pub fn render(&mut self, request: Option<&FixRequest>, events: &[SpotEvent]) -> Vec<FixMessage> {
+        let (cancel, new_order) = match request {
+            Some(FixRequest::NewOrder(order)) => (None, Some(order)),
+            Some(FixRequest::Cancel(cancel)) => (Some(cancel), None),
+            Some(FixRequest::Replace { cancel, order }) => (Some(cancel), Some(order)),
+            None => (None, None),
+        };
+        let is_replace = matches!(request, Some(FixRequest::Replace { .. }));
+        let mut cancel_reported = false;
+        // ClOrdID of the order cancelled by a replace, and its Canceled report held back for the Replaced report
+        let mut replaced: Option<(Option<String>, FixMessage)> = None;
+        let mut reports = Vec::new();
+
+        for event in events {
+            match event {
+                SpotEvent::SpotOrderPlaced {
+                    order_id, maker_account_id, pair_id, coid, is_bid, price, amnt, cqty, timestamp, ..
+                } => {
+                    let order = ReportedOrder {
+                        order_id, account: *maker_account_id, pair_id: Some(*pair_id), is_bid: *is_bid, price: *price,
+                        amnt: *amnt, cqty: *cqty, timestamp: *timestamp,
+                    };
+                    let mut tracked = TrackedOrder {
+                        cl_ord_id: coid.as_ref().map(|coid| String::from_utf8_lossy(coid).into_owned()),
+                        ..TrackedOrder::default()
+                    };
+                    let requested = new_order.filter(|new_order| coid.as_deref() == Some(new_order.cl_ord_id.as_bytes()));
+                    if let Some(new_order) = requested {
+                        tracked.order_qty = new_order.order_qty;
+                        if let SpotCommand::MarketBuy(market) | SpotCommand::MarketSell(market) = &new_order.command {
+                            tracked.is_market = true;
+                            if let MarketOrderSize::Quote(cash) = market.size {
+                                tracked.cash_order_qty = Some(cash);
+                            }
+                        }
+                    }
+                    let exec_type = if requested.is_some() && is_replace { "5" } else { "0" };
+                    let mut report = self.report(exec_type, "0", &order, &tracked, false);
+                    if let Some((Some(orig_cl_ord_id), _)) = replaced.take().filter(|_| requested.is_some()) {
+                        report.push(tag::ORIG_CL_ORD_ID, orig_cl_ord_id);
+                    }
+                    reports.push(report);
+                    self.orders.insert(order_id.clone(), tracked);
+                }
+                SpotEvent::SpotOrderPartiallyFilled {
+                    is_taker_event, taker_order_id, maker_order_id, taker_account_id, maker_account_id,
+                    taker_order_is_bid, maker_order_is_bid, price, pair_id, base_volume, quote_volume, amnt, cqty,
+                    timestamp, ..
+                }
+                | SpotEvent::SpotOrderFullyFilled {
+                    is_taker_event, taker_order_id, maker_order_id, taker_account_id, maker_account_id,
+                    taker_order_is_bid, maker_order_is_bid, price, pair_id, base_volume, quote_volume, amnt, cqty,
+                    timestamp, ..
+                } => {
+                    let is_filled = matches!(event, SpotEvent::SpotOrderFullyFilled { .. });
+                    let (order_id, account, is_bid) = if *is_taker_event {
+                        (taker_order_id, taker_account_id, taker_order_is_bid)
+                    } else {
+                        (maker_order_id, maker_account_id, maker_order_is_bid)
+                    };
+                    let order = ReportedOrder {
+                        order_id, account: *account, pair_id: Some(*pair_id), is_bid: *is_bid, price: *price,
+                        amnt: *amnt, cqty: *cqty, timestamp: *timestamp,
+                    };
+                    let mut tracked = self.orders.remove(order_id).unwrap_or_default();
+                    // a buy sized in quote at its limit price converts to other base amounts when it matches,
+                    // its executions are capped at the OrderQty and the fill that fills it up executes the rest
+                    let (last_qty, last_quote) = match tracked.order_qty.filter(|_| tracked.cash_order_qty.is_none()) {
+                        Some(order_qty) => {
+                            let left = order_qty.saturating_sub(tracked.cum_qty);
+                            let last_qty = if is_filled { left } else { (*base_volume).min(left) };
+                            (last_qty, mul_div(*quote_volume, last_qty, *base_volume, Rounding::Floor).unwrap_or(*quote_volume))
+                        }
+                        None => (*base_volume, *quote_volume),
+                    };
+                    tracked.cum_qty += last_qty;
+                    tracked.cum_quote += last_quote;
+                    let mut report = self.report("F", if is_filled { "2" } else { "1" }, &order, &tracked, is_filled);
+                    let last_px = mul_div(*quote_volume, SCALE_8, *base_volume, Rounding::Floor).unwrap_or(*price);
+                    report.push(tag::LAST_QTY, format_decimal(last_qty)).push(tag::LAST_PX, format_decimal(last_px));
+                    reports.push(report);
+                    if !is_filled {
+                        self.orders.insert(order_id.clone(), tracked);
+                    }
+                }
+                SpotEvent::SpotOrderCancelled {
+                    order_id, maker_account_id, pair_id, is_bid, price, amnt, cqty, timestamp, ..
+                }
+                | SpotEvent::SpotOrderExpired {
+                    order_id, maker_account_id, pair_id, is_bid, price, amnt, cqty, timestamp, ..
+                } => {
+                    let order = ReportedOrder {
+                        order_id, account: *maker_account_id, pair_id: *pair_id, is_bid: *is_bid, price: *price,
+                        amnt: *amnt, cqty: *cqty, timestamp: *timestamp,
+                    };
+                    let tracked = self.orders.remove(order_id).unwrap_or_default();
+                    let is_expiry = matches!(event, SpotEvent::SpotOrderExpired { .. });
+                    let (exec_type, ord_status) = if is_expiry { ("C", "C") } else { ("4", "4") };
+                    let mut report = self.report(exec_type, ord_status, &order, &tracked, true);
+                    // the first cancel of the commands of a cancel request is the one it asked for
+                    match cancel {
+                        Some(cancel) if !is_expiry && !cancel_reported => {
+                            cancel_reported = true;
+                            let orig_cl_ord_id = tracked.cl_ord_id.or_else(|| cancel.orig_cl_ord_id.clone());
+                            set_field(&mut report, tag::CL_ORD_ID, &cancel.cl_ord_id);
+                            if let Some(orig_cl_ord_id) = &orig_cl_ord_id {
+                                report.push(tag::ORIG_CL_ORD_ID, orig_cl_ord_id);
+                            }
+                            if is_replace {
+                                replaced = Some((orig_cl_ord_id, report));
+                            } else {
+                                reports.push(report);
+                            }
+                        }
+                        _ => reports.push(report),
+                    }
+                }
+                _ => {}
+            }
+        }
+        // the new order of a replace was not placed, the original order is reported cancelled
+        if let Some((_, report)) = replaced {
+            reports.push(report);
+        }
+        for report in &mut reports {
+            report.fields.sort_by_key(|(tag, _)| report_position(*tag));
+        }
+        reports
+    }
+
+    /// Renders the rejection of a request by the engine or the gateway, with the reason as Text (58).
+    /// - a NewOrderSingle is rejected by a Rejected ExecutionReport.
+    /// - a cancel or replace is rejected by an OrderCancelReject, when its cancel failed. A replace whose
+    ///   new order failed after the cancel is reported cancelled by `render`, and its new order is rejected
+    ///   with `FixRequest::NewOrder`.
+    pub fn reject(&mut self, request: &FixRequest, text: &str) -> FixMessage {
+        match request {
+            FixRequest::NewOrder(order) => {
+                let (account, pair_id, is_bid, timestamp) = match &order.command {
+                    SpotCommand::LimitBuy(o) => (o.owner, o.pair_id, true, o.timestamp),
+                    SpotCommand::LimitSell(o) => (o.owner, o.pair_id, false, o.timestamp),
+                    SpotCommand::MarketBuy(o) => (o.owner, o.pair_id, true, o.timestamp),
+                    SpotCommand::MarketSell(o) => (o.owner, o.pair_id, false, o.timestamp),
+                    _ => (AccountId::default(), PairId::default(), true, 0),
+                };
+                self.exec_id += 1;
+                FixMessage::new(msg_type::EXECUTION_REPORT)
+                    .with(tag::ORDER_ID, "NONE")
+                    .with(tag::CL_ORD_ID, &order.cl_ord_id)
+                    .with(tag::EXEC_ID, self.exec_id)
+                    .with(tag::EXEC_TYPE, "8")
+                    .with(tag::ORD_STATUS, "8")
+                    .with(tag::ACCOUNT, String::from_utf8_lossy(account.as_slice()))
+                    .with(tag::SYMBOL, String::from_utf8_lossy(pair_id.as_slice()))
+                    .with(tag::SIDE, if is_bid { "1" } else { "2" })
+                    .with(tag::LEAVES_QTY, 0)
+                    .with(tag::CUM_QTY, 0)
+                    .with(tag::AVG_PX, 0)
+                    .with(tag::TRANSACT_TIME, format_utc_timestamp(timestamp))
+                    // other
+                    .with(tag::ORD_REJ_REASON, 99)
+                    .with(tag::TEXT, text)
+            }
+            FixRequest::Cancel(cancel) | FixRequest::Replace { cancel, .. } => {
+                let (order_id, account) = match &cancel.command {
+                    SpotCommand::CancelOrder { order: OrderRef::Id(id), owner, .. } => (id.to_string(), *owner),
+                    SpotCommand::CancelOrder { owner, .. } => ("NONE".to_string(), *owner),
+                    _ => ("NONE".to_string(), AccountId::default()),
+                };
+                let response_to = if matches!(request, FixRequest::Cancel(_)) { 1 } else { 2 };
+                FixMessage::new(msg_type::ORDER_CANCEL_REJECT)
+                    .with(tag::ORDER_ID, order_id)
+                    .with(tag::CL_ORD_ID, &cancel.cl_ord_id)
+                    .with(tag::ORIG_CL_ORD_ID, cancel.orig_cl_ord_id.as_deref().unwrap_or("NONE"))
+                    .with(tag::ORD_STATUS, "8")
+                    .with(tag::ACCOUNT, String::from_utf8_lossy(account.as_slice()))
+                    .with(tag::CXL_REJ_RESPONSE_TO, response_to)
+                    // other
+                    .with(tag::CXL_REJ_REASON, 99)
+                    .with(tag::TEXT, text)
+            }
+        }
+    }
+
+    fn report(&mut self, exec_type: &str, ord_status: &str, order: &ReportedOrder, tracked: &TrackedOrder, is_done: bool) -> FixMessage {
+        self.exec_id += 1;
+        let mut report = FixMessage::new(msg_type::EXECUTION_REPORT);
+        report.push(tag::ORDER_ID, order_id_string(order.order_id));
+        if let Some(cl_ord_id) = &tracked.cl_ord_id {
+            report.push(tag::CL_ORD_ID, cl_ord_id);
+        }
+        report
+            .push(tag::EXEC_ID, self.exec_id)
+            .push(tag::EXEC_TYPE, exec_type)
+            .push(tag::ORD_STATUS, ord_status)
+            .push(tag::ACCOUNT, String::from_utf8_lossy(order.account.as_slice()));
+        if let Some(pair_id) = order.pair_id {
+            report.push(tag::SYMBOL, String::from_utf8_lossy(pair_id.as_slice()));
+        }
+        report.push(tag::SIDE, if order.is_bid { "1" } else { "2" });
+        // quantities of buys are in quote, converted to base at the price of the order
+        let base = |amount: u64| {
+            if order.is_bid {
+                Price(order.price).quantity(Notional(amount), Rounding::Floor).map(|qty| qty.0).unwrap_or_default()
+            } else {
+                amount
+            }
+        };
+        match tracked.cash_order_qty {
+            Some(cash) => report.push(tag::CASH_ORDER_QTY, format_decimal(cash)),
+            None => report.push(tag::ORDER_QTY, format_decimal(tracked.order_qty.unwrap_or_else(|| base(order.amnt)))),
+        };
+        // orders placed before the reporter only have the quantities executed since, their LeavesQty is the book's
+        let leaves_qty = match tracked.order_qty.filter(|_| tracked.cash_order_qty.is_none()) {
+            _ if is_done => 0,
+            Some(order_qty) => order_qty.saturating_sub(tracked.cum_qty),
+            None => base(order.cqty),
+        };
+        report.push(tag::ORD_TYPE, if tracked.is_market { "1" } else { "2" });
+        if !tracked.is_market {
+            report.push(tag::PRICE, format_decimal(order.price));
+        }
+        let avg_px = mul_div(tracked.cum_quote, SCALE_8, tracked.cum_qty, Rounding::Floor).unwrap_or_default();
+        report
+            .push(tag::LEAVES_QTY, format_decimal(leaves_qty))
+            .push(tag::CUM_QTY, format_decimal(tracked.cum_qty))
+            .push(tag::AVG_PX, format_decimal(avg_px))
+            .push(tag::TRANSACT_TIME, format_utc_timestamp(order.timestamp));
+        report
+    }
+}
+
+/// Position of the fields of an ExecutionReport, in the order of the FIX 4.4 message layout
+fn report_position(tag: u32) -> usize {
+    const LAYOUT: [u32; 21] = [
+        tag::MSG_TYPE, tag::ORDER_ID, tag::CL_ORD_ID, tag::ORIG_CL_ORD_ID, tag::EXEC_ID, tag::EXEC_TYPE,
+        tag::ORD_STATUS, tag::ACCOUNT, tag::SYMBOL, tag::SIDE, tag::ORDER_QTY, tag::CASH_ORDER_QTY, tag::ORD_TYPE,
+        tag::PRICE, tag::LAST_QTY, tag::LAST_PX, tag::LEAVES_QTY, tag::CUM_QTY, tag::AVG_PX, tag::TRANSACT_TIME,
+        tag::TEXT,
+    ];
+    LAYOUT.iter().position(|t| *t == tag).unwrap_or(LAYOUT.len())
+}
+
+fn set_field(message: &mut FixMessage, tag: u32, value: &str) {
+    match message.fields.iter_mut().find(|(t, _)| *t == tag) {
+        Some((_, field)) => *field = value.to_string(),
+        None => {
+            message.push(tag, value);
+        }
+    }
+}
+
+fn order_id_string(order_id: &[u8]) -> String {
+    match <[u8; 16]>::try_from(order_id) {
+        Ok(bytes) => OrderId::from_bytes(bytes).to_string(),
+        Err(_) => order_id.iter().map(|byte| format!("{byte:02x}")).collect(),
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+/// Scale of prices and amounts, 8 decimals
+pub const SCALE_8: u64 = 1_0000_0000;
+
+/// Basis points denominator (10000 = 100%)
+pub const BPS_DENOM: u64 = 10000;
+
+#[derive(Debug, Clone, Copy, thiserror::Error, PartialEq, Eq)]
+pub enum FixedPointError {
+    #[error("fixed point overflow")]
+    Overflow,
+    #[error("division by zero price")]
+    ZeroPrice,
+}
+
+/// Rounding mode of a fixed-point conversion.
+/// - `Floor` is used for what the taker receives.
+/// - `Ceil` is used for what the taker pays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Rounding {
+    Floor,
+    Ceil,
+}
+
+/// Computes `a * b / c` with a u128 intermediate, failing if the result does not fit in u64.
+pub fn mul_div(a: u64, b: u64, c: u64, rounding: Rounding) -> Result<u64, FixedPointError> {
+    if c == 0 {
+        return Err(FixedPointError::ZeroPrice);
+    }
+    let product = a as u128 * b as u128;
+    let result = match rounding {
+        Rounding::Floor => product / c as u128,
+        Rounding::Ceil => product.div_ceil(c as u128),
+    };
+    u64::try_from(result).map_err(|_| FixedPointError::Overflow)
+}
+
+/// Price of one base unit in quote units, 8 decimals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Price(pub u64);
+
+/// Base asset amount, 8 decimals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Quantity(pub u64);
+
+/// Quote asset amount, 8 decimals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Notional(pub u64);
+
+impl Price {
+    pub const MAX: Price = Price(u64::MAX);
+
+    /// Quote value of a base quantity at this price
+    pub fn notional(self, quantity: Quantity, rounding: Rounding) -> Result<Notional, FixedPointError> {
+        mul_div(quantity.0, self.0, SCALE_8, rounding).map(Notional)
+    }
+
+    /// Base quantity a quote amount is worth at this price
+    pub fn quantity(self, notional: Notional, rounding: Rounding) -> Result<Quantity, FixedPointError> {
+        mul_div(notional.0, SCALE_8, self.0, rounding).map(Quantity)
+    }
+
+    /// Price moved up by `bps` basis points
+    pub fn add_bps(self, bps: u64, rounding: Rounding) -> Result<Price, FixedPointError> {
+        let factor = BPS_DENOM.checked_add(bps).ok_or(FixedPointError::Overflow)?;
+        mul_div(self.0, factor, BPS_DENOM, rounding).map(Price)
+    }
+
+    /// Price moved down by `bps` basis points, a move of 100% or more gives zero
+    pub fn sub_bps(self, bps: u64, rounding: Rounding) -> Result<Price, FixedPointError> {
+        mul_div(self.0, BPS_DENOM - bps.min(BPS_DENOM), BPS_DENOM, rounding).map(Price)
+    }
+}
+
+macro_rules! amount_ops {
+    ($name:ident) => {
+        impl $name {
+            pub const ZERO: $name = $name(0);
+
+            pub fn checked_add(self, other: $name) -> Result<$name, FixedPointError> {
+                self.0.checked_add(other.0).map($name).ok_or(FixedPointError::Overflow)
+            }
+
+            pub fn checked_sub(self, other: $name) -> Result<$name, FixedPointError> {
+                self.0.checked_sub(other.0).map($name).ok_or(FixedPointError::Overflow)
+            }
+
+            /// Share of the amount in basis points, e.g. a fee
+            pub fn bps(self, bps: u64, rounding: Rounding) -> Result<$name, FixedPointError> {
+                mul_div(self.0, bps, BPS_DENOM, rounding).map($name)
+            }
+        }
+    };
+}
+
+amount_ops!(Quantity);
+amount_ops!(Notional);
@@ -0,0 +1,276 @@
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::intern::{self, Symbol};
+
+/// Declares a `Copy` id interned in the process-wide table of `spot::intern`.
+/// - ids of distinct kinds do not convert into each other, bytes convert into any of them.
+/// - equality and hashing use the symbol, ordering compares the bytes so it does not depend on interning order.
+/// - ids serialize as the bytes they were interned from, the same as the `serde_bytes` fields they replace.
+/// - in untrusted input, see `intern::untrusted`, ids deserialize as `$untrusted` says.
+macro_rules! interned_id {
+    ($(#[$meta:meta])* $name:ident: $untrusted:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name(Symbol);
+
+        impl $name {
+            /// Returns the id of the bytes, interning them if they are new
+            pub fn new(bytes: &[u8]) -> Self {
+                Self(intern::intern(bytes))
+            }
+
+            /// Returns the id of the bytes if they are interned, without interning them
+            pub fn lookup(bytes: &[u8]) -> Option<Self> {
+                intern::lookup(bytes).map(Self)
+            }
+
+            /// Returns the id of the bytes, interning them if they are new within the bounds of
+            /// `intern::intern_bounded`
+            pub fn new_bounded(bytes: &[u8]) -> Option<Self> {
+                intern::intern_bounded(bytes).map(Self)
+            }
+
+            /// Deserializes the id of a command registering it, interned within the bounds of
+            /// `intern::intern_bounded` in untrusted input
+            pub fn deserialize_registered<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_bytes(SymbolVisitor(Untrusted::Bounded)).map(Self)
+            }
+
+            /// Returns the symbol of the id
+            pub fn symbol(&self) -> Symbol {
+                self.0
+            }
+
+            /// Returns the bytes the id was interned from
+            pub fn as_slice(&self) -> &'static [u8] {
+                intern::resolve(self.0)
+            }
+
+            pub fn to_vec(&self) -> Vec<u8> {
+                self.as_slice().to_vec()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.as_slice().is_empty()
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new(&[])
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_tuple(stringify!($name)).field(&self.as_slice()).finish()
+            }
+        }
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                if self.0 == other.0 {
+                    return Ordering::Equal;
+                }
+                self.as_slice().cmp(other.as_slice())
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                self.as_slice()
+            }
+        }
+
+        impl From<&$name> for $name {
+            fn from(id: &$name) -> Self {
+                *id
+            }
+        }
+
+        impl From<&[u8]> for $name {
+            fn from(bytes: &[u8]) -> Self {
+                Self::new(bytes)
+            }
+        }
+
+        impl From<Vec<u8>> for $name {
+            fn from(bytes: Vec<u8>) -> Self {
+                Self::new(&bytes)
+            }
+        }
+
+        impl From<&Vec<u8>> for $name {
+            fn from(bytes: &Vec<u8>) -> Self {
+                Self::new(bytes)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(s: &str) -> Self {
+                Self::new(s.as_bytes())
+            }
+        }
+
+        impl<const N: usize> From<[u8; N]> for $name {
+            fn from(bytes: [u8; N]) -> Self {
+                Self::new(&bytes)
+            }
+        }
+
+        impl<const N: usize> From<&[u8; N]> for $name {
+            fn from(bytes: &[u8; N]) -> Self {
+                Self::new(bytes)
+            }
+        }
+
+        impl From<$name> for Vec<u8> {
+            fn from(id: $name) -> Self {
+                id.to_vec()
+            }
+        }
+
+        impl PartialEq<[u8]> for $name {
+            fn eq(&self, other: &[u8]) -> bool {
+                self.as_slice() == other
+            }
+        }
+
+        impl PartialEq<&[u8]> for $name {
+            fn eq(&self, other: &&[u8]) -> bool {
+                self.as_slice() == *other
+            }
+        }
+
+        impl PartialEq<Vec<u8>> for $name {
+            fn eq(&self, other: &Vec<u8>) -> bool {
+                self.as_slice() == other.as_slice()
+            }
+        }
+
+        impl<const N: usize> PartialEq<[u8; N]> for $name {
+            fn eq(&self, other: &[u8; N]) -> bool {
+                self.as_slice() == other
+            }
+        }
+
+        impl<const N: usize> PartialEq<&[u8; N]> for $name {
+            fn eq(&self, other: &&[u8; N]) -> bool {
+                self.as_slice() == *other
+            }
+        }
+
+        impl PartialEq<$name> for Vec<u8> {
+            fn eq(&self, other: &$name) -> bool {
+                self.as_slice() == other.as_slice()
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(self.as_slice())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_bytes(SymbolVisitor(Untrusted::$untrusted)).map(Self)
+            }
+        }
+
+        /// Ids are the bytes they were interned from, an array of numbers in JSON
+        #[cfg(feature = "schema")]
+        impl schemars::JsonSchema for $name {
+            fn schema_name() -> std::borrow::Cow<'static, str> {
+                stringify!($name).into()
+            }
+
+            fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+                schemars::json_schema!({
+                    "description": concat!("bytes of the ", stringify!($name)),
+                    "type": "array",
+                    "items": { "type": "integer", "format": "uint8", "minimum": 0, "maximum": 255 }
+                })
+            }
+        }
+    };
+}
+
+interned_id! {
+    /// Id of a trading pair.
+    PairId: Lookup
+}
+
+interned_id! {
+    /// Id of an asset traded in a pair.
+    AssetId: Lookup
+}
+
+interned_id! {
+    /// Id of an account owning orders and balances.
+    AccountId: Bounded
+}
+
+interned_id! {
+    /// Id of an exchange client sharing an orderbook.
+    ClientId: Lookup
+}
+
+/// How the ids of a kind deserialize from untrusted input, see `intern::untrusted`
+#[derive(Debug, Clone, Copy)]
+enum Untrusted {
+    /// only interned ids deserialize, except in snapshots where they are `Bounded`
+    Lookup,
+    /// new ids are interned within the bounds of `intern::intern_bounded`
+    Bounded,
+}
+
+/// Interns the bytes of an id, accepting them as bytes, a string or a sequence of bytes.
+struct SymbolVisitor(Untrusted);
+
+impl SymbolVisitor {
+    fn symbol<E: de::Error>(self, bytes: &[u8]) -> Result<Symbol, E> {
+        if !intern::is_untrusted() {
+            return Ok(intern::intern(bytes));
+        }
+        match self.0 {
+            Untrusted::Lookup if !intern::is_untrusted_snapshot() => intern::lookup(bytes)
+                .ok_or_else(|| E::custom(format_args!("unknown id {:?}", String::from_utf8_lossy(bytes)))),
+            Untrusted::Lookup | Untrusted::Bounded => intern::intern_bounded(bytes)
+                .ok_or_else(|| E::custom(format_args!("id of {} bytes exceeds the interning bounds", bytes.len()))),
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for SymbolVisitor {
+    type Value = Symbol;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("id bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Symbol, E> {
+        self.symbol(v)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Symbol, E> {
+        self.symbol(v.as_bytes())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Symbol, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        self.symbol(&bytes)
+    }
+}
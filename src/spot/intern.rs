@@ -0,0 +1,224 @@
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Compact id of a byte string interned in an `Interner`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Symbol(pub u32);
+
+/// Table of interned byte strings.
+/// - every distinct string gets the next `Symbol` and keeps it for the lifetime of the process,
+///   the bytes are leaked so they can be resolved without holding the table.
+/// - interning a known string does not allocate.
+/// - the table is unbounded, ids of untrusted input go through `intern_bounded` or `lookup`, see `untrusted`.
+/// - the process-wide table also publishes the bytes of its symbols to `resolve` them without its lock.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Interner {
+    /// Mapping bytes -> symbol
+    pub symbols: HashMap<&'static [u8], Symbol>,
+    /// Bytes of each symbol, indexed by the symbol
+    pub bytes: Vec<&'static [u8]>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the symbol of the bytes, interning them if they are new
+    pub fn intern(&mut self, bytes: &[u8]) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(bytes) {
+            return symbol;
+        }
+        let symbol = Symbol(u32::try_from(self.bytes.len()).expect("fewer than 2^32 interned strings"));
+        let bytes: &'static [u8] = Box::leak(bytes.to_vec().into_boxed_slice());
+        self.bytes.push(bytes);
+        self.symbols.insert(bytes, symbol);
+        symbol
+    }
+
+    /// Returns the symbol of the bytes if they are interned
+    pub fn get(&self, bytes: &[u8]) -> Option<Symbol> {
+        self.symbols.get(bytes).copied()
+    }
+
+    /// Returns the bytes of a symbol of this table
+    pub fn resolve(&self, symbol: Symbol) -> &'static [u8] {
+        self.bytes[symbol.0 as usize]
+    }
+
+    /// Returns the number of interned strings
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+/// Longest string `intern_bounded` interns
+pub const BOUNDED_LEN: usize = 128;
+
+/// New strings the untrusted input of a source may intern by default, see `InternBudget`
+pub const DEFAULT_INTERN_BUDGET: usize = 1 << 16;
+
+/// Number of new strings the untrusted input of one source, as a gateway session, may add to the process-wide
+/// table, see `untrusted`. Interned bytes are leaked, the budget caps what one source leaks, and a source past
+/// its budget has its input with new ids rejected without affecting the other sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InternBudget {
+    remaining: usize,
+}
+
+impl InternBudget {
+    pub const fn new(strings: usize) -> Self {
+        Self { remaining: strings }
+    }
+
+    /// Returns the number of new strings the source may still intern
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl Default for InternBudget {
+    fn default() -> Self {
+        Self::new(DEFAULT_INTERN_BUDGET)
+    }
+}
+
+/// Untrusted input decoded by a thread
+#[derive(Debug, Clone, Copy)]
+struct Scope {
+    budget: InternBudget,
+    /// whether the input is a snapshot, see `untrusted_snapshot`
+    snapshot: bool,
+}
+
+static TABLE: OnceCell<RwLock<Interner>> = OnceCell::new();
+
+/// Bytes of the process-wide symbols, symbol `i` in segment `ilog2(i + 1)` of `2^segment` slots.
+/// Slots are filled under the write lock of the table before their symbol is returned, and never change.
+static RESOLVED: [OnceCell<Segment>; 33] = [const { OnceCell::new() }; 33];
+
+/// Slots of a segment of `RESOLVED`
+type Segment = Box<[OnceCell<&'static [u8]>]>;
+
+thread_local! {
+    // Untrusted input decoded by the current thread, `None` for trusted input, see `untrusted`
+    static UNTRUSTED: Cell<Option<Scope>> = const { Cell::new(None) };
+}
+
+/// Process-wide table behind the ids of `spot::ids`
+fn table() -> &'static RwLock<Interner> {
+    TABLE.get_or_init(|| RwLock::new(Interner::new()))
+}
+
+/// Slot of a process-wide symbol in `RESOLVED`
+fn slot(symbol: Symbol) -> (usize, usize) {
+    let index = u64::from(symbol.0) + 1;
+    let segment = index.ilog2();
+    (segment as usize, (index - (1 << segment)) as usize)
+}
+
+/// Interns the bytes in the locked process-wide table and publishes them to `resolve`
+fn intern_locked(table: &mut Interner, bytes: &[u8]) -> Symbol {
+    let symbol = table.intern(bytes);
+    let (segment, offset) = slot(symbol);
+    let slots = RESOLVED[segment].get_or_init(|| (0..1usize << segment).map(|_| OnceCell::new()).collect());
+    let _ = slots[offset].set(table.resolve(symbol));
+    symbol
+}
+
+/// Returns the process-wide symbol of the bytes, interning them if they are new
+pub fn intern(bytes: &[u8]) -> Symbol {
+    if let Some(symbol) = lookup(bytes) {
+        return symbol;
+    }
+    intern_locked(&mut table().write().unwrap_or_else(|e| e.into_inner()), bytes)
+}
+
+/// Returns the process-wide symbol of the bytes if they are interned
+pub fn lookup(bytes: &[u8]) -> Option<Symbol> {
+    table().read().unwrap_or_else(|e| e.into_inner()).get(bytes)
+}
+
+/// Returns the bytes of a process-wide symbol, without locking the table
+pub fn resolve(symbol: Symbol) -> &'static [u8] {
+    let (segment, offset) = slot(symbol);
+    RESOLVED[segment]
+        .get()
+        .and_then(|slots| slots[offset].get())
+        .expect("symbol of the process-wide table")
+}
+
+/// Returns the process-wide symbol of the bytes, interning them if they are new and at most `BOUNDED_LEN` long.
+/// In `untrusted`, a new string is charged to the budget of the input and is not interned past it.
+pub fn intern_bounded(bytes: &[u8]) -> Option<Symbol> {
+    if let Some(symbol) = lookup(bytes) {
+        return Some(symbol);
+    }
+    if bytes.len() > BOUNDED_LEN {
+        return None;
+    }
+    let mut table = table().write().unwrap_or_else(|e| e.into_inner());
+    if let Some(symbol) = table.get(bytes) {
+        return Some(symbol);
+    }
+    if let Some(mut scope) = UNTRUSTED.with(Cell::get) {
+        if scope.budget.remaining == 0 {
+            return None;
+        }
+        scope.budget.remaining -= 1;
+        UNTRUSTED.with(|untrusted| untrusted.set(Some(scope)));
+    }
+    Some(intern_locked(&mut table, bytes))
+}
+
+/// Runs `f` decoding the ids of untrusted input of the source of `budget`, as a session of the FFI, wasm or FIX
+/// gateways. Ids deserialized by `f` on the calling thread do not grow the table without bound:
+/// - pair, asset and client ids must be interned already, unknown ones fail to deserialize. The commands
+///   registering them, `AddPair` and `AddPairClient`, intern theirs with `intern_bounded`.
+/// - account ids are interned with `intern_bounded`, past its bounds unknown ones fail to deserialize.
+/// - the new strings interned by `f` are charged to `budget`, an input past it fails to deserialize and the
+///   strings it interned before stay charged.
+pub fn untrusted<R>(budget: &mut InternBudget, f: impl FnOnce() -> R) -> R {
+    decode_untrusted(budget, false, f)
+}
+
+/// Runs `f` decoding an untrusted snapshot, as `untrusted` but the pair, asset and client ids the snapshot
+/// restores are interned with `intern_bounded` as account ids, rather than having to be interned already.
+pub fn untrusted_snapshot<R>(budget: &mut InternBudget, f: impl FnOnce() -> R) -> R {
+    decode_untrusted(budget, true, f)
+}
+
+fn decode_untrusted<R>(budget: &mut InternBudget, snapshot: bool, f: impl FnOnce() -> R) -> R {
+    // charges the budget and restores the mode of nested calls, even if `f` unwinds
+    struct Restore<'a> {
+        budget: &'a mut InternBudget,
+        outer: Option<Scope>,
+    }
+    impl Drop for Restore<'_> {
+        fn drop(&mut self) {
+            if let Some(scope) = UNTRUSTED.with(|untrusted| untrusted.replace(self.outer)) {
+                *self.budget = scope.budget;
+            }
+        }
+    }
+    let scope = Scope { budget: *budget, snapshot };
+    let _restore = Restore { outer: UNTRUSTED.with(|untrusted| untrusted.replace(Some(scope))), budget };
+    f()
+}
+
+/// Returns whether the calling thread decodes untrusted input, see `untrusted`
+pub fn is_untrusted() -> bool {
+    UNTRUSTED.with(Cell::get).is_some()
+}
+
+/// Returns whether the calling thread decodes an untrusted snapshot, see `untrusted_snapshot`
+pub fn is_untrusted_snapshot() -> bool {
+    UNTRUSTED.with(Cell::get).is_some_and(|scope| scope.snapshot)
+}
@@ -0,0 +1,265 @@
+use std::collections::{HashMap, HashSet};
+
+use super::event::SpotEvent;
+use super::fixed_point::{Notional, Price, Quantity, Rounding};
+use super::orderbook::OrderBook;
+use super::orders::OrderId;
+
+/// Violation of a book or matching invariant.
+#[derive(Debug, Clone, thiserror::Error, PartialEq, Eq)]
+pub enum InvariantError {
+    #[error("L2 price list of side isBid: {is_bid} is broken at {price}")]
+    PriceListBroken { is_bid: bool, price: u64 },
+    #[error("L3 order list at {price} is broken at order {order_id}")]
+    OrderListBroken { price: u64, order_id: OrderId },
+    #[error("order {order_id} is not reachable from its L3 price level")]
+    OrderUnreachable { order_id: OrderId },
+    #[error("order {order_id} rests at {price} without an L2 level isBid: {is_bid}")]
+    MissingLevel { order_id: OrderId, is_bid: bool, price: u64 },
+    #[error("L2 level {price} isBid: {is_bid} has no order in L3")]
+    EmptyLevel { is_bid: bool, price: u64 },
+    #[error("L2 level {price} isBid: {is_bid} is pqty {l2_pqty} cqty {l2_cqty}, L3 orders sum to pqty {l3_pqty} cqty {l3_cqty}")]
+    LevelMismatch { is_bid: bool, price: u64, l2_pqty: u64, l2_cqty: u64, l3_pqty: u64, l3_cqty: u64 },
+    #[error("fill of {base} base at {price} exchanged {quote} quote")]
+    FillValue { price: u64, base: u64, quote: u64 },
+    #[error("fill fees {base_fee} base {quote_fee} quote exceed the fill of {base} base {quote} quote")]
+    FeeExceedsFill { base: u64, quote: u64, base_fee: u64, quote_fee: u64 },
+    #[error("fill fees {base_fee} base {quote_fee} quote differ from {expected_base_fee} base {expected_quote_fee} quote at maker bps {maker_fee_bps} taker bps {taker_fee_bps}")]
+    FeeMismatch {
+        maker_fee_bps: i16,
+        taker_fee_bps: i16,
+        base_fee: u64,
+        quote_fee: u64,
+        expected_base_fee: u64,
+        expected_quote_fee: u64,
+    },
+    #[error("fill charges order {order_id} at {bps} bps, the order is placed at {order_bps} bps")]
+    FeeBpsMismatch { order_id: OrderId, bps: i16, order_bps: i16 },
+    #[error("order {order_id} went from cqty {before} to {after} on a fill of {amount}")]
+    FillDecrement { order_id: OrderId, before: u64, amount: u64, after: u64 },
+    #[error("fill of an unknown order {order_id}")]
+    UnknownOrder { order_id: OrderId },
+    #[error("events leave order {order_id} at cqty {expected}, the book has {actual:?}")]
+    StateMismatch { order_id: OrderId, expected: u64, actual: Option<u64> },
+}
+
+/// Checks the resting state of an order book.
+/// - the L2 price list of each side holds exactly the prices of its public and current levels.
+/// - every L3 price level is a well formed FIFO list holding every order of the book.
+/// - every L2 level has orders of its side in L3, and every resting order has an L2 level.
+/// - the public and current quantities of every L2 level equal the sums over its L3 orders.
+pub fn check_book(orderbook: &OrderBook) -> Result<(), InvariantError> {
+    let l2 = &orderbook.l2;
+    let l3 = &orderbook.l3;
+
+    // L3 price levels
+    let mut reachable = HashSet::new();
+    for price in l3.prices() {
+        // a level whose head slot is free leaves its orders unreachable
+        let Some(head) = l3.head(price) else { continue };
+        let mut prev = None;
+        let mut current = Some(head);
+        while let Some(id) = current {
+            let broken = InvariantError::OrderListBroken { price, order_id: id };
+            let node = l3.node(id).ok_or(broken.clone())?;
+            let order = l3.get_order(id).map_err(|_| broken.clone())?;
+            if node.prev != prev || order.price != price || !reachable.insert(id) {
+                return Err(broken);
+            }
+            prev = Some(id);
+            current = node.next;
+        }
+        if prev != l3.tail(price) {
+            return Err(InvariantError::OrderListBroken { price, order_id: head });
+        }
+    }
+    if let Some(order) = l3.orders().find(|order| !reachable.contains(&order.id)) {
+        return Err(InvariantError::OrderUnreachable { order_id: order.id });
+    }
+
+    for is_bid in [true, false] {
+        let (prices, public, current) = if is_bid {
+            (&l2.bid_prices, &l2.public_bid_level_map, &l2.current_bid_level_map)
+        } else {
+            (&l2.ask_prices, &l2.public_ask_level_map, &l2.current_ask_level_map)
+        };
+
+        // L2 price list
+        for levels in [public, current] {
+            let unlisted = levels.keys().find(|price| !prices.contains(price));
+            let missing = prices.iter().find(|price| !levels.contains_key(price));
+            if let Some(&price) = unlisted.or(missing) {
+                return Err(InvariantError::PriceListBroken { is_bid, price });
+            }
+        }
+
+        // L2 levels against their L3 orders
+        for &price in prices {
+            let (mut l3_pqty, mut l3_cqty, mut count) = (0u64, 0u64, 0usize);
+            let mut current = l3.head(price);
+            while let Some(id) = current {
+                if let Some(order) = l3.get_order(id).ok().filter(|order| order.is_bid == is_bid) {
+                    l3_pqty = l3_pqty.saturating_add(order.pqty);
+                    l3_cqty = l3_cqty.saturating_add(order.cqty);
+                    count += 1;
+                }
+                current = l3.next(price, id);
+            }
+            if count == 0 {
+                return Err(InvariantError::EmptyLevel { is_bid, price });
+            }
+            let (l2_pqty, l2_cqty) = if is_bid {
+                (l2.public_bid_level(price).unwrap_or(0), l2.current_bid_level(price).unwrap_or(0))
+            } else {
+                (l2.public_ask_level(price).unwrap_or(0), l2.current_ask_level(price).unwrap_or(0))
+            };
+            if (l2_pqty, l2_cqty) != (l3_pqty, l3_cqty) {
+                return Err(InvariantError::LevelMismatch { is_bid, price, l2_pqty, l2_cqty, l3_pqty, l3_cqty });
+            }
+        }
+    }
+
+    for order in l3.orders() {
+        if !l2.price_exists(order.is_bid, order.price) {
+            return Err(InvariantError::MissingLevel { order_id: order.id, is_bid: order.is_bid, price: order.price });
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the fills of one command conserve value, given the book before it, the book after it
+/// and the events it emitted.
+/// - every fill exchanges the maker price times the base amount, rounded either way, or for a limit match the
+///   amount of the taker's side converted at the taker's price, rounded down.
+/// - fees never exceed the amounts they are taken from, and are the bps of the orders rounded down: a taker bid
+///   pays its bps on the quote and the maker's on the base, a taker ask its bps on the base and the maker's on the
+///   quote. Negative bps are rebated and charge no fee.
+/// - every fill that leaves the order open decreases it by its side of the fill, bids by quote and asks by base.
+///   A fully filled order is released whole, as a limit match clears the order it fills up.
+/// - orders the events leave open have the same current quantity in the book.
+pub fn check_fills(before: &OrderBook, after: &OrderBook, events: &[SpotEvent]) -> Result<(), InvariantError> {
+    let mut taker_price = 0;
+    let mut cqtys: HashMap<OrderId, u64> = HashMap::new();
+    let mut fee_bps: HashMap<OrderId, i16> = HashMap::new();
+
+    for event in events {
+        match event {
+            SpotEvent::SpotOrderPlaced { order_id, cqty, fee_bps: bps, .. } => {
+                if let Some(id) = order_id_from_bytes(order_id) {
+                    cqtys.insert(id, *cqty);
+                    fee_bps.insert(id, *bps);
+                }
+            }
+            SpotEvent::SpotOrderCancelled { order_id, .. } | SpotEvent::SpotOrderExpired { order_id, .. } => {
+                if let Some(id) = order_id_from_bytes(order_id) {
+                    cqtys.insert(id, 0);
+                }
+            }
+            SpotEvent::SpotOrderPartiallyFilled {
+                is_taker_event, taker_order_id, maker_order_id, taker_order_is_bid, maker_order_is_bid,
+                price, base_volume, quote_volume, base_fee, quote_fee, maker_fee_bps, taker_fee_bps, cqty, ..
+            }
+            | SpotEvent::SpotOrderFullyFilled {
+                is_taker_event, taker_order_id, maker_order_id, taker_order_is_bid, maker_order_is_bid,
+                price, base_volume, quote_volume, base_fee, quote_fee, maker_fee_bps, taker_fee_bps, cqty, ..
+            } => {
+                let (order_id, is_bid) = if *is_taker_event {
+                    taker_price = *price;
+                    (taker_order_id, *taker_order_is_bid)
+                } else {
+                    // maker events carry the maker price, the taker event before them the taker price
+                    check_fill_value(*price, *base_volume, *quote_volume).or_else(|err| {
+                        check_taker_fill_value(taker_price, *taker_order_is_bid, *base_volume, *quote_volume)
+                            .map_err(|_| err)
+                    })?;
+                    (maker_order_id, *maker_order_is_bid)
+                };
+                if base_fee > base_volume || quote_fee > quote_volume {
+                    return Err(InvariantError::FeeExceedsFill {
+                        base: *base_volume,
+                        quote: *quote_volume,
+                        base_fee: *base_fee,
+                        quote_fee: *quote_fee,
+                    });
+                }
+                for (order_id, bps) in [(maker_order_id, *maker_fee_bps), (taker_order_id, *taker_fee_bps)] {
+                    let Some(id) = order_id_from_bytes(order_id) else { continue };
+                    let order_bps = fee_bps.get(&id).copied().or(before.l3.get_order(id).ok().map(|order| order.fee_bps));
+                    if let Some(order_bps) = order_bps.filter(|order_bps| *order_bps != bps) {
+                        return Err(InvariantError::FeeBpsMismatch { order_id: id, bps, order_bps });
+                    }
+                }
+                let (base_bps, quote_bps) =
+                    if *taker_order_is_bid { (*maker_fee_bps, *taker_fee_bps) } else { (*taker_fee_bps, *maker_fee_bps) };
+                let expected_base_fee = Quantity(*base_volume).bps(base_bps.max(0) as u64, Rounding::Floor).map(|fee| fee.0);
+                let expected_quote_fee = Notional(*quote_volume).bps(quote_bps.max(0) as u64, Rounding::Floor).map(|fee| fee.0);
+                if expected_base_fee != Ok(*base_fee) || expected_quote_fee != Ok(*quote_fee) {
+                    return Err(InvariantError::FeeMismatch {
+                        maker_fee_bps: *maker_fee_bps,
+                        taker_fee_bps: *taker_fee_bps,
+                        base_fee: *base_fee,
+                        quote_fee: *quote_fee,
+                        expected_base_fee: expected_base_fee.unwrap_or(u64::MAX),
+                        expected_quote_fee: expected_quote_fee.unwrap_or(u64::MAX),
+                    });
+                }
+                let Some(id) = order_id_from_bytes(order_id) else { continue };
+                let prev = match cqtys.get(&id) {
+                    Some(prev) => *prev,
+                    None => before.l3.get_order(id).map_err(|_| InvariantError::UnknownOrder { order_id: id })?.cqty,
+                };
+                let amount = if is_bid { *quote_volume } else { *base_volume };
+                let conserved = *cqty == 0 || prev.checked_sub(*cqty) == Some(amount);
+                if !conserved {
+                    return Err(InvariantError::FillDecrement { order_id: id, before: prev, amount, after: *cqty });
+                }
+                cqtys.insert(id, *cqty);
+            }
+            _ => {}
+        }
+    }
+
+    for (&order_id, &expected) in &cqtys {
+        let actual = after.l3.get_order(order_id).ok().map(|order| order.cqty);
+        if actual.unwrap_or(0) != expected {
+            return Err(InvariantError::StateMismatch { order_id, expected, actual });
+        }
+    }
+    Ok(())
+}
+
+/// Checks a command: the fills it emitted against the book before and after it, and the book after it.
+pub fn check_command(before: &OrderBook, after: &OrderBook, events: &[SpotEvent]) -> Result<(), InvariantError> {
+    check_fills(before, after, events)?;
+    check_book(after)
+}
+
+fn check_fill_value(price: u64, base: u64, quote: u64) -> Result<(), InvariantError> {
+    let price_value = Price(price);
+    let floor = price_value.notional(Quantity(base), Rounding::Floor).map(|n| n.0);
+    let ceil = price_value.notional(Quantity(base), Rounding::Ceil).map(|n| n.0);
+    match (floor, ceil) {
+        (Ok(floor), Ok(ceil)) if floor <= quote && quote <= ceil => Ok(()),
+        _ => Err(InvariantError::FillValue { price, base, quote }),
+    }
+}
+
+/// Checks a fill converted at the taker's price: a taker bid receives the base its quote buys, a taker ask
+/// the quote of its base, both rounded down.
+fn check_taker_fill_value(price: u64, taker_is_bid: bool, base: u64, quote: u64) -> Result<(), InvariantError> {
+    let price_value = Price(price);
+    let converted = if taker_is_bid {
+        price_value.quantity(Notional(quote), Rounding::Floor).map(|q| q.0 == base)
+    } else {
+        price_value.notional(Quantity(base), Rounding::Floor).map(|n| n.0 == quote)
+    };
+    match converted {
+        Ok(true) => Ok(()),
+        _ => Err(InvariantError::FillValue { price, base, quote }),
+    }
+}
+
+fn order_id_from_bytes(bytes: &[u8]) -> Option<OrderId> {
+    let bytes: [u8; 16] = bytes.try_into().ok()?;
+    Some(OrderId::from_bytes(bytes))
+}
@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+use super::fixed_point::{Price, Rounding};
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct L1 {
     /// Last match price
     pub lmp: Option<u64>,
@@ -16,8 +19,15 @@ pub struct L1 {
     pub market_buy_slippage_limit: Option<u64>,
     /// Slippage limit for market sell orders in 8 decimals
     pub market_sell_slippage_limit: Option<u64>,
+    /// Protection band of market buy orders in basis points above `lmp`, `None` disables the protection
+    pub market_buy_protection_bps: Option<u64>,
+    /// Protection band of market sell orders in basis points below `lmp`, `None` disables the protection
+    pub market_sell_protection_bps: Option<u64>,
 }
 
+/// Default protection band of market orders, 5% from the reference price
+pub const DEFAULT_MARKET_PROTECTION_BPS: u64 = 500;
+
 #[derive(Debug, thiserror::Error, PartialEq, Eq)]
 pub enum L1Error {
     #[error("price is zero")]
@@ -34,6 +44,8 @@ impl Default for L1 {
             limit_sell_slippage_limit: Some(10000u64),
             market_buy_slippage_limit: Some(10000u64),
             market_sell_slippage_limit: Some(10000u64),
+            market_buy_protection_bps: Some(DEFAULT_MARKET_PROTECTION_BPS),
+            market_sell_protection_bps: Some(DEFAULT_MARKET_PROTECTION_BPS),
         }
     }
 }
@@ -60,6 +72,7 @@ impl L1 {
             limit_sell_slippage_limit,
             market_buy_slippage_limit,
             market_sell_slippage_limit,
+            ..Self::default()
         }
     }
 
@@ -109,6 +122,34 @@ impl L1 {
         self.market_sell_slippage_limit = slippage_limit;
     }
 
+    /// Set the protection band of market buy orders in basis points
+    pub fn set_market_buy_protection_bps(&mut self, protection_bps: Option<u64>) {
+        self.market_buy_protection_bps = protection_bps;
+    }
+
+    /// Set the protection band of market sell orders in basis points
+    pub fn set_market_sell_protection_bps(&mut self, protection_bps: Option<u64>) {
+        self.market_sell_protection_bps = protection_bps;
+    }
+
+    /// Determine the protection price of a market order
+    /// A market order does not match beyond this price, the rest is cancelled as IOC.
+    ///
+    /// - `is_bid`: Whether the market order is buying
+    /// - `best_price`: Best opposite price when the order arrives
+    /// - Returns `None` when no market protection band is set, otherwise the reference price moved by
+    ///   the band in basis points (10000 = 100%). The reference is `lmp`, or `best_price` before the first match.
+    pub fn det_market_protection_price(&self, is_bid: bool, best_price: u64) -> Option<u64> {
+        let reference = Price(self.lmp().unwrap_or(best_price));
+        if is_bid {
+            let limit = self.market_buy_protection_bps?;
+            Some(reference.add_bps(limit, Rounding::Floor).unwrap_or(Price::MAX).0)
+        } else {
+            let limit = self.market_sell_protection_bps?;
+            Some(reference.sub_bps(limit, Rounding::Ceil).unwrap_or(Price::MAX).0)
+        }
+    }
+
     /// Determine the maker price for a limit sell order
     /// This function calculates the price at which a limit sell order should be placed
     /// as a maker order based on current market conditions, limit price, and spread.
@@ -126,14 +167,13 @@ impl L1 {
         ask_head: u64,
         spread: u32,
     ) -> u64 {
-        const DENOM: u64 = 10000; // Basis points denominator (10000 = 100%)
         let lmp = self.lmp().unwrap_or(0);
 
         if ask_head == 0 && bid_head == 0 {
             // No orders in orderbook
             if lmp != 0 {
                 // Use lmp with spread (subtract spread for sell)
-                let down = ((lmp as u128 * (DENOM - spread as u64) as u128) / DENOM as u128) as u64;
+                let down = spread_down(lmp, spread);
                 let price = if lp <= down { down } else { lp };
                 return price;
             }
@@ -142,31 +182,31 @@ impl L1 {
         } else if ask_head == 0 && bid_head != 0 {
             // Only bids exist
             if lmp != 0 {
-                let mut down = ((lmp as u128 * (DENOM - spread as u64) as u128) / DENOM as u128) as u64;
+                let mut down = spread_down(lmp, spread);
                 down = if lp <= down { down } else { lp };
                 let price = if down <= bid_head { bid_head } else { down };
                 return price;
             }
             // No lmp, use bidHead with spread
-            let mut down = ((bid_head as u128 * (DENOM - spread as u64) as u128) / DENOM as u128) as u64;
+            let mut down = spread_down(bid_head, spread);
             down = if lp <= down { down } else { lp };
             let price = if down <= bid_head { bid_head } else { down };
             return price;
         } else if ask_head != 0 && bid_head == 0 {
             // Only asks exist
             if lmp != 0 {
-                let down = ((lmp as u128 * (DENOM - spread as u64) as u128) / DENOM as u128) as u64;
+                let down = spread_down(lmp, spread);
                 let price = if lp <= down { down } else { lp };
                 return price;
             }
             // No lmp, use askHead with spread
-            let down = ((ask_head as u128 * (DENOM - spread as u64) as u128) / DENOM as u128) as u64;
+            let down = spread_down(ask_head, spread);
             let price = if lp <= down { down } else { lp };
             return price;
         } else {
             // Both bids and asks exist
             if lmp != 0 {
-                let down = ((lmp as u128 * (DENOM - spread as u64) as u128) / DENOM as u128) as u64;
+                let down = spread_down(lmp, spread);
                 let price = if lp <= down { down } else { lp };
                 return price;
             }
@@ -194,14 +234,13 @@ impl L1 {
         ask_head: u64,
         spread: u32,
     ) -> u64 {
-        const DENOM: u64 = 10000; // Basis points denominator (10000 = 100%)
         let lmp = self.lmp().unwrap_or(0);
 
         if ask_head == 0 && bid_head == 0 {
             // No orders in orderbook
             if lmp != 0 {
                 // Use lmp with spread (add spread for buy)
-                let up = ((lmp as u128 * (DENOM + spread as u64) as u128) / DENOM as u128) as u64;
+                let up = spread_up(lmp, spread);
                 let price = if lp >= up { up } else { lp };
                 return price;
             }
@@ -211,31 +250,31 @@ impl L1 {
             // Only bids exist
             if lmp != 0 {
                 // Use lmp with spread (add spread for buy)
-                let up = ((lmp as u128 * (DENOM + spread as u64) as u128) / DENOM as u128) as u64;
+                let up = spread_up(lmp, spread);
                 let price = if lp >= up { up } else { lp };
                 return price;
             }
             // No lmp, use bidHead with spread
-            let up = ((bid_head as u128 * (DENOM + spread as u64) as u128) / DENOM as u128) as u64;
+            let up = spread_up(bid_head, spread);
             let price = if lp >= up { up } else { lp };
             return price;
         } else if ask_head != 0 && bid_head == 0 {
             // Only asks exist
             if lmp != 0 {
-                let mut up = ((lmp as u128 * (DENOM + spread as u64) as u128) / DENOM as u128) as u64;
+                let mut up = spread_up(lmp, spread);
                 up = if lp >= up { up } else { lp };
                 let price = if up >= ask_head { ask_head } else { up };
                 return price;
             }
             // No lmp, use askHead with spread
-            let mut up = ((ask_head as u128 * (DENOM + spread as u64) as u128) / DENOM as u128) as u64;
+            let mut up = spread_up(ask_head, spread);
             up = if lp >= up { up } else { lp };
             let price = if up >= ask_head { ask_head } else { up };
             return price;
         } else {
             // Both bids and asks exist
             if lmp != 0 {
-                let mut up = ((lmp as u128 * (DENOM + spread as u64) as u128) / DENOM as u128) as u64;
+                let mut up = spread_up(lmp, spread);
                 up = if lp >= up { up } else { lp };
                 let price = if up >= ask_head { ask_head } else { up };
                 return price;
@@ -261,7 +300,6 @@ impl L1 {
         ask_head: u64,
         spread: u32,
     ) -> u64 {
-        const DENOM: u64 = 10000; // Basis points denominator (10000 = 100%)
         let lmp = self.lmp().unwrap_or(0);
 
         if ask_head == 0 && bid_head == 0 {
@@ -269,7 +307,7 @@ impl L1 {
             // lmp must exist unless there has been no order in orderbook
             if lmp != 0 {
                 // Use lmp with spread (add spread for buy)
-                let up = ((lmp as u128 * (DENOM + spread as u64) as u128) / DENOM as u128) as u64;
+                let up = spread_up(lmp, spread);
                 return up;
             }
             // No lmp, return 0 (should not happen in practice)
@@ -278,16 +316,16 @@ impl L1 {
             // Only bids exist
             if lmp != 0 {
                 let temp = if bid_head >= lmp { bid_head } else { lmp };
-                let up = ((temp as u128 * (DENOM + spread as u64) as u128) / DENOM as u128) as u64;
+                let up = spread_up(temp, spread);
                 return up;
             }
             // No lmp, use bid_head with spread
-            let up = ((bid_head as u128 * (DENOM + spread as u64) as u128) / DENOM as u128) as u64;
+            let up = spread_up(bid_head, spread);
             return up;
         } else if ask_head != 0 && bid_head == 0 {
             // Only asks exist
             if lmp != 0 {
-                let up = ((lmp as u128 * (DENOM + spread as u64) as u128) / DENOM as u128) as u64;
+                let up = spread_up(lmp, spread);
                 let price = if ask_head >= up { up } else { ask_head };
                 return price;
             }
@@ -297,7 +335,7 @@ impl L1 {
             // Both bids and asks exist
             if lmp != 0 {
                 let temp = if bid_head >= lmp { bid_head } else { lmp };
-                let up = ((temp as u128 * (DENOM + spread as u64) as u128) / DENOM as u128) as u64;
+                let up = spread_up(temp, spread);
                 let price = if ask_head >= up { up } else { ask_head };
                 return price;
             }
@@ -321,7 +359,6 @@ impl L1 {
         ask_head: u64,
         spread: u32,
     ) -> u64 {
-        const DENOM: u64 = 10000; // Basis points denominator (10000 = 100%)
         let lmp = self.lmp().unwrap_or(0);
 
         if ask_head == 0 && bid_head == 0 {
@@ -329,7 +366,7 @@ impl L1 {
             // lmp must exist unless there has been no order in orderbook
             if lmp != 0 {
                 // Use lmp with spread (subtract spread for sell)
-                let down = ((lmp as u128 * (DENOM - spread as u64) as u128) / DENOM as u128) as u64;
+                let down = spread_down(lmp, spread);
                 // Ensure price is never 0
                 let price = if down == 0 { 1 } else { down };
                 return price;
@@ -339,7 +376,7 @@ impl L1 {
         } else if ask_head == 0 && bid_head != 0 {
             // Only bids exist
             if lmp != 0 {
-                let mut down = ((lmp as u128 * (DENOM - spread as u64) as u128) / DENOM as u128) as u64;
+                let mut down = spread_down(lmp, spread);
                 down = if down <= bid_head { bid_head } else { down };
                 // Ensure price is never 0
                 let price = if down == 0 { 1 } else { down };
@@ -351,13 +388,13 @@ impl L1 {
             // Only asks exist
             if lmp != 0 {
                 let temp = if lmp <= ask_head { lmp } else { ask_head };
-                let down = ((temp as u128 * (DENOM - spread as u64) as u128) / DENOM as u128) as u64;
+                let down = spread_down(temp, spread);
                 // Ensure price is never 0
                 let price = if down == 0 { 1 } else { down };
                 return price;
             }
             // No lmp, use ask_head with spread
-            let down = ((ask_head as u128 * (DENOM - spread as u64) as u128) / DENOM as u128) as u64;
+            let down = spread_down(ask_head, spread);
             // Ensure price is never 0
             let price = if down == 0 { 1 } else { down };
             return price;
@@ -365,7 +402,7 @@ impl L1 {
             // Both bids and asks exist
             if lmp != 0 {
                 let temp = if lmp <= ask_head { lmp } else { ask_head };
-                let mut down = ((temp as u128 * (DENOM - spread as u64) as u128) / DENOM as u128) as u64;
+                let mut down = spread_down(temp, spread);
                 down = if down <= bid_head { bid_head } else { down };
                 // Ensure price is never 0
                 let price = if down == 0 { 1 } else { down };
@@ -375,6 +412,14 @@ impl L1 {
             return bid_head;
         }
     }
+}
+
+/// Price moved up by `spread` basis points, rounded down and clamped to `u64::MAX` on overflow
+fn spread_up(price: u64, spread: u32) -> u64 {
+    Price(price).add_bps(spread as u64, Rounding::Floor).unwrap_or(Price::MAX).0
+}
 
-    
+/// Price moved down by `spread` basis points, rounded down and floored at zero for a spread of 100% or more
+fn spread_down(price: u64, spread: u32) -> u64 {
+    Price(price).sub_bps(spread as u64, Rounding::Floor).unwrap_or(Price::MAX).0
 }
@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::ids::{AssetId, PairId};
+use super::pair::Pair;
+use super::prices::Level;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum MarketDataError {
+    #[error("pair does not exist")]
+    PairNotFound,
+    #[error("scale is not configured on the pair: {0}")]
+    ScaleNotConfigured(u64),
+    #[error("snapshot is for another pair or scale")]
+    SnapshotMismatch,
+    #[error("replica is not synced to a snapshot")]
+    NotSynced,
+    #[error("sequence gap in depth feed: expected {expected}, got {got}")]
+    SequenceGap { expected: u64, got: u64 },
+}
+
+/// Full L2 depth of a pair in one scale.
+/// - `seq` is the sequence number of the last delta reflected in the snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct L2Snapshot {
+    /// pair id
+    pub pair_id: PairId,
+    /// base asset id
+    pub base_asset_id: AssetId,
+    /// quote asset id
+    pub quote_asset_id: AssetId,
+    /// price scale in 8 decimals
+    pub scale: u64,
+    /// sequence number
+    pub seq: u64,
+    /// bid levels sorted by price descending
+    pub bids: Vec<Level>,
+    /// ask levels sorted by price ascending
+    pub asks: Vec<Level>,
+}
+
+/// Change of a single scaled level in the depth feed.
+/// - `pqty` and `cqty` are the new quantities of the level, a level with both at zero is removed.
+/// - `seq` increases by one for every delta of the pair and scale.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct L2Delta {
+    /// pair id
+    pub pair_id: PairId,
+    /// base asset id
+    pub base_asset_id: AssetId,
+    /// quote asset id
+    pub quote_asset_id: AssetId,
+    /// price scale in 8 decimals
+    pub scale: u64,
+    /// sequence number
+    pub seq: u64,
+    /// is bid level
+    pub is_bid: bool,
+    /// bucket price in 8 decimals
+    pub price: u64,
+    /// public quantity in 8 decimals
+    pub pqty: u64,
+    /// current quantity in 8 decimals
+    pub cqty: u64,
+}
+
+/// Takes a snapshot of a pair's depth in one scale and opens the depth feed of the scale.
+/// Deltas with a sequence number above the snapshot's must be applied on top of it.
+pub fn snapshot(pair: &mut Pair, scale: u64) -> Result<L2Snapshot, MarketDataError> {
+    let l2 = &mut pair.orderbook.l2;
+    if !l2.scales.contains(&scale) {
+        return Err(MarketDataError::ScaleNotConfigured(scale));
+    }
+    let seq = *l2.depth_seqs.entry(scale).or_insert(0);
+    Ok(L2Snapshot {
+        pair_id: pair.pair_id,
+        base_asset_id: pair.base_asset_id,
+        quote_asset_id: pair.quote_asset_id,
+        scale,
+        seq,
+        bids: l2.bid_level_list.get(&scale).cloned().unwrap_or_default(),
+        asks: l2.ask_level_list.get(&scale).cloned().unwrap_or_default(),
+    })
+}
+
+/// Publishes the levels changed since the last call as ordered deltas, for every scale with an open feed.
+pub fn deltas(pair: &mut Pair) -> Vec<L2Delta> {
+    let l2 = &mut pair.orderbook.l2;
+    let changed = std::mem::take(&mut l2.changed_levels);
+    let mut deltas = Vec::with_capacity(changed.len());
+    for (scale, is_bid, price) in changed {
+        let seq = match l2.depth_seqs.get_mut(&scale) {
+            Some(seq) => {
+                *seq += 1;
+                *seq
+            }
+            None => continue,
+        };
+        let (pqty, cqty) = l2
+            .scaled_level(is_bid, scale, price)
+            .map(|level| (level.pqty, level.cqty))
+            .unwrap_or((0, 0));
+        deltas.push(L2Delta {
+            pair_id: pair.pair_id,
+            base_asset_id: pair.base_asset_id,
+            quote_asset_id: pair.quote_asset_id,
+            scale,
+            seq,
+            is_bid,
+            price,
+            pqty,
+            cqty,
+        });
+    }
+    deltas
+}
+
+/// Client-side copy of a pair's depth in one scale, rebuilt from a snapshot and deltas.
+/// The replica drops out of sync on a sequence gap and needs a new snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct L2Replica {
+    /// pair id
+    pub pair_id: PairId,
+    /// price scale in 8 decimals
+    pub scale: u64,
+    /// sequence number of the last applied delta, `None` when out of sync
+    seq: Option<u64>,
+    bids: BTreeMap<u64, Level>,
+    asks: BTreeMap<u64, Level>,
+}
+
+impl L2Replica {
+    pub fn new(pair_id: impl Into<PairId>, scale: u64) -> Self {
+        Self {
+            pair_id: pair_id.into(),
+            scale,
+            seq: None,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+        }
+    }
+
+    pub fn seq(&self) -> Option<u64> {
+        self.seq
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.seq.is_some()
+    }
+
+    /// Replaces the replica's levels with a snapshot of the same pair and scale.
+    pub fn apply_snapshot(&mut self, snapshot: &L2Snapshot) -> Result<(), MarketDataError> {
+        if snapshot.pair_id != self.pair_id || snapshot.scale != self.scale {
+            return Err(MarketDataError::SnapshotMismatch);
+        }
+        self.bids = snapshot.bids.iter().map(|level| (level.price, level.clone())).collect();
+        self.asks = snapshot.asks.iter().map(|level| (level.price, level.clone())).collect();
+        self.seq = Some(snapshot.seq);
+        Ok(())
+    }
+
+    /// Applies a delta of the feed.
+    /// - deltas of other pairs or scales and deltas already reflected in the replica are ignored.
+    /// - a gap in the sequence puts the replica out of sync.
+    pub fn apply_delta(&mut self, delta: &L2Delta) -> Result<(), MarketDataError> {
+        if delta.pair_id != self.pair_id || delta.scale != self.scale {
+            return Ok(());
+        }
+        let seq = self.seq.ok_or(MarketDataError::NotSynced)?;
+        if delta.seq <= seq {
+            return Ok(());
+        }
+        if delta.seq != seq + 1 {
+            self.seq = None;
+            return Err(MarketDataError::SequenceGap {
+                expected: seq + 1,
+                got: delta.seq,
+            });
+        }
+        let levels = if delta.is_bid { &mut self.bids } else { &mut self.asks };
+        if delta.pqty == 0 && delta.cqty == 0 {
+            levels.remove(&delta.price);
+        } else {
+            levels.insert(
+                delta.price,
+                Level {
+                    price: delta.price,
+                    pqty: delta.pqty,
+                    cqty: delta.cqty,
+                },
+            );
+        }
+        self.seq = Some(delta.seq);
+        Ok(())
+    }
+
+    /// Bid levels sorted by price descending
+    pub fn bids(&self) -> Vec<Level> {
+        self.bids.values().rev().cloned().collect()
+    }
+
+    /// Ask levels sorted by price ascending
+    pub fn asks(&self) -> Vec<Level> {
+        self.asks.values().cloned().collect()
+    }
+}
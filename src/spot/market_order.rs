@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+
+use super::fixed_point::{FixedPointError, Notional, Price, Quantity, Rounding};
+use super::orderbook::OrderBook;
+use super::orders::OrderId;
+
+/// Size of a market order.
+/// - `Base` is the exact base amount to buy or sell.
+/// - `Quote` is the exact quote amount to spend on a buy or to receive on a sell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MarketOrderSize {
+    Base(u64),
+    Quote(u64),
+}
+
+impl MarketOrderSize {
+    pub fn amount(&self) -> u64 {
+        match self {
+            MarketOrderSize::Base(amount) | MarketOrderSize::Quote(amount) => *amount,
+        }
+    }
+}
+
+/// Outcome of a market order.
+/// - `remaining` is what is left of the order after matching, in the order's own terms
+///   (quote for buys, base for sells). It rests on the book only when `is_resting` is true,
+///   otherwise it is refunded to the owner.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketOrderFill {
+    /// order id of the taker order
+    pub order_id: OrderId,
+    /// base amount exchanged in 8 decimals
+    pub base_filled: u64,
+    /// quote amount exchanged in 8 decimals
+    pub quote_filled: u64,
+    /// amount left after matching in 8 decimals
+    pub remaining: u64,
+    /// whether the remaining amount rests on the book
+    pub is_resting: bool,
+    /// protection price the order stopped at, `None` if it did not reach it
+    pub protection_price: Option<u64>,
+    /// part of the size left unfilled in the size's terms
+    pub unfilled: u64,
+}
+
+/// Fill of a single maker order planned for a market order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PlannedFill {
+    pub maker_id: OrderId,
+    pub price: u64,
+    pub base: u64,
+    pub quote: u64,
+    pub maker_clear: bool,
+}
+
+/// Fills of a market order against the book, best price first.
+/// - `price` is the last price reached, the taker order is placed at it so its unfilled part rests where it is locked.
+/// - `amount` is the amount to lock for the taker order in its own terms.
+/// - `unfilled` is the part of the size the book could not fill.
+/// - `exhausted` is whether matching stopped because the book ran out of orders.
+/// - `protected` is whether matching stopped at the protection price.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct MarketPlan {
+    pub fills: Vec<PlannedFill>,
+    pub price: u64,
+    pub amount: u64,
+    pub unfilled: u64,
+    pub exhausted: bool,
+    pub protected: bool,
+}
+
+/// Plans the fills of a market order against the opposite side of the book without changing it.
+/// - amounts are rounded per fill in favor of the makers, a buyer pays the quote amount rounded up
+///   and a seller receives the quote amount rounded down, so a quote size is never exceeded.
+/// - matching stops when the rest of the size cannot buy or sell a single base unit at the next price.
+/// - matching stops before a level beyond `protection_price`, above it for buys and below it for sells.
+/// - expired makers are skipped.
+pub(crate) fn plan(
+    orderbook: &OrderBook,
+    is_bid: bool,
+    size: MarketOrderSize,
+    protection_price: Option<u64>,
+    now: i64,
+) -> Result<MarketPlan, FixedPointError> {
+    let l2 = &orderbook.l2;
+    let l3 = &orderbook.l3;
+    let mut plan = MarketPlan::default();
+    let mut remaining = size.amount();
+    let head = if is_bid { l2.ask_head() } else { l2.bid_head() };
+    let mut current = head;
+    let mut last_price = head.unwrap_or(0);
+
+    'levels: while let Some(price) = current {
+        if remaining == 0 {
+            break;
+        }
+        if protection_price.is_some_and(|limit| if is_bid { price > limit } else { price < limit }) {
+            plan.protected = true;
+            break;
+        }
+        let mut maker_id = l3.head(price);
+        while let Some(id) = maker_id {
+            if remaining == 0 {
+                break 'levels;
+            }
+            maker_id = l3.next(price, id);
+            let maker = match l3.get_order(id) {
+                Ok(maker) => maker,
+                Err(_) => continue,
+            };
+            // price levels are shared by both sides in L3
+            if maker.is_bid == is_bid || maker.expires_at <= now || maker.cqty == 0 {
+                continue;
+            }
+            last_price = price;
+            let maker_price = Price(price);
+            let fill = if is_bid {
+                // maker is an ask holding base
+                let base = match size {
+                    MarketOrderSize::Base(_) => remaining.min(maker.cqty),
+                    MarketOrderSize::Quote(_) => {
+                        let full = maker_price.notional(Quantity(maker.cqty), Rounding::Ceil)?;
+                        if remaining >= full.0 {
+                            maker.cqty
+                        } else {
+                            maker_price.quantity(Notional(remaining), Rounding::Floor)?.0
+                        }
+                    }
+                };
+                let quote = maker_price.notional(Quantity(base), Rounding::Ceil)?.0;
+                PlannedFill { maker_id: id, price, base, quote, maker_clear: base == maker.cqty }
+            } else {
+                // maker is a bid holding quote
+                let capacity = maker_price.quantity(Notional(maker.cqty), Rounding::Floor)?.0;
+                if capacity == 0 {
+                    // dust bid that cannot buy a single base unit
+                    continue;
+                }
+                let base = match size {
+                    MarketOrderSize::Base(_) => remaining.min(capacity),
+                    MarketOrderSize::Quote(_) => maker_price.quantity(Notional(remaining), Rounding::Floor)?.0.min(capacity),
+                };
+                let quote = maker_price.notional(Quantity(base), Rounding::Floor)?.0;
+                PlannedFill { maker_id: id, price, base, quote, maker_clear: base == capacity }
+            };
+            if fill.base == 0 || fill.quote == 0 {
+                // the rest of the size is too small to trade at this price or worse
+                break 'levels;
+            }
+            remaining -= match size {
+                MarketOrderSize::Base(_) => fill.base,
+                MarketOrderSize::Quote(_) => fill.quote,
+            };
+            let maker_clear = fill.maker_clear;
+            plan.fills.push(fill);
+            if !maker_clear {
+                // a maker left on the book means the size is met or its rest is too small to trade
+                break 'levels;
+            }
+        }
+        current = l2.next_price(!is_bid, price);
+        if current.is_none() {
+            plan.exhausted = remaining > 0;
+        }
+    }
+
+    plan.unfilled = remaining;
+    plan.price = last_price;
+    let base_filled = plan.fills.iter().try_fold(Quantity::ZERO, |sum, fill| sum.checked_add(Quantity(fill.base)))?;
+    let quote_filled = plan.fills.iter().try_fold(Notional::ZERO, |sum, fill| sum.checked_add(Notional(fill.quote)))?;
+    let last_price = Price(last_price);
+    plan.amount = match (is_bid, size) {
+        (true, MarketOrderSize::Quote(amount)) | (false, MarketOrderSize::Base(amount)) => amount,
+        // lock what the fills cost plus the unfilled part at the last price reached
+        (true, MarketOrderSize::Base(_)) if plan.exhausted || plan.protected => {
+            quote_filled.checked_add(last_price.notional(Quantity(remaining), Rounding::Ceil)?)?.0
+        }
+        (true, MarketOrderSize::Base(_)) => quote_filled.0,
+        (false, MarketOrderSize::Quote(_)) if plan.exhausted || plan.protected => {
+            base_filled.checked_add(last_price.quantity(Notional(remaining), Rounding::Ceil)?)?.0
+        }
+        (false, MarketOrderSize::Quote(_)) => base_filled.0,
+    };
+    Ok(plan)
+}
@@ -3,11 +3,18 @@ use std::collections::HashMap;
 
 use crate::spot::event::SpotEvent;
 
+use super::command::{LimitOrder, MarketOrder, SpotCommand};
 use super::event::{self, EventQueue};
+use super::ids::{AccountId, ClientId, PairId};
+#[cfg(feature = "invariants")]
+use super::invariants;
+use super::market_data::{self, L2Delta, L2Snapshot, MarketDataError};
 use super::orderbook::{OrderBook, OrderBookError};
-use super::orders::OrderId;
+use super::orders::{Order, OrderId, OrderRef};
 use super::pair::Pair;
-use super::time_in_force::TimeInForce;
+use super::query::{self, BestBidAsk, Depth, OrderView, OrdersPage, QueryError};
+use super::ticker::Ticker;
+use super::trades::{Candle, CandleInterval};
 
 /// Matching engine managing spot trading pairs and their orderbooks.
 ///
@@ -23,11 +30,11 @@ use super::time_in_force::TimeInForce;
 ///
 /// // By default there are no pairs
 /// assert_eq!(engine.pair_count(), 0);
-/// assert!(!engine.has_pair(&b"BTC-USD".to_vec()));
+/// assert!(!engine.has_pair(b"BTC-USD"));
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MatchingEngine {
-    pairs: HashMap<Vec<u8>, Pair>,
+    pairs: HashMap<PairId, Pair>,
     total_pairs: u32,
 }
 
@@ -40,17 +47,22 @@ impl MatchingEngine {
         }
     }
 
-    pub fn add_pair(&mut self, cid: impl Into<Vec<u8>>, client_admin_account_id: impl Into<Vec<u8>>, client_fee_account_id: impl Into<Vec<u8>>, pair_id: impl Into<Vec<u8>>, timestamp: i64) {
+    /// Creates an exchange of restored pairs, see `spot::snapshot`
+    pub(crate) fn from_pairs(pairs: HashMap<PairId, Pair>, total_pairs: u32) -> Self {
+        Self { pairs, total_pairs }
+    }
+
+    pub fn add_pair(&mut self, cid: impl Into<ClientId>, client_admin_account_id: impl Into<AccountId>, client_fee_account_id: impl Into<AccountId>, pair_id: impl Into<PairId>, timestamp: i64) {
         // check if the pair already exists
-        let pair_id_vec = pair_id.into();
-        if self.pairs.contains_key(&pair_id_vec) {
+        let pair_id = pair_id.into();
+        if self.pairs.contains_key(&pair_id) {
             // add the client to the pair
-            let cid_vec = cid.into();
-            self.pairs.get_mut(&pair_id_vec).unwrap().add_client(cid_vec.clone(), client_admin_account_id, client_fee_account_id);
+            let cid = cid.into();
+            self.pairs.get_mut(&pair_id).unwrap().add_client(cid, client_admin_account_id, client_fee_account_id, timestamp);
             // emit the event
             event::emit_event(SpotEvent::SpotPairAdded {
-                cid: cid_vec,
-                pair_id: pair_id_vec,
+                cid,
+                pair_id,
                 timestamp: timestamp,
             });
             return;
@@ -58,244 +70,284 @@ impl MatchingEngine {
 
         // create the pair
         let mut pair = Pair::new();
-        pair.pair_id = pair_id_vec.clone();
-        let cid_vec = cid.into();
-        pair.add_client(cid_vec.clone(), client_admin_account_id, client_fee_account_id);
+        pair.pair_id = pair_id;
+        let cid = cid.into();
+        pair.add_client(cid, client_admin_account_id, client_fee_account_id, timestamp);
         // emit the event
         event::emit_event(SpotEvent::SpotPairAdded {
-            cid: cid_vec,
-            pair_id: pair_id_vec.clone(),
+            cid,
+            pair_id,
             timestamp: timestamp,
         });
-        self.pairs.insert(pair_id_vec, pair);
+        self.pairs.insert(pair_id, pair);
         self.total_pairs += 1;
     }
 
     pub fn add_pair_client(
         &mut self,
-        cid: impl Into<Vec<u8>>,
-        pair_id: impl Into<Vec<u8>>,
-        admin_account_id: impl Into<Vec<u8>>,
-        fee_account_id: impl Into<Vec<u8>>,
+        cid: impl Into<ClientId>,
+        pair_id: impl Into<PairId>,
+        admin_account_id: impl Into<AccountId>,
+        fee_account_id: impl Into<AccountId>,
+        timestamp: i64,
     ) -> Result<EventQueue, OrderBookError> {
-        let pair_id_vec = pair_id.into();
-        let pair = self.pairs.get_mut(&pair_id_vec).unwrap();
-        pair.add_client(cid.into(), admin_account_id, fee_account_id);
+        let pair_id = pair_id.into();
+        let pair = self.pairs.get_mut(&pair_id).unwrap();
+        pair.add_client(cid.into(), admin_account_id, fee_account_id, timestamp);
         Ok(event::drain_events())
     }
 
     /// Place a limit sell order (ask order)
     /// Matches against existing orders first, then places remaining in orderbook based on time_in_force
     ///
-    /// Returns `((order_id, found_dormant), events)` where:
-    /// - `order_id`: The order ID of the placed order
-    /// - `found_dormant`: Whether a dormant order was found and reused
-    /// - `events`: Vector of events emitted during this operation
-    pub fn limit_sell(
-        &mut self,
-        cid: impl Into<Vec<u8>>,
-        pair_id: impl Into<Vec<u8>>,
-        existing_order_id: Option<OrderId>,
-        owner: impl Into<Vec<u8>>,
-        price: u64,
-        // whole amount
-        amnt: u64,
-        // iceberg quantity
-        iqty: u64,
-        timestamp: i64,
-        expires_at: i64,
-        maker_fee_bps: i16,
-        taker_fee_bps: i16,
-        time_in_force: TimeInForce,
-    ) -> Result<EventQueue, OrderBookError> {
+    /// Returns `events` - Vector of events emitted during this operation
+    ///
+    /// - a post-only order that would match on arrival is rejected with `PostOnlyWouldTake`.
+    pub fn limit_sell(&mut self, order: LimitOrder) -> Result<EventQueue, OrderBookError> {
         // find a pair
-        let pair_id_vec = pair_id.into();
-        let pair = self.pairs.get_mut(&pair_id_vec).unwrap();
-        pair.limit_sell(
-            cid,
-            existing_order_id,
-            owner,
-            price,
-            amnt,
-            iqty,
-            timestamp,
-            expires_at,
-            maker_fee_bps,
-            taker_fee_bps,
-            time_in_force,
-        )?;
-        
-        // Drain all events that were emitted during this operation
-        let events = event::drain_events();
-        
-        Ok(events)
+        let pair_id = order.pair_id;
+        self.run(&pair_id, |pair| {
+            pair.limit_sell(order)?;
+            Ok(())
+        })
     }
 
     /// Place a limit buy order (bid order)
     /// Matches against existing orders first, then places remaining in orderbook based on time_in_force
     ///
-    /// Returns `((order_id, found_dormant), events)` where:
-    /// - `order_id`: The order ID of the placed order
-    /// - `found_dormant`: Whether a dormant order was found and reused
-    /// - `events`: Vector of events emitted during this operation
-    pub fn limit_buy(
-        &mut self,
-        cid: impl Into<Vec<u8>>,
-        pair_id: impl Into<Vec<u8>>,
-        existing_order_id: Option<OrderId>,
-        owner: impl Into<Vec<u8>>,
-        price: u64,
-        amount: u64,
-        public_amount: u64,
-        timestamp: i64,
-        expires_at: i64,
-        maker_fee_bps: i16,
-        taker_fee_bps: i16,
-        time_in_force: TimeInForce,
-    ) -> Result<EventQueue, OrderBookError> {
+    /// Returns `events` - Vector of events emitted during this operation
+    ///
+    /// - a post-only order that would match on arrival is rejected with `PostOnlyWouldTake`.
+    pub fn limit_buy(&mut self, order: LimitOrder) -> Result<EventQueue, OrderBookError> {
         // find a pair
-        let pair_id_vec = pair_id.into();
-        let pair = self.pairs.get_mut(&pair_id_vec).unwrap();
-        pair.limit_buy(
-            cid,
-            existing_order_id,
-            owner,
-            price,
-            amount,
-            public_amount,
-            timestamp,
-            expires_at,
-            maker_fee_bps,
-            taker_fee_bps,
-            time_in_force,
-        )?;
-        
-        // Drain all events that were emitted during this operation
-        let events = event::drain_events();
-        
-        Ok(events)
+        let pair_id = order.pair_id;
+        self.run(&pair_id, |pair| {
+            pair.limit_buy(order)?;
+            Ok(())
+        })
     }
 
     /// Execute a market sell order
     /// Matches against existing orders first (market orders match at any price)
     ///
-    /// Returns `(OrderMatch, events)` where:
-    /// - `OrderMatch`: Contains trade execution details
-    /// - `events`: Vector of events emitted during this operation
-    pub fn market_sell(
-        &mut self,
-        cid: impl Into<Vec<u8>>,
-        pair_id: impl Into<Vec<u8>>,
-        existing_order_id: Option<OrderId>,
-        owner: impl Into<Vec<u8>>,
-        amount: u64,
-        public_amount: u64,
-        timestamp: i64,
-        expires_at: i64,
-        maker_fee_bps: i16,
-        taker_fee_bps: i16,
-        time_in_force: TimeInForce,
-    ) -> Result<EventQueue, OrderBookError> {
-        let pair_id_vec = pair_id.into();
-        let pair = self.pairs.get_mut(&pair_id_vec).unwrap();
-        pair.market_sell(
-            cid,
-            existing_order_id,
-            owner,
-            amount,
-            public_amount,
-            timestamp,
-            expires_at,
-            maker_fee_bps,
-            taker_fee_bps,
-            time_in_force,
-        )?;
-        
-        // Drain all events that were emitted during this operation
-        let events = event::drain_events();
-        
-        Ok(events)
+    /// Returns `events` - Vector of events emitted during this operation
+    pub fn market_sell(&mut self, order: MarketOrder) -> Result<EventQueue, OrderBookError> {
+        let pair_id = order.pair_id;
+        self.run(&pair_id, |pair| {
+            pair.market_sell(order)?;
+            Ok(())
+        })
     }
 
     /// Execute a market buy order
     /// Matches against existing orders first (market orders match at any price)
     ///
-    /// Returns `(OrderMatch, events)` where:
-    /// - `OrderMatch`: Contains trade execution details
-    /// - `events`: Vector of events emitted during this operation
-    pub fn market_buy(
-        &mut self,
-        cid: impl Into<Vec<u8>>,
-        pair_id: impl Into<Vec<u8>>,
-        existing_order_id: Option<OrderId>,
-        owner: impl Into<Vec<u8>>,
-        amount: u64,
-        public_amount: u64,
-        timestamp: i64,
-        expires_at: i64,
-        maker_fee_bps: i16,
-        taker_fee_bps: i16,
-        time_in_force: TimeInForce,
-    ) -> Result<EventQueue, OrderBookError> {
-        let pair_id_vec = pair_id.into();
-        let pair = self.pairs.get_mut(&pair_id_vec).unwrap();
-        pair.market_buy(
-            cid,
-            existing_order_id,
-            owner,
-            amount,
-            public_amount,
-            timestamp,
-            expires_at,
-            maker_fee_bps,
-            taker_fee_bps,
-            time_in_force,
-        )?;
-        
-        // Drain all events that were emitted during this operation
-        let events = event::drain_events();
-        
-        Ok(events)
+    /// Returns `events` - Vector of events emitted during this operation
+    pub fn market_buy(&mut self, order: MarketOrder) -> Result<EventQueue, OrderBookError> {
+        let pair_id = order.pair_id;
+        self.run(&pair_id, |pair| {
+            pair.market_buy(order)?;
+            Ok(())
+        })
     }
 
     /// Cancel an order
     ///
     /// Returns `events` - Vector of events emitted during this operation
     ///
-    /// - `order_id`: The ID of the order to cancel
+    /// - `order`: The order id, or the owner's client order id, of the order to cancel
     /// - `owner`: The owner of the order (for authorization)
-    /// - `is_bid`: Whether the order is a bid (true) or ask (false)
     pub fn cancel_order(
         &mut self,
-        cid: impl Into<Vec<u8>>,
-        pair_id: impl Into<Vec<u8>>,
-        order_id: OrderId,
-        owner: impl Into<Vec<u8>>,
-        is_bid: bool,
+        cid: impl Into<ClientId>,
+        pair_id: impl Into<PairId>,
+        order: impl Into<OrderRef>,
+        owner: impl Into<AccountId>,
         ) -> Result<EventQueue, OrderBookError> {
-        let pair_id_vec = pair_id.into();
-        let pair = self.pairs.get_mut(&pair_id_vec).unwrap();
-        pair.cancel_order(cid, pair_id_vec, is_bid, order_id, owner)?;
-        
+        let pair_id = pair_id.into();
+        self.run(&pair_id, |pair| {
+            pair.cancel_order(cid, pair_id, order, owner)?;
+            Ok(())
+        })
+    }
+
+    /// Run an order command on its pair
+    ///
+    /// Returns `events` - Vector of events emitted by the command
+    ///
+    /// - with the `invariants` feature a command violating `invariants::check_command` is rolled back: the pair is
+    ///   restored as it was before the command and its events are discarded.
+    /// - a rejected command keeps its events queued and what it did before failing, with or without the feature.
+    fn run(
+        &mut self,
+        pair_id: &PairId,
+        command: impl FnOnce(&mut Pair) -> Result<(), OrderBookError>,
+    ) -> Result<EventQueue, OrderBookError> {
+        let pair = self.pairs.get_mut(pair_id).unwrap();
+        #[cfg(feature = "invariants")]
+        let before = pair.clone();
+        command(pair)?;
+
         // Drain all events that were emitted during this operation
         let events = event::drain_events();
-        
+        #[cfg(feature = "invariants")]
+        if let Err(err) = invariants::check_command(&before.orderbook, &pair.orderbook, &events) {
+            *pair = before;
+            return Err(err.into());
+        }
         Ok(events)
     }
 
+    /// Execute a command on its pair
+    ///
+    /// Returns `events` - Vector of events emitted by the command
+    ///
+    /// - commands other than `AddPair` fail with `PairNotFound` when the pair does not exist.
+    /// - the events of a rejected command are discarded, so they do not leak into the next command.
+    pub fn execute(&mut self, command: SpotCommand) -> Result<EventQueue, OrderBookError> {
+        if !matches!(command, SpotCommand::AddPair { .. }) && !self.pairs.contains_key(&command.pair_id()) {
+            return Err(OrderBookError::PairNotFound);
+        }
+        let result = match command {
+            SpotCommand::AddPair { cid, admin_account_id, fee_account_id, pair_id, timestamp } => {
+                self.add_pair(cid, admin_account_id, fee_account_id, pair_id, timestamp);
+                Ok(event::drain_events())
+            }
+            SpotCommand::AddPairClient { cid, pair_id, admin_account_id, fee_account_id, timestamp } => {
+                self.add_pair_client(cid, pair_id, admin_account_id, fee_account_id, timestamp)
+            }
+            SpotCommand::LimitBuy(order) => self.limit_buy(order),
+            SpotCommand::LimitSell(order) => self.limit_sell(order),
+            SpotCommand::MarketBuy(order) => self.market_buy(order),
+            SpotCommand::MarketSell(order) => self.market_sell(order),
+            SpotCommand::CancelOrder { cid, pair_id, order, owner } => self.cancel_order(cid, pair_id, order, owner),
+            SpotCommand::SetDust { pair_id, dust } => {
+                self.set_dust(pair_id, dust);
+                Ok(EventQueue::new())
+            }
+        };
+        if result.is_err() {
+            event::clear_events();
+        }
+        result
+    }
+
+    /// Execute a command on a copy of its pair, for order entry previews
+    ///
+    /// Returns the events the command would emit, the engine and the events of the calling thread are left untouched
+    pub fn preview(&self, command: SpotCommand) -> Result<EventQueue, OrderBookError> {
+        let pair_id = command.pair_id();
+        let mut copy = MatchingEngine::new();
+        if let Some(pair) = self.pairs.get(&pair_id) {
+            copy.pairs.insert(pair_id, pair.clone());
+            copy.total_pairs = self.total_pairs;
+        }
+        event::isolated(|| copy.execute(command))
+    }
+
     /// Get the number of pairs in the matching engine
     pub fn pair_count(&self) -> usize {
         self.pairs.len()
     }
 
     /// Check if a pair exists
-    pub fn has_pair(&self, pair_id: &Vec<u8>) -> bool {
-        self.pairs.contains_key(&pair_id.clone())
+    pub fn has_pair(&self, pair_id: impl Into<PairId>) -> bool {
+        self.pairs.contains_key(&pair_id.into())
+    }
+
+    /// Get the resting orders of an owner on a pair, ordered by order id.
+    pub fn orders_by_owner(&self, pair_id: impl Into<PairId>, owner: impl Into<AccountId>) -> Vec<&Order> {
+        self.pairs
+            .get(&pair_id.into())
+            .map(|p| p.orderbook.orders_by_owner(owner))
+            .unwrap_or_default()
+    }
+
+    /// Get a resting order of an owner on a pair by its client order id.
+    pub fn order_by_client_order_id(&self, pair_id: impl Into<PairId>, owner: impl Into<AccountId>, coid: &[u8]) -> Option<&Order> {
+        self.pairs
+            .get(&pair_id.into())
+            .and_then(|p| p.orderbook.order_by_client_order_id(owner, coid))
+    }
+
+    /// Get a page of an owner's open orders on a pair, ordered by order id.
+    /// - `cursor` is the `next_cursor` of the previous page, `None` for the first page.
+    /// - `limit` is the maximum number of orders in the page.
+    pub fn open_orders(
+        &self,
+        pair_id: impl Into<PairId>,
+        owner: impl Into<AccountId>,
+        cursor: Option<OrderId>,
+        limit: u32,
+    ) -> Result<OrdersPage, QueryError> {
+        let pair = self.pairs.get(&pair_id.into()).ok_or(QueryError::PairNotFound)?;
+        Ok(query::open_orders(pair, owner.into(), cursor, limit))
+    }
+
+    /// Get a single resting order on a pair.
+    pub fn order(&self, pair_id: impl Into<PairId>, order_id: OrderId) -> Result<OrderView, QueryError> {
+        let pair = self.pairs.get(&pair_id.into()).ok_or(QueryError::PairNotFound)?;
+        query::order(pair, order_id)
+    }
+
+    /// Get up to `n` public L2 levels on each side of a pair's book.
+    pub fn depth(&self, pair_id: impl Into<PairId>, n: u32) -> Result<Depth, QueryError> {
+        let pair = self.pairs.get(&pair_id.into()).ok_or(QueryError::PairNotFound)?;
+        Ok(query::depth(&pair.orderbook, n))
+    }
+
+    /// Get the best bid and ask of a pair.
+    pub fn best_bid_ask(&self, pair_id: impl Into<PairId>) -> Result<BestBidAsk, QueryError> {
+        let pair = self.pairs.get(&pair_id.into()).ok_or(QueryError::PairNotFound)?;
+        Ok(query::best_bid_ask(&pair.orderbook))
+    }
+
+    /// Get the last matched price of a pair.
+    pub fn last_price(&self, pair_id: impl Into<PairId>) -> Result<Option<u64>, QueryError> {
+        let pair = self.pairs.get(&pair_id.into()).ok_or(QueryError::PairNotFound)?;
+        Ok(pair.l1.lmp())
+    }
+
+    /// Get up to the last `n` candles of an interval on a pair, oldest first.
+    pub fn candles(&self, pair_id: impl Into<PairId>, interval: CandleInterval, n: usize) -> Result<Vec<Candle>, QueryError> {
+        let pair = self.pairs.get(&pair_id.into()).ok_or(QueryError::PairNotFound)?;
+        Ok(pair.candles.candles(interval, n))
+    }
+
+    /// Get the 24h rolling ticker of a pair at `now` in milliseconds.
+    pub fn ticker(&self, pair_id: impl Into<PairId>, now: i64) -> Result<Ticker, QueryError> {
+        let pair = self.pairs.get(&pair_id.into()).ok_or(QueryError::PairNotFound)?;
+        Ok(pair.ticker.ticker(now))
+    }
+
+    /// Take a depth snapshot of a pair in one scale, opening the depth feed of the scale.
+    pub fn depth_snapshot(&mut self, pair_id: impl Into<PairId>, scale: u64) -> Result<L2Snapshot, MarketDataError> {
+        let pair = self.pairs.get_mut(&pair_id.into()).ok_or(MarketDataError::PairNotFound)?;
+        market_data::snapshot(pair, scale)
+    }
+
+    /// Publish the depth deltas of a pair since the last call, for every open depth feed.
+    pub fn depth_deltas(&mut self, pair_id: impl Into<PairId>) -> Result<Vec<L2Delta>, MarketDataError> {
+        let pair = self.pairs.get_mut(&pair_id.into()).ok_or(MarketDataError::PairNotFound)?;
+        Ok(market_data::deltas(pair))
+    }
+
+    /// Set the dust limit of a pair, order remainders at or below it are deleted after a fill.
+    /// - does nothing if the pair does not exist.
+    pub fn set_dust(&mut self, pair_id: impl Into<PairId>, dust: u64) {
+        if let Some(pair) = self.pairs.get_mut(&pair_id.into()) {
+            pair.orderbook.set_dust(dust);
+        }
     }
 
     /// Get the orderbook for a pair (for tests / inspection).
-    pub fn orderbook(&self, pair_id: &[u8]) -> Option<&OrderBook> {
-        self.pairs.get(pair_id).map(|p| &p.orderbook)
+    pub fn orderbook(&self, pair_id: impl Into<PairId>) -> Option<&OrderBook> {
+        self.pairs.get(&pair_id.into()).map(|p| &p.orderbook)
+    }
+
+    /// Get a pair with its L1 state and orderbook (for tests / inspection).
+    pub fn pair(&self, pair_id: impl Into<PairId>) -> Option<&Pair> {
+        self.pairs.get(&pair_id.into())
     }
 }
 
@@ -2,13 +2,38 @@ pub mod market;
 pub mod prices;
 pub mod orders;
 pub mod event;
+pub mod event_bus;
+pub mod event_backends;
 pub mod orderbook;
 pub mod pair;
 pub mod time_in_force;
 pub mod matching_engine;
+pub mod query;
+pub mod market_data;
+pub mod trades;
+pub mod ticker;
+pub mod market_order;
+pub mod fixed_point;
+pub mod invariants;
+pub mod intern;
+pub mod ids;
+pub mod command;
+pub mod clock;
+pub mod runtime;
+pub mod projection;
+pub mod wire;
+pub mod snapshot;
+pub mod fix;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 
 pub use market::L1;
 pub use prices::{L2, Level};
 pub use orders::{L3, L3Error, Order, Node};
 pub use pair::Pair;
+pub use ids::{AccountId, AssetId, ClientId, PairId};
 pub use matching_engine::MatchingEngine;
\ No newline at end of file
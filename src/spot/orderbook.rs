@@ -8,8 +8,13 @@ use crate::spot::{
 };
 
 use super::{
-    orders::{L3Error, OrderId},
+    clock,
+    fixed_point::{FixedPointError, Notional, Price, Quantity, Rounding},
+    ids::{AccountId, AssetId, ClientId, PairId},
+    invariants::InvariantError,
+    orders::{ClientOrderId, L3Error, OrderId, OrderRef},
     prices::L2Error,
+    trades::Trade,
     L2, L3,
 };
 
@@ -18,6 +23,7 @@ use super::{
 /// - `l3` is the L3 state of the orderbook.
 /// - `fee_recipients` is the map of client id to fee recipient account id.
 /// - `dust` is the dust limit to determine if the order should be deleted.
+/// - `last_trade_id` is the id of the last trade matched on the orderbook.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct OrderBook {
     // L2 state
@@ -25,9 +31,11 @@ pub struct OrderBook {
     // L3 state
     pub l3: L3,
     // Fee recipients map where key is the client id, and value is the fee recipient account id
-    pub fee_recipients: HashMap<Vec<u8>, Vec<u8>>,
+    pub fee_recipients: HashMap<ClientId, AccountId>,
     // dust limit to determine if the order should be deleted
     pub dust: u64,
+    // id of the last trade matched on the orderbook
+    pub last_trade_id: u64,
 }
 
 #[derive(Debug, thiserror::Error, PartialEq, Eq)]
@@ -58,6 +66,14 @@ pub enum OrderBookError {
     NoAskOrdersInOrderbook,
     #[error("no bid orders in the orderbook")]
     NoBidOrdersInOrderbook,
+    #[error("fixed point error: {0}")]
+    FixedPoint(FixedPointError),
+    #[error("invariant violated: {0}")]
+    Invariant(InvariantError),
+    #[error("pair does not exist")]
+    PairNotFound,
+    #[error("post-only order would take liquidity")]
+    PostOnlyWouldTake,
 }
 
 impl From<L3Error> for OrderBookError {
@@ -72,6 +88,18 @@ impl From<L2Error> for OrderBookError {
     }
 }
 
+impl From<FixedPointError> for OrderBookError {
+    fn from(err: FixedPointError) -> Self {
+        OrderBookError::FixedPoint(err)
+    }
+}
+
+impl From<InvariantError> for OrderBookError {
+    fn from(err: InvariantError) -> Self {
+        OrderBookError::Invariant(err)
+    }
+}
+
 impl OrderBook {
     pub fn new() -> Self {
         Self {
@@ -79,6 +107,7 @@ impl OrderBook {
             l3: L3::new(),
             fee_recipients: HashMap::new(),
             dust: 1000,
+            last_trade_id: 0,
         }
     }
 
@@ -90,7 +119,8 @@ impl OrderBook {
     /// Gets the required amount to match an order as taker to match with the maker order and clear it.
     /// - `taker_order` is the taker order.
     /// - `price` is the price of the maker order.
-    /// - `amount` is the amount of the taker order to match with the maker order.
+    /// - `amount` is the maker side amount to clear, base for a bid taker and quote for an ask taker.
+    /// - returns the amount in the taker's terms, rounded up as the taker pays it.
     pub fn get_required(
         &self,
         taker_order: Order,
@@ -98,13 +128,13 @@ impl OrderBook {
         amount: u64,
     ) -> Result<u64, OrderBookError> {
         if taker_order.is_bid {
-            Ok(amount.saturating_mul(price).saturating_div(1_0000_0000))
+            Ok(Price(price).notional(Quantity(amount), Rounding::Ceil)?.0)
         } else {
-            Ok(amount.saturating_mul(1_0000_0000).saturating_div(price))
+            Ok(Price(price).quantity(Notional(amount), Rounding::Ceil)?.0)
         }
     }
 
-    /// clears empty head of the order book where price is in linked list, but order is not in the price level
+    /// clears empty head of the order book where price is in linked list, but no order of the side is in the price level
     pub fn clear_empty_head(&mut self, is_bid: bool) -> Result<u64, OrderBookError> {
         // Get the current head price
         let mut head = if is_bid {
@@ -115,11 +145,9 @@ impl OrderBook {
 
         // While head exists and has no orders, clear it and move to the next head
         while let Some(head_price) = head {
-            // Check if there are orders at this price level
-            let order_id = self.l3.head(head_price);
-
-            // If there are orders at this price level, we're done
-            if order_id.is_some() {
+            // If there are orders of the side at this price level, we're done.
+            // The level is shared with a taker of the other side resting at the head price.
+            if self.l3.has_side_orders(head_price, is_bid) {
                 return Ok(head_price);
             }
 
@@ -138,10 +166,7 @@ impl OrderBook {
 
     /// pop front on the orderbook
     pub fn pop_front(&mut self, is_bid: bool) -> Result<Order, OrderBookError> {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as i64;
+        let now = clock::now_millis();
         loop {
             self.clear_empty_head(is_bid)?;
             let head = if is_bid {
@@ -159,7 +184,7 @@ impl OrderBook {
                 let order = self.l3.get_order(order_id)?;
                 // if the order is expired, expire it and continue
                 if order.expires_at <= now {
-                    self._expire_order(order_id, is_bid, Vec::new(), now)?;
+                    self._expire_order(order_id, is_bid, PairId::default(), now)?;
                     continue;
                 }
                 // if the expired order empties the price level, remove the price level, move to next head and continue
@@ -172,9 +197,7 @@ impl OrderBook {
                 if is_empty {
                     self.l2.clear_head(is_bid)?;
                 }
-                return Ok(order
-                    .expect("head price must have at least one order")
-                    .clone());
+                return Ok(order.expect("head price must have at least one order"));
             }
         }
     }
@@ -189,11 +212,11 @@ impl OrderBook {
     /// - `timestamp` is the timestamp of the order.
     pub fn place_bid(
         &mut self,
-        cid: impl Into<Vec<u8>>,
-        pair_id: impl Into<Vec<u8>>,
-        base_asset_id: impl Into<Vec<u8>>,
-        quote_asset_id: impl Into<Vec<u8>>,
-        owner: impl Into<Vec<u8>>,
+        cid: impl Into<ClientId>,
+        pair_id: impl Into<PairId>,
+        base_asset_id: impl Into<AssetId>,
+        quote_asset_id: impl Into<AssetId>,
+        owner: impl Into<AccountId>,
         price: u64,
         amnt: u64,
         iqty: u64,
@@ -201,50 +224,7 @@ impl OrderBook {
         expires_at: i64,
         maker_fee_bps: i16,
     ) -> Result<Order, OrderBookError> {
-        let cid = cid.into();
-        let pair_id = pair_id.into();
-        let base_asset_id = base_asset_id.into();
-        let quote_asset_id = quote_asset_id.into();
-        let owner = owner.into();
-        let price = price;
-        if iqty > amnt {
-            return Err(OrderBookError::IcebergQuantityIsBiggerThanWholeAmount);
-        }
-        let pqty = amnt - iqty;
-
-        let order = self.l3.create_order(
-            cid.clone(),
-            owner.clone(),
-            true,
-            price,
-            amnt,
-            iqty,
-            timestamp,
-            expires_at,
-            maker_fee_bps,
-        )?;
-
-        // emit the event for the order created
-        event::emit_event(SpotEvent::SpotOrderPlaced {
-            cid: cid.clone(),
-            pair_id: pair_id.clone(),
-            base_asset_id: base_asset_id.clone(),
-            quote_asset_id: quote_asset_id.clone(),
-            order_id: order.id.to_bytes().to_vec(),
-            maker_account_id: owner.into(),
-            is_bid: true,
-            price: price,
-            amnt: amnt,
-            iqty: iqty,
-            pqty: pqty,
-            cqty: amnt,
-            timestamp: timestamp,
-            expires_at: expires_at,
-        });
-
-        // update the price level on the orderbook
-        self.update_price_level(pair_id, true, true, price, pqty, amnt, None)?;
-        Ok(order)
+        self.place_order(cid, pair_id, base_asset_id, quote_asset_id, owner, None, true, price, amnt, iqty, timestamp, expires_at, maker_fee_bps)
     }
 
     /// Places an ask order.
@@ -257,63 +237,95 @@ impl OrderBook {
     /// - `timestamp` is the timestamp of the order.
     pub fn place_ask(
         &mut self,
-        cid: impl Into<Vec<u8>>,
-        pair_id: impl Into<Vec<u8>>,
-        base_asset_id: impl Into<Vec<u8>>,
-        quote_asset_id: impl Into<Vec<u8>>,
-        owner: impl Into<Vec<u8>>,
+        cid: impl Into<ClientId>,
+        pair_id: impl Into<PairId>,
+        base_asset_id: impl Into<AssetId>,
+        quote_asset_id: impl Into<AssetId>,
+        owner: impl Into<AccountId>,
         price: u64,
         amnt: u64,
         iqty: u64,
         timestamp: i64,
         expires_at: i64,
         maker_fee_bps: i16,
+    ) -> Result<Order, OrderBookError> {
+        self.place_order(cid, pair_id, base_asset_id, quote_asset_id, owner, None, false, price, amnt, iqty, timestamp, expires_at, maker_fee_bps)
+    }
+
+    /// Places an order on either side.
+    /// The order is emitted with its client order id, so the events are enough to rebuild it.
+    /// - `coid` is the optional client order id of the order, unique among the owner's resting orders.
+    /// - `fee_bps` is the fee basis points the order is placed with.
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_order(
+        &mut self,
+        cid: impl Into<ClientId>,
+        pair_id: impl Into<PairId>,
+        base_asset_id: impl Into<AssetId>,
+        quote_asset_id: impl Into<AssetId>,
+        owner: impl Into<AccountId>,
+        coid: Option<ClientOrderId>,
+        is_bid: bool,
+        price: u64,
+        amnt: u64,
+        iqty: u64,
+        timestamp: i64,
+        expires_at: i64,
+        fee_bps: i16,
     ) -> Result<Order, OrderBookError> {
         let cid = cid.into();
         let pair_id = pair_id.into();
         let base_asset_id = base_asset_id.into();
         let quote_asset_id = quote_asset_id.into();
         let owner = owner.into();
-        let price = price.into();
-        let amnt = amnt.into();
-        let iqty = iqty.into();
         if iqty > amnt {
             return Err(OrderBookError::IcebergQuantityIsBiggerThanWholeAmount);
         }
+        if let Some(coid) = &coid {
+            if self.l3.has_client_order_id(owner, coid) {
+                return Err(OrderBookError::L3(L3Error::DuplicateClientOrderId));
+            }
+        }
         let pqty = amnt - iqty;
 
-        let order = self.l3.create_order(
-            cid.clone(),
-            owner.clone(),
-            false,
+        let mut order = self.l3.create_order(
+            cid,
+            owner,
+            is_bid,
             price,
             amnt,
             iqty,
             timestamp,
             expires_at,
-            maker_fee_bps,
+            fee_bps,
         )?;
+        if let Some(coid) = &coid {
+            self.l3.set_client_order_id(order.id, coid.clone())?;
+            order.coid = Some(coid.clone());
+        }
 
         // emit the event for the order created
         event::emit_event(SpotEvent::SpotOrderPlaced {
-            cid: cid.clone(),
-            pair_id: pair_id.clone(),
-            base_asset_id: base_asset_id.clone(),
-            quote_asset_id: quote_asset_id.clone(),
+            cid,
+            pair_id,
+            base_asset_id,
+            quote_asset_id,
             order_id: order.id.to_bytes().to_vec(),
             maker_account_id: owner,
-            is_bid: false,
-            price: price,
-            amnt: amnt,
-            iqty: iqty,
-            pqty: pqty,
+            coid,
+            is_bid,
+            price,
+            amnt,
+            iqty,
+            pqty,
             cqty: amnt,
-            timestamp: timestamp,
-            expires_at: expires_at,
+            fee_bps,
+            timestamp,
+            expires_at,
         });
 
         // update the price level on the orderbook
-        self.update_price_level(pair_id, true, false, price, pqty, amnt, None)?;
+        self.update_price_level(pair_id, true, is_bid, price, pqty, amnt, None)?;
         Ok(order)
     }
 
@@ -332,41 +344,84 @@ impl OrderBook {
         &mut self,
         taker_order: Order,
         maker_order: Order,
-        pair_id: impl Into<Vec<u8>>,
-        base_asset_id: impl Into<Vec<u8>>,
-        quote_asset_id: impl Into<Vec<u8>>,
+        pair_id: impl Into<PairId>,
+        base_asset_id: impl Into<AssetId>,
+        quote_asset_id: impl Into<AssetId>,
         now: i64,
     ) -> Result<(), OrderBookError> {
-        // Normalize IDs up front so we don't move the Into<Vec<u8>> values multiple times
-        let pair_id_vec = pair_id.into();
-        let base_asset_id_vec = base_asset_id.into();
-        let quote_asset_id_vec = quote_asset_id.into();
+        self.execute_trade(taker_order, maker_order, pair_id, base_asset_id, quote_asset_id, now)
+            .map(|_| ())
+    }
+
+    /// Executes a trade like `execute`, returning the canonical trade record of the match.
+    pub fn execute_trade(
+        &mut self,
+        taker_order: Order,
+        maker_order: Order,
+        pair_id: impl Into<PairId>,
+        base_asset_id: impl Into<AssetId>,
+        quote_asset_id: impl Into<AssetId>,
+        now: i64,
+    ) -> Result<Trade, OrderBookError> {
+        // Normalize IDs up front so we don't move the Into values multiple times
+        let pair_id = pair_id.into();
+        let base_asset_id = base_asset_id.into();
+        let quote_asset_id = quote_asset_id.into();
+        let (matching_base_amount, matching_quote_amount, taker_clear, maker_clear) =
+            self._get_matching_amount(taker_order.clone(), maker_order.clone())?;
+
+        self.execute_fill(
+            taker_order,
+            maker_order,
+            matching_base_amount,
+            matching_quote_amount,
+            taker_clear,
+            maker_clear,
+            self.dust,
+            pair_id,
+            base_asset_id,
+            quote_asset_id,
+            now,
+        )
+    }
+
+    /// Executes a fill of explicit base and quote amounts between a taker and a maker order.
+    /// - returns the canonical trade record of the fill.
+    /// - `matching_base_amount` and `matching_quote_amount` are the base and quote amounts exchanged,
+    ///   bid orders are decreased by the quote amount and ask orders by the base amount.
+    /// - `taker_clear` and `maker_clear` are whether to delete the orders after the fill.
+    /// - `taker_dust` is the dust limit applied to the taker order, the maker order uses the book's limit.
+    pub fn execute_fill(
+        &mut self,
+        taker_order: Order,
+        maker_order: Order,
+        matching_base_amount: u64,
+        matching_quote_amount: u64,
+        taker_clear: bool,
+        maker_clear: bool,
+        taker_dust: u64,
+        pair_id: impl Into<PairId>,
+        base_asset_id: impl Into<AssetId>,
+        quote_asset_id: impl Into<AssetId>,
+        now: i64,
+    ) -> Result<Trade, OrderBookError> {
+        let pair_id = pair_id.into();
+        let base_asset_id = base_asset_id.into();
+        let quote_asset_id = quote_asset_id.into();
         let taker_is_bid = taker_order.is_bid;
-        let (matching_amount, taker_clear, maker_clear) = self._get_matching_amount(taker_order.clone(), maker_order.clone())?;
-        // matching_amount is expressed in taker terms; convert to base/quote by side
-        let matching_base_amount = if taker_is_bid {
-            matching_amount.saturating_mul(1_0000_0000).saturating_div(taker_order.price)
-        } else {
-            matching_amount
-        };
-        let matching_quote_amount = if taker_is_bid {
-            matching_amount
-        } else {
-            matching_amount.saturating_mul(taker_order.price).saturating_div(1_0000_0000)
-        };
 
         let taker_matching_amount = if taker_is_bid { matching_quote_amount.clone() } else { matching_base_amount.clone() };
         let maker_matching_amount = if taker_is_bid { matching_base_amount.clone() } else { matching_quote_amount.clone() };
 
         // Get order data before mutable borrow
         if maker_order.expires_at <= now {
-            self._expire_order(maker_order.id, !taker_is_bid, pair_id_vec.clone(), now)?;
+            self._expire_order(maker_order.id, !taker_is_bid, pair_id, now)?;
             // let _match_at at pair.rs handle the expired order error
             return Err(OrderBookError::OrderExpired);
         }
         let (_taker_delete_amount, taker_delete_price) =
             self.l3
-                .decrease_order(taker_order.id, taker_matching_amount, self.dust, taker_clear)?;
+                .decrease_order(taker_order.id, taker_matching_amount, taker_dust, taker_clear)?;
         let (_maker_delete_amount, maker_delete_price) =
             self.l3
                 .decrease_order(maker_order.id, maker_matching_amount, self.dust, maker_clear)?;
@@ -378,7 +433,7 @@ impl OrderBook {
             matching_quote_amount,
             maker_order.fee_bps,
             taker_order.fee_bps,
-        );
+        )?;
 
         // emit the event for order matched
         let match_timestamp = now;
@@ -405,9 +460,9 @@ impl OrderBook {
             taker_remaining_pqty,
             maker_remaining_cqty,
             maker_remaining_pqty,
-            pair_id_vec.clone(),
-            base_asset_id_vec.clone(),
-            quote_asset_id_vec.clone(),
+            pair_id,
+            base_asset_id,
+            quote_asset_id,
             matching_base_amount,
             matching_quote_amount,
             base_fee,
@@ -417,11 +472,28 @@ impl OrderBook {
             maker_order.expires_at,
         )?;
 
+        // emit the canonical trade record once per match
+        self.last_trade_id += 1;
+        let trade = Trade {
+            trade_id: self.last_trade_id,
+            pair_id,
+            base_asset_id,
+            quote_asset_id,
+            taker_order_id: taker_order.id.to_bytes().to_vec(),
+            maker_order_id: maker_order.id.to_bytes().to_vec(),
+            taker_is_bid,
+            price: maker_order.price,
+            base_volume: matching_base_amount,
+            quote_volume: matching_quote_amount,
+            timestamp: match_timestamp,
+        };
+        event::emit_event(trade.clone().into());
+
         // adjust price level on the matched amount
         // Update levels and remove price if level becomes 0 or below
         // Also handle delete_price removal if an order was fully consumed
         self.update_price_level(
-            pair_id_vec.clone(),
+            pair_id,
             false,
             taker_order.is_bid,
             taker_order.price,
@@ -430,7 +502,7 @@ impl OrderBook {
             taker_delete_price,
         )?;
         self.update_price_level(
-            pair_id_vec.clone(),
+            pair_id,
             false,
             maker_order.is_bid,
             maker_order.price,
@@ -439,38 +511,35 @@ impl OrderBook {
             maker_delete_price,
         )?;
 
-        self._emit_order_rebates(pair_id_vec.clone(), base_asset_id_vec.clone(), quote_asset_id_vec.clone(), maker_order.clone(), taker_order.clone(), matching_base_amount, matching_quote_amount)?;
+        self._emit_order_rebates(pair_id, base_asset_id, quote_asset_id, maker_order.clone(), taker_order.clone(), matching_base_amount, matching_quote_amount)?;
 
-        Ok(())
+        Ok(trade)
     }
 
     fn _emit_order_rebates(
         &self,
-        pair_id: impl Into<Vec<u8>>,
-        base_asset_id: impl Into<Vec<u8>>,
-        quote_asset_id: impl Into<Vec<u8>>,
+        pair_id: impl Into<PairId>,
+        base_asset_id: impl Into<AssetId>,
+        quote_asset_id: impl Into<AssetId>,
         maker_order: Order,
         taker_order: Order,
         matching_base_amount: u64,
         matching_quote_amount: u64,
     ) -> Result<(), OrderBookError> {
-        let pair_id_vec = pair_id.into();
-        let base_asset_id_vec = base_asset_id.into();
-        let quote_asset_id_vec = quote_asset_id.into();
+        let pair_id = pair_id.into();
+        let base_asset_id = base_asset_id.into();
+        let quote_asset_id = quote_asset_id.into();
 
         // check if maker order is rebatable 
         if maker_order.fee_bps < 0 {
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as i64;
+            let now = clock::now_millis();
             event::emit_event(SpotEvent::SpotPairRebate {
-                pair_id: pair_id_vec.clone(),
-                base_asset_id: base_asset_id_vec.clone(),
-                quote_asset_id: quote_asset_id_vec.clone(),
+                pair_id,
+                base_asset_id,
+                quote_asset_id,
                 price: maker_order.price,
                 is_bid: maker_order.is_bid,
-                account_id: maker_order.owner.clone(),
+                account_id: maker_order.owner,
                 is_maker: true,
                 fee_bps: maker_order.fee_bps as i16,
                 base_volume: matching_base_amount,
@@ -479,17 +548,14 @@ impl OrderBook {
             });
         }
         if taker_order.fee_bps < 0 {
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as i64;
+            let now = clock::now_millis();
             event::emit_event(SpotEvent::SpotPairRebate {
-                pair_id: pair_id_vec.clone(),
-                base_asset_id: base_asset_id_vec.clone(),
-                quote_asset_id: quote_asset_id_vec.clone(),
+                pair_id,
+                base_asset_id,
+                quote_asset_id,
                 price: taker_order.price,
                 is_bid: taker_order.is_bid,
-                account_id: taker_order.owner.clone(),
+                account_id: taker_order.owner,
                 is_maker: false,
                 fee_bps: taker_order.fee_bps as i16,
                 base_volume: matching_base_amount,
@@ -503,34 +569,36 @@ impl OrderBook {
     /// Determines the matching amount between the taker and maker orders.
     /// - `taker_order` is the taker order.
     /// - `maker_order` is the maker order.
-    /// - returns the matching amount and whether the taker order is fully matched and whether the maker order is fully matched.
+    /// - returns the matching base and quote amounts, converted at the taker's price, whether the taker order is fully matched and whether the maker order is fully matched.
     fn _get_matching_amount(
         &mut self,
         taker_order: Order,
         maker_order: Order,
-    ) -> Result<(u64, bool, bool), OrderBookError> {
+    ) -> Result<(u64, u64, bool, bool), OrderBookError> {
+        let taker_price = Price(taker_order.price);
         let taker_converted_matching_cqty = if taker_order.is_bid {
-            taker_order.cqty.saturating_mul(1_0000_0000).saturating_div(taker_order.price)
+            taker_price.quantity(Notional(taker_order.cqty), Rounding::Floor)?.0
         } else {
-            taker_order.cqty.saturating_mul(taker_order.price).saturating_div(1_0000_0000)
+            taker_price.notional(Quantity(taker_order.cqty), Rounding::Floor)?.0
         };
         // there are two cases:
-        // 1. taker order's converted matching amount is bigger than maker order's matching amount
-        if taker_converted_matching_cqty >= maker_order.cqty {
+        // 1. taker order's converted matching amount covers the maker order's matching amount
+        let (matching_amount, taker_clear, maker_clear) = if taker_converted_matching_cqty >= maker_order.cqty {
             // get the taker's matching amount from the maker order
-            let taker_matching_amount = self.get_required(maker_order.clone(), taker_order.price, maker_order.cqty)?;
-            return Ok((taker_matching_amount, false, true));
-        } 
+            (self.get_required(maker_order.clone(), taker_order.price, maker_order.cqty)?, false, true)
+        }
         // 2. taker order's converted matching amount is smaller than maker order's matching amount
-        else if taker_converted_matching_cqty < maker_order.cqty {
+        else {
             // get the maker's matching amount from the taker order
-            let maker_matching_cqty = self.get_required(taker_order.clone(), maker_order.price, taker_order.cqty)?;
-            return Ok((maker_matching_cqty, true, false));
-        }
-        // 3. taker order's converted matching amount is equal to maker order's matching amount
-        else { 
-            // get the taker's matching amount from the maker order
-            return Ok((taker_order.cqty, true, true)); 
+            (self.get_required(taker_order.clone(), maker_order.price, taker_order.cqty)?, true, false)
+        };
+        // matching_amount is expressed in taker terms; convert to base/quote by side
+        if taker_order.is_bid {
+            let base = taker_price.quantity(Notional(matching_amount), Rounding::Floor)?;
+            Ok((base.0, matching_amount, taker_clear, maker_clear))
+        } else {
+            let quote = taker_price.notional(Quantity(matching_amount), Rounding::Floor)?;
+            Ok((matching_amount, quote.0, taker_clear, maker_clear))
         }
     }
 
@@ -541,21 +609,23 @@ impl OrderBook {
         matching_quote_amount: u64,
         mut maker_fee_bps: i16,
         mut taker_fee_bps: i16,
-    ) -> (u64, u64) {
+    ) -> Result<(u64, u64), OrderBookError> {
         // find maker and taker from base and quote amount
         // redefine 
         maker_fee_bps = if maker_fee_bps < 0 { 0 } else { maker_fee_bps };
         taker_fee_bps = if taker_fee_bps < 0 { 0 } else { taker_fee_bps };
+        let base = Quantity(matching_base_amount);
+        let quote = Notional(matching_quote_amount);
         if is_bid {
-            (
-                matching_base_amount * maker_fee_bps as u64 / 10000,
-                matching_quote_amount * taker_fee_bps as u64 / 10000,
-            )
+            Ok((
+                base.bps(maker_fee_bps as u64, Rounding::Floor)?.0,
+                quote.bps(taker_fee_bps as u64, Rounding::Floor)?.0,
+            ))
         } else {
-            (
-                matching_base_amount * taker_fee_bps as u64 / 10000,
-                matching_quote_amount * maker_fee_bps as u64 / 10000,
-            )
+            Ok((
+                base.bps(taker_fee_bps as u64, Rounding::Floor)?.0,
+                quote.bps(maker_fee_bps as u64, Rounding::Floor)?.0,
+            ))
         }
     }
 
@@ -563,7 +633,7 @@ impl OrderBook {
         &mut self,
         order_id: OrderId,
         is_bid: bool,
-        pair_id: Vec<u8>,
+        pair_id: PairId,
         now: i64,
     ) -> Result<(), OrderBookError> {
         let order = self.l3.get_order(order_id)?.clone();
@@ -580,9 +650,10 @@ impl OrderBook {
         )?;
         // emit event for the order expired
         event::emit_event(SpotEvent::SpotOrderExpired {
-            cid: order.cid.clone(),
+            cid: order.cid,
+            pair_id: Some(pair_id),
             order_id: order_id.to_bytes().to_vec(),
-            maker_account_id: order.owner.clone(),
+            maker_account_id: order.owner,
             is_bid,
             price: order.price,
             amnt: order.amnt,
@@ -603,9 +674,9 @@ impl OrderBook {
         taker_remaining_pqty: u64,
         maker_remaining_cqty: u64,
         maker_remaining_pqty: u64,
-        pair_id: impl Into<Vec<u8>>,
-        base_asset_id: impl Into<Vec<u8>>,
-        quote_asset_id: impl Into<Vec<u8>>,
+        pair_id: impl Into<PairId>,
+        base_asset_id: impl Into<AssetId>,
+        quote_asset_id: impl Into<AssetId>,
         matching_base_amount: u64,
         matching_quote_amount: u64,
         base_fee: u64,
@@ -614,26 +685,26 @@ impl OrderBook {
         taker_expires_at: i64,
         maker_expires_at: i64,
     ) -> Result<(), OrderBookError> {
-        let pair_id_vec = pair_id.into();
-        let base_asset_id_vec = base_asset_id.into();
-        let quote_asset_id_vec = quote_asset_id.into();
+        let pair_id = pair_id.into();
+        let base_asset_id = base_asset_id.into();
+        let quote_asset_id = quote_asset_id.into();
 
         // emit event for taker order filled
         if taker_remaining_cqty > 0 {
             event::emit_event(SpotEvent::SpotOrderPartiallyFilled {
                 is_taker_event: true,
-                taker_cid: taker_order.cid.clone(),
-                maker_cid: maker_order.cid.clone(),
+                taker_cid: taker_order.cid,
+                maker_cid: maker_order.cid,
                 taker_order_id: taker_order.id.to_bytes().to_vec(),
                 maker_order_id: maker_order.id.to_bytes().to_vec(),
-                taker_account_id: taker_order.owner.clone(),
-                maker_account_id: maker_order.owner.clone(),
+                taker_account_id: taker_order.owner,
+                maker_account_id: maker_order.owner,
                 taker_order_is_bid: taker_order.is_bid,
                 maker_order_is_bid: maker_order.is_bid,
                 price: taker_order.price,
-                pair_id: pair_id_vec.clone(),
-                base_asset_id: base_asset_id_vec.clone(),
-                quote_asset_id: quote_asset_id_vec.clone(),
+                pair_id,
+                base_asset_id,
+                quote_asset_id,
                 base_volume: matching_base_amount,
                 quote_volume: matching_quote_amount,
                 base_fee: base_fee,
@@ -650,18 +721,18 @@ impl OrderBook {
         } else {
             event::emit_event(SpotEvent::SpotOrderFullyFilled {
                 is_taker_event: true,
-                taker_cid: taker_order.cid.clone(),
-                maker_cid: maker_order.cid.clone(),
+                taker_cid: taker_order.cid,
+                maker_cid: maker_order.cid,
                 taker_order_id: taker_order.id.to_bytes().to_vec(),
                 maker_order_id: maker_order.id.to_bytes().to_vec(),
-                taker_account_id: taker_order.owner.clone(),
-                maker_account_id: maker_order.owner.clone(),
+                taker_account_id: taker_order.owner,
+                maker_account_id: maker_order.owner,
                 taker_order_is_bid: taker_order.is_bid,
                 maker_order_is_bid: maker_order.is_bid,
                 price: taker_order.price,
-                pair_id: pair_id_vec.clone(),
-                base_asset_id: base_asset_id_vec.clone(),
-                quote_asset_id: quote_asset_id_vec.clone(),
+                pair_id,
+                base_asset_id,
+                quote_asset_id,
                 base_volume: matching_base_amount,
                 quote_volume: matching_quote_amount,
                 base_fee: base_fee,
@@ -681,17 +752,17 @@ impl OrderBook {
         if maker_remaining_cqty > 0 {
             event::emit_event(SpotEvent::SpotOrderPartiallyFilled {
                 is_taker_event: false,
-                taker_cid: taker_order.cid.clone(),
-                maker_cid: maker_order.cid.clone(),
+                taker_cid: taker_order.cid,
+                maker_cid: maker_order.cid,
                 taker_order_id: taker_order.id.to_bytes().to_vec(),
                 maker_order_id: maker_order.id.to_bytes().to_vec(),
-                taker_account_id: taker_order.owner.clone(),
-                maker_account_id: maker_order.owner.clone(),
+                taker_account_id: taker_order.owner,
+                maker_account_id: maker_order.owner,
                 taker_order_is_bid: taker_order.is_bid,
                 maker_order_is_bid: maker_order.is_bid,
-                pair_id: pair_id_vec.clone(),
-                base_asset_id: base_asset_id_vec.clone(),
-                quote_asset_id: quote_asset_id_vec.clone(),
+                pair_id,
+                base_asset_id,
+                quote_asset_id,
                 price: maker_order.price,
                 base_volume: matching_base_amount,
                 quote_volume: matching_quote_amount,
@@ -709,17 +780,17 @@ impl OrderBook {
         } else {
             event::emit_event(SpotEvent::SpotOrderFullyFilled {
                 is_taker_event: false,
-                taker_cid: taker_order.cid.clone(),
-                maker_cid: maker_order.cid.clone(),
+                taker_cid: taker_order.cid,
+                maker_cid: maker_order.cid,
                 taker_order_id: taker_order.id.to_bytes().to_vec(),
                 maker_order_id: maker_order.id.to_bytes().to_vec(),
-                taker_account_id: taker_order.owner.clone(),
-                maker_account_id: maker_order.owner.clone(),
+                taker_account_id: taker_order.owner,
+                maker_account_id: maker_order.owner,
                 taker_order_is_bid: taker_order.is_bid,
                 maker_order_is_bid: maker_order.is_bid,
-                pair_id: pair_id_vec.clone(),
-                base_asset_id: base_asset_id_vec.clone(),
-                quote_asset_id: quote_asset_id_vec.clone(),
+                pair_id,
+                base_asset_id,
+                quote_asset_id,
                 price: maker_order.price,
                 base_volume: matching_base_amount,
                 quote_volume: matching_quote_amount,
@@ -750,7 +821,7 @@ impl OrderBook {
     /// Removes the price if the level becomes 0 or below.
     pub fn update_price_level(
         &mut self,
-        pair_id: Vec<u8>,
+        pair_id: PairId,
         is_placed: bool,
         is_bid: bool,
         price: u64,
@@ -827,10 +898,7 @@ impl OrderBook {
                 price,
                 pqty: new_pqty,
                 cqty: new_cqty,
-                timestamp: std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis() as i64,
+                timestamp: clock::now_millis(),
             });
             Ok(())
         } else {
@@ -883,8 +951,8 @@ impl OrderBook {
                     // Level is 0 or below, remove the price
                     self.l2.set_public_bid_level(price, 0)?;
                     self.l2.set_current_bid_level(price, 0)?;
-                    // Check if price level is empty in L3, and if so, remove it
-                    if self.l3.is_empty(price) {
+                    // Check if the side has no order left at the price in L3, and if so, remove it
+                    if !self.l3.has_side_orders(price, is_bid) {
                         self.l2.remove_price(is_bid, price)?;
                     }
                 }
@@ -896,8 +964,8 @@ impl OrderBook {
                     // Level is 0 or below, remove the price
                     self.l2.set_public_ask_level(price, 0)?;
                     self.l2.set_current_ask_level(price, 0)?;
-                    // Check if price level is empty in L3, and if so, remove it
-                    if self.l3.is_empty(price) {
+                    // Check if the side has no order left at the price in L3, and if so, remove it
+                    if !self.l3.has_side_orders(price, is_bid) {
                         self.l2.remove_price(is_bid, price)?;
                     }
                 }
@@ -916,10 +984,7 @@ impl OrderBook {
                 price,
                 pqty: new_pqty,
                 cqty: new_cqty,
-                timestamp: std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis() as i64,
+                timestamp: clock::now_millis(),
             });
 
             Ok(())
@@ -928,35 +993,35 @@ impl OrderBook {
 
     /// Cancels an order.
     /// - returns the amount to send and the delete price.
-    /// - `order_id` is the id of the order to cancel.
+    /// - `order` is the order id or the owner's client order id of the order to cancel.
     /// - `owner` is the owner of the order.
     pub fn cancel_order(
         &mut self,
-        cid: impl Into<Vec<u8>>,
-        pair_id: impl Into<Vec<u8>>,
-        is_bid: bool,
-        order_id: OrderId,
-        owner: impl Into<Vec<u8>>,
+        cid: impl Into<ClientId>,
+        pair_id: impl Into<PairId>,
+        order: impl Into<OrderRef>,
+        owner: impl Into<AccountId>,
     ) -> Result<(), OrderBookError> {
         let cid = cid.into();
         let pair_id = pair_id.into();
         let owner = owner.into();
-        // check if the order exists
+        // resolve the order id and check if the order exists
+        let order_id = self.l3.resolve(owner, &order.into())?;
         let order = self.l3.get_order(order_id)?.clone();
         // check if the owner is the same as the owner of the order
         if order.owner != owner {
             return Err(OrderBookError::OrderNotOwnedBySender);
         }
+        let is_bid = order.is_bid;
+        // an emptied price level is removed by update_price_level below
         let deleted_price_opt = self.l3.delete_order(order_id)?;
-        if let Some(price) = deleted_price_opt {
-            self.l2.remove_price(is_bid, price)?;
-        }
 
         // emit the event for the order cancelled
         event::emit_event(SpotEvent::SpotOrderCancelled {
-            cid: cid.clone(),
+            cid,
+            pair_id: Some(pair_id),
             order_id: order_id.to_bytes().to_vec(),
-            maker_account_id: order.owner.clone(),
+            maker_account_id: order.owner,
             is_bid,
             price: order.price,
             amnt: order.amnt,
@@ -969,7 +1034,7 @@ impl OrderBook {
 
         // update the price level on the orderbook
         self.update_price_level(
-            pair_id.clone(),
+            pair_id,
             false,
             is_bid,
             order.price,
@@ -980,13 +1045,23 @@ impl OrderBook {
         Ok(())
     }
 
+    /// Returns the resting orders of the owner, ordered by id.
+    pub fn orders_by_owner(&self, owner: impl Into<AccountId>) -> Vec<&Order> {
+        self.l3.get_orders_by_owner(owner.into())
+    }
+
+    /// Returns the resting order of the owner with the given client order id.
+    pub fn order_by_client_order_id(&self, owner: impl Into<AccountId>, coid: &[u8]) -> Option<&Order> {
+        self.l3.get_order_by_client_order_id(owner.into(), coid).ok()
+    }
+
     pub fn expire_orders(
         &mut self,
         is_bid: bool,
-        pair_id: impl Into<Vec<u8>>,
-        base_asset_id: impl Into<Vec<u8>>,
-        quote_asset_id: impl Into<Vec<u8>>,
-        managing_account_id: impl Into<Vec<u8>>,
+        pair_id: impl Into<PairId>,
+        base_asset_id: impl Into<AssetId>,
+        quote_asset_id: impl Into<AssetId>,
+        managing_account_id: impl Into<AccountId>,
         now: i64,
     ) -> Result<(), OrderBookError> {
         let pair_id = pair_id.into();
@@ -997,9 +1072,10 @@ impl OrderBook {
         for (order_id, order) in expired_orders {
             // emit event for the order expired
             event::emit_event(SpotEvent::SpotOrderExpired {
-                cid: order.cid.clone(),
+                cid: order.cid,
+                pair_id: Some(pair_id),
                 order_id: order_id.to_bytes().to_vec(),
-                maker_account_id: order.owner.clone(),
+                maker_account_id: order.owner,
                 is_bid,
                 price: order.price,
                 amnt: order.amnt,
@@ -1011,14 +1087,14 @@ impl OrderBook {
             });
             // emit event for transfer of the expired asset to order owner
             let expired_asset_id = if is_bid {
-                quote_asset_id.clone()
+                quote_asset_id
             } else {
-                base_asset_id.clone()
+                base_asset_id
             };
             event::emit_event(SpotEvent::Transfer {
-                cid: order.cid.clone(),
-                from: managing_account_id.clone(),
-                to: order.owner.clone(),
+                cid: order.cid,
+                from: managing_account_id,
+                to: order.owner,
                 asset: expired_asset_id,
                 amnt: order.amnt,
                 timestamp: now,
@@ -1031,7 +1107,7 @@ impl OrderBook {
                 None
             };
             self.update_price_level(
-                pair_id.clone(),
+                pair_id,
                 false,
                 is_bid,
                 order.price,
@@ -1045,8 +1121,8 @@ impl OrderBook {
 
     pub fn set_iceberg_quantity(
         &mut self,
-        cid: impl Into<Vec<u8>>,
-        pair_id: impl Into<Vec<u8>>,
+        cid: impl Into<ClientId>,
+        pair_id: impl Into<PairId>,
         is_bid: bool,
         order_id: OrderId,
         iqty: u64,
@@ -1074,6 +1150,7 @@ impl OrderBook {
         // emit event for the iceberg quantity changed
         event::emit_event(SpotEvent::SpotOrderIcebergQuantityChanged {
             cid,
+            pair_id: Some(pair_id),
             order_id: order_id.to_bytes().to_vec(),
             amnt: order.amnt,
             iqty: iqty,
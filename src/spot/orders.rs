@@ -1,18 +1,31 @@
-use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Error as _, SerializeMap, SerializeStruct};
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::ops::{Bound, Deref};
+use std::sync::Arc;
 use ulid::Ulid;
 
+use super::ids::{AccountId, ClientId};
+
 pub type OrderId = Ulid;
 
 /// Represents an order stored in the order book.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Order {
-    /// client order id
-    pub cid: Vec<u8>,
+    /// gateway client id
+    pub cid: ClientId,
+    /// user-supplied client order id, unique per owner
+    #[cfg_attr(feature = "schema", schemars(with = "Option<Vec<u8>>"))]
+    pub coid: Option<ClientOrderId>,
     /// order id
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub id: OrderId,
     /// owner of the order
-    pub owner: Vec<u8>,
+    pub owner: AccountId,
     /// is bid order
     pub is_bid: bool,
     /// price of the order in 8 decimals
@@ -36,9 +49,9 @@ pub struct Order {
 impl Order {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
-        cid: Vec<u8>,
+        cid: impl Into<ClientId>,
         id: OrderId,
-        owner: Vec<u8>,
+        owner: impl Into<AccountId>,
         is_bid: bool,
         price: u64,
         amnt: u64,
@@ -50,9 +63,10 @@ impl Order {
         fee_bps: i16,
     ) -> Self {
         Self {
-            cid,
+            cid: cid.into(),
+            coid: None,
             id,
-            owner,
+            owner: owner.into(),
             is_bid,
             price,
             amnt,
@@ -66,6 +80,145 @@ impl Order {
     }
 }
 
+/// Client order id supplied by the owner of an order.
+/// - the bytes are allocated once, when the command is decoded, and shared by the copies of the order, the client
+///   order id index of its book and its events.
+/// - serializes as the bytes, the same as the `Vec<u8>` it replaces.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct ClientOrderId(Arc<[u8]>);
+
+impl ClientOrderId {
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+}
+
+impl fmt::Debug for ClientOrderId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl Deref for ClientOrderId {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for ClientOrderId {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Indexes keyed by client order id are looked up by bytes
+impl Borrow<[u8]> for ClientOrderId {
+    fn borrow(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for ClientOrderId {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes.into())
+    }
+}
+
+impl From<&[u8]> for ClientOrderId {
+    fn from(bytes: &[u8]) -> Self {
+        Self(bytes.into())
+    }
+}
+
+impl From<&str> for ClientOrderId {
+    fn from(s: &str) -> Self {
+        Self(s.as_bytes().into())
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for ClientOrderId {
+    fn from(bytes: &[u8; N]) -> Self {
+        Self(bytes.as_slice().into())
+    }
+}
+
+impl PartialEq<[u8]> for ClientOrderId {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl PartialEq<Vec<u8>> for ClientOrderId {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Serialize for ClientOrderId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for ClientOrderId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_byte_buf(ClientOrderIdVisitor)
+    }
+}
+
+/// Accepts a client order id as bytes, a string or a sequence of bytes
+struct ClientOrderIdVisitor;
+
+impl<'de> Visitor<'de> for ClientOrderIdVisitor {
+    type Value = ClientOrderId;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("client order id bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<ClientOrderId, E> {
+        Ok(v.into())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<ClientOrderId, E> {
+        Ok(v.into())
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<ClientOrderId, E> {
+        Ok(v.into())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<ClientOrderId, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        Ok(bytes.into())
+    }
+}
+
+/// Reference to a resting order, either by the engine assigned order id
+/// or by the client order id supplied by its owner.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OrderRef {
+    /// engine assigned order id
+    Id(OrderId),
+    /// user-supplied client order id, resolved within the owner's orders
+    ClientOrderId(Vec<u8>),
+}
+
+impl From<OrderId> for OrderRef {
+    fn from(id: OrderId) -> Self {
+        OrderRef::Id(id)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct Node {
     pub prev: Option<OrderId>,
@@ -80,31 +233,223 @@ pub enum L3Error {
     OrderDoesNotExist(OrderId),
     #[error("iceberg quantity is bigger than whole amount")]
     IcebergQuantityIsBiggerThanWholeAmount,
+    #[error("client order id does not exist")]
+    ClientOrderIdDoesNotExist,
+    #[error("client order id is already used by the owner")]
+    DuplicateClientOrderId,
+    #[error("order already exists: {0}")]
+    OrderAlreadyExists(OrderId),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+/// Compact handle of an order slot in the `L3` arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OrderHandle(pub u32);
+
+impl OrderHandle {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Arena slot of an order, linked into the FIFO of its price level.
+/// - `prev` and `next` are the neighbours in the price level, none for the ends of the level
+///   and for an order popped off its level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot {
+    pub order: Order,
+    pub prev: Option<OrderHandle>,
+    pub next: Option<OrderHandle>,
+}
+
+/// Ends of the FIFO of orders at a price level, `head` is the oldest order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderQueue {
+    pub head: OrderHandle,
+    pub tail: OrderHandle,
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct L3 {
-    /// Mapping price -> head of the linked list in a price level.
-    pub price_head: BTreeMap<u64, OrderId>,
-    /// Mapping price -> last of the linked list in a price level.
-    pub price_tail: BTreeMap<u64, OrderId>,
-    /// Order details keyed by id. Mapping order_id -> Order.
-    pub order_nodes: HashMap<OrderId, Node>,
-    /// Mapping order_id -> Order.
-    pub orders: HashMap<OrderId, Order>,
+    /// Arena of order slots, a `None` slot is free.
+    pub slots: Vec<Option<Slot>>,
+    /// Free slots of the arena, reused before the arena grows.
+    pub free: Vec<OrderHandle>,
+    /// Mapping order_id -> slot of the order.
+    pub handles: HashMap<OrderId, OrderHandle>,
+    /// Mapping price -> FIFO of the orders in a price level.
+    pub levels: BTreeMap<u64, OrderQueue>,
+    /// Mapping owner -> ids of the owner's orders, ordered by id.
+    pub owner_orders: HashMap<AccountId, BTreeSet<OrderId>>,
+    /// Mapping owner -> client order id -> order id.
+    pub coid_orders: HashMap<AccountId, HashMap<ClientOrderId, OrderId>>,
     /// dust limit to determine if the order should be deleted
     pub dust: u64,
     /// Last displaced order when IDs collide.
     pub dormant_order: Option<OrderId>,
 }
 
+/// Books are equal when they hold the same orders linked in the same order at the same levels,
+/// whichever slots of the arena the orders are in.
+impl PartialEq for L3 {
+    fn eq(&self, other: &Self) -> bool {
+        self.dust == other.dust
+            && self.dormant_order == other.dormant_order
+            && self.handles.len() == other.handles.len()
+            && self.levels.len() == other.levels.len()
+            && self
+                .levels
+                .keys()
+                .all(|&price| self.head(price) == other.head(price) && self.tail(price) == other.tail(price))
+            && self
+                .orders()
+                .all(|order| other.get_order(order.id).ok() == Some(order) && self.node(order.id) == other.node(order.id))
+    }
+}
+
+impl Eq for L3 {}
+
+/// Serialized layout of `Order` in the 0.1.0 release, before client order ids.
+/// Orders of version 0 snapshots deserialize through it, see `spot::snapshot`.
+#[derive(Deserialize)]
+pub(crate) struct LinkedOrder {
+    cid: ClientId,
+    id: OrderId,
+    owner: AccountId,
+    is_bid: bool,
+    price: u64,
+    amnt: u64,
+    iqty: u64,
+    pqty: u64,
+    cqty: u64,
+    timestamp: i64,
+    expires_at: i64,
+    fee_bps: i16,
+}
+
+impl From<LinkedOrder> for Order {
+    fn from(order: LinkedOrder) -> Self {
+        Order::new(
+            order.cid,
+            order.id,
+            order.owner,
+            order.is_bid,
+            order.price,
+            order.amnt,
+            order.iqty,
+            order.pqty,
+            order.cqty,
+            order.timestamp,
+            order.expires_at,
+            order.fee_bps,
+        )
+    }
+}
+
+/// Serialized layout of `L3`, kept from when orders were stored in maps keyed by order id and linked by id.
+/// Snapshots deserialize through it into the arena, the owner and client order id indexes are rebuilt from the orders.
+/// Version 0 snapshots hold `LinkedOrder`s, later versions `Order`s.
+#[derive(Deserialize)]
+pub(crate) struct LinkedL3<O = Order> {
+    price_head: BTreeMap<u64, OrderId>,
+    price_tail: BTreeMap<u64, OrderId>,
+    order_nodes: HashMap<OrderId, Node>,
+    orders: HashMap<OrderId, O>,
+    dust: u64,
+    dormant_order: Option<OrderId>,
+}
+
+impl<O: Into<Order>> From<LinkedL3<O>> for L3 {
+    fn from(linked: LinkedL3<O>) -> Self {
+        let mut l3 = L3 { dust: linked.dust, dormant_order: linked.dormant_order, ..L3::default() };
+        for (_, order) in linked.orders {
+            let order: Order = order.into();
+            if let Some(coid) = &order.coid {
+                l3.coid_orders.entry(order.owner).or_default().insert(coid.clone(), order.id);
+            }
+            l3.allocate(order);
+        }
+        let handle = |handles: &HashMap<OrderId, OrderHandle>, id: &OrderId| handles.get(id).copied();
+        for (id, node) in &linked.order_nodes {
+            let Some(own) = handle(&l3.handles, id) else { continue };
+            let prev = node.prev.and_then(|prev| handle(&l3.handles, &prev));
+            let next = node.next.and_then(|next| handle(&l3.handles, &next));
+            if let Some(slot) = l3.slot_mut(own) {
+                slot.prev = prev;
+                slot.next = next;
+            }
+        }
+        for (price, head) in &linked.price_head {
+            let tail = linked.price_tail.get(price).and_then(|tail| handle(&l3.handles, tail));
+            if let (Some(head), Some(tail)) = (handle(&l3.handles, head), tail) {
+                l3.levels.insert(*price, OrderQueue { head, tail });
+            }
+        }
+        l3
+    }
+}
+
+/// Serializes `len` entries built by a closure as a map, an entry linking a free slot fails the serialization.
+struct MapOf<F> {
+    len: usize,
+    entries: F,
+}
+
+impl<F, I, K, V> Serialize for MapOf<F>
+where
+    F: Fn() -> I,
+    I: Iterator<Item = Option<(K, V)>>,
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len))?;
+        for entry in (self.entries)() {
+            let (key, value) = entry.ok_or_else(|| S::Error::custom("L3 links a free order slot"))?;
+            map.serialize_entry(&key, &value)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for L3 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let id = |handle: OrderHandle| self.slot(handle).map(|slot| slot.order.id);
+        let (levels, orders) = (self.levels.len(), self.handles.len());
+        let mut state = serializer.serialize_struct("L3", 6)?;
+        state.serialize_field("price_head", &MapOf {
+            len: levels,
+            entries: || self.levels.iter().map(move |(price, level)| Some((price, id(level.head)?))),
+        })?;
+        state.serialize_field("price_tail", &MapOf {
+            len: levels,
+            entries: || self.levels.iter().map(move |(price, level)| Some((price, id(level.tail)?))),
+        })?;
+        state.serialize_field("order_nodes", &MapOf {
+            len: orders,
+            entries: || self.orders().map(|order| Some((order.id, self.node(order.id)?))),
+        })?;
+        state.serialize_field("orders", &MapOf { len: orders, entries: || self.orders().map(|order| Some((order.id, order))) })?;
+        state.serialize_field("dust", &self.dust)?;
+        state.serialize_field("dormant_order", &self.dormant_order)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for L3 {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        LinkedL3::<Order>::deserialize(deserializer).map(L3::from)
+    }
+}
+
 impl L3 {
     pub fn new() -> Self {
         Self {
-            price_head: BTreeMap::new(),
-            price_tail: BTreeMap::new(),
-            order_nodes: HashMap::new(),
-            orders: HashMap::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            handles: HashMap::new(),
+            levels: BTreeMap::new(),
+            owner_orders: HashMap::new(),
+            coid_orders: HashMap::new(),
             dust: 1,
             dormant_order: None,
         }
@@ -118,6 +463,80 @@ impl L3 {
         }
     }
 
+    fn slot(&self, handle: OrderHandle) -> Option<&Slot> {
+        self.slots.get(handle.index()).and_then(Option::as_ref)
+    }
+
+    fn slot_mut(&mut self, handle: OrderHandle) -> Option<&mut Slot> {
+        self.slots.get_mut(handle.index()).and_then(Option::as_mut)
+    }
+
+    fn handle(&self, id: OrderId) -> Result<OrderHandle, L3Error> {
+        self.handles.get(&id).copied().ok_or(L3Error::OrderDoesNotExist(id))
+    }
+
+    /// Stores an unlinked order in a free slot and indexes it by id and owner
+    fn allocate(&mut self, order: Order) -> OrderHandle {
+        let (owner, id) = (order.owner, order.id);
+        let slot = Slot { order, prev: None, next: None };
+        let handle = match self.free.pop() {
+            Some(handle) => {
+                self.slots[handle.index()] = Some(slot);
+                handle
+            }
+            None => {
+                self.slots.push(Some(slot));
+                OrderHandle(u32::try_from(self.slots.len() - 1).expect("fewer than 2^32 order slots"))
+            }
+        };
+        self.handles.insert(id, handle);
+        self.owner_orders.entry(owner).or_default().insert(id);
+        handle
+    }
+
+    /// Unlinks an order from its price level, returning the price if the level becomes empty.
+    /// An order that is not linked into its level leaves the level untouched.
+    fn unlink(&mut self, handle: OrderHandle) -> Option<u64> {
+        let slot = self.slot_mut(handle)?;
+        let (price, prev, next) = (slot.order.price, slot.prev.take(), slot.next.take());
+        match (prev, next) {
+            (Some(prev), Some(next)) => {
+                if let Some(slot) = self.slot_mut(prev) {
+                    slot.next = Some(next);
+                }
+                if let Some(slot) = self.slot_mut(next) {
+                    slot.prev = Some(prev);
+                }
+            }
+            // the order is the tail, make prev the tail of the price level
+            (Some(prev), None) => {
+                if let Some(slot) = self.slot_mut(prev) {
+                    slot.next = None;
+                }
+                if let Some(level) = self.levels.get_mut(&price) {
+                    level.tail = prev;
+                }
+            }
+            // the order is the head, make next the head of the price level
+            (None, Some(next)) => {
+                if let Some(slot) = self.slot_mut(next) {
+                    slot.prev = None;
+                }
+                if let Some(level) = self.levels.get_mut(&price) {
+                    level.head = next;
+                }
+            }
+            // the order is the only one of the price level
+            (None, None) => {
+                if self.levels.get(&price).is_some_and(|level| level.head == handle) {
+                    self.levels.remove(&price);
+                    return Some(price);
+                }
+            }
+        }
+        None
+    }
+
     /// Sets the dust limit to determine if the order should be deleted
     pub fn set_dust(&mut self, dust: u64) {
         self.dust = dust;
@@ -128,73 +547,42 @@ impl L3 {
     pub fn insert_id(&mut self, price: u64, id: OrderId, _amount: u128) -> Result<(), L3Error> {
         Self::ensure_price(price)?;
         // ensure the order exists from the orders map
-        let order_node = self.order_nodes.get_mut(&id).ok_or(L3Error::OrderDoesNotExist(id))?;
-
-        let price_tail = self.price_tail.get(&price).copied();
-
-        // if the price level is not empty
-        if let Some(price_tail) = price_tail {
-            // insert the order node at the tail of the price level
-            // First, update the prev of the new order node to point to old tail
-            order_node.prev = Some(price_tail);
-            order_node.next = None;
-            // To avoid double mutable borrow of self.order_nodes, we do this after:
-            let price_tail_node_ptr = self.order_nodes.get_mut(&price_tail).ok_or(L3Error::OrderDoesNotExist(price_tail))?;
-            price_tail_node_ptr.next = Some(id);
-            // Update the tail pointer to the new node
-            self.price_tail.insert(price, id);
-        } 
-        // if the price level is empty
-        else {
-            // insert the order node at the head of the price level
-            order_node.prev = None;
-            order_node.next = None;
-            self.price_head.insert(price, id);
-            self.price_tail.insert(price, id);
+        let handle = self.handle(id)?;
+
+        // if the price level is not empty, insert the order at the tail of the price level
+        let prev = match self.levels.get_mut(&price) {
+            Some(level) => Some(std::mem::replace(&mut level.tail, handle)),
+            None => {
+                self.levels.insert(price, OrderQueue { head: handle, tail: handle });
+                None
+            }
+        };
+        if let Some(prev) = prev {
+            self.slot_mut(prev).ok_or(L3Error::OrderDoesNotExist(id))?.next = Some(handle);
         }
-
+        let slot = self.slot_mut(handle).ok_or(L3Error::OrderDoesNotExist(id))?;
+        slot.prev = prev;
+        slot.next = None;
         Ok(())
     }
 
-    /// Removes and returns the first order id at the given price level.
-    /// returns (order_id, is_empty)
-    /// - `order_id` is the id of the first order in the price level.
+    /// Removes and returns the first order at the given price level.
+    /// The order is moved out of the storage and its indexes, as if deleted.
+    /// returns (order, is_empty)
+    /// - `order` is the first order in the price level.
     /// - `is_empty` is true when the price level becomes empty.
     pub fn pop_front(&mut self, price: u64) -> Result<(Option<Order>, bool), L3Error> {
         Self::ensure_price(price)?;
-        let head_id = self.price_head.get(&price).copied();
-        if let Some(head_id) = head_id {
-            let order_node = self
-                .order_nodes
-                .get_mut(&head_id)
-                .ok_or(L3Error::OrderDoesNotExist(head_id))?;
-            let next = order_node.next;
-            if let Some(next) = next {
-                order_node.next = None;
-                let next_node = self
-                    .order_nodes
-                    .get_mut(&next)
-                    .ok_or(L3Error::OrderDoesNotExist(next))?;
-                next_node.prev = None;
-                // set next node as the new head of the price level
-                self.price_head.insert(price, next);
-                // return order from the head_id value
-                let order = self.orders.get(&head_id).ok_or(L3Error::OrderDoesNotExist(head_id))?;
-                Ok((Some(order.clone()), false))
-            } else {
-                self.price_head.remove(&price);
-                self.price_tail.remove(&price);
-                // return order from the head_id value
-                let order = self.orders.get(&head_id).ok_or(L3Error::OrderDoesNotExist(head_id))?;
-                Ok((Some(order.clone()), true))
-            }
-        } else {
-            Ok((None, true))
-        }
+        let Some(head) = self.levels.get(&price).map(|level| level.head) else {
+            return Ok((None, true));
+        };
+        let removed = self.remove(head);
+        let is_empty = removed.as_ref().is_none_or(|(_, emptied_price)| emptied_price.is_some());
+        Ok((removed.map(|(order, _)| order), is_empty))
     }
 
     pub fn set_iceberg_quantity(&mut self, id: OrderId, iqty: u64) -> Result<Order, L3Error> {
-        let order = self.orders.get_mut(&id).ok_or(L3Error::OrderDoesNotExist(id))?;
+        let order = self.get_order_mut(id)?;
         // update iqty of the order and public quantity of the order
         order.iqty = iqty;
         // update pqty from the difference between amnt and iqty
@@ -211,8 +599,8 @@ impl L3 {
     /// Creates a new order, assigning the next id. Returns the new order id.
     pub fn create_order(
         &mut self,
-        cid: impl Into<Vec<u8>>,
-        owner: impl Into<Vec<u8>>,
+        cid: impl Into<ClientId>,
+        owner: impl Into<AccountId>,
         is_bid: bool,
         price: u64,
         amnt: u64,
@@ -222,10 +610,8 @@ impl L3 {
         maker_fee_bps: i16,
     ) -> Result<Order, L3Error> {
         Self::ensure_price(price)?;
-        let cid = cid.into();
         // generate a new order id
         let id = Ulid::new();
-        let owner = owner.into();
         if iqty > amnt {
             return Err(L3Error::IcebergQuantityIsBiggerThanWholeAmount);
         }
@@ -245,20 +631,31 @@ impl L3 {
             maker_fee_bps,
         );
 
-        // Create a new node for the order
-        self.order_nodes.insert(
-            id,
-            Node {
-                prev: None,
-                next: None,
-            },
-        );
-        self.orders.insert(id, order.clone());
+        // store the order in a slot and link it at the tail of its price level
+        self.allocate(order.clone());
         self.insert_id(price, id, amnt as u128)?;
 
         Ok(order)
     }
 
+    /// Inserts an order keeping its id, at the tail of its price level.
+    /// Unlike `create_order` the order is taken as it is, to rebuild a book from its events.
+    pub fn insert_order(&mut self, order: Order) -> Result<(), L3Error> {
+        Self::ensure_price(order.price)?;
+        if self.contains(order.id) {
+            return Err(L3Error::OrderAlreadyExists(order.id));
+        }
+        if let Some(coid) = &order.coid {
+            if self.has_client_order_id(order.owner, coid) {
+                return Err(L3Error::DuplicateClientOrderId);
+            }
+            self.coid_orders.entry(order.owner).or_default().insert(coid.clone(), order.id);
+        }
+        let (price, id, amnt) = (order.price, order.id, order.amnt);
+        self.allocate(order);
+        self.insert_id(price, id, amnt as u128)
+    }
+
     /// Decreases the deposit amount for a given order id.
     /// If the order is the order that needs to be cleared by floating point precision, the amount_to_send will be the full amount of the order.
     /// Returns `(amount_to_send, deleted_price_if_level_empty)` where:
@@ -276,9 +673,9 @@ impl L3 {
         let mut should_delete = false;
 
         {
-            let order = match self.orders.get_mut(&id) {
-                Some(order) => order,
-                None => return Ok((0, None)),
+            let order = match self.get_order_mut(id) {
+                Ok(order) => order,
+                Err(_) => return Ok((0, None)),
             };
 
             let original = order.cqty;
@@ -308,58 +705,124 @@ impl L3 {
     /// Deletes an order from the storage, returning the price level if it becomes empty.
     /// - returns the price level if it becomes empty.
     pub fn delete_order(&mut self, id: OrderId) -> Result<Option<u64>, L3Error> {
+        let handle = self.handle(id)?;
+        self.remove(handle).map(|(_, emptied_price)| emptied_price).ok_or(L3Error::OrderDoesNotExist(id))
+    }
 
-        // remove order id from the list of orders
-        let price = self
-            .orders
-            .get(&id)
-            .ok_or(L3Error::OrderDoesNotExist(id))?
-            .price;
-
-        // remove order node from the order nodes map
-        let order_node = self.order_nodes.get_mut(&id).ok_or(L3Error::OrderDoesNotExist(id))?;
-        let prev = order_node.prev;
-        let next = order_node.next;
-        
-        let mut emptied_price = None;
-        // connect prev and next nodes
-        // if both prev and next are some, connect prev to next
-        if let (Some(prev), Some(next)) = (prev, next) {
-            let prev_node = self
-                .order_nodes
-                .get_mut(&prev)
-                .ok_or(L3Error::OrderDoesNotExist(prev))?;
-            prev_node.next = Some(next);
+    /// Moves an order out of its slot, returning it and the price level if it becomes empty.
+    fn remove(&mut self, handle: OrderHandle) -> Option<(Order, Option<u64>)> {
+        // unlink the order from its price level, then free its slot
+        let emptied_price = self.unlink(handle);
+        let slot = self.slots.get_mut(handle.index()).and_then(Option::take)?;
+        self.free.push(handle);
+        self.handles.remove(&slot.order.id);
+
+        // remove the order from the owner indexes
+        self.unindex_order(&slot.order);
+        Some((slot.order, emptied_price))
+    }
+
+    /// Removes an order from the owner and client order id indexes.
+    fn unindex_order(&mut self, order: &Order) {
+        let owner = order.owner;
+        if let Some(ids) = self.owner_orders.get_mut(&owner) {
+            ids.remove(&order.id);
+            if ids.is_empty() {
+                self.owner_orders.remove(&owner);
+            }
         }
-        // if prev is some and next is none, make prev the tail of the price level
-        else if let Some(prev) = prev {
-            let prev_node = self
-                .order_nodes
-                .get_mut(&prev)
-                .ok_or(L3Error::OrderDoesNotExist(prev))?;
-            prev_node.next = None;
-            self.price_tail.insert(price, prev);
+        if let Some(coid) = &order.coid {
+            if let Some(coids) = self.coid_orders.get_mut(&owner) {
+                coids.remove(coid);
+                if coids.is_empty() {
+                    self.coid_orders.remove(&owner);
+                }
+            }
         }
-        // if prev is none and next is some, make next the head of the price level
-        else if let Some(next) = next {
-            let next_node = self
-                .order_nodes
-                .get_mut(&next)
-                .ok_or(L3Error::OrderDoesNotExist(next))?;
-            next_node.prev = None;
-            self.price_head.insert(price, next);
+    }
+
+    /// Assigns a client order id to an existing order.
+    /// The client order id must not be used by another order of the same owner.
+    pub fn set_client_order_id(&mut self, id: OrderId, coid: impl Into<ClientOrderId>) -> Result<(), L3Error> {
+        let coid = coid.into();
+        let handle = self.handle(id)?;
+        let slot = self.slots[handle.index()].as_mut().ok_or(L3Error::OrderDoesNotExist(id))?;
+        let coids = self.coid_orders.entry(slot.order.owner).or_default();
+        match coids.get(&coid) {
+            Some(existing) if *existing != id => return Err(L3Error::DuplicateClientOrderId),
+            _ => {}
         }
-        // if both prev and next are none, the price level is empty, and return the price level
-        else {
-            self.price_head.remove(&price);
-            self.price_tail.remove(&price);
-            self.order_nodes.remove(&id);
-            emptied_price = Some(price);
+        // release the previous client order id of the order
+        if let Some(previous) = slot.order.coid.take() {
+            coids.remove(&previous);
         }
-        
-        // remove order from the orders map
-        self.orders.remove(&id);
-        Ok(emptied_price)
+        coids.insert(coid.clone(), id);
+        slot.order.coid = Some(coid);
+        Ok(())
+    }
+
+    /// Returns true if the client order id is used by one of the owner's orders.
+    pub fn has_client_order_id(&self, owner: impl Into<AccountId>, coid: &[u8]) -> bool {
+        self.order_id_by_client_order_id(owner, coid).is_some()
+    }
+
+    /// Resolves a client order id within the owner's orders.
+    pub fn order_id_by_client_order_id(&self, owner: impl Into<AccountId>, coid: &[u8]) -> Option<OrderId> {
+        self.coid_orders.get(&owner.into()).and_then(|coids| coids.get(coid)).copied()
+    }
+
+    /// Resolves an order reference within the owner's orders.
+    pub fn resolve(&self, owner: impl Into<AccountId>, order: &OrderRef) -> Result<OrderId, L3Error> {
+        match order {
+            OrderRef::Id(id) => Ok(*id),
+            OrderRef::ClientOrderId(coid) => self
+                .order_id_by_client_order_id(owner, coid)
+                .ok_or(L3Error::ClientOrderIdDoesNotExist),
+        }
+    }
+
+    /// Returns the order placed by the owner with the given client order id.
+    pub fn get_order_by_client_order_id(&self, owner: impl Into<AccountId>, coid: &[u8]) -> Result<&Order, L3Error> {
+        let id = self
+            .order_id_by_client_order_id(owner, coid)
+            .ok_or(L3Error::ClientOrderIdDoesNotExist)?;
+        self.get_order(id)
+    }
+
+    fn owner_ids(&self, owner: impl Into<AccountId>) -> Option<&BTreeSet<OrderId>> {
+        self.owner_orders.get(&owner.into())
+    }
+
+    /// Collects the ids of the owner's orders, ordered by id.
+    pub fn get_order_ids_by_owner(&self, owner: impl Into<AccountId>) -> Vec<OrderId> {
+        self.owner_ids(owner)
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Collects up to `n` ids of the owner's orders after the `after` id, ordered by id.
+    pub fn get_order_ids_by_owner_after(&self, owner: impl Into<AccountId>, after: Option<OrderId>, n: usize) -> Vec<OrderId> {
+        let ids = match self.owner_ids(owner) {
+            Some(ids) => ids,
+            None => return Vec::new(),
+        };
+        match after {
+            Some(after) => ids
+                .range((Bound::Excluded(after), Bound::Unbounded))
+                .take(n)
+                .copied()
+                .collect(),
+            None => ids.iter().take(n).copied().collect(),
+        }
+    }
+
+    /// Collects the owner's orders, ordered by id.
+    pub fn get_orders_by_owner(&self, owner: impl Into<AccountId>) -> Vec<&Order> {
+        self.owner_ids(owner)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.get_order(*id).ok())
+            .collect()
     }
 
     /// Returns the next id that would be assigned on order creation.
@@ -367,96 +830,106 @@ impl L3 {
         Ulid::new()
     }
 
+    /// Iterates the orders of a price level from the front.
+    pub fn level_orders(&self, price: u64) -> impl Iterator<Item = &Order> + '_ {
+        let mut current = self.levels.get(&price).map(|level| level.head);
+        std::iter::from_fn(move || {
+            let slot = self.slot(current?)?;
+            current = slot.next;
+            Some(&slot.order)
+        })
+    }
+
     /// Collects up to `n` order ids from the front of the specified price level.
     pub fn get_order_ids(&self, price: u64, n: u32) -> Vec<OrderId> {
-        let mut result = Vec::with_capacity(n as usize);
-        let mut current = self.price_head.get(&price).copied();
-
-        while let Some(id) = current {
-            result.push(id);
-            if result.len() as u32 >= n {
-                break;
-            }
-
-            current = self.order_nodes.get(&id).and_then(|node| node.next);
-        }
-
-        result
+        self.level_orders(price).take(n as usize).map(|order| order.id).collect()
     }
 
     /// Collects up to `n` orders from the front of the specified price level.
-    pub fn get_orders(&self, price: u64, n: u32) -> Vec<Order> {
-        self.get_order_ids(price, n)
-            .into_iter()
-            .filter_map(|id| self.orders.get(&id).cloned())
-            .collect()
+    pub fn get_orders(&self, price: u64, n: u32) -> Vec<&Order> {
+        self.level_orders(price).take(n as usize).collect()
     }
 
     /// Collects orders within the `[start, end)` window from the specified price level.
-    pub fn get_orders_in_range(&self, price: u64, start: u32, end: u32) -> Vec<Order> {
+    pub fn get_orders_in_range(&self, price: u64, start: u32, end: u32) -> Vec<&Order> {
         if start >= end {
             return Vec::new();
         }
+        self.level_orders(price)
+            .skip(start as usize)
+            .take((end - start) as usize)
+            .collect()
+    }
 
-        let mut current_index = 0;
-        let mut current = self.price_head.get(&price).copied();
-        let mut result = Vec::with_capacity((end - start) as usize);
-
-        while let Some(id) = current {
-            if current_index >= start && current_index < end {
-                if let Some(order) = self.orders.get(&id) {
-                    result.push(order.clone());
-                }
-            }
+    /// Iterates all orders in the storage, including orders popped off their price level.
+    pub fn orders(&self) -> impl Iterator<Item = &Order> + '_ {
+        self.slots.iter().flatten().map(|slot| &slot.order)
+    }
 
-            if current_index >= end {
-                break;
-            }
+    /// Iterates the prices of the non-empty price levels in ascending order.
+    pub fn prices(&self) -> impl Iterator<Item = u64> + '_ {
+        self.levels.keys().copied()
+    }
 
-            current = self.order_nodes.get(&id).and_then(|node| node.next);
-            current_index += 1;
-        }
+    pub fn contains(&self, id: OrderId) -> bool {
+        self.handles.contains_key(&id)
+    }
 
-        result
+    /// Returns the neighbours of an order in its price level by order id.
+    pub fn node(&self, id: OrderId) -> Option<Node> {
+        let slot = self.slot(*self.handles.get(&id)?)?;
+        let id = |handle: Option<OrderHandle>| handle.and_then(|handle| self.slot(handle)).map(|slot| slot.order.id);
+        Some(Node { prev: id(slot.prev), next: id(slot.next) })
     }
 
     pub fn head(&self, price: u64) -> Option<OrderId> {
-        self.price_head.get(&price).copied()
+        let level = self.levels.get(&price)?;
+        self.slot(level.head).map(|slot| slot.order.id)
     }
 
     pub fn tail(&self, price: u64) -> Option<OrderId> {
-        self.price_tail.get(&price).copied()
+        let level = self.levels.get(&price)?;
+        self.slot(level.tail).map(|slot| slot.order.id)
     }
 
     pub fn is_empty(&self, price: u64) -> bool {
-        self.price_head.get(&price).is_none()
+        !self.levels.contains_key(&price)
+    }
+
+    /// Whether the price level holds an order of the side, levels are shared by bids and asks
+    /// when a taker is placed at the opposite head price.
+    pub fn has_side_orders(&self, price: u64, is_bid: bool) -> bool {
+        self.level_orders(price).any(|order| order.is_bid == is_bid)
     }
 
     pub fn next(&self, _price: u64, current: OrderId) -> Option<OrderId> {
         // get the next node in the price level from the current node
-        let next_node = self.order_nodes.get(&current).and_then(|node| node.next);
-        match next_node {
-            Some(next) => Some(next),
-            None => None,   
-        }
+        self.node(current).and_then(|node| node.next)
     }
 
     pub fn get_order(&self, id: OrderId) -> Result<&Order, L3Error> {
-        self.orders.get(&id).ok_or(L3Error::OrderDoesNotExist(id))
+        self.handles
+            .get(&id)
+            .and_then(|handle| self.slot(*handle))
+            .map(|slot| &slot.order)
+            .ok_or(L3Error::OrderDoesNotExist(id))
+    }
+
+    /// Returns an order to update in place.
+    /// Changing its `id`, `owner`, `coid` or `price` does not update the indexes or move it to another level.
+    pub fn get_order_mut(&mut self, id: OrderId) -> Result<&mut Order, L3Error> {
+        let handle = self.handle(id)?;
+        self.slot_mut(handle)
+            .map(|slot| &mut slot.order)
+            .ok_or(L3Error::OrderDoesNotExist(id))
     }
 
     /// Remove orders that have expired. Returns removed order ids.
     pub fn remove_dormant_orders(&mut self, now: i64) -> Vec<(OrderId, Order)> {
         let expired_orders: Vec<(OrderId, Order)> = self
-            .orders
-            .iter()
-            .filter_map(|(id, order)| {
-                if order.expires_at <= now {
-                    Some((*id, order.clone()))
-                } else {
-                    None
-                }
-            })
+            .orders()
+            .filter(|order| order.expires_at <= now)
+            .map(|order| (order.id, order.clone()))
             .collect();
 
         for (id, _) in &expired_orders {
@@ -4,21 +4,27 @@ use serde::{Deserialize, Serialize};
 
 use crate::spot::Order;
 
+use super::command::{LimitOrder, MarketOrder};
 use super::event::{self, SpotEvent};
+use super::ids::{AccountId, AssetId, ClientId, PairId};
 use super::orderbook::{OrderBook, OrderBookError};
-use super::orders::OrderId;
+use super::orders::{L3Error, OrderId, OrderRef};
 use super::time_in_force::TimeInForce;
 
 use super::market::L1;
+use super::market_order::{self, MarketOrderFill, MarketOrderSize};
+use super::prices::DEFAULT_SCALES;
+use super::ticker::{RollingTicker, TICKER_BUCKET_MS, TICKER_WINDOW_MS};
+use super::trades::{CandleAggregator, CandleInterval, DEFAULT_CANDLE_HISTORY};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct Pair {
     /// Pair ID
-    pub pair_id: Vec<u8>,
+    pub pair_id: PairId,
     /// base asset id
-    pub base_asset_id: Vec<u8>,
+    pub base_asset_id: AssetId,
     /// quote asset id
-    pub quote_asset_id: Vec<u8>,
+    pub quote_asset_id: AssetId,
     /// L1 state
     pub l1: L1,
     /// market price
@@ -26,58 +32,64 @@ pub struct Pair {
     /// Orderbook
     pub orderbook: OrderBook,
     /// list of exchange clients which shares the orderbook 
-    pub clients: Vec<Vec<u8>>,
+    pub clients: Vec<ClientId>,
     /// Hash map of client id -> client admin account id
-    pub client_admin_account_ids: HashMap<Vec<u8>, Vec<u8>>,
+    pub client_admin_account_ids: HashMap<ClientId, AccountId>,
     /// Hash map of client id -> client fee account id
-    pub client_fee_account_ids: HashMap<Vec<u8>, Vec<u8>>,
+    pub client_fee_account_ids: HashMap<ClientId, AccountId>,
+    /// OHLCV candles built from the trades of the pair
+    pub candles: CandleAggregator,
+    /// 24h rolling ticker statistics of the pair
+    pub ticker: RollingTicker,
 }
 
 impl Pair {
 
     pub fn new() -> Self {
+        // maintain aggregated depth for the default scales
+        let mut orderbook = OrderBook::default();
+        orderbook.l2.set_scales(&DEFAULT_SCALES);
         Self {
-            pair_id: Vec::new(),
-            base_asset_id: Vec::new(),
-            quote_asset_id: Vec::new(),
+            pair_id: PairId::default(),
+            base_asset_id: AssetId::default(),
+            quote_asset_id: AssetId::default(),
             l1: L1::default(),
             market_price: None,
-            orderbook: OrderBook::default(),
+            orderbook,
             clients: Vec::new(),
             client_admin_account_ids: HashMap::new(),
             client_fee_account_ids: HashMap::new(),
+            candles: CandleAggregator::new(&CandleInterval::ALL, DEFAULT_CANDLE_HISTORY),
+            ticker: RollingTicker::new(TICKER_WINDOW_MS, TICKER_BUCKET_MS),
         }
     }
 
     pub fn add_client(
         &mut self,
-        cid: impl Into<Vec<u8>>,
-        admin_account_id: impl Into<Vec<u8>>,
-        fee_account_id: impl Into<Vec<u8>>,
+        cid: impl Into<ClientId>,
+        admin_account_id: impl Into<AccountId>,
+        fee_account_id: impl Into<AccountId>,
+        timestamp: i64,
     ) {
         let cid = cid.into();
         let admin_account_id = admin_account_id.into();
         let fee_account_id = fee_account_id.into();
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as i64;
 
         // Store client and associated accounts
-        self.clients.push(cid.clone());
+        self.clients.push(cid);
         self.client_admin_account_ids
-            .insert(cid.clone(), admin_account_id.clone());
+            .insert(cid, admin_account_id);
         self.client_fee_account_ids
-            .insert(cid.clone(), fee_account_id.clone());
+            .insert(cid, fee_account_id);
 
         // Set up fee account for the orderbook
         self.orderbook
             .fee_recipients
-            .insert(cid.clone(), fee_account_id.clone());
+            .insert(cid, fee_account_id);
 
         // Emit event using the values we already have, avoiding extra lookups
         event::emit_event(SpotEvent::SpotPairClientAccountChanged {
-            pair_id: self.pair_id.clone(),
+            pair_id: self.pair_id,
             cid: Some(cid),
             admin_account_id: Some(admin_account_id),
             fee_account_id: Some(fee_account_id),
@@ -85,7 +97,7 @@ impl Pair {
         });
     }
 
-    pub fn remove_client(&mut self, cid: impl Into<Vec<u8>>) {
+    pub fn remove_client(&mut self, cid: impl Into<ClientId>, timestamp: i64) {
         let cid = cid.into();
 
         // Remove from in-memory structures
@@ -98,13 +110,8 @@ impl Pair {
         // Emit an event indicating the client was removed from this pair.
         // We keep `cid` so downstream consumers know which client changed,
         // and set admin/fee accounts to None to indicate removal.
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as i64;
-
         event::emit_event(SpotEvent::SpotPairClientAccountChanged {
-            pair_id: self.pair_id.clone(),
+            pair_id: self.pair_id,
             cid: Some(cid),
             admin_account_id: None,
             fee_account_id: None,
@@ -145,24 +152,36 @@ impl Pair {
 
             // Get maker order and refresh taker order before executing
             let maker_order = self.orderbook.l3.get_order(maker_order_id)?.clone();
+            // the next order is taken before the fill deletes the maker from the level
+            let next_maker_id = self.orderbook.l3.next(price, maker_order_id);
+            // price levels are shared by both sides in L3, skip the orders of the taker's side
+            if maker_order.is_bid == is_matching_asks {
+                match next_maker_id {
+                    Some(id) => {
+                        maker_order_id = id;
+                        continue;
+                    }
+                    None => break,
+                }
+            }
             let taker_current = match self.orderbook.l3.get_order(taker_id) {
                 Ok(order) => order.clone(),
                 Err(_) => break,
             };
 
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as i64;
+            // the trade is stamped with the taker's command, like the fills of a market order
+            let timestamp = taker_current.timestamp;
 
-            self.orderbook.execute(
+            let trade = self.orderbook.execute_trade(
                 taker_current,
                 maker_order,
-                self.pair_id.clone(),
-                self.base_asset_id.clone(),
-                self.quote_asset_id.clone(),
-                now,
+                self.pair_id,
+                self.base_asset_id,
+                self.quote_asset_id,
+                timestamp,
             )?;
+            self.candles.apply_trade(&trade);
+            self.ticker.apply_trade(&trade);
 
             match self.orderbook.l3.get_order(taker_id) {
                 Ok(updated) => current_remaining = updated.cqty,
@@ -172,7 +191,7 @@ impl Pair {
             }
 
             // traverse to the next order at the price level
-            maker_order_id = match self.orderbook.l3.next(price, maker_order_id) {
+            maker_order_id = match next_maker_id {
                 Some(id) => id,
                 None => break, // No more orders
             };
@@ -278,11 +297,12 @@ impl Pair {
             self.l1.set_lmp(lmp);
             event::emit_event(SpotEvent::SpotNewMarketPrice {
                 client_ids: self.clients.clone(),
-                pair_id: self.pair_id.clone(),
-                base_asset_id: self.base_asset_id.clone(),
-                quote_asset_id: self.quote_asset_id.clone(),
+                pair_id: self.pair_id,
+                base_asset_id: self.base_asset_id,
+                quote_asset_id: self.quote_asset_id,
                 price: lmp,
-                timestamp: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as i64,
+                is_make_price: false,
+                timestamp: taker_order.timestamp,
             });
         }
 
@@ -322,17 +342,18 @@ impl Pair {
         self.market_price = Some(make_price);
         event::emit_event(SpotEvent::SpotNewMarketPrice {
             client_ids: self.clients.clone(),
-            pair_id: self.pair_id.clone(),
-            base_asset_id: self.base_asset_id.clone(),
-            quote_asset_id: self.quote_asset_id.clone(),
+            pair_id: self.pair_id,
+            base_asset_id: self.base_asset_id,
+            quote_asset_id: self.quote_asset_id,
             price: make_price,
-            timestamp: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as i64,
+            is_make_price: true,
+            timestamp: maker_order.timestamp,
         });
         match time_in_force {
             TimeInForce::ImmediateOrCancel => {
                 // IOC: Fill what can be filled immediately, cancel the rest
                 if maker_order.cqty > 0 {
-                    self.orderbook.cancel_order(maker_order.cid.clone(), self.pair_id.clone(), maker_order.is_bid, maker_order.id, maker_order.owner.clone())?;
+                    self.orderbook.cancel_order(maker_order.cid, self.pair_id, maker_order.id, maker_order.owner)?;
                 }
                 Ok(())
             }
@@ -347,28 +368,36 @@ impl Pair {
                 } 
                 Ok(())
             }
-            _ => Err(OrderBookError::UnsupportedTimeInForce),
+            TimeInForce::FillOrKill => {
+                // FOK: checked to fill completely before matching, cancel anything left over
+                if maker_order.cqty > 0 {
+                    self.orderbook.cancel_order(maker_order.cid, self.pair_id, maker_order.id, maker_order.owner)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether a limit order at the price would match on arrival, at the head of the other side
+    pub fn crosses(&self, is_bid: bool, price: u64) -> bool {
+        if is_bid {
+            self.orderbook.l2.ask_head().is_some_and(|ask| ask <= price)
+        } else {
+            self.orderbook.l2.bid_head().is_some_and(|bid| bid >= price)
         }
     }
 
     fn can_fill_fok(&self, limit_price: u64, taker_order: &Order) -> Result<bool, OrderBookError> {
         let mut remaining = taker_order.cqty;
 
-        let prices = if taker_order.is_bid {
-            self.orderbook.l2.collect_ask_prices()
+        // only the levels crossing the limit price, best first
+        let prices: Vec<u64> = if taker_order.is_bid {
+            self.orderbook.l2.ask_prices.range(..=limit_price).copied().collect()
         } else {
-            self.orderbook.l2.collect_bid_prices()
+            self.orderbook.l2.bid_prices.range(limit_price..).rev().copied().collect()
         };
 
         for price in prices {
-            if taker_order.is_bid {
-                if price > limit_price {
-                    break;
-                }
-            } else if price < limit_price {
-                break;
-            }
-
             let level_cqty = if taker_order.is_bid {
                 match self.orderbook.l2.current_ask_level(price) {
                     Some(level) => level,
@@ -399,60 +428,38 @@ impl Pair {
     }
 
     /// Matches against existing orders first, then places remaining in orderbook based on time_in_force
-    /// - returns the order id and if a dormant order was found.
-    /// - `cid` is the gateway client id.
-    /// - `existing_order_id` is the order id to update with the transaction if it exists.
-    /// - `owner` is the owner of the order.
-    /// - `price` is the price of the order.
-    /// - `amount` is the total amount of the order.
-    /// - `public_amount` is the public amount of the order in case of iceberg order.
-    /// - `timestamp` is the timestamp of the order.
-    /// - `expires_at` is the expiring timestamp of the order.
-    /// - `maker_fee_bps` is the maker fee basis points of the order.
-    /// - `taker_fee_bps` is the taker fee basis points of the order.
-    /// - `time_in_force` is the time in force of the order.
-    pub fn limit_sell(
-        &mut self,
-        // gateway client id
-        cid: impl Into<Vec<u8>>,
-        // order id to update with the transaction if it exists
-        existing_order_id: Option<OrderId>, // None if new order
-        // owner of the order
-        owner: impl Into<Vec<u8>>,
-        // price of the order
-        price: u64,
-        // total amount of the order
-        amnt: u64,
-        // iceberg quantity of the order
-        iqty: u64,
-        // timestamp of the order
-        timestamp: i64,
-        // expiring timestamp of the order
-        expires_at: i64,
-        // maker fee basis points of the order
-        maker_fee_bps: i16,
-        // taker fee basis points of the order
-        taker_fee_bps: i16,
-        // time in force of the order
-        time_in_force: TimeInForce,
-    ) -> Result<OrderId, OrderBookError> {
+    /// - returns the order id of the order.
+    /// - `order.existing_order_id` must be an order of the gateway client `order.cid`.
+    /// - `order.coid` must be unique among the owner's resting orders.
+    /// - a post-only order crossing the book is rejected with `PostOnlyWouldTake` before anything is placed.
+    /// - the order is placed on this pair, `order.pair_id` is how `MatchingEngine` routes it here.
+    pub fn limit_sell(&mut self, order: LimitOrder) -> Result<OrderId, OrderBookError> {
+        let LimitOrder {
+            cid, existing_order_id, owner, coid, price, amnt, iqty, timestamp, expires_at, maker_fee_bps,
+            taker_fee_bps, time_in_force, post_only, ..
+        } = order;
         // If existing order id is provided, update the order
-        let cid_vec: Vec<u8> = cid.into();
-        let owner_vec: Vec<u8> = owner.into();
         if let Some(existing_order_id) = existing_order_id {
             let order = self.orderbook.l3.get_order(existing_order_id)?;
-            if order.cid != cid_vec {
+            if order.cid != cid {
                 return Err(OrderBookError::OrderNotOwnedBySender);
             }
         }
+        if post_only && self.crosses(false, price) {
+            return Err(OrderBookError::PostOnlyWouldTake);
+        }
 
         // place taker order to feed into _limit_order function
-        let taker_order = self.orderbook.place_ask(
-            cid_vec.clone(),
-            self.pair_id.clone(),
-            self.base_asset_id.clone(),
-            self.quote_asset_id.clone(),
-            owner_vec.clone(),
+        self.ensure_client_order_id(owner, coid.as_deref())?;
+
+        let taker_order = self.orderbook.place_order(
+            cid,
+            self.pair_id,
+            self.base_asset_id,
+            self.quote_asset_id,
+            owner,
+            coid,
+            false,
             price,
             amnt,
             iqty,
@@ -465,11 +472,10 @@ impl Pair {
             && !self.can_fill_fok(price, &taker_order)?
         {
             self.orderbook.cancel_order(
-                cid_vec.clone(),
-                self.pair_id.clone(),
-                false,
+                cid,
+                self.pair_id,
                 taker_order.id,
-                owner_vec.clone(),
+                owner,
             )?;
             return Err(OrderBookError::OrderNotFullyFilled);
         }
@@ -488,62 +494,38 @@ impl Pair {
 
     /// Place a limit buy order (bid order)
     /// Matches against existing orders first, then places remaining in orderbook based on time_in_force
-    /// - returns the order id and if a dormant order was found.
-    /// - `cid` is the gateway client id.
-    /// - `existing_order_id` is the order id to update with the transaction if it exists.
-    /// - `owner` is the owner of the order.
-    /// - `price` is the price of the order.
-    /// - `amount` is the total amount of the order.
-    /// - `public_amount` is the public amount of the order in case of iceberg order.
-    /// - `timestamp` is the timestamp of the order.
-    /// - `expires_at` is the expiring timestamp of the order.
-    /// - `maker_fee_bps` is the maker fee basis points of the order.
-    /// - `taker_fee_bps` is the taker fee basis points of the order.
-    /// - `time_in_force` is the time in force of the order.
-    pub fn limit_buy(
-        &mut self,
-        // gateway client id
-        cid: impl Into<Vec<u8>>,
-        // order id to update with the transaction if it exists
-        existing_order_id: Option<OrderId>, // None if new order
-        // owner of the order
-        owner: impl Into<Vec<u8>>,
-        // price of the order
-        price: u64,
-        // total amount of the order
-        amnt: u64,
-        // iceberg quantity of the order
-        iqty: u64,
-        // timestamp of the order
-        timestamp: i64,
-        // expiring timestamp of the order
-        expires_at: i64,
-        // maker fee basis points of the order
-        maker_fee_bps: i16,
-        // taker fee basis points of the order
-        taker_fee_bps: i16,
-        // time in force of the order
-        time_in_force: TimeInForce,
-    ) -> Result<(), OrderBookError> {
-
-        let cid_vec: Vec<u8> = cid.into();
-        let owner_vec: Vec<u8> = owner.into();
+    /// - `order.existing_order_id` must be an order of the gateway client `order.cid` and of `order.owner`.
+    /// - `order.coid` must be unique among the owner's resting orders.
+    /// - a post-only order crossing the book is rejected with `PostOnlyWouldTake` before anything is placed.
+    /// - the order is placed on this pair, `order.pair_id` is how `MatchingEngine` routes it here.
+    pub fn limit_buy(&mut self, order: LimitOrder) -> Result<(), OrderBookError> {
+        let LimitOrder {
+            cid, existing_order_id, owner, coid, price, amnt, iqty, timestamp, expires_at, maker_fee_bps,
+            taker_fee_bps, time_in_force, post_only, ..
+        } = order;
         if let Some(existing_order_id) = existing_order_id {
             let order = self.orderbook.l3.get_order(existing_order_id)?;
-            if order.cid != cid_vec {
+            if order.cid != cid {
                 return Err(OrderBookError::OrderNotSupportedByClientId);
             }
-            if order.owner != owner_vec {
+            if order.owner != owner {
                 return Err(OrderBookError::OrderNotOwnedBySender);
             }
         }
+        if post_only && self.crosses(true, price) {
+            return Err(OrderBookError::PostOnlyWouldTake);
+        }
+
+        self.ensure_client_order_id(owner, coid.as_deref())?;
 
-        let taker_order = self.orderbook.place_bid(
-            cid_vec.clone(),
-            self.pair_id.clone(),
-            self.base_asset_id.clone(),
-            self.quote_asset_id.clone(),
-            owner_vec.clone(),
+        let taker_order = self.orderbook.place_order(
+            cid,
+            self.pair_id,
+            self.base_asset_id,
+            self.quote_asset_id,
+            owner,
+            coid,
+            true,
             price,
             amnt,
             iqty,
@@ -556,11 +538,10 @@ impl Pair {
             && !self.can_fill_fok(price, &taker_order)?
         {
             self.orderbook.cancel_order(
-                cid_vec.clone(),
-                self.pair_id.clone(),
-                true,
+                cid,
+                self.pair_id,
                 taker_order.id,
-                owner_vec.clone(),
+                owner,
             )?;
             return Err(OrderBookError::OrderNotFullyFilled);
         }
@@ -576,196 +557,186 @@ impl Pair {
     }
 
     /// Execute a market sell order
-    /// Matches against existing orders first (market orders match at any price)
-    /// - returns the match result.
-    /// - `cid` is the gateway client id.
-    /// - `existing_order_id` is the order id to update with the transaction if it exists.
-    /// - `owner` is the owner of the order.
-    /// - `amount` is the total amount of the order.
-    /// - `clear` is whether to clear the order.
-    /// - `taker_fee_bps` is the taker fee basis points of the order.
-    /// - `time_in_force` is the time in force of the order.
-    pub fn market_sell(
-        &mut self,
-        // gateway client id
-        cid: impl Into<Vec<u8>>,
-        // existing order id to update with the transaction if it exists
-        existing_order_id: Option<OrderId>, // None if new order
-        // owner of the order
-        owner: impl Into<Vec<u8>>,
-        // total amount of the order
-        amnt: u64,
-        // iceberg quantity of the order
-        iqty: u64,
-        // timestamp of the order
-        timestamp: i64,
-        // expiring timestamp of the order
-        expires_at: i64,
-        // maker fee basis points of the order
-        maker_fee_bps: i16,
-        // taker fee basis points of the order
-        taker_fee_bps: i16,
-        // time in force of the order
-        time_in_force: TimeInForce,
-    ) -> Result<(), OrderBookError> {
+    /// Matches against the bids from the best price down, level by level
+    /// - returns the fill of the order.
+    /// - `order.size` is the exact base amount to sell or the exact quote amount to receive.
+    /// - the order is placed on this pair, `order.pair_id` is how `MatchingEngine` routes it here.
+    pub fn market_sell(&mut self, order: MarketOrder) -> Result<MarketOrderFill, OrderBookError> {
+        self._market_order(false, order)
+    }
 
-        let cid_vec: Vec<u8> = cid.into();
-        let owner_vec: Vec<u8> = owner.into();
+    /// Execute a market buy order
+    /// Matches against the asks from the best price up, level by level
+    /// - returns the fill of the order.
+    /// - `order.size` is the exact base amount to buy or the exact quote amount to spend.
+    /// - the order is placed on this pair, `order.pair_id` is how `MatchingEngine` routes it here.
+    pub fn market_buy(&mut self, order: MarketOrder) -> Result<MarketOrderFill, OrderBookError> {
+        self._market_order(true, order)
+    }
+
+    /// Place a market order (internal helper)
+    /// The fills are planned against the book first, then the taker order is placed at the best opposite
+    /// price for the amount the fills need and executed maker by maker.
+    /// - whatever the taker order holds after matching is refunded unless the book ran out of orders,
+    ///   in which case the time in force decides whether the rest is cancelled or rests on the book.
+    /// - matching stops at the protection price set by the market protection band of `L1`, the rest is
+    ///   cancelled as IOC and reported in a `SpotMarketOrderProtected` event.
+    /// - fill or kill orders are rejected before placing when the book cannot fill the whole size.
+    /// - the taker order is placed at the last price the fills reach, which its unfilled part is locked and rests at.
+    /// - makers expire and fills are stamped at the order's `timestamp`.
+    fn _market_order(&mut self, is_bid: bool, order: MarketOrder) -> Result<MarketOrderFill, OrderBookError> {
+        let MarketOrder {
+            cid, existing_order_id, owner, coid, size, public_amount: iqty, timestamp, expires_at, maker_fee_bps,
+            taker_fee_bps, time_in_force, ..
+        } = order;
         // if existing order id is provided, update the order
         if let Some(existing_order_id) = existing_order_id {
             let order = self.orderbook.l3.get_order(existing_order_id)?;
-            if order.cid != cid_vec {
+            if order.cid != cid {
                 return Err(OrderBookError::OrderNotSupportedByClientId);
             }
-            if order.owner != owner_vec {
+            if order.owner != owner {
                 return Err(OrderBookError::OrderNotOwnedBySender);
             }
         }
 
-        // get the best bid price
-        let best_bid_price = self.orderbook.l2.bid_head();
-        if best_bid_price.is_none() {
-            return Err(OrderBookError::NoBidOrdersInOrderbook);
+        // get the best opposite price
+        let price = if is_bid {
+            self.orderbook.l2.ask_head().ok_or(OrderBookError::NoAskOrdersInOrderbook)?
+        } else {
+            self.orderbook.l2.bid_head().ok_or(OrderBookError::NoBidOrdersInOrderbook)?
+        };
+
+        self.ensure_client_order_id(owner, coid.as_deref())?;
+
+        let protection_price = self.l1.det_market_protection_price(is_bid, price);
+        let plan = market_order::plan(&self.orderbook, is_bid, size, protection_price, timestamp)?;
+        if matches!(time_in_force, TimeInForce::FillOrKill) && plan.unfilled > 0 {
+            return Err(OrderBookError::OrderNotFullyFilled);
         }
-        let price = best_bid_price.unwrap();
-
-        let taker_order = self.orderbook.place_ask(
-            cid_vec.clone(),
-            self.pair_id.clone(),
-            self.base_asset_id.clone(),
-            self.quote_asset_id.clone(),
-            owner_vec.clone(),
-            price,
-            amnt,
-            iqty,
+
+        let taker_order = self.orderbook.place_order(
+            cid,
+            self.pair_id,
+            self.base_asset_id,
+            self.quote_asset_id,
+            owner,
+            coid,
+            is_bid,
+            plan.price,
+            plan.amount,
+            iqty.min(plan.amount),
             timestamp,
             expires_at,
             taker_fee_bps,
         )?;
+        let taker_id = taker_order.id;
 
-        if matches!(time_in_force, TimeInForce::FillOrKill)
-            && !self.can_fill_fok(price, &taker_order)?
-        {
-            self.orderbook.cancel_order(
-                cid_vec.clone(),
-                self.pair_id.clone(),
+        let (mut lmp, mut base_filled, mut quote_filled) = (0, 0, 0);
+        for fill in plan.fills.iter() {
+            let taker_current = self.orderbook.l3.get_order(taker_id)?.clone();
+            let maker_order = self.orderbook.l3.get_order(fill.maker_id)?.clone();
+            let trade = self.orderbook.execute_fill(
+                taker_current,
+                maker_order,
+                fill.base,
+                fill.quote,
                 false,
-                taker_order.id,
-                owner_vec.clone(),
+                fill.maker_clear,
+                // keep the taker's remainder exact so it can be refunded in full
+                0,
+                self.pair_id,
+                self.base_asset_id,
+                self.quote_asset_id,
+                timestamp,
             )?;
-            return Err(OrderBookError::OrderNotFullyFilled);
+            self.candles.apply_trade(&trade);
+            self.ticker.apply_trade(&trade);
+            lmp = fill.price;
+            base_filled += trade.base_volume;
+            quote_filled += trade.quote_volume;
         }
-        
-        let (taker_order, _bid_head, _ask_head) = self._limit_order(
-            0,
-            &mut taker_order.clone(),
-        )?;
-
-        self._handle_time_in_force_post_matching(time_in_force, &mut taker_order.clone(), false, maker_fee_bps)?;
-
-        Ok(())
-    }
-
-    /// Execute a market buy order
-    /// Matches against existing orders first (market orders match at any price)
-    /// - returns the match result.
-    /// - `cid` is the gateway client id.
-    /// - `existing_order_id` is the order id to update with the transaction if it exists.
-    /// - `owner` is the owner of the order.
-    /// - `amount` is the total amount of the order.
-    /// - `taker_fee_bps` is the taker fee basis points of the order.
-    /// - `time_in_force` is the time in force of the order.
-    pub fn market_buy(
-        &mut self,
-        // gateway client id
-        cid: impl Into<Vec<u8>>,
-        // existing order id to update with the transaction if it exists
-        existing_order_id: Option<OrderId>, // None if new order
-        // owner of the order
-        owner: impl Into<Vec<u8>>,
-        // total amount of the order
-        amnt: u64,
-        // iceberg quantity of the order
-        iqty: u64,
-        // timestamp of the order
-        timestamp: i64,
-        // expiring timestamp of the order
-        expires_at: i64,
-        // maker fee basis points of the order
-        maker_fee_bps: i16,
-        // taker fee basis points of the order
-        taker_fee_bps: i16,
-        // time in force of the order
-        time_in_force: TimeInForce,
-    ) -> Result<(), OrderBookError> {
+        self.orderbook.clear_empty_head_or_zero(true);
+        self.orderbook.clear_empty_head_or_zero(false);
 
-        let cid_vec: Vec<u8> = cid.into();
-        let owner_vec: Vec<u8> = owner.into();
-        // if existing order id is provided, update the order
-        if let Some(existing_order_id) = existing_order_id {
-            let order = self.orderbook.l3.get_order(existing_order_id)?;
-            if order.cid != cid_vec {
-                return Err(OrderBookError::OrderNotSupportedByClientId);
-            }
-            if order.owner != owner_vec {
-                return Err(OrderBookError::OrderNotOwnedBySender);
-            }
+        // Set new market price if matches occurred
+        if lmp != 0 {
+            self.l1.set_lmp(lmp);
+            event::emit_event(SpotEvent::SpotNewMarketPrice {
+                client_ids: self.clients.clone(),
+                pair_id: self.pair_id,
+                base_asset_id: self.base_asset_id,
+                quote_asset_id: self.quote_asset_id,
+                price: lmp,
+                is_make_price: false,
+                timestamp,
+            });
         }
 
-        // get the best ask price
-        let best_ask_price = self.orderbook.l2.ask_head();
-        if best_ask_price.is_none() {
-            return Err(OrderBookError::NoAskOrdersInOrderbook);
+        if plan.protected {
+            event::emit_event(SpotEvent::SpotMarketOrderProtected {
+                cid,
+                pair_id: self.pair_id,
+                base_asset_id: self.base_asset_id,
+                quote_asset_id: self.quote_asset_id,
+                order_id: taker_id.to_bytes().to_vec(),
+                maker_account_id: owner,
+                is_bid,
+                protection_price: protection_price.unwrap_or(0),
+                is_quote_size: matches!(size, MarketOrderSize::Quote(_)),
+                unfilled: plan.unfilled,
+                timestamp,
+            });
         }
-        let price = best_ask_price.unwrap();
-
-        let taker_order = self.orderbook.place_bid(
-            cid_vec.clone(),
-            self.pair_id.clone(),
-            self.base_asset_id.clone(),
-            self.quote_asset_id.clone(),
-            owner_vec.clone(),
-            price,
-            amnt,
-            iqty,
-            timestamp,
-            expires_at,
-            taker_fee_bps,
-        )?;
 
-        if matches!(time_in_force, TimeInForce::FillOrKill)
-            && !self.can_fill_fok(price, &taker_order)?
-        {
-            self.orderbook.cancel_order(
-                cid_vec.clone(),
-                self.pair_id.clone(),
-                true,
-                taker_order.id,
-                owner_vec.clone(),
-            )?;
-            return Err(OrderBookError::OrderNotFullyFilled);
+        let remaining = self.orderbook.l3.get_order(taker_id).map(|order| order.cqty).unwrap_or(0);
+        // refund what is left when the size was met, the rest is too small to trade or it is beyond the protection price
+        if !plan.exhausted && remaining > 0 {
+            self.orderbook.cancel_order(cid, self.pair_id, taker_id, owner)?;
         }
-
-        let (taker_order, _bid_head, _ask_head) = self._limit_order(
-            u64::MAX,
-            &mut taker_order.clone(),
-        )?;
-
-        self._handle_time_in_force_post_matching(time_in_force, &mut taker_order.clone(), false,maker_fee_bps)?;
-
-        Ok(())
+        let mut taker_order = match self.orderbook.l3.get_order(taker_id) {
+            Ok(order) => order.clone(),
+            Err(_) => {
+                let mut fallback = taker_order.clone();
+                fallback.cqty = 0;
+                fallback.pqty = 0;
+                fallback
+            }
+        };
+        self._handle_time_in_force_post_matching(time_in_force, &mut taker_order, false, maker_fee_bps)?;
+
+        Ok(MarketOrderFill {
+            order_id: taker_id,
+            base_filled,
+            quote_filled,
+            remaining,
+            is_resting: remaining > 0 && self.orderbook.l3.get_order(taker_id).is_ok(),
+            protection_price: protection_price.filter(|_| plan.protected),
+            unfilled: plan.unfilled,
+        })
     }
 
+    /// Cancels a resting order of the owner.
+    /// - `order` is the order id or the owner's client order id of the order to cancel.
     pub fn cancel_order(
         &mut self,
-        cid: impl Into<Vec<u8>>,
-        pair_id: impl Into<Vec<u8>>,
-        is_bid: bool,
-        order_id: OrderId,
-        owner: impl Into<Vec<u8>>,
+        cid: impl Into<ClientId>,
+        pair_id: impl Into<PairId>,
+        order: impl Into<OrderRef>,
+        owner: impl Into<AccountId>,
     ) -> Result<(), OrderBookError> {
         self.orderbook
-            .cancel_order(cid, pair_id, is_bid, order_id, owner)?;
+            .cancel_order(cid, pair_id, order, owner)?;
+        Ok(())
+    }
+
+    /// Ensures the client order id is not used by another resting order of the owner.
+    fn ensure_client_order_id(&self, owner: AccountId, coid: Option<&[u8]>) -> Result<(), OrderBookError> {
+        if let Some(coid) = coid {
+            if self.orderbook.l3.has_client_order_id(owner, coid) {
+                return Err(OrderBookError::L3(L3Error::DuplicateClientOrderId));
+            }
+        }
         Ok(())
     }
 }
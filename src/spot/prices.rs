@@ -1,8 +1,11 @@
-use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
+use std::ops::Bound::{Excluded, Unbounded};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Level {
     /// price in 8 decimals
     pub price: u64,
@@ -29,26 +32,24 @@ fn format_levels(levels: &[Level]) -> String {
     format!("[{}]", formatted.join(", "))
 }
 
+/// Default price scales for aggregated depth in 8 decimals: 0.01, 0.1, 1 and 10
+pub const DEFAULT_SCALES: [u64; 4] = [100_0000, 1000_0000, 1_0000_0000, 10_0000_0000];
+
+/// Neighbours of a price in the price list of its side, `prev` towards the head and `next` towards the tail.
+/// Snapshots keep the price lists as nodes, see `LinkedL2`.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct PriceNode {
     pub prev: Option<u64>,
     pub next: Option<u64>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Default)]
+#[serde(from = "ScaledL2")]
 pub struct L2 {
-    /// Head of the bid price linked list
-    pub bid_price_head: Option<u64>,
-    /// Head of the ask price linked list
-    pub ask_price_head: Option<u64>,
-    /// Tail of the bid price linked list
-    pub bid_price_tail: Option<u64>,
-    /// Tail of the ask price linked list
-    pub ask_price_tail: Option<u64>,
-    /// Mapping price -> node of the bid price linked list
-    pub bid_price_nodes: BTreeMap<u64, PriceNode>,
-    /// Mapping price -> node of the ask price linked list
-    pub ask_price_nodes: BTreeMap<u64, PriceNode>,
+    /// Bid prices with a level, the head is the highest
+    pub bid_prices: BTreeSet<u64>,
+    /// Ask prices with a level, the head is the lowest
+    pub ask_prices: BTreeSet<u64>,
     /// Public bid levels sorted by price descending
     pub public_bid_level_map: BTreeMap<u64, u64>,
     /// Public ask levels sorted by price ascending
@@ -65,6 +66,128 @@ pub struct L2 {
     /// key is scale in 8 decimals integer (e.g. 100000000 for 1.00000000, 1000000000 for 10.00000000)
     /// value is a vector of levels in the quantized price space
     pub ask_level_list: BTreeMap<u64, Vec<Level>>,
+    /// Price scales in 8 decimals maintained in `bid_level_list` and `ask_level_list`
+    /// as levels change. Bids are bucketed down and asks are bucketed up to the scale.
+    pub scales: Vec<u64>,
+    /// Last sequence number of the depth feed per scale.
+    /// Scaled level changes are only tracked for scales with an open feed.
+    pub depth_seqs: BTreeMap<u64, u64>,
+    /// Scaled levels changed since the depth feed was last published, as (scale, is_bid, price)
+    pub changed_levels: BTreeSet<(u64, bool, u64)>,
+}
+
+/// Serialized layout of `L2` in the 0.1.0 release, when the price lists were linked lists of `PriceNode`.
+/// Snapshots of version 0 deserialize through it, dropping the heads, tails and links which the sets are ordered by
+/// themselves. The scaled depth starts without scales, see `spot::snapshot`.
+#[derive(Deserialize)]
+// the heads and tails are read only to be dropped
+#[allow(dead_code)]
+pub(crate) struct LinkedL2 {
+    bid_price_head: Option<u64>,
+    ask_price_head: Option<u64>,
+    bid_price_tail: Option<u64>,
+    ask_price_tail: Option<u64>,
+    bid_price_nodes: BTreeMap<u64, PriceNode>,
+    ask_price_nodes: BTreeMap<u64, PriceNode>,
+    public_bid_level_map: BTreeMap<u64, u64>,
+    public_ask_level_map: BTreeMap<u64, u64>,
+    current_bid_level_map: BTreeMap<u64, u64>,
+    current_ask_level_map: BTreeMap<u64, u64>,
+    bid_level_list: BTreeMap<u64, Vec<Level>>,
+    ask_level_list: BTreeMap<u64, Vec<Level>>,
+}
+
+impl From<LinkedL2> for L2 {
+    fn from(snapshot: LinkedL2) -> Self {
+        Self {
+            bid_prices: snapshot.bid_price_nodes.into_keys().collect(),
+            ask_prices: snapshot.ask_price_nodes.into_keys().collect(),
+            public_bid_level_map: snapshot.public_bid_level_map,
+            public_ask_level_map: snapshot.public_ask_level_map,
+            current_bid_level_map: snapshot.current_bid_level_map,
+            current_ask_level_map: snapshot.current_ask_level_map,
+            bid_level_list: snapshot.bid_level_list,
+            ask_level_list: snapshot.ask_level_list,
+            scales: Vec::new(),
+            depth_seqs: BTreeMap::new(),
+            changed_levels: BTreeSet::new(),
+        }
+    }
+}
+
+/// Serialized layout of `L2` since snapshot version 1, the fields of `LinkedL2` followed by the scaled depth state
+#[derive(Deserialize)]
+// the heads and tails are read only to be dropped
+#[allow(dead_code)]
+struct ScaledL2 {
+    bid_price_head: Option<u64>,
+    ask_price_head: Option<u64>,
+    bid_price_tail: Option<u64>,
+    ask_price_tail: Option<u64>,
+    bid_price_nodes: BTreeMap<u64, PriceNode>,
+    ask_price_nodes: BTreeMap<u64, PriceNode>,
+    public_bid_level_map: BTreeMap<u64, u64>,
+    public_ask_level_map: BTreeMap<u64, u64>,
+    current_bid_level_map: BTreeMap<u64, u64>,
+    current_ask_level_map: BTreeMap<u64, u64>,
+    bid_level_list: BTreeMap<u64, Vec<Level>>,
+    ask_level_list: BTreeMap<u64, Vec<Level>>,
+    scales: Vec<u64>,
+    depth_seqs: BTreeMap<u64, u64>,
+    changed_levels: BTreeSet<(u64, bool, u64)>,
+}
+
+impl From<ScaledL2> for L2 {
+    fn from(snapshot: ScaledL2) -> Self {
+        Self {
+            bid_prices: snapshot.bid_price_nodes.into_keys().collect(),
+            ask_prices: snapshot.ask_price_nodes.into_keys().collect(),
+            public_bid_level_map: snapshot.public_bid_level_map,
+            public_ask_level_map: snapshot.public_ask_level_map,
+            current_bid_level_map: snapshot.current_bid_level_map,
+            current_ask_level_map: snapshot.current_ask_level_map,
+            bid_level_list: snapshot.bid_level_list,
+            ask_level_list: snapshot.ask_level_list,
+            scales: snapshot.scales,
+            depth_seqs: snapshot.depth_seqs,
+            changed_levels: snapshot.changed_levels,
+        }
+    }
+}
+
+/// Price list of a side serialized as the `PriceNode` map of `ScaledL2`
+struct PriceNodes<'a> {
+    l2: &'a L2,
+    is_bid: bool,
+}
+
+impl Serialize for PriceNodes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let prices = if self.is_bid { &self.l2.bid_prices } else { &self.l2.ask_prices };
+        serializer.collect_map(prices.iter().map(|&price| (price, self.l2.price_node(self.is_bid, price).unwrap_or_default())))
+    }
+}
+
+impl Serialize for L2 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("L2", 15)?;
+        state.serialize_field("bid_price_head", &self.bid_head())?;
+        state.serialize_field("ask_price_head", &self.ask_head())?;
+        state.serialize_field("bid_price_tail", &self.bid_tail())?;
+        state.serialize_field("ask_price_tail", &self.ask_tail())?;
+        state.serialize_field("bid_price_nodes", &PriceNodes { l2: self, is_bid: true })?;
+        state.serialize_field("ask_price_nodes", &PriceNodes { l2: self, is_bid: false })?;
+        state.serialize_field("public_bid_level_map", &self.public_bid_level_map)?;
+        state.serialize_field("public_ask_level_map", &self.public_ask_level_map)?;
+        state.serialize_field("current_bid_level_map", &self.current_bid_level_map)?;
+        state.serialize_field("current_ask_level_map", &self.current_ask_level_map)?;
+        state.serialize_field("bid_level_list", &self.bid_level_list)?;
+        state.serialize_field("ask_level_list", &self.ask_level_list)?;
+        state.serialize_field("scales", &self.scales)?;
+        state.serialize_field("depth_seqs", &self.depth_seqs)?;
+        state.serialize_field("changed_levels", &self.changed_levels)?;
+        state.end()
+    }
 }
 
 #[derive(Debug, thiserror::Error, PartialEq, Eq)]
@@ -86,18 +209,17 @@ pub enum L2Error {
 impl L2 {
     pub fn new() -> Self {
         Self {
-            bid_price_head: None,
-            bid_price_tail: None,
-            ask_price_head: None,
-            ask_price_tail: None,
-            bid_price_nodes: BTreeMap::new(),
-            ask_price_nodes: BTreeMap::new(),
+            bid_prices: BTreeSet::new(),
+            ask_prices: BTreeSet::new(),
             public_bid_level_map: BTreeMap::new(),
             public_ask_level_map: BTreeMap::new(),
             current_bid_level_map: BTreeMap::new(),
             current_ask_level_map: BTreeMap::new(),
             bid_level_list: BTreeMap::new(),
             ask_level_list: BTreeMap::new(),
+            scales: Vec::new(),
+            depth_seqs: BTreeMap::new(),
+            changed_levels: BTreeSet::new(),
         }
     }
 
@@ -110,11 +232,19 @@ impl L2 {
     }
 
     pub fn bid_head(&self) -> Option<u64> {
-        self.bid_price_head
+        self.bid_prices.last().copied()
     }
 
     pub fn ask_head(&self) -> Option<u64> {
-        self.ask_price_head
+        self.ask_prices.first().copied()
+    }
+
+    pub fn bid_tail(&self) -> Option<u64> {
+        self.bid_prices.first().copied()
+    }
+
+    pub fn ask_tail(&self) -> Option<u64> {
+        self.ask_prices.last().copied()
     }
 
     pub fn public_bid_level(&self, price: u64) -> Option<u64> {
@@ -141,6 +271,21 @@ impl L2 {
         &self.ask_level_list.get(&scale).unwrap()
     }
 
+    /// Returns the level of the bucket at `price` in the given scale
+    pub fn scaled_level(&self, is_bid: bool, scale: u64, price: u64) -> Option<&Level> {
+        let levels = if is_bid {
+            self.bid_level_list.get(&scale)?
+        } else {
+            self.ask_level_list.get(&scale)?
+        };
+        let position = if is_bid {
+            levels.binary_search_by(|level| price.cmp(&level.price))
+        } else {
+            levels.binary_search_by(|level| level.price.cmp(&price))
+        };
+        position.ok().map(|index| &levels[index])
+    }
+
     pub fn scale_bid_levels(&self, scale: u64, n: u32) -> Vec<Level> {
         let levels = self.bid_level_list.get(&scale).cloned().unwrap_or(Vec::new());
         levels.iter().take(n as usize).cloned().collect()
@@ -152,22 +297,26 @@ impl L2 {
     }
 
     pub fn set_current_bid_level(&mut self, price: u64, level: u64) -> Result<(), L2Error> {
-        self.current_bid_level_map.insert(price, level);
+        let old = self.current_bid_level_map.insert(price, level).unwrap_or(0);
+        self.update_scaled_levels(true, price, (0, 0), (old, level));
         Ok(())
     }
 
     pub fn set_current_ask_level(&mut self, price: u64, level: u64) -> Result<(), L2Error> {
-        self.current_ask_level_map.insert(price, level);
+        let old = self.current_ask_level_map.insert(price, level).unwrap_or(0);
+        self.update_scaled_levels(false, price, (0, 0), (old, level));
         Ok(())
     }
 
     pub fn set_public_bid_level(&mut self, price: u64, level: u64) -> Result<(), L2Error> {
-        self.public_bid_level_map.insert(price, level);
+        let old = self.public_bid_level_map.insert(price, level).unwrap_or(0);
+        self.update_scaled_levels(true, price, (old, level), (0, 0));
         Ok(())
     }
 
     pub fn set_public_ask_level(&mut self, price: u64, level: u64) -> Result<(), L2Error> {
-        self.public_ask_level_map.insert(price, level);
+        let old = self.public_ask_level_map.insert(price, level).unwrap_or(0);
+        self.update_scaled_levels(false, price, (old, level), (0, 0));
         Ok(())
     }
 
@@ -181,333 +330,202 @@ impl L2 {
         Ok(())
     }
 
+    /// Removes the head price of a side without touching its levels, returns the new head
     pub fn clear_head(&mut self, is_bid: bool) -> Result<Option<u64>, L2Error> {
         if is_bid {
-            let old_head = self.bid_price_head.unwrap();
-            // update the head of the bid price linked list
-            // if the next is empty, set the head and tail to none
-            if self.bid_price_nodes.get(&old_head).and_then(|node| node.next).is_none() {
-                self.bid_price_head = None;
-                self.bid_price_tail = None;
-            }
-            else {
-                self.bid_price_head = self.bid_price_nodes.get(&old_head).and_then(|node| node.next);
-            }
-            // remove the node from the bid price nodes map
-            self.bid_price_nodes.remove(&old_head);
-            Ok(self.bid_price_head)
+            self.bid_prices.pop_last();
+            Ok(self.bid_head())
         } else {
-            let old_head = self.ask_price_head.unwrap();
-            // update the head of the ask price linked list
-            // if the next is empty, set the head and tail to none
-            if self.ask_price_nodes.get(&old_head).and_then(|node| node.next).is_none() {
-                self.ask_price_head = None;
-                self.ask_price_tail = None;
-            }
-            else {
-                self.ask_price_head = self.ask_price_nodes.get(&old_head).and_then(|node| node.next);
-            }
-            // remove the node from the ask price nodes map
-            self.ask_price_nodes.remove(&old_head);
-            Ok(self.ask_price_head)
+            self.ask_prices.pop_first();
+            Ok(self.ask_head())
         }
     }
 
     pub fn price_exists(&self, is_bid: bool, price: u64) -> bool {
         if is_bid {
-            self.bid_price_nodes.contains_key(&price)
+            self.bid_prices.contains(&price)
         }
         else {
-            self.ask_price_nodes.contains_key(&price)
+            self.ask_prices.contains(&price)
         }
     }
 
+    /// Inserts a price into the price list of a side and sets its levels to 0
     pub fn insert_price(&mut self, is_bid: bool, price: u64) -> Result<(), L2Error> {
+        Self::ensure_price(price)?;
         if is_bid {
-            let _ = self._insert_bid_price(price)?;
+            self.bid_prices.insert(price);
             self.set_public_bid_level(price, 0)?;
             self.set_current_bid_level(price, 0)?;
             Ok(())
         }
         else {
-            let _ = self._insert_ask_price(price)?;
+            self.ask_prices.insert(price);
             self.set_public_ask_level(price, 0)?;
             self.set_current_ask_level(price, 0)?;
             Ok(())
         }
     }
 
-    /// inserts a bid price into the bid price linked list
-    /// price linked list is sorted in descending order
-    fn _insert_bid_price(&mut self, price: u64) -> Result<(), L2Error> {
-        Self::ensure_price(price)?;
-        // compare head of the bid price head
-        if self.bid_price_head == None {
-            self.bid_price_head = Some(price);
-            // set the tail of the bid price linked list
-            self.bid_price_tail = Some(price);
-            // create a node for the first price
-            self.bid_price_nodes.insert(price, PriceNode {
-                prev: None,
-                next: None,
-            });
-            return Ok(());
-        }
-        else if price > self.bid_price_head.unwrap() {
-            let old_head = self.bid_price_head.unwrap();
-            self.bid_price_head = Some(price);
-            // update old head's node to point back to new head
-            self.bid_price_nodes.get_mut(&old_head).map(|node| node.prev = Some(price));
-            // create node for new head
-            self.bid_price_nodes.insert(price, PriceNode {
-                prev: None,
-                next: Some(old_head),
-            });
-            return Ok(());
-        } 
-        else if price == self.bid_price_head.unwrap() {
-            return Ok(());
-        }
-        else {
-            // traverse through the bid price linked list and insert the price at the correct position so that the list is sorted in descending order
-            let mut current = self.bid_price_head;
-            while current.is_some() {
-                let next = self.bid_price_nodes.get(&current.unwrap()).and_then(|node| node.next);
-                // next does not exist
-                if next.is_none() {
-                    // insert the price at the current position
-                    let curr = current.unwrap();
-                    self.bid_price_nodes.get_mut(&curr).map(|node| node.next = Some(price));
-                    // create a node for the new price
-                    self.bid_price_nodes.insert(price, PriceNode {
-                        prev: Some(curr),
-                        next: None,
-                    });
-                    // set the tail of the bid price linked list
-                    self.bid_price_tail = Some(price);
-                    return Ok(());
-                }
-                else {
-                    // next exists
-                    let next_val = next.unwrap();
-                    if next_val > price {
-                        // traverse until the price is bigger than the current price
-                        current = Some(next_val);
-                    }
-                    else if next_val < price {
-                        // To avoid mutable and immutable borrow at the same time, collect value first
-                        let curr = current.unwrap();
-                        // Update current's next to point to new price
-                        self.bid_price_nodes.get_mut(&curr).map(|node| node.next = Some(price));
-                        // Update next_val's prev to point to new price
-                        self.bid_price_nodes.get_mut(&next_val).map(|node| node.prev = Some(price));
-                        // Create node for new price
-                        self.bid_price_nodes.insert(price, PriceNode {
-                            prev: Some(curr),
-                            next: Some(next_val),
-                        });
-                        return Ok(());
-                    }
-                }
-            }
-            return Ok(());
-        }
-    }
-
-    /// price linked list is sorted in ascending order
-    /// linked list is sorted in ascending order
-    fn _insert_ask_price(&mut self, price: u64) -> Result<(), L2Error> {
-        Self::ensure_price(price)?;
-        // compare head of the ask price head
-        if self.ask_price_head == None {
-            self.ask_price_head = Some(price);
-            // set the tail of the ask price linked list
-            self.ask_price_tail = Some(price);
-            // create a node for the first price
-            self.ask_price_nodes.insert(price, PriceNode {
-                prev: None,
-                next: None,
-            });
-            return Ok(());
-        }
-        else if price < self.ask_price_head.unwrap() {
-            let old_head = self.ask_price_head.unwrap();
-            self.ask_price_head = Some(price);
-            // update old head's prev pointer to point to new head
-            self.ask_price_nodes.get_mut(&old_head).map(|node| node.prev = Some(price));
-            // create node for new head
-            self.ask_price_nodes.insert(price, PriceNode {
-                prev: None,
-                next: Some(old_head),
-            });
-            return Ok(());
-        }
-        else if price == self.ask_price_head.unwrap() {
-            return Ok(());
-        }
-        else {
-            // traverse through the ask price linked list and insert the price at the correct position so that the list is sorted in ascending order
-            let mut current = self.ask_price_head;
-            while current.is_some() {
-                let next = self.ask_price_nodes.get(&current.unwrap()).and_then(|node| node.next);
-                // next does not exist
-                if next.is_none() {
-                    // insert the price at the tail position
-                    let curr = current.unwrap();
-                    // update current node's next pointer
-                    self.ask_price_nodes.get_mut(&curr).map(|node| node.next = Some(price));
-                    // create a node for the new price
-                    self.ask_price_nodes.insert(price, PriceNode {
-                        prev: Some(curr),
-                        next: None,
-                    });
-                    // set the tail of the ask price linked list
-                    self.ask_price_tail = Some(price);
-                    return Ok(());
-                }
-                else {
-                    // next exists
-                    let next_val = next.unwrap();
-                    if next_val < price {
-                        // traverse until the price is smaller than the current price
-                        current = Some(next_val);
-                    }
-                    else if next_val > price {
-                        // To avoid mutable and immutable borrow at the same time, collect value first
-                        let curr = current.unwrap();
-                        // Update current's next pointer to point to new price
-                        self.ask_price_nodes.get_mut(&curr).map(|node| node.next = Some(price));
-                        // Update next_val's prev pointer to point to new price
-                        self.ask_price_nodes.get_mut(&next_val).map(|node| node.prev = Some(price));
-                        // Create node for new price
-                        self.ask_price_nodes.insert(price, PriceNode {
-                            prev: Some(curr),
-                            next: Some(next_val),
-                        });
-                        return Ok(());
-                    }
-                    else {
-                        break;
-                    }
-                }
-            }
-            return Ok(());
-        }
-    }
-
-    // remove price from the price linked list
+    // remove price from the price list with its levels
     pub fn remove_price(&mut self, is_bid: bool, price: u64) -> Result<(), L2Error> {
         if is_bid {
-            self._remove_bid_price(price)?;
-            // remove the level from the level map
-            self.public_bid_level_map.remove(&price);
-            self.current_bid_level_map.remove(&price);
-            Ok(())
+            self.bid_prices.remove(&price);
         }
         else {
-            self._remove_ask_price(price)?;
-            // remove the level from the level map
-            self.public_ask_level_map.remove(&price);
-            self.current_ask_level_map.remove(&price);
-            Ok(())
+            self.ask_prices.remove(&price);
         }
+        // remove the level from the level map
+        self.remove_levels(is_bid, price);
+        Ok(())
     }
-    // remove price from the bid price linked list
-    fn _remove_bid_price(&mut self, price: u64) -> Result<(), L2Error> {
-        // Get the node to be removed before removing it
-        let node = match self.bid_price_nodes.get(&price) {
-            Some(node) => node.clone(),
-            None => return Ok(()), // Price doesn't exist, nothing to remove
-        };
 
-        // Update the previous node's next pointer
-        if let Some(prev_price) = node.prev {
-            if let Some(prev_node) = self.bid_price_nodes.get_mut(&prev_price) {
-                prev_node.next = node.next;
-            }
-        } else {
-            // This is the head, update head pointer
-            self.bid_price_head = node.next;
+    /// Returns the neighbours of `price` in the price list of the side, none if the price is not listed
+    pub fn price_node(&self, is_bid: bool, price: u64) -> Option<PriceNode> {
+        let prices = if is_bid { &self.bid_prices } else { &self.ask_prices };
+        if !prices.contains(&price) {
+            return None;
         }
+        let lower = prices.range(..price).next_back().copied();
+        let higher = prices.range((Excluded(price), Unbounded)).next().copied();
+        Some(if is_bid {
+            PriceNode { prev: higher, next: lower }
+        } else {
+            PriceNode { prev: lower, next: higher }
+        })
+    }
 
-        // Update the next node's prev pointer
-        if let Some(next_price) = node.next {
-            if let Some(next_node) = self.bid_price_nodes.get_mut(&next_price) {
-                next_node.prev = node.prev;
-            }
+    /// Returns the next price after `price` in the price list of the side
+    pub fn next_price(&self, is_bid: bool, price: u64) -> Option<u64> {
+        if !self.price_exists(is_bid, price) {
+            return None;
+        }
+        if is_bid {
+            self.bid_prices.range(..price).next_back().copied()
         } else {
-            // This is the tail, update tail pointer
-            self.bid_price_tail = node.prev;
+            self.ask_prices.range((Excluded(price), Unbounded)).next().copied()
         }
+    }
 
-        // Remove the node from the map
-        self.bid_price_nodes.remove(&price);
+    /// Iterates the bid prices from the head (descending)
+    pub fn iter_bid_prices(&self) -> impl Iterator<Item = u64> + '_ {
+        self.bid_prices.iter().rev().copied()
+    }
 
-        // Remove the level from the level map
-        self.public_bid_level_map.remove(&price);
-        self.current_bid_level_map.remove(&price);
+    /// Iterates the ask prices from the head (ascending)
+    pub fn iter_ask_prices(&self) -> impl Iterator<Item = u64> + '_ {
+        self.ask_prices.iter().copied()
+    }
 
-        Ok(())
+    /// Helper function to collect all bid prices in order (descending)
+    pub fn collect_bid_prices(&self) -> Vec<u64> {
+        self.iter_bid_prices().collect()
     }
 
-    // remove price from the ask price linked list
-    fn _remove_ask_price(&mut self, price: u64) -> Result<(), L2Error> {
-        // Get the node to be removed before removing it
-        let node = match self.ask_price_nodes.get(&price) {
-            Some(node) => node.clone(),
-            None => return Ok(()), // Price doesn't exist, nothing to remove
-        };
+    /// Helper function to collect all ask prices in order (ascending)
+    pub fn collect_ask_prices(&self) -> Vec<u64> {
+        self.iter_ask_prices().collect()
+    }
 
-        // Update the previous node's next pointer
-        if let Some(prev_price) = node.prev {
-            if let Some(prev_node) = self.ask_price_nodes.get_mut(&prev_price) {
-                prev_node.next = node.next;
-            }
+    /// Removes the public and current levels of a price, keeping the scaled levels in sync
+    fn remove_levels(&mut self, is_bid: bool, price: u64) {
+        let (public, current) = if is_bid {
+            (self.public_bid_level_map.remove(&price), self.current_bid_level_map.remove(&price))
         } else {
-            // This is the head, update head pointer
-            self.ask_price_head = node.next;
+            (self.public_ask_level_map.remove(&price), self.current_ask_level_map.remove(&price))
+        };
+        self.update_scaled_levels(is_bid, price, (public.unwrap_or(0), 0), (current.unwrap_or(0), 0));
+    }
+
+    /// Sets the price scales of the aggregated depth and rebuilds the scaled levels
+    /// from the current price levels. Levels of scales no longer configured are dropped.
+    pub fn set_scales(&mut self, scales: &[u64]) {
+        let mut scales: Vec<u64> = scales.iter().copied().filter(|scale| *scale > 0).collect();
+        scales.sort_unstable();
+        scales.dedup();
+        self.scales = scales;
+        // close the depth feeds of dropped scales
+        let scales = &self.scales;
+        self.depth_seqs.retain(|scale, _| scales.contains(scale));
+        self.changed_levels.retain(|(scale, _, _)| scales.contains(scale));
+        self.rebuild_scaled_levels();
+    }
+
+    /// Recomputes `bid_level_list` and `ask_level_list` for every configured scale
+    pub fn rebuild_scaled_levels(&mut self) {
+        self.bid_level_list.clear();
+        self.ask_level_list.clear();
+        for scale in self.scales.clone() {
+            self.bid_level_list.insert(scale, Vec::new());
+            self.ask_level_list.insert(scale, Vec::new());
         }
-
-        // Update the next node's prev pointer
-        if let Some(next_price) = node.next {
-            if let Some(next_node) = self.ask_price_nodes.get_mut(&next_price) {
-                next_node.prev = node.prev;
-            }
-        } else {
-            // This is the tail, update tail pointer
-            self.ask_price_tail = node.prev;
+        let bids: Vec<(u64, u64)> = self.public_bid_level_map.iter().map(|(p, q)| (*p, *q)).collect();
+        for (price, pqty) in bids {
+            self.update_scaled_levels(true, price, (0, pqty), (0, 0));
+        }
+        let bids: Vec<(u64, u64)> = self.current_bid_level_map.iter().map(|(p, q)| (*p, *q)).collect();
+        for (price, cqty) in bids {
+            self.update_scaled_levels(true, price, (0, 0), (0, cqty));
+        }
+        let asks: Vec<(u64, u64)> = self.public_ask_level_map.iter().map(|(p, q)| (*p, *q)).collect();
+        for (price, pqty) in asks {
+            self.update_scaled_levels(false, price, (0, pqty), (0, 0));
+        }
+        let asks: Vec<(u64, u64)> = self.current_ask_level_map.iter().map(|(p, q)| (*p, *q)).collect();
+        for (price, cqty) in asks {
+            self.update_scaled_levels(false, price, (0, 0), (0, cqty));
         }
-
-        // Remove the node from the map
-        self.ask_price_nodes.remove(&price);
-
-        // Remove the level from the level map
-        self.public_ask_level_map.remove(&price);
-        self.current_ask_level_map.remove(&price);
-
-        Ok(())
     }
 
-    /// Helper function to collect all bid prices in order (descending)
-    pub fn collect_bid_prices(&self) -> Vec<u64> {
-        let mut prices = Vec::new();
-        let mut current = self.bid_price_head;
-        while let Some(price) = current {
-            prices.push(price);
-            current = self.bid_price_nodes.get(&price).and_then(|node| node.next);
+    /// Returns the price of the bucket containing `price` in the given scale.
+    /// Bids are bucketed down and asks are bucketed up, so a bucket never shows a better price than its orders.
+    pub fn scaled_price(is_bid: bool, price: u64, scale: u64) -> u64 {
+        if is_bid {
+            price / scale * scale
+        } else {
+            price.div_ceil(scale).saturating_mul(scale)
         }
-        prices
     }
 
-    /// Helper function to collect all ask prices in order (ascending)
-    pub fn collect_ask_prices(&self) -> Vec<u64> {
-        let mut prices = Vec::new();
-        let mut current = self.ask_price_head;
-        while let Some(price) = current {
-            prices.push(price);
-            current = self.ask_price_nodes.get(&price).and_then(|node| node.next);
+    /// Applies a change of the public quantity `(old, new)` and the current quantity `(old, new)`
+    /// at a price to the bucket containing the price in every configured scale.
+    /// Buckets are removed once both of their quantities reach zero.
+    /// Changed buckets are recorded in `changed_levels` for scales with an open depth feed.
+    fn update_scaled_levels(&mut self, is_bid: bool, price: u64, pqty: (u64, u64), cqty: (u64, u64)) {
+        if pqty.0 == pqty.1 && cqty.0 == cqty.1 {
+            return;
+        }
+        let level_list = if is_bid {
+            &mut self.bid_level_list
+        } else {
+            &mut self.ask_level_list
+        };
+        for scale in self.scales.iter().copied() {
+            let bucket = Self::scaled_price(is_bid, price, scale);
+            let levels = level_list.entry(scale).or_default();
+            // bids are sorted by price descending, asks by price ascending
+            let position = if is_bid {
+                levels.binary_search_by(|level| bucket.cmp(&level.price))
+            } else {
+                levels.binary_search_by(|level| level.price.cmp(&bucket))
+            };
+            let index = match position {
+                Ok(index) => index,
+                Err(index) => {
+                    levels.insert(index, Level { price: bucket, pqty: 0, cqty: 0 });
+                    index
+                }
+            };
+            let level = &mut levels[index];
+            level.pqty = level.pqty.saturating_sub(pqty.0).saturating_add(pqty.1);
+            level.cqty = level.cqty.saturating_sub(cqty.0).saturating_add(cqty.1);
+            if level.pqty == 0 && level.cqty == 0 {
+                levels.remove(index);
+            }
+            if self.depth_seqs.contains_key(&scale) {
+                self.changed_levels.insert((scale, is_bid, bucket));
+            }
         }
-        prices
     }
 
     /// Helper function to format a u64 number (in 8 decimals) to a string with 8 decimal places
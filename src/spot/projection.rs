@@ -0,0 +1,269 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::event::SpotEvent;
+use super::ids::PairId;
+use super::market::L1;
+use super::orderbook::OrderBook;
+use super::orders::{L3Error, Order, OrderId};
+use super::pair::Pair;
+use super::prices::{L2Error, DEFAULT_SCALES, L2};
+
+/// Failure to apply an event to a projection, or a difference between a projection and a book.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ProjectionError {
+    #[error("order id of {len} bytes is not an order id")]
+    InvalidOrderId { len: usize },
+    #[error("L3 error: {0}")]
+    L3(#[from] L3Error),
+    #[error("L2 error: {0}")]
+    L2(#[from] L2Error),
+    #[error("L2 level {price} isBid: {is_bid} is {projected:?} in the projection, {book:?} in the book")]
+    LevelMismatch { is_bid: bool, price: u64, projected: Option<(u64, u64)>, book: Option<(u64, u64)> },
+    #[error("L2 levels of scale {scale} isBid: {is_bid} differ")]
+    ScaledLevelsMismatch { is_bid: bool, scale: u64 },
+    #[error("L3 orders at {price} differ from position {position}, order {projected:?} in the projection, {book:?} in the book")]
+    OrderMismatch { price: u64, position: usize, projected: Option<OrderId>, book: Option<OrderId> },
+    #[error("last trade id is {projected} in the projection, {book} in the book")]
+    LastTradeMismatch { projected: u64, book: u64 },
+    #[error("last matched price is {projected:?} in the projection, {book:?} in the pair")]
+    LastMatchedPriceMismatch { projected: Option<u64>, book: Option<u64> },
+    #[error("market price is {projected:?} in the projection, {book:?} in the pair")]
+    MarketPriceMismatch { projected: Option<u64>, book: Option<u64> },
+}
+
+/// Order book of a pair rebuilt from the events of the engine alone, for read replicas.
+/// - `l1` holds the last matched price, the slippage limits are configuration the events do not carry.
+/// - `market_price` is the make price of the last order resting after matching.
+/// - `orderbook` holds the L2 levels, the L3 resting orders in their FIFO order and the last trade id.
+///
+/// Events of other pairs are skipped, so a projection can be fed the whole event stream.
+///
+/// # Examples
+///
+/// ```
+/// use off_grid_primitives::spot::projection::OrderBookProjection;
+/// use off_grid_primitives::spot::MatchingEngine;
+///
+/// let mut engine = MatchingEngine::new();
+/// let mut projection = OrderBookProjection::new(b"BTC-USD");
+/// engine.add_pair([1], [10], [11], b"BTC-USD", 0);
+/// projection.apply_all(off_grid_primitives::spot::event::drain_events().iter()).unwrap();
+/// projection.verify(engine.pair(b"BTC-USD").unwrap()).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderBookProjection {
+    pub pair_id: PairId,
+    pub l1: L1,
+    pub market_price: Option<u64>,
+    pub orderbook: OrderBook,
+}
+
+impl OrderBookProjection {
+    /// Creates an empty projection of a pair, maintaining the aggregated depth of the default scales like `Pair`
+    pub fn new(pair_id: impl Into<PairId>) -> Self {
+        let mut orderbook = OrderBook::default();
+        orderbook.l2.set_scales(&DEFAULT_SCALES);
+        Self { pair_id: pair_id.into(), l1: L1::default(), market_price: None, orderbook }
+    }
+
+    /// Applies events in the order they were emitted
+    pub fn apply_all<'a>(&mut self, events: impl IntoIterator<Item = &'a SpotEvent>) -> Result<(), ProjectionError> {
+        events.into_iter().try_for_each(|event| self.apply(event))
+    }
+
+    /// Applies an event of the pair, skipping the events of other pairs and the events not changing a book.
+    /// - a placed order rests at the tail of its price level, opening the L2 level if its side has none.
+    /// - fills, cancels and expiries update or remove the order, closing the L2 level once its side has no order left.
+    ///   Cancels, expiries and iceberg changes of schema version 1 carry no pair id, they apply to the projection
+    ///   holding the order.
+    /// - price level changes carry the absolute quantities of the level.
+    pub fn apply(&mut self, event: &SpotEvent) -> Result<(), ProjectionError> {
+        match event {
+            SpotEvent::SpotOrderPlaced {
+                cid, pair_id, order_id, maker_account_id, coid, is_bid, price, amnt, iqty, cqty, pqty, fee_bps,
+                timestamp, expires_at, ..
+            } if *pair_id == self.pair_id => {
+                let mut order = Order::new(
+                    *cid, order_id_from_bytes(order_id)?, *maker_account_id, *is_bid, *price, *amnt, *iqty, *pqty, *cqty,
+                    *timestamp, *expires_at, *fee_bps,
+                );
+                order.coid = coid.clone();
+                self.orderbook.l3.insert_order(order)?;
+                let l2 = &mut self.orderbook.l2;
+                if !l2.price_exists(*is_bid, *price) {
+                    l2.insert_price(*is_bid, *price)?;
+                    set_level(l2, *is_bid, *price, *pqty, *cqty)?;
+                }
+            }
+            SpotEvent::SpotOrderPartiallyFilled {
+                is_taker_event, taker_order_id, maker_order_id, pair_id, pqty, cqty, ..
+            } if *pair_id == self.pair_id => {
+                let order_id = if *is_taker_event { taker_order_id } else { maker_order_id };
+                let order = self.orderbook.l3.get_order_mut(order_id_from_bytes(order_id)?)?;
+                order.pqty = *pqty;
+                order.cqty = *cqty;
+            }
+            SpotEvent::SpotOrderFullyFilled { is_taker_event, taker_order_id, maker_order_id, pair_id, .. }
+                if *pair_id == self.pair_id =>
+            {
+                let order_id = if *is_taker_event { taker_order_id } else { maker_order_id };
+                self.remove_order(order_id_from_bytes(order_id)?)?;
+            }
+            SpotEvent::SpotOrderCancelled { pair_id, order_id, .. } | SpotEvent::SpotOrderExpired { pair_id, order_id, .. }
+                if self.holds(*pair_id, order_id) =>
+            {
+                self.remove_order(order_id_from_bytes(order_id)?)?;
+            }
+            SpotEvent::SpotOrderIcebergQuantityChanged { pair_id, order_id, iqty, pqty, cqty, .. } if self.holds(*pair_id, order_id) => {
+                let order = self.orderbook.l3.get_order_mut(order_id_from_bytes(order_id)?)?;
+                order.iqty = *iqty;
+                order.pqty = *pqty;
+                order.cqty = *cqty;
+            }
+            SpotEvent::SpotPriceLevelChanged { pair_id, is_bid, price, pqty, cqty, .. } if *pair_id == self.pair_id => {
+                let l2 = &mut self.orderbook.l2;
+                if self.orderbook.l3.has_side_orders(*price, *is_bid) {
+                    if !l2.price_exists(*is_bid, *price) {
+                        l2.insert_price(*is_bid, *price)?;
+                    }
+                    set_level(l2, *is_bid, *price, *pqty, *cqty)?;
+                } else if l2.price_exists(*is_bid, *price) {
+                    l2.remove_price(*is_bid, *price)?;
+                }
+            }
+            SpotEvent::SpotTrade { pair_id, trade_id, .. } if *pair_id == self.pair_id => {
+                self.orderbook.last_trade_id = *trade_id;
+            }
+            SpotEvent::SpotNewMarketPrice { pair_id, price, is_make_price, .. } if *pair_id == self.pair_id => {
+                if *is_make_price {
+                    self.market_price = Some(*price);
+                } else {
+                    self.l1.set_lmp(*price);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Whether an event of an order is for this pair, by the order id when the event carries no pair id
+    fn holds(&self, pair_id: Option<PairId>, order_id: &[u8]) -> bool {
+        match pair_id {
+            Some(pair_id) => pair_id == self.pair_id,
+            None => order_id_from_bytes(order_id).is_ok_and(|order_id| self.orderbook.l3.get_order(order_id).is_ok()),
+        }
+    }
+
+    /// Removes a resting order, closing its L2 level once its side has no order left at the price
+    fn remove_order(&mut self, order_id: OrderId) -> Result<(), ProjectionError> {
+        let (is_bid, price) = {
+            let order = self.orderbook.l3.get_order(order_id)?;
+            (order.is_bid, order.price)
+        };
+        self.orderbook.l3.delete_order(order_id)?;
+        let l2 = &mut self.orderbook.l2;
+        if !self.orderbook.l3.has_side_orders(price, is_bid) && l2.price_exists(is_bid, price) {
+            l2.remove_price(is_bid, price)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the projection holds the same book as an order book.
+    /// - the L2 levels of both sides, raw and aggregated per scale.
+    /// - the L3 orders of every price level, compared field by field in FIFO order.
+    /// - the last trade id.
+    ///
+    /// The dust limit, the fee recipients and the depth feeds are not part of the events and are not compared.
+    pub fn verify_orderbook(&self, orderbook: &OrderBook) -> Result<(), ProjectionError> {
+        let (projected, book) = (&self.orderbook, orderbook);
+        for is_bid in [true, false] {
+            let (mine, theirs) = (side(&projected.l2, is_bid), side(&book.l2, is_bid));
+            if mine != theirs {
+                let differs = |price: &u64| {
+                    mine.0.contains(price) != theirs.0.contains(price)
+                        || mine.1.get(price) != theirs.1.get(price)
+                        || mine.2.get(price) != theirs.2.get(price)
+                };
+                let prices = [mine, theirs].into_iter().flat_map(|(prices, public, current)| prices.iter().chain(public.keys()).chain(current.keys()));
+                let price = prices.copied().find(differs).unwrap_or_default();
+                return Err(ProjectionError::LevelMismatch { is_bid, price, projected: level(mine, price), book: level(theirs, price) });
+            }
+            let scaled = |orderbook: &OrderBook| {
+                let l2 = &orderbook.l2;
+                if is_bid { l2.bid_level_list.clone() } else { l2.ask_level_list.clone() }
+            };
+            let (projected_scaled, book_scaled) = (scaled(projected), scaled(book));
+            if let Some(&scale) = projected_scaled.keys().chain(book_scaled.keys()).find(|scale| projected_scaled.get(scale) != book_scaled.get(scale)) {
+                return Err(ProjectionError::ScaledLevelsMismatch { is_bid, scale });
+            }
+        }
+
+        for price in projected.l3.prices().chain(book.l3.prices()) {
+            let mut projected_orders = projected.l3.level_orders(price);
+            let mut book_orders = book.l3.level_orders(price);
+            for position in 0.. {
+                match (projected_orders.next(), book_orders.next()) {
+                    (None, None) => break,
+                    (projected, book) if projected == book => {}
+                    (projected, book) => {
+                        return Err(ProjectionError::OrderMismatch {
+                            price,
+                            position,
+                            projected: projected.map(|order| order.id),
+                            book: book.map(|order| order.id),
+                        })
+                    }
+                }
+            }
+        }
+
+        if projected.last_trade_id != book.last_trade_id {
+            return Err(ProjectionError::LastTradeMismatch { projected: projected.last_trade_id, book: book.last_trade_id });
+        }
+        Ok(())
+    }
+
+    /// Checks that the projection holds the same book as a pair, and the same last matched and market prices
+    pub fn verify(&self, pair: &Pair) -> Result<(), ProjectionError> {
+        self.verify_orderbook(&pair.orderbook)?;
+        if self.l1.lmp() != pair.l1.lmp() {
+            return Err(ProjectionError::LastMatchedPriceMismatch { projected: self.l1.lmp(), book: pair.l1.lmp() });
+        }
+        if self.market_price != pair.market_price {
+            return Err(ProjectionError::MarketPriceMismatch { projected: self.market_price, book: pair.market_price });
+        }
+        Ok(())
+    }
+}
+
+type Side<'a> = (&'a BTreeSet<u64>, &'a BTreeMap<u64, u64>, &'a BTreeMap<u64, u64>);
+
+/// Price list, public levels and current levels of a side
+fn side(l2: &L2, is_bid: bool) -> Side<'_> {
+    if is_bid {
+        (&l2.bid_prices, &l2.public_bid_level_map, &l2.current_bid_level_map)
+    } else {
+        (&l2.ask_prices, &l2.public_ask_level_map, &l2.current_ask_level_map)
+    }
+}
+
+/// Public and current quantity of a level, `None` if the side has neither the price nor a level at it
+fn level((prices, public, current): Side<'_>, price: u64) -> Option<(u64, u64)> {
+    let listed = prices.contains(&price) || public.contains_key(&price) || current.contains_key(&price);
+    listed.then(|| (public.get(&price).copied().unwrap_or(0), current.get(&price).copied().unwrap_or(0)))
+}
+
+fn set_level(l2: &mut L2, is_bid: bool, price: u64, pqty: u64, cqty: u64) -> Result<(), L2Error> {
+    if is_bid {
+        l2.set_public_bid_level(price, pqty)?;
+        l2.set_current_bid_level(price, cqty)
+    } else {
+        l2.set_public_ask_level(price, pqty)?;
+        l2.set_current_ask_level(price, cqty)
+    }
+}
+
+fn order_id_from_bytes(bytes: &[u8]) -> Result<OrderId, ProjectionError> {
+    let bytes: [u8; 16] = bytes.try_into().map_err(|_| ProjectionError::InvalidOrderId { len: bytes.len() })?;
+    Ok(OrderId::from_bytes(bytes))
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+use super::ids::{AccountId, ClientId};
+use super::orderbook::OrderBook;
+use super::orders::{ClientOrderId, Order, OrderId};
+use super::pair::Pair;
+
+/// Maximum number of orders returned in a single page of open orders.
+pub const MAX_PAGE_LIMIT: u32 = 1000;
+
+/// Maximum number of levels returned on each side of a depth query.
+pub const MAX_DEPTH_LEVELS: u32 = 1000;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum QueryError {
+    #[error("pair does not exist")]
+    PairNotFound,
+    #[error("order does not exist: {0}")]
+    OrderNotFound(OrderId),
+}
+
+/// Read-only view of a resting order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OrderView {
+    /// order id
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub id: OrderId,
+    /// gateway client id
+    pub cid: ClientId,
+    /// user-supplied client order id
+    #[cfg_attr(feature = "schema", schemars(with = "Option<Vec<u8>>"))]
+    pub coid: Option<ClientOrderId>,
+    /// owner of the order
+    pub owner: AccountId,
+    /// is bid order
+    pub is_bid: bool,
+    /// price in 8 decimals
+    pub price: u64,
+    /// whole amount in 8 decimals
+    pub amnt: u64,
+    /// iceberg quantity in 8 decimals
+    pub iqty: u64,
+    /// public quantity in 8 decimals
+    pub pqty: u64,
+    /// current quantity in 8 decimals
+    pub cqty: u64,
+    /// fee basis points of the order
+    pub fee_bps: i16,
+    /// timestamp in milliseconds, i64 is chosen because of js type compatibility
+    pub timestamp: i64,
+    /// expires at timestamp in milliseconds, i64 is chosen because of js type compatibility
+    pub expires_at: i64,
+}
+
+impl From<&Order> for OrderView {
+    fn from(order: &Order) -> Self {
+        Self {
+            id: order.id,
+            cid: order.cid,
+            coid: order.coid.clone(),
+            owner: order.owner,
+            is_bid: order.is_bid,
+            price: order.price,
+            amnt: order.amnt,
+            iqty: order.iqty,
+            pqty: order.pqty,
+            cqty: order.cqty,
+            fee_bps: order.fee_bps,
+            timestamp: order.timestamp,
+            expires_at: order.expires_at,
+        }
+    }
+}
+
+/// A page of open orders.
+/// - `next_cursor` is the cursor to pass to fetch the next page, `None` on the last page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OrdersPage {
+    pub orders: Vec<OrderView>,
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub next_cursor: Option<OrderId>,
+}
+
+/// Public quantity resting at a price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DepthLevel {
+    /// price in 8 decimals
+    pub price: u64,
+    /// public quantity in 8 decimals
+    pub qty: u64,
+}
+
+/// L2 depth of a pair, best prices first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Depth {
+    pub bids: Vec<DepthLevel>,
+    pub asks: Vec<DepthLevel>,
+}
+
+/// Best bid and ask of a pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BestBidAsk {
+    pub bid: Option<DepthLevel>,
+    pub ask: Option<DepthLevel>,
+}
+
+/// Collects a page of the owner's open orders on a pair, ordered by order id.
+/// - `cursor` is the last order id of the previous page, `None` for the first page.
+/// - `limit` is capped at `MAX_PAGE_LIMIT`.
+pub fn open_orders(pair: &Pair, owner: AccountId, cursor: Option<OrderId>, limit: u32) -> OrdersPage {
+    let limit = limit.min(MAX_PAGE_LIMIT) as usize;
+    let l3 = &pair.orderbook.l3;
+    let ids = l3.get_order_ids_by_owner_after(owner, cursor, limit + 1);
+    let has_more = ids.len() > limit;
+    let orders: Vec<OrderView> = ids
+        .into_iter()
+        .take(limit)
+        .filter_map(|id| l3.get_order(id).ok().map(OrderView::from))
+        .collect();
+    let next_cursor = if has_more { orders.last().map(|order| order.id) } else { None };
+    OrdersPage { orders, next_cursor }
+}
+
+/// Returns a single resting order on a pair.
+pub fn order(pair: &Pair, order_id: OrderId) -> Result<OrderView, QueryError> {
+    pair.orderbook
+        .l3
+        .get_order(order_id)
+        .map(OrderView::from)
+        .map_err(|_| QueryError::OrderNotFound(order_id))
+}
+
+/// Collects up to `n` public levels on each side of the book, capped at `MAX_DEPTH_LEVELS`.
+pub fn depth(orderbook: &OrderBook, n: u32) -> Depth {
+    let n = n.min(MAX_DEPTH_LEVELS);
+    Depth {
+        bids: levels(orderbook, true, n),
+        asks: levels(orderbook, false, n),
+    }
+}
+
+/// Returns the best bid and ask levels of the book.
+pub fn best_bid_ask(orderbook: &OrderBook) -> BestBidAsk {
+    BestBidAsk {
+        bid: levels(orderbook, true, 1).into_iter().next(),
+        ask: levels(orderbook, false, 1).into_iter().next(),
+    }
+}
+
+/// Collects up to `n` non-empty public levels on one side of the book.
+/// Levels of the side without a current quantity are skipped.
+fn levels(orderbook: &OrderBook, is_bid: bool, n: u32) -> Vec<DepthLevel> {
+    let l2 = &orderbook.l2;
+    let mut result = Vec::with_capacity(n as usize);
+    let mut current = if is_bid { l2.bid_head() } else { l2.ask_head() };
+    while let Some(price) = current {
+        if result.len() as u32 >= n {
+            break;
+        }
+        let (cqty, pqty) = if is_bid {
+            (l2.current_bid_level(price), l2.public_bid_level(price))
+        } else {
+            (l2.current_ask_level(price), l2.public_ask_level(price))
+        };
+        if cqty.is_some_and(|cqty| cqty > 0) {
+            result.push(DepthLevel { price, qty: pqty.unwrap_or(0) });
+        }
+        current = l2.next_price(is_bid, price);
+    }
+    result
+}
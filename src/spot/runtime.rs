@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::sync::mpsc;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::thread::{self, JoinHandle};
+
+use super::command::SpotCommand;
+use super::event::{self, SpotEvent};
+use super::ids::PairId;
+use super::matching_engine::MatchingEngine;
+use super::orderbook::OrderBookError;
+
+/// Event of a command run by a `ShardedEngine`, with the metadata ordering it in the merged stream.
+/// - `seq` is the submission sequence number of the command, the events of a command share it.
+/// - `pair_seq` numbers the events of a pair from zero, without gaps.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SequencedEvent {
+    pub seq: u64,
+    pub pair_id: PairId,
+    pub pair_seq: u64,
+    pub event: SpotEvent,
+}
+
+/// Result of a command run by a `ShardedEngine`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CommandOutput {
+    /// submission sequence number of the command
+    pub seq: u64,
+    /// shard the command ran on
+    pub shard: usize,
+    /// pair of the command
+    pub pair_id: PairId,
+    /// events of the command, or the error it was rejected with
+    pub result: Result<Vec<SequencedEvent>, OrderBookError>,
+}
+
+impl CommandOutput {
+    /// Returns the events of the command, none for a rejected command
+    pub fn events(&self) -> &[SequencedEvent] {
+        self.result.as_deref().unwrap_or(&[])
+    }
+}
+
+/// Returns the shard owning a pair, from a hash of the pair id bytes so it does not depend on the process.
+pub fn shard_of(pair_id: PairId, shards: usize) -> usize {
+    let hash = blake3::hash(pair_id.as_slice());
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&hash.as_bytes()[..8]);
+    (u64::from_le_bytes(prefix) % shards as u64) as usize
+}
+
+/// Engine of the pairs of a shard and the next event sequence number of each pair
+struct Shard {
+    index: usize,
+    engine: MatchingEngine,
+    pair_seqs: HashMap<PairId, u64>,
+}
+
+impl Shard {
+    fn new(index: usize) -> Self {
+        Self { index, engine: MatchingEngine::new(), pair_seqs: HashMap::new() }
+    }
+
+    /// Executes a command, its events are read from the queue of the calling thread
+    fn run(&mut self, seq: u64, command: SpotCommand) -> CommandOutput {
+        let pair_id = command.pair_id();
+        let result = self.engine.execute(command).map(|events| {
+            let pair_seq = self.pair_seqs.entry(pair_id).or_insert(0);
+            events
+                .into_vec()
+                .into_iter()
+                .map(|event| {
+                    *pair_seq += 1;
+                    SequencedEvent { seq, pair_id, pair_seq: *pair_seq - 1, event }
+                })
+                .collect()
+        });
+        CommandOutput { seq, shard: self.index, pair_id, result }
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+enum Reply {
+    Output(CommandOutput),
+    Panicked { seq: u64, shard: usize, message: String },
+}
+
+enum Workers {
+    /// Shards run inline on the caller's thread, outputs wait for the next flush
+    Synchronous { shards: Vec<Shard>, ready: Vec<CommandOutput> },
+    /// Shards run on worker threads fed by one channel each, outputs come back on `replies`
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    Threaded {
+        commands: Vec<mpsc::Sender<(u64, SpotCommand)>>,
+        replies: mpsc::Receiver<Reply>,
+        handles: Vec<JoinHandle<MatchingEngine>>,
+        pending: usize,
+    },
+}
+
+/// Runtime owning the pairs of a `MatchingEngine` on a number of shards.
+/// - every pair lives on the shard given by `shard_of`, so the commands of a pair run in submission order
+///   on one engine and behave exactly as on a single `MatchingEngine`.
+/// - commands of pairs on different shards run in parallel on worker threads, or inline on the caller's
+///   thread for the synchronous mode.
+/// - `flush` returns the outputs in submission order, so the merged event stream is the same whatever
+///   the scheduling of the workers.
+///
+/// # Examples
+///
+/// ```
+/// use off_grid_primitives::spot::command::SpotCommand;
+/// use off_grid_primitives::spot::runtime::ShardedEngine;
+///
+/// let mut runtime = ShardedEngine::synchronous(4);
+/// let seq = runtime.submit(SpotCommand::AddPair {
+///     cid: "gateway".into(),
+///     admin_account_id: "admin".into(),
+///     fee_account_id: "fees".into(),
+///     pair_id: "BTC-USD".into(),
+///     timestamp: 0,
+/// });
+/// let outputs = runtime.flush();
+/// assert_eq!(outputs[0].seq, seq);
+/// assert!(outputs[0].result.is_ok());
+/// ```
+pub struct ShardedEngine {
+    workers: Workers,
+    shards: usize,
+    next_seq: u64,
+}
+
+impl ShardedEngine {
+    /// Starts a runtime with one worker thread per shard.
+    /// Not available on `wasm32-unknown-unknown`, which has no threads, use `synchronous` there.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn new(shards: usize) -> Self {
+        assert!(shards > 0, "a runtime needs at least one shard");
+        let (reply_tx, replies) = mpsc::channel();
+        let mut commands = Vec::with_capacity(shards);
+        let mut handles = Vec::with_capacity(shards);
+        for index in 0..shards {
+            let (tx, rx) = mpsc::channel::<(u64, SpotCommand)>();
+            let reply_tx = reply_tx.clone();
+            let handle = thread::Builder::new()
+                .name(format!("spot-shard-{index}"))
+                .spawn(move || event::isolated(|| work(Shard::new(index), rx, reply_tx)))
+                .expect("spawn shard worker");
+            commands.push(tx);
+            handles.push(handle);
+        }
+        Self { workers: Workers::Threaded { commands, replies, handles, pending: 0 }, shards, next_seq: 0 }
+    }
+
+    /// Creates a runtime running its shards inline on the caller's thread, in submission order
+    pub fn synchronous(shards: usize) -> Self {
+        assert!(shards > 0, "a runtime needs at least one shard");
+        Self {
+            workers: Workers::Synchronous { shards: (0..shards).map(Shard::new).collect(), ready: Vec::new() },
+            shards,
+            next_seq: 0,
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards
+    }
+
+    /// Routes a command to the shard of its pair, returning the sequence number of the command
+    pub fn submit(&mut self, command: SpotCommand) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let shard = shard_of(command.pair_id(), self.shards);
+        match &mut self.workers {
+            Workers::Synchronous { shards, ready } => {
+                // keep the caller's queue out of the shard's events, as on a worker thread
+                ready.push(event::isolated(|| shards[shard].run(seq, command)));
+            }
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            Workers::Threaded { commands, pending, .. } => {
+                commands[shard].send((seq, command)).expect("shard worker stopped");
+                *pending += 1;
+            }
+        }
+        seq
+    }
+
+    /// Waits for the outputs of all submitted commands, returning them in submission order.
+    /// Panics if a shard panicked on a command.
+    pub fn flush(&mut self) -> Vec<CommandOutput> {
+        let mut outputs = match &mut self.workers {
+            Workers::Synchronous { ready, .. } => std::mem::take(ready),
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            Workers::Threaded { replies, pending, .. } => {
+                let mut outputs = Vec::with_capacity(*pending);
+                while *pending > 0 {
+                    match replies.recv().expect("shard workers stopped") {
+                        Reply::Output(output) => outputs.push(output),
+                        Reply::Panicked { seq, shard, message } => {
+                            panic!("shard {shard} panicked on command {seq}: {message}")
+                        }
+                    }
+                    *pending -= 1;
+                }
+                outputs
+            }
+        };
+        outputs.sort_by_key(|output| output.seq);
+        outputs
+    }
+
+    /// Stops the runtime, returning the engine of every shard in shard order.
+    /// Commands not flushed yet still run before their worker stops.
+    pub fn shutdown(self) -> Vec<MatchingEngine> {
+        match self.workers {
+            Workers::Synchronous { shards, .. } => shards.into_iter().map(|shard| shard.engine).collect(),
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            Workers::Threaded { commands, handles, .. } => {
+                drop(commands);
+                handles.into_iter().map(|handle| handle.join().expect("shard worker panicked")).collect()
+            }
+        }
+    }
+}
+
+/// Runs the commands of a shard until the runtime stops or the shard panics
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn work(mut shard: Shard, commands: mpsc::Receiver<(u64, SpotCommand)>, replies: mpsc::Sender<Reply>) -> MatchingEngine {
+    while let Ok((seq, command)) = commands.recv() {
+        let reply = match panic::catch_unwind(AssertUnwindSafe(|| shard.run(seq, command))) {
+            Ok(output) => Reply::Output(output),
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                // the shard may be left half way through the command, stop taking commands
+                let _ = replies.send(Reply::Panicked { seq, shard: shard.index, message });
+                break;
+            }
+        };
+        if replies.send(reply).is_err() {
+            break;
+        }
+    }
+    shard.engine
+}
@@ -0,0 +1,174 @@
+//! JSON Schema and TypeScript definitions of the events and DTOs a frontend reads, with the `schema` feature.
+//!
+//! Both are generated from the Rust types and describe their JSON encoding (`serde_json`):
+//! - ids and `serde_bytes` fields are arrays of byte numbers, order ids (`Ulid`) are strings.
+//! - enums are externally tagged, `{ "SpotOrderPlaced": { ... } }`.
+//! - `u64` and `i64` fields are numbers, exact in JS up to `Number.MAX_SAFE_INTEGER`.
+//!
+//! The generated files are checked in under `schema/`, a test fails when they drift from the types.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use schemars::generate::SchemaSettings;
+use schemars::JsonSchema;
+use serde_json::{Map, Value};
+
+use super::event::SpotEvent;
+use super::market::L1;
+use super::market_data::{L2Delta, L2Snapshot};
+use super::orders::Order;
+use super::prices::Level;
+use super::query::{BestBidAsk, Depth, DepthLevel, OrderView, OrdersPage};
+use super::ticker::Ticker;
+use super::trades::{Candle, Trade};
+
+/// Returns one JSON Schema document defining every exported type under `$defs`
+pub fn json_schema() -> Value {
+    let mut generator = SchemaSettings::draft2020_12().into_generator();
+    let mut roots = Vec::new();
+    macro_rules! export {
+        ($($ty:ty),* $(,)?) => {
+            $(
+                generator.subschema_for::<$ty>();
+                roots.push(Value::String(<$ty as JsonSchema>::schema_name().into_owned()));
+            )*
+        };
+    }
+    export!(SpotEvent, Order, Level, L1, L2Snapshot, L2Delta, Trade, Candle, Ticker, OrderView, OrdersPage, Depth, DepthLevel, BestBidAsk);
+
+    let definitions: BTreeMap<String, Value> = generator.take_definitions(true).into_iter().collect();
+    let mut schema = Map::new();
+    schema.insert("$schema".into(), "https://json-schema.org/draft/2020-12/schema".into());
+    schema.insert("title".into(), "off-grid-primitives spot".into());
+    schema.insert("x-exported".into(), Value::Array(roots));
+    schema.insert("$defs".into(), Value::Object(definitions.into_iter().collect()));
+    Value::Object(schema)
+}
+
+/// Returns the JSON Schema document as pretty JSON, the content of `schema/spot.schema.json`
+pub fn json_schema_string() -> String {
+    let mut text = serde_json::to_string_pretty(&json_schema()).expect("schema is valid JSON");
+    text.push('\n');
+    text
+}
+
+/// Returns TypeScript definitions of every type of `json_schema`, the content of `schema/spot.d.ts`
+pub fn typescript() -> String {
+    let schema = json_schema();
+    let mut out = String::from("// Generated from the Rust types by `off_grid_primitives::spot::schema`, do not edit.\n");
+    for (name, definition) in schema["$defs"].as_object().into_iter().flatten() {
+        out.push('\n');
+        write_doc(&mut out, definition, "");
+        let _ = writeln!(out, "export type {name} ={};", assigned(ts_type(definition, "")));
+    }
+    out
+}
+
+fn write_doc(out: &mut String, schema: &Value, indent: &str) {
+    if let Some(description) = schema.get("description").and_then(Value::as_str) {
+        let _ = writeln!(out, "{indent}/**");
+        for line in description.lines() {
+            let _ = writeln!(out, "{indent} * {line}");
+        }
+        let _ = writeln!(out, "{indent} */");
+    }
+}
+
+/// TypeScript type of a schema of the subset schemars generates for serde types
+fn ts_type(schema: &Value, indent: &str) -> String {
+    if schema == &Value::Bool(true) {
+        return "unknown".into();
+    }
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference.rsplit('/').next().unwrap_or(reference).to_string();
+    }
+    if let Some(value) = schema.get("const") {
+        return value.to_string();
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return values.iter().map(Value::to_string).collect::<Vec<_>>().join(" | ");
+    }
+    if let Some(variants) = schema.get("oneOf").or_else(|| schema.get("anyOf")).and_then(Value::as_array) {
+        let types: Vec<String> = variants.iter().map(|variant| ts_type(variant, indent)).collect();
+        if !types.iter().any(|ty| ty.contains('\n')) && !variants.iter().any(|variant| variant.get("description").is_some()) {
+            return types.join(" | ");
+        }
+        // one variant per line, with its doc
+        let inner = format!("{indent}  ");
+        let mut out = String::new();
+        for variant in variants {
+            out.push('\n');
+            write_doc(&mut out, variant, &inner);
+            let _ = write!(out, "{inner}| {}", ts_type(variant, &inner));
+        }
+        return out;
+    }
+    match schema.get("type") {
+        Some(Value::Array(types)) => types
+            .iter()
+            .map(|ty| {
+                let mut single = schema.clone();
+                single["type"] = ty.clone();
+                ts_type(&single, indent)
+            })
+            .collect::<Vec<_>>()
+            .join(" | "),
+        Some(Value::String(ty)) => match ty.as_str() {
+            "integer" | "number" => "number".into(),
+            "string" => "string".into(),
+            "boolean" => "boolean".into(),
+            "null" => "null".into(),
+            "array" => match schema.get("items") {
+                Some(items) => {
+                    let item = ts_type(items, indent);
+                    if item.contains(' ') { format!("({item})[]") } else { format!("{item}[]") }
+                }
+                None => "unknown[]".into(),
+            },
+            "object" => ts_object(schema, indent),
+            _ => "unknown".into(),
+        },
+        _ => "unknown".into(),
+    }
+}
+
+/// Separates an assigned type from `=` or `:`, multi-line unions start on the next line
+fn assigned(ty: String) -> String {
+    if ty.starts_with('\n') { ty } else { format!(" {ty}") }
+}
+
+fn is_nullable(schema: &Value) -> bool {
+    let null = Value::String("null".into());
+    match schema.get("type") {
+        Some(Value::Array(types)) => types.contains(&null),
+        Some(ty) => ty == &null,
+        None => ["oneOf", "anyOf"]
+            .iter()
+            .filter_map(|key| schema.get(*key).and_then(Value::as_array))
+            .flatten()
+            .any(is_nullable),
+    }
+}
+
+fn ts_object(schema: &Value, indent: &str) -> String {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return "Record<string, unknown>".into();
+    };
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let inner = format!("{indent}  ");
+    let mut out = String::from("{\n");
+    for (name, property) in properties {
+        write_doc(&mut out, property, &inner);
+        // serde writes every field, `Option` fields are not required in the schema since they deserialize when missing
+        let optional = if required.contains(&name.as_str()) || is_nullable(property) { "" } else { "?" };
+        let _ = writeln!(out, "{inner}{name}{optional}:{};", assigned(ts_type(property, &inner)));
+    }
+    out.push_str(indent);
+    out.push('}');
+    out
+}
@@ -0,0 +1,205 @@
+//! Versioned snapshots of the engine state.
+//!
+//! A snapshot is `SNAPSHOT_MAGIC` followed by the postcard `SNAPSHOT_VERSION` it was written with and the postcard
+//! encoding of the state in the layout of that version.
+//!
+//! - version 0 is the layout of the 0.1.0 release, which serialized the state with plain postcard and no header.
+//!   Bytes not starting with `SNAPSHOT_MAGIC` are read as version 0, through the 0.1.0 layouts kept next to each
+//!   type. The state added since takes its defaults.
+//! - a change of the serialized layout of a snapshot type bumps `SNAPSHOT_VERSION`, the layouts of older versions
+//!   stay decodable. The fixtures in `tests/spot/golden/snapshot` were written by the release of their version.
+//!
+//! Version 1 adds to the 0.1.0 layout:
+//! - the price scales, the depth feed sequences and the changed scaled levels of `L2`. Pairs of version 0 maintain
+//!   the `DEFAULT_SCALES` of new pairs, rebuilt from their price levels, and their depth feeds are closed until the
+//!   next `market_data::snapshot`.
+//! - the id of the last trade of `OrderBook` and the candles of `Pair`. Trade ids of version 0 books start from 1 and
+//!   their candles from the first trade after the restore.
+//! - the rolling ticker of `Pair`, which starts from the first trade after the restore of a version 0 pair.
+//! - the client order id of `Order`. The owner and client order id indexes of `L3` are rebuilt from the orders.
+//! - the market protection bands of `L1`, at `DEFAULT_MARKET_PROTECTION_BPS` for version 0 pairs. Their 8 decimals
+//!   market slippage limits are kept as they are.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::matching_engine::MatchingEngine;
+use super::orderbook::OrderBook;
+use super::orders::{LinkedL3, LinkedOrder, L3};
+use super::pair::Pair;
+use super::prices::{LinkedL2, L2};
+
+/// Snapshot version written by this version of the crate
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+/// First bytes of a versioned snapshot. A version 0 snapshot starts with an option tag or a canonical varint
+/// length, and a canonical varint never continues into a zero byte.
+pub const SNAPSHOT_MAGIC: [u8; 2] = [0xEE, 0x00];
+
+#[derive(Debug, Clone, thiserror::Error, PartialEq, Eq)]
+pub enum SnapshotError {
+    #[error("postcard error: {0}")]
+    Postcard(#[from] postcard::Error),
+    #[error("unsupported snapshot version {0}")]
+    UnsupportedVersion(u16),
+}
+
+/// State that can be snapshotted and restored, from this or an older version
+pub trait Snapshot: Serialize + DeserializeOwned {
+    /// Decodes the state from its layout of version 0
+    fn from_v0(bytes: &[u8]) -> Result<Self, postcard::Error>;
+}
+
+impl Snapshot for L2 {
+    fn from_v0(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes::<LinkedL2>(bytes).map(L2::from)
+    }
+}
+
+impl Snapshot for L3 {
+    fn from_v0(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes::<LinkedL3<LinkedOrder>>(bytes).map(L3::from)
+    }
+}
+
+impl Snapshot for OrderBook {
+    fn from_v0(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes::<v0::OrderBook>(bytes).map(OrderBook::from)
+    }
+}
+
+impl Snapshot for Pair {
+    fn from_v0(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes::<v0::Pair>(bytes).map(Pair::from)
+    }
+}
+
+impl Snapshot for MatchingEngine {
+    fn from_v0(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes::<v0::MatchingEngine>(bytes).map(MatchingEngine::from)
+    }
+}
+
+/// Encodes the state in the current snapshot version
+pub fn encode<T: Snapshot>(state: &T) -> Result<Vec<u8>, SnapshotError> {
+    let mut bytes = SNAPSHOT_MAGIC.to_vec();
+    bytes.extend(postcard::to_allocvec(&SNAPSHOT_VERSION)?);
+    bytes.extend(postcard::to_allocvec(state)?);
+    Ok(bytes)
+}
+
+/// Decodes a snapshot written by `encode` of any version, or by plain postcard serialization in version 0
+pub fn decode<T: Snapshot>(bytes: &[u8]) -> Result<T, SnapshotError> {
+    let Some(versioned) = bytes.strip_prefix(SNAPSHOT_MAGIC.as_slice()) else {
+        return Ok(T::from_v0(bytes)?);
+    };
+    let (version, state) = postcard::take_from_bytes::<u16>(versioned)?;
+    match version {
+        SNAPSHOT_VERSION => Ok(postcard::from_bytes(state)?),
+        version => Err(SnapshotError::UnsupportedVersion(version)),
+    }
+}
+
+/// Layouts of the 0.1.0 release of `L1` and of the types holding an `L2` or `L3`.
+/// `L2`, `L3` and `Order` keep theirs next to them as `LinkedL2`, `LinkedL3` and `LinkedOrder`.
+pub(crate) mod v0 {
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    use super::super::ids::{AccountId, AssetId, ClientId, PairId};
+    use super::super::market::DEFAULT_MARKET_PROTECTION_BPS;
+    use super::super::orders::{LinkedL3, LinkedOrder};
+    use super::super::prices::{LinkedL2, DEFAULT_SCALES};
+
+    #[derive(Deserialize)]
+    pub(crate) struct OrderBook {
+        l2: LinkedL2,
+        l3: LinkedL3<LinkedOrder>,
+        fee_recipients: HashMap<ClientId, AccountId>,
+        dust: u64,
+    }
+
+    impl From<OrderBook> for super::OrderBook {
+        fn from(orderbook: OrderBook) -> Self {
+            Self {
+                l2: orderbook.l2.into(),
+                l3: orderbook.l3.into(),
+                fee_recipients: orderbook.fee_recipients,
+                dust: orderbook.dust,
+                ..Self::new()
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub(crate) struct L1 {
+        lmp: Option<u64>,
+        bid_head: Option<u64>,
+        ask_head: Option<u64>,
+        limit_buy_slippage_limit: Option<u64>,
+        limit_sell_slippage_limit: Option<u64>,
+        market_buy_slippage_limit: Option<u64>,
+        market_sell_slippage_limit: Option<u64>,
+    }
+
+    impl From<L1> for super::super::market::L1 {
+        fn from(l1: L1) -> Self {
+            Self {
+                lmp: l1.lmp,
+                bid_head: l1.bid_head,
+                ask_head: l1.ask_head,
+                limit_buy_slippage_limit: l1.limit_buy_slippage_limit,
+                limit_sell_slippage_limit: l1.limit_sell_slippage_limit,
+                market_buy_slippage_limit: l1.market_buy_slippage_limit,
+                market_sell_slippage_limit: l1.market_sell_slippage_limit,
+                market_buy_protection_bps: Some(DEFAULT_MARKET_PROTECTION_BPS),
+                market_sell_protection_bps: Some(DEFAULT_MARKET_PROTECTION_BPS),
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub(crate) struct Pair {
+        pair_id: PairId,
+        base_asset_id: AssetId,
+        quote_asset_id: AssetId,
+        l1: L1,
+        market_price: Option<u64>,
+        orderbook: OrderBook,
+        clients: Vec<ClientId>,
+        client_admin_account_ids: HashMap<ClientId, AccountId>,
+        client_fee_account_ids: HashMap<ClientId, AccountId>,
+    }
+
+    impl From<Pair> for super::Pair {
+        fn from(pair: Pair) -> Self {
+            let mut orderbook: super::OrderBook = pair.orderbook.into();
+            orderbook.l2.set_scales(&DEFAULT_SCALES);
+            Self {
+                pair_id: pair.pair_id,
+                base_asset_id: pair.base_asset_id,
+                quote_asset_id: pair.quote_asset_id,
+                l1: pair.l1.into(),
+                market_price: pair.market_price,
+                orderbook,
+                clients: pair.clients,
+                client_admin_account_ids: pair.client_admin_account_ids,
+                client_fee_account_ids: pair.client_fee_account_ids,
+                ..Self::new()
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub(crate) struct MatchingEngine {
+        pairs: HashMap<PairId, Pair>,
+        total_pairs: u32,
+    }
+
+    impl From<MatchingEngine> for super::MatchingEngine {
+        fn from(engine: MatchingEngine) -> Self {
+            let pairs = engine.pairs.into_iter().map(|(pair_id, pair)| (pair_id, pair.into())).collect();
+            Self::from_pairs(pairs, engine.total_pairs)
+        }
+    }
+}
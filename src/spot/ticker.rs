@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use super::trades::Trade;
+
+/// Length of the rolling ticker window in milliseconds
+pub const TICKER_WINDOW_MS: i64 = 24 * 60 * 60_000;
+
+/// Length of a rolling ticker bucket in milliseconds
+pub const TICKER_BUCKET_MS: i64 = 60_000;
+
+/// Trade statistics of one time bucket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct TickerBucket {
+    /// start of the bucket in milliseconds
+    pub start: i64,
+    /// first trade price in 8 decimals
+    pub open: u64,
+    /// highest trade price in 8 decimals
+    pub high: u64,
+    /// lowest trade price in 8 decimals
+    pub low: u64,
+    /// base volume in 8 decimals
+    pub base_volume: u64,
+    /// quote volume in 8 decimals
+    pub quote_volume: u64,
+    /// number of trades
+    pub trades: u64,
+}
+
+/// Ticker statistics of a pair over the rolling window.
+/// - `open`, `high` and `low` are `None` when there was no trade in the window.
+/// - `change_bps` is the change from `open` to `last` in basis points.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Ticker {
+    /// last trade price in 8 decimals
+    pub last: Option<u64>,
+    /// first trade price of the window in 8 decimals
+    pub open: Option<u64>,
+    /// highest trade price of the window in 8 decimals
+    pub high: Option<u64>,
+    /// lowest trade price of the window in 8 decimals
+    pub low: Option<u64>,
+    /// base volume of the window in 8 decimals
+    pub base_volume: u64,
+    /// quote volume of the window in 8 decimals
+    pub quote_volume: u64,
+    /// number of trades in the window
+    pub trades: u64,
+    /// price change from open to last in basis points
+    pub change_bps: i64,
+}
+
+/// Rolling window trade statistics of a pair kept in fixed time buckets.
+/// Applying a trade is O(1) amortized, a ticker query walks the buckets of the window.
+/// The component is serializable so it can be persisted with its pair.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct RollingTicker {
+    /// window length in milliseconds
+    pub window: i64,
+    /// bucket length in milliseconds
+    pub bucket: i64,
+    /// last trade price in 8 decimals, kept after the window has no trade
+    pub last: Option<u64>,
+    /// buckets sorted by start ascending
+    pub buckets: VecDeque<TickerBucket>,
+}
+
+impl RollingTicker {
+    pub fn new(window: i64, bucket: i64) -> Self {
+        Self {
+            window,
+            bucket,
+            last: None,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    /// Applies a trade to the bucket containing its timestamp and drops buckets out of the window.
+    /// - a late trade is applied to its bucket, inserted in order if it had no trade yet. It does not
+    ///   move `last`, and it is dropped if its bucket already left the window of the latest bucket.
+    pub fn apply_trade(&mut self, trade: &Trade) {
+        let start = trade.timestamp - trade.timestamp.rem_euclid(self.bucket.max(1));
+        match self.buckets.back().map(|bucket| bucket.start) {
+            Some(last) if last > start => {
+                if start + self.bucket <= last - self.window {
+                    return;
+                }
+                let at = self.buckets.partition_point(|bucket| bucket.start < start);
+                match self.buckets.get_mut(at) {
+                    Some(bucket) if bucket.start == start => Self::apply(bucket, trade),
+                    _ => self.buckets.insert(at, Self::open(start, trade)),
+                }
+                return;
+            }
+            Some(last) if last == start => Self::apply(self.buckets.back_mut().unwrap(), trade),
+            _ => self.buckets.push_back(Self::open(start, trade)),
+        }
+        self.last = Some(trade.price);
+        self.evict(trade.timestamp);
+    }
+
+    fn open(start: i64, trade: &Trade) -> TickerBucket {
+        TickerBucket {
+            start,
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            base_volume: trade.base_volume,
+            quote_volume: trade.quote_volume,
+            trades: 1,
+        }
+    }
+
+    fn apply(bucket: &mut TickerBucket, trade: &Trade) {
+        bucket.high = bucket.high.max(trade.price);
+        bucket.low = bucket.low.min(trade.price);
+        bucket.base_volume = bucket.base_volume.saturating_add(trade.base_volume);
+        bucket.quote_volume = bucket.quote_volume.saturating_add(trade.quote_volume);
+        bucket.trades += 1;
+    }
+
+    /// Drops the buckets that ended before the window at `now`
+    pub fn evict(&mut self, now: i64) {
+        let from = now - self.window;
+        while self.buckets.front().is_some_and(|bucket| bucket.start + self.bucket <= from) {
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Returns the ticker of the window ending at `now` in milliseconds.
+    pub fn ticker(&self, now: i64) -> Ticker {
+        let from = now - self.window;
+        let mut ticker = Ticker {
+            last: self.last,
+            ..Ticker::default()
+        };
+        for bucket in self.buckets.iter().filter(|bucket| bucket.start + self.bucket > from) {
+            ticker.open.get_or_insert(bucket.open);
+            ticker.high = Some(ticker.high.map_or(bucket.high, |high| high.max(bucket.high)));
+            ticker.low = Some(ticker.low.map_or(bucket.low, |low| low.min(bucket.low)));
+            ticker.base_volume = ticker.base_volume.saturating_add(bucket.base_volume);
+            ticker.quote_volume = ticker.quote_volume.saturating_add(bucket.quote_volume);
+            ticker.trades += bucket.trades;
+        }
+        if let (Some(open), Some(last)) = (ticker.open, ticker.last) {
+            if open > 0 {
+                ticker.change_bps = ((last as i128 - open as i128) * 10000 / open as i128) as i64;
+            }
+        }
+        ticker
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use super::event::SpotEvent;
+use super::ids::{AssetId, PairId};
+
+/// Number of closed candles kept per interval by default
+pub const DEFAULT_CANDLE_HISTORY: usize = 1000;
+
+/// Canonical record of a single match between a taker and a maker order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Trade {
+    /// trade id, increasing by one per match on the pair
+    pub trade_id: u64,
+    /// pair id
+    pub pair_id: PairId,
+    /// base asset id
+    pub base_asset_id: AssetId,
+    /// quote asset id
+    pub quote_asset_id: AssetId,
+    /// taker order id
+    #[serde(with = "serde_bytes")]
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
+    pub taker_order_id: Vec<u8>,
+    /// maker order id
+    #[serde(with = "serde_bytes")]
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<u8>"))]
+    pub maker_order_id: Vec<u8>,
+    /// aggressor side, true when the taker is buying
+    pub taker_is_bid: bool,
+    /// execution price in 8 decimals, the maker's price
+    pub price: u64,
+    /// base volume in 8 decimals
+    pub base_volume: u64,
+    /// quote volume in 8 decimals
+    pub quote_volume: u64,
+    /// timestamp in milliseconds, i64 is chosen because of js type compatibility
+    pub timestamp: i64,
+}
+
+impl From<Trade> for SpotEvent {
+    fn from(trade: Trade) -> Self {
+        SpotEvent::SpotTrade {
+            trade_id: trade.trade_id,
+            pair_id: trade.pair_id,
+            base_asset_id: trade.base_asset_id,
+            quote_asset_id: trade.quote_asset_id,
+            taker_order_id: trade.taker_order_id,
+            maker_order_id: trade.maker_order_id,
+            taker_is_bid: trade.taker_is_bid,
+            price: trade.price,
+            base_volume: trade.base_volume,
+            quote_volume: trade.quote_volume,
+            timestamp: trade.timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum CandleInterval {
+    OneMinute,
+    FiveMinutes,
+    OneHour,
+    OneDay,
+}
+
+impl CandleInterval {
+    pub const ALL: [CandleInterval; 4] = [
+        CandleInterval::OneMinute,
+        CandleInterval::FiveMinutes,
+        CandleInterval::OneHour,
+        CandleInterval::OneDay,
+    ];
+
+    /// Length of the interval in milliseconds
+    pub fn millis(&self) -> i64 {
+        match self {
+            CandleInterval::OneMinute => 60_000,
+            CandleInterval::FiveMinutes => 5 * 60_000,
+            CandleInterval::OneHour => 60 * 60_000,
+            CandleInterval::OneDay => 24 * 60 * 60_000,
+        }
+    }
+
+    /// Start of the interval containing `timestamp` in milliseconds
+    pub fn open_time(&self, timestamp: i64) -> i64 {
+        timestamp - timestamp.rem_euclid(self.millis())
+    }
+}
+
+/// OHLCV bar of an interval.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Candle {
+    /// start of the interval in milliseconds
+    pub open_time: i64,
+    /// first trade price in 8 decimals
+    pub open: u64,
+    /// highest trade price in 8 decimals
+    pub high: u64,
+    /// lowest trade price in 8 decimals
+    pub low: u64,
+    /// last trade price in 8 decimals
+    pub close: u64,
+    /// base volume in 8 decimals
+    pub base_volume: u64,
+    /// quote volume in 8 decimals
+    pub quote_volume: u64,
+    /// number of trades
+    pub trades: u64,
+}
+
+impl Candle {
+    fn new(open_time: i64, trade: &Trade) -> Self {
+        Self {
+            open_time,
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            base_volume: trade.base_volume,
+            quote_volume: trade.quote_volume,
+            trades: 1,
+        }
+    }
+
+    fn apply(&mut self, trade: &Trade) {
+        self.high = self.high.max(trade.price);
+        self.low = self.low.min(trade.price);
+        self.close = trade.price;
+        self.base_volume = self.base_volume.saturating_add(trade.base_volume);
+        self.quote_volume = self.quote_volume.saturating_add(trade.quote_volume);
+        self.trades += 1;
+    }
+}
+
+/// Builds OHLCV candles of the configured intervals from trades.
+/// - intervals without trades produce no candle.
+/// - the last candle of an interval is the open one, up to `history` closed candles are kept before it.
+/// - the aggregator is serializable so it can be snapshotted and restored with its pair.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct CandleAggregator {
+    /// number of closed candles kept per interval
+    pub history: usize,
+    /// candles per interval sorted by open time ascending
+    pub candles: BTreeMap<CandleInterval, VecDeque<Candle>>,
+}
+
+impl CandleAggregator {
+    pub fn new(intervals: &[CandleInterval], history: usize) -> Self {
+        Self {
+            history,
+            candles: intervals.iter().map(|interval| (*interval, VecDeque::new())).collect(),
+        }
+    }
+
+    pub fn intervals(&self) -> Vec<CandleInterval> {
+        self.candles.keys().copied().collect()
+    }
+
+    /// Applies a trade to the candle containing its timestamp in every interval.
+    /// Late trades are only applied if their candle is still kept.
+    pub fn apply_trade(&mut self, trade: &Trade) {
+        for (interval, candles) in self.candles.iter_mut() {
+            let open_time = interval.open_time(trade.timestamp);
+            match candles.back().map(|candle| candle.open_time) {
+                Some(last) if last == open_time => candles.back_mut().unwrap().apply(trade),
+                Some(last) if last > open_time => {
+                    // late trade, update its candle if it is still kept
+                    if let Some(candle) = candles.iter_mut().rev().find(|candle| candle.open_time == open_time) {
+                        candle.apply(trade);
+                    }
+                }
+                _ => {
+                    candles.push_back(Candle::new(open_time, trade));
+                    while candles.len() > self.history + 1 {
+                        candles.pop_front();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns up to the last `n` candles of an interval, oldest first
+    pub fn candles(&self, interval: CandleInterval, n: usize) -> Vec<Candle> {
+        self.candles
+            .get(&interval)
+            .map(|candles| candles.iter().skip(candles.len().saturating_sub(n)).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the open candle of an interval
+    pub fn current(&self, interval: CandleInterval) -> Option<&Candle> {
+        self.candles.get(&interval).and_then(|candles| candles.back())
+    }
+}
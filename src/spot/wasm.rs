@@ -0,0 +1,186 @@
+//! wasm-bindgen wrappers of the spot engine, with the `wasm` feature, for order entry previews and local
+//! simulation in the browser.
+//!
+//! - commands are `SpotCommand`s and results are the JSON of the Rust types (see `spot::schema`), ids may be
+//!   given as strings. The `*_postcard` variants take and return postcard bytes instead.
+//! - commands and snapshots are decoded as untrusted input of the engine, see `intern::untrusted`. Queries of an unknown pair fail with
+//!   `PairNotFound` and the owner of no order has no open orders, without interning their ids.
+//! - events are returned by every call, nothing is published to the process-wide event bus, so no thread is
+//!   started. Events emitted without a command timestamp take the time from `Date.now()`.
+//! - errors are thrown as JS `Error`s with the message of the Rust error.
+
+use std::cell::Cell;
+
+use wasm_bindgen::prelude::*;
+
+use super::command::SpotCommand;
+use super::event::EventQueue;
+use super::ids::{AccountId, PairId};
+use super::intern::{self, InternBudget};
+use super::market_data::MarketDataError;
+use super::matching_engine::MatchingEngine;
+use super::orderbook::OrderBookError;
+use super::orders::OrderId;
+use super::query::{OrdersPage, QueryError};
+use super::snapshot::{self, SnapshotError};
+use super::trades::CandleInterval;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WasmError {
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("postcard error: {0}")]
+    Postcard(#[from] postcard::Error),
+    #[error(transparent)]
+    OrderBook(#[from] OrderBookError),
+    #[error(transparent)]
+    Query(#[from] QueryError),
+    #[error(transparent)]
+    MarketData(#[from] MarketDataError),
+    #[error(transparent)]
+    Snapshot(#[from] SnapshotError),
+    #[error("invalid order id: {0}")]
+    InvalidOrderId(String),
+}
+
+impl From<WasmError> for JsValue {
+    fn from(err: WasmError) -> Self {
+        JsError::new(&err.to_string()).into()
+    }
+}
+
+/// A `MatchingEngine` owned by JS.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default)]
+pub struct WasmEngine {
+    engine: MatchingEngine,
+    /// new ids the commands of the engine may intern, previews included
+    budget: Cell<InternBudget>,
+}
+
+#[wasm_bindgen]
+impl WasmEngine {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self { engine: MatchingEngine::new(), budget: Cell::default() }
+    }
+
+    /// Executes a JSON `SpotCommand`, returns the JSON array of its events
+    pub fn execute(&mut self, command: &str) -> Result<String, WasmError> {
+        let events = self.engine.execute(self.untrusted(|| serde_json::from_str(command))?)?;
+        Ok(serde_json::to_string(&events)?)
+    }
+
+    /// Executes a JSON `SpotCommand` on a copy of its pair, returns the JSON array of the events it would emit
+    pub fn preview(&self, command: &str) -> Result<String, WasmError> {
+        let events = self.engine.preview(self.untrusted(|| serde_json::from_str(command))?)?;
+        Ok(serde_json::to_string(&events)?)
+    }
+
+    /// Executes a postcard `SpotCommand`, returns its events as a postcard `EventQueue`
+    #[wasm_bindgen(js_name = executePostcard)]
+    pub fn execute_postcard(&mut self, command: &[u8]) -> Result<Vec<u8>, WasmError> {
+        let events = self.engine.execute(self.untrusted(|| postcard::from_bytes::<SpotCommand>(command))?)?;
+        Ok(postcard::to_allocvec(&events)?)
+    }
+
+    #[wasm_bindgen(js_name = hasPair)]
+    pub fn has_pair(&self, pair_id: &str) -> bool {
+        PairId::lookup(pair_id.as_bytes()).is_some_and(|pair_id| self.engine.has_pair(pair_id))
+    }
+
+    /// JSON `OrderView` of a resting order
+    pub fn order(&self, pair_id: &str, order_id: &str) -> Result<String, WasmError> {
+        Ok(serde_json::to_string(&self.engine.order(known_pair(pair_id)?, parse_order_id(order_id)?)?)?)
+    }
+
+    /// JSON `OrdersPage` of the owner's open orders, `cursor` is the last order id of the previous page
+    #[wasm_bindgen(js_name = openOrders)]
+    pub fn open_orders(&self, pair_id: &str, owner: &str, cursor: Option<String>, limit: u32) -> Result<String, WasmError> {
+        let cursor = cursor.as_deref().map(parse_order_id).transpose()?;
+        let pair_id = known_pair(pair_id)?;
+        let page = match AccountId::lookup(owner.as_bytes()) {
+            Some(owner) => self.engine.open_orders(pair_id, owner, cursor, limit)?,
+            None => OrdersPage { orders: Vec::new(), next_cursor: None },
+        };
+        Ok(serde_json::to_string(&page)?)
+    }
+
+    /// JSON `Depth` of the `n` best levels of each side
+    pub fn depth(&self, pair_id: &str, n: u32) -> Result<String, WasmError> {
+        Ok(serde_json::to_string(&self.engine.depth(known_pair(pair_id)?, n)?)?)
+    }
+
+    /// JSON `BestBidAsk`
+    #[wasm_bindgen(js_name = bestBidAsk)]
+    pub fn best_bid_ask(&self, pair_id: &str) -> Result<String, WasmError> {
+        Ok(serde_json::to_string(&self.engine.best_bid_ask(known_pair(pair_id)?)?)?)
+    }
+
+    /// Last matched price in 8 decimals, as a `bigint`
+    #[wasm_bindgen(js_name = lastPrice)]
+    pub fn last_price(&self, pair_id: &str) -> Result<Option<u64>, WasmError> {
+        Ok(self.engine.last_price(known_pair(pair_id)?)?)
+    }
+
+    /// JSON array of the last `n` `Candle`s of an interval, `interval` is a `CandleInterval` name as `"OneMinute"`
+    pub fn candles(&self, pair_id: &str, interval: &str, n: usize) -> Result<String, WasmError> {
+        let interval: CandleInterval = serde_json::from_value(serde_json::Value::String(interval.to_string()))?;
+        Ok(serde_json::to_string(&self.engine.candles(known_pair(pair_id)?, interval, n)?)?)
+    }
+
+    /// JSON `Ticker` of the rolling window ending at `now` in milliseconds
+    pub fn ticker(&self, pair_id: &str, now: i64) -> Result<String, WasmError> {
+        Ok(serde_json::to_string(&self.engine.ticker(known_pair(pair_id)?, now)?)?)
+    }
+
+    /// JSON `L2Snapshot` of the depth in a price scale
+    #[wasm_bindgen(js_name = depthSnapshot)]
+    pub fn depth_snapshot(&mut self, pair_id: &str, scale: u64) -> Result<String, WasmError> {
+        let pair_id = PairId::lookup(pair_id.as_bytes()).ok_or(MarketDataError::PairNotFound)?;
+        Ok(serde_json::to_string(&self.engine.depth_snapshot(pair_id, scale)?)?)
+    }
+
+    /// Versioned snapshot of the whole engine, see `spot::snapshot`
+    pub fn snapshot(&self) -> Result<Vec<u8>, WasmError> {
+        Ok(snapshot::encode(&self.engine)?)
+    }
+
+    /// Restores an engine from a snapshot of this or an older version, decoded as untrusted input whose new ids
+    /// are bounded by its length
+    pub fn restore(snapshot: &[u8]) -> Result<WasmEngine, WasmError> {
+        let engine = intern::untrusted_snapshot(&mut InternBudget::new(snapshot.len()), || snapshot::decode(snapshot))?;
+        Ok(Self { engine, budget: Cell::default() })
+    }
+}
+
+impl WasmEngine {
+    /// Runs `f` decoding untrusted input charged to the budget of the engine
+    fn untrusted<R>(&self, f: impl FnOnce() -> R) -> R {
+        let mut budget = self.budget.get();
+        let result = intern::untrusted(&mut budget, f);
+        self.budget.set(budget);
+        result
+    }
+}
+
+/// Converts a postcard `EventQueue` into the JSON array of its events
+#[wasm_bindgen(js_name = eventsToJson)]
+pub fn events_to_json(events: &[u8]) -> Result<String, WasmError> {
+    Ok(serde_json::to_string(&postcard::from_bytes::<EventQueue>(events)?)?)
+}
+
+/// Converts a JSON array of events into a postcard `EventQueue`
+#[wasm_bindgen(js_name = eventsFromJson)]
+pub fn events_from_json(events: &str) -> Result<Vec<u8>, WasmError> {
+    Ok(postcard::to_allocvec(&serde_json::from_str::<EventQueue>(events)?)?)
+}
+
+/// Pair id of a query, without interning unknown ones
+fn known_pair(pair_id: &str) -> Result<PairId, QueryError> {
+    PairId::lookup(pair_id.as_bytes()).ok_or(QueryError::PairNotFound)
+}
+
+fn parse_order_id(order_id: &str) -> Result<OrderId, WasmError> {
+    OrderId::from_string(order_id).map_err(|_| WasmError::InvalidOrderId(order_id.to_string()))
+}
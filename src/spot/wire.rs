@@ -0,0 +1,226 @@
+//! Versioned wire format of `SpotEvent`.
+//!
+//! An encoded event is `ENVELOPE_MAGIC` followed by a postcard `EventEnvelope`: the schema version the event
+//! was written with, the `EventType` tag of its variant and the postcard encoding of the variant's fields.
+//!
+//! Compatibility rules, checked by the golden files in `tests/spot/golden`:
+//! - tags are never renumbered or reused, a new variant takes the next free tag and is appended to `SpotEvent`.
+//! - fields are only ever appended to a variant, never removed, reordered or retyped. Appending a field
+//!   bumps `SCHEMA_VERSION` and the golden files of the variant are added for the new version.
+//! - backward: a payload of an older version is a prefix of the current layout, the fields appended
+//!   since take their defaults. A field without a meaningful default is appended as an `Option`.
+//! - forward: payloads of a newer version decode the fields this version knows and ignore the appended
+//!   ones. Variants this version does not know fail with `WireError::UnknownEventType`, which a reader
+//!   can skip since the envelope is self delimiting.
+//!
+//! Version 1 is the layout of the 0.1.0 release, which serialized `SpotEvent` with plain serde derive
+//! and no envelope. Bytes not starting with `ENVELOPE_MAGIC` are read as such a version 1 event.
+
+use serde::{Deserialize, Serialize};
+
+use super::event::SpotEvent;
+use super::ids::PairId;
+
+/// Schema version of the events written by this version of the crate
+pub const SCHEMA_VERSION: u16 = 2;
+
+/// First byte of an encoded envelope, never the first byte of a version 1 event whose variant index is below 128
+pub const ENVELOPE_MAGIC: u8 = 0xEE;
+
+#[derive(Debug, Clone, thiserror::Error, PartialEq, Eq)]
+pub enum WireError {
+    #[error("postcard error: {0}")]
+    Postcard(#[from] postcard::Error),
+    #[error("unknown event type {tag} of schema version {version}")]
+    UnknownEventType { tag: u16, version: u16 },
+    #[error("unsupported schema version {0}")]
+    UnsupportedVersion(u16),
+    #[error("empty event")]
+    Empty,
+    #[error("frame does not start with the envelope magic byte")]
+    MissingEnvelope,
+}
+
+/// Stable tag of every `SpotEvent` variant, independent of the order of the variants in the enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum EventType {
+    SpotNewMarketPrice = 1,
+    SpotPairRebate = 2,
+    SpotPairClientAccountChanged = 3,
+    SpotPairAdded = 4,
+    Transfer = 5,
+    SpotPriceLevelChanged = 6,
+    SpotOrderPlaced = 7,
+    SpotOrderPartiallyFilled = 8,
+    SpotOrderFullyFilled = 9,
+    SpotOrderCancelled = 10,
+    SpotOrderExpired = 11,
+    SpotOrderIcebergQuantityChanged = 12,
+    SpotTrade = 13,
+    SpotMarketOrderProtected = 14,
+}
+
+impl EventType {
+    pub const ALL: [EventType; 14] = [
+        EventType::SpotNewMarketPrice,
+        EventType::SpotPairRebate,
+        EventType::SpotPairClientAccountChanged,
+        EventType::SpotPairAdded,
+        EventType::Transfer,
+        EventType::SpotPriceLevelChanged,
+        EventType::SpotOrderPlaced,
+        EventType::SpotOrderPartiallyFilled,
+        EventType::SpotOrderFullyFilled,
+        EventType::SpotOrderCancelled,
+        EventType::SpotOrderExpired,
+        EventType::SpotOrderIcebergQuantityChanged,
+        EventType::SpotTrade,
+        EventType::SpotMarketOrderProtected,
+    ];
+
+    /// Returns the type of an event
+    pub fn of(event: &SpotEvent) -> Self {
+        match event {
+            SpotEvent::SpotNewMarketPrice { .. } => EventType::SpotNewMarketPrice,
+            SpotEvent::SpotPairRebate { .. } => EventType::SpotPairRebate,
+            SpotEvent::SpotPairClientAccountChanged { .. } => EventType::SpotPairClientAccountChanged,
+            SpotEvent::SpotPairAdded { .. } => EventType::SpotPairAdded,
+            SpotEvent::Transfer { .. } => EventType::Transfer,
+            SpotEvent::SpotPriceLevelChanged { .. } => EventType::SpotPriceLevelChanged,
+            SpotEvent::SpotOrderPlaced { .. } => EventType::SpotOrderPlaced,
+            SpotEvent::SpotOrderPartiallyFilled { .. } => EventType::SpotOrderPartiallyFilled,
+            SpotEvent::SpotOrderFullyFilled { .. } => EventType::SpotOrderFullyFilled,
+            SpotEvent::SpotTrade { .. } => EventType::SpotTrade,
+            SpotEvent::SpotMarketOrderProtected { .. } => EventType::SpotMarketOrderProtected,
+            SpotEvent::SpotOrderCancelled { .. } => EventType::SpotOrderCancelled,
+            SpotEvent::SpotOrderExpired { .. } => EventType::SpotOrderExpired,
+            SpotEvent::SpotOrderIcebergQuantityChanged { .. } => EventType::SpotOrderIcebergQuantityChanged,
+        }
+    }
+
+    pub fn tag(self) -> u16 {
+        self as u16
+    }
+
+    pub fn from_tag(tag: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|event_type| event_type.tag() == tag)
+    }
+
+    /// First schema version the variant exists in
+    pub fn since(self) -> u16 {
+        match self {
+            EventType::SpotTrade | EventType::SpotMarketOrderProtected => 2,
+            _ => 1,
+        }
+    }
+
+    /// Index of the variant in the serde encoding of `SpotEvent`, variants are appended in the order of their tags
+    fn variant_index(self) -> u8 {
+        (self.tag() - 1) as u8
+    }
+}
+
+/// An event as written on the wire.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    /// schema version the payload was written with
+    pub version: u16,
+    /// `EventType` tag of the variant
+    pub event_type: u16,
+    /// postcard encoding of the fields of the variant
+    #[serde(with = "serde_bytes")]
+    pub payload: Vec<u8>,
+}
+
+impl EventEnvelope {
+    /// Wraps an event in the current schema version
+    pub fn new(event: &SpotEvent) -> Result<Self, WireError> {
+        let event_type = EventType::of(event);
+        let encoded = postcard::to_allocvec(event)?;
+        // the serde encoding of an enum is the variant index followed by the fields
+        debug_assert_eq!(encoded.first(), Some(&event_type.variant_index()));
+        Ok(Self { version: SCHEMA_VERSION, event_type: event_type.tag(), payload: encoded[1..].to_vec() })
+    }
+
+    /// Decodes the event, upgrading payloads of older versions
+    pub fn decode(&self) -> Result<SpotEvent, WireError> {
+        if self.version == 0 {
+            return Err(WireError::UnsupportedVersion(0));
+        }
+        let unknown = WireError::UnknownEventType { tag: self.event_type, version: self.version };
+        let event_type = EventType::from_tag(self.event_type).ok_or(unknown.clone())?;
+        match self.version {
+            version if version < event_type.since() => Err(unknown),
+            1 => v1::decode(event_type, &self.payload),
+            _ => decode_current(event_type, &self.payload),
+        }
+    }
+}
+
+/// Encodes an event in the current schema version
+pub fn encode(event: &SpotEvent) -> Result<Vec<u8>, WireError> {
+    let mut bytes = vec![ENVELOPE_MAGIC];
+    bytes.extend(postcard::to_allocvec(&EventEnvelope::new(event)?)?);
+    Ok(bytes)
+}
+
+/// Decodes an event written by `encode` of any version, or by plain postcard serialization in version 1
+pub fn decode(bytes: &[u8]) -> Result<SpotEvent, WireError> {
+    match bytes.split_first() {
+        None => Err(WireError::Empty),
+        Some((&ENVELOPE_MAGIC, envelope)) => postcard::from_bytes::<EventEnvelope>(envelope)?.decode(),
+        Some((&index, payload)) => {
+            // events of version 1 carry the variant index of the 0.1.0 enum, in the order of the tags
+            let tag = u16::from(index) + 1;
+            let event_type = EventType::from_tag(tag)
+                .filter(|event_type| event_type.since() == 1)
+                .ok_or(WireError::UnknownEventType { tag, version: 1 })?;
+            v1::decode(event_type, payload)
+        }
+    }
+}
+
+/// Encodes an event in the current schema version, COBS framed so the frame ends with the only zero byte in it
+pub fn encode_cobs(event: &SpotEvent) -> Result<Vec<u8>, WireError> {
+    Ok(postcard::to_allocvec_cobs(&(ENVELOPE_MAGIC, EventEnvelope::new(event)?))?)
+}
+
+/// Decodes a frame written by `encode_cobs`, decoding the frame in place
+pub fn decode_cobs(frame: &mut [u8]) -> Result<SpotEvent, WireError> {
+    match postcard::from_bytes_cobs::<(u8, EventEnvelope)>(frame)? {
+        (ENVELOPE_MAGIC, envelope) => envelope.decode(),
+        _ => Err(WireError::MissingEnvelope),
+    }
+}
+
+/// Decodes a payload in the current layout, ignoring fields appended by newer versions
+fn decode_current(event_type: EventType, payload: &[u8]) -> Result<SpotEvent, WireError> {
+    let mut encoded = Vec::with_capacity(payload.len() + 1);
+    encoded.push(event_type.variant_index());
+    encoded.extend_from_slice(payload);
+    Ok(postcard::from_bytes(&encoded)?)
+}
+
+/// Decoding of version 1 payloads, which are a prefix of their layout in version 2
+mod v1 {
+    use super::*;
+
+    /// Decodes a payload of version 1 by appending the fields added in version 2.
+    /// - `SpotNewMarketPrice` did not tell the make price apart, it is read as the last matched price.
+    /// - placed orders had no client order id and no fee bps, read as none and 0.
+    /// - cancelled, expired and iceberg changed orders had no pair id, read as `None`.
+    pub(super) fn decode(event_type: EventType, payload: &[u8]) -> Result<SpotEvent, WireError> {
+        let appended = match event_type {
+            EventType::SpotNewMarketPrice => postcard::to_allocvec(&false)?,
+            EventType::SpotOrderPlaced => postcard::to_allocvec(&(None::<Vec<u8>>, 0i16))?,
+            EventType::SpotOrderCancelled | EventType::SpotOrderExpired | EventType::SpotOrderIcebergQuantityChanged => {
+                postcard::to_allocvec(&None::<PairId>)?
+            }
+            _ => Vec::new(),
+        };
+        let mut upgraded = payload.to_vec();
+        upgraded.extend(appended);
+        decode_current(event_type, &upgraded)
+    }
+}
@@ -0,0 +1,141 @@
+// Tests of the C ABI, the harness in `tests/ffi/harness.c` is compiled by the `tests/ffi` dev-dependency and calls
+// the engine through the checked-in header. The header is rewritten with `UPDATE_GOLDEN=1 cargo test --features ffi --test ffi`.
+// The test requires the `ffi` feature, which compiles the harness.
+
+use std::path::PathBuf;
+
+use off_grid_primitives::spot::command::{LimitOrder, SpotCommand};
+use off_grid_primitives::spot::event::{EventQueue, SpotEvent};
+use off_grid_primitives::spot::ffi::{self, og_buffer_free, OgBuffer, OgStatus};
+use off_grid_primitives::spot::intern::BOUNDED_LEN;
+use off_grid_primitives::spot::orders::{ClientOrderId, OrderRef};
+use off_grid_primitives::spot::snapshot;
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::{AccountId, MatchingEngine};
+use off_grid_primitives_ffi_harness::{og_harness_abi_version, og_harness_errors, og_harness_resume, og_harness_run};
+
+const PAIR: &str = "BTC-USD";
+const SCALE_8: u64 = 1_0000_0000;
+
+fn add_pair() -> SpotCommand {
+    SpotCommand::AddPair {
+        cid: "c".into(),
+        admin_account_id: "admin".into(),
+        fee_account_id: "fees".into(),
+        pair_id: PAIR.into(),
+        timestamp: 1,
+    }
+}
+
+fn limit(owner: &str, coid: Option<&str>, price: u64, amnt: u64) -> LimitOrder {
+    LimitOrder {
+        cid: "c".into(),
+        pair_id: PAIR.into(),
+        existing_order_id: None,
+        owner: owner.into(),
+        coid: coid.map(ClientOrderId::from),
+        price: price * SCALE_8,
+        amnt: amnt * SCALE_8,
+        iqty: 0,
+        timestamp: 2,
+        expires_at: i64::MAX,
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        time_in_force: TimeInForce::GoodTillCanceled,
+        post_only: false,
+    }
+}
+
+fn encode(command: &SpotCommand) -> Vec<u8> {
+    postcard::to_allocvec(command).unwrap()
+}
+
+/// Copies a buffer of the engine and releases it
+fn take(buffer: OgBuffer) -> Vec<u8> {
+    let bytes = if buffer.data.is_null() {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(buffer.data, buffer.len) }.to_vec()
+    };
+    unsafe { og_buffer_free(buffer) };
+    bytes
+}
+
+fn empty() -> OgBuffer {
+    OgBuffer { data: std::ptr::null_mut(), len: 0 }
+}
+
+/// Runs the commands through the harness, returns the drained events and the snapshot
+fn run(commands: &[SpotCommand]) -> (EventQueue, Vec<u8>) {
+    let commands: Vec<Vec<u8>> = commands.iter().map(encode).collect();
+    let pointers: Vec<*const u8> = commands.iter().map(|command| command.as_ptr()).collect();
+    let lens: Vec<usize> = commands.iter().map(Vec::len).collect();
+    let (mut events, mut snapshot) = (empty(), empty());
+    let status =
+        unsafe { og_harness_run(pointers.as_ptr(), lens.as_ptr(), commands.len(), &mut events, &mut snapshot) };
+    assert_eq!(status, OgStatus::Ok as i32);
+    (postcard::from_bytes(&take(events)).unwrap(), take(snapshot))
+}
+
+#[test]
+fn checked_in_header_matches_the_abi() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("include/off_grid_primitives.h");
+    let generated = ffi::header();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &generated).unwrap();
+    }
+    let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(checked_in == generated, "include/off_grid_primitives.h drifted from the ABI, regenerate it with UPDATE_GOLDEN=1");
+    assert_eq!(unsafe { og_harness_abi_version() }, 1);
+}
+
+#[test]
+fn commands_submitted_from_c_emit_the_events_of_the_engine() {
+    let commands = [add_pair(), SpotCommand::LimitBuy(limit("alice", None, 99, 990)), SpotCommand::LimitSell(limit("bob", None, 99, 4))];
+    let (events, snapshot) = run(&commands);
+    assert!(events.iter().any(|event| matches!(event, SpotEvent::SpotPairAdded { .. })));
+    assert!(events.iter().any(|event| matches!(event, SpotEvent::SpotTrade { .. })));
+
+    // the snapshot is the versioned engine, with the book left by the commands
+    let engine: MatchingEngine = snapshot::decode(&snapshot).unwrap();
+    let depth = engine.depth(PAIR, 10).unwrap();
+    assert_eq!(depth.bids[0].qty, 985_99999905);
+    assert!(depth.asks.is_empty());
+}
+
+#[test]
+fn restored_engines_resume_from_the_snapshot() {
+    let (_, snapshot) = run(&[add_pair(), SpotCommand::LimitBuy(limit("alice", Some("bid-1"), 99, 990))]);
+    let cancel = encode(&SpotCommand::CancelOrder {
+        cid: "c".into(),
+        pair_id: PAIR.into(),
+        order: OrderRef::ClientOrderId(b"bid-1".to_vec()),
+        owner: "alice".into(),
+    });
+    let mut events = empty();
+    let status = unsafe { og_harness_resume(snapshot.as_ptr(), snapshot.len(), cancel.as_ptr(), cancel.len(), &mut events) };
+    assert_eq!(status, OgStatus::Ok as i32);
+    let events: EventQueue = postcard::from_bytes(&take(events)).unwrap();
+    assert!(matches!(events.as_vec()[0], SpotEvent::SpotOrderCancelled { .. }));
+
+    // snapshots are untrusted input, ids past the interning bounds are rejected
+    let (long, unknown) = (vec![b'f'; BOUNDED_LEN + 1], vec![b'g'; BOUNDED_LEN + 1]);
+    let mut trusted = MatchingEngine::new();
+    trusted.add_pair("c", long.as_slice(), "fees", "ffi-long-pair", 1);
+    let mut bytes = snapshot::encode(&trusted).unwrap();
+    let at = bytes.windows(long.len()).position(|window| window == long).expect("long id in the snapshot");
+    bytes[at..at + long.len()].copy_from_slice(&unknown);
+    let mut restored = std::ptr::null_mut();
+    assert_eq!(unsafe { ffi::og_engine_restore(bytes.as_ptr(), bytes.len(), &mut restored) }, OgStatus::Postcard);
+    assert!(restored.is_null());
+    assert_eq!(AccountId::lookup(&unknown), None);
+}
+
+#[test]
+fn invalid_calls_and_rejected_commands_return_their_status() {
+    let missing_pair = encode(&SpotCommand::LimitSell(limit("bob", None, 101, 5)));
+    let mut message = empty();
+    let status = unsafe { og_harness_errors(missing_pair.as_ptr(), missing_pair.len(), &mut message) };
+    assert_eq!(status, OgStatus::Rejected as i32);
+    assert_eq!(String::from_utf8(take(message)).unwrap(), "pair does not exist");
+}
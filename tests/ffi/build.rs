@@ -0,0 +1,19 @@
+/// Compiles the C harness against `include/off_grid_primitives.h`, as a dev-dependency of `off-grid-primitives`
+/// it is built for its tests only, and with its `ffi` feature only
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=harness.c");
+    println!("cargo:rerun-if-changed=../../include/off_grid_primitives.h");
+    #[cfg(feature = "c")]
+    compile();
+}
+
+#[cfg(feature = "c")]
+fn compile() {
+    cc::Build::new()
+        .file("harness.c")
+        .include("../../include")
+        .warnings(true)
+        .extra_warnings(true)
+        .compile("og_harness");
+}
@@ -0,0 +1,100 @@
+/* C side of tests/ffi.rs, calls the ABI through the checked-in header only. */
+#include <string.h>
+
+#include "off_grid_primitives.h"
+
+/* Harness failures, negative so they are told apart from og_status */
+enum { HARNESS_ENGINE = -1, HARNESS_SNAPSHOT = -2, HARNESS_STATUS = -3 };
+
+int og_harness_abi_version(void) {
+    return og_abi_version() == OG_ABI_VERSION;
+}
+
+/* Submits the commands to a new engine, drains their events into events and snapshots the engine into snapshot.
+   The snapshot is restored and snapshotted again, both snapshots must be equal. */
+int og_harness_run(const uint8_t *const *commands, const size_t *lens, size_t n, og_buffer *events,
+                   og_buffer *snapshot) {
+    og_engine *engine = og_engine_new();
+    if (engine == NULL) {
+        return HARNESS_ENGINE;
+    }
+    for (size_t i = 0; i < n; i++) {
+        og_status status = og_engine_submit(engine, commands[i], lens[i]);
+        if (status != OG_OK) {
+            og_engine_free(engine);
+            return (int)status;
+        }
+    }
+    og_status status = og_engine_drain_events(engine, events);
+    if (status == OG_OK) {
+        status = og_engine_snapshot(engine, snapshot);
+    }
+    og_engine_free(engine);
+    if (status != OG_OK) {
+        return (int)status;
+    }
+
+    og_engine *restored = NULL;
+    status = og_engine_restore(snapshot->data, snapshot->len, &restored);
+    if (status != OG_OK) {
+        return (int)status;
+    }
+    og_buffer again = {NULL, 0};
+    status = og_engine_snapshot(restored, &again);
+    int equal = status == OG_OK && again.len == snapshot->len && memcmp(again.data, snapshot->data, again.len) == 0;
+    og_buffer_free(again);
+    og_engine_free(restored);
+    return equal ? OG_OK : HARNESS_SNAPSHOT;
+}
+
+/* Restores an engine from snapshot, submits command and drains its events into events */
+int og_harness_resume(const uint8_t *snapshot, size_t snapshot_len, const uint8_t *command, size_t command_len,
+                      og_buffer *events) {
+    og_engine *engine = NULL;
+    og_status status = og_engine_restore(snapshot, snapshot_len, &engine);
+    if (status != OG_OK) {
+        return (int)status;
+    }
+    status = og_engine_submit(engine, command, command_len);
+    if (status == OG_OK) {
+        status = og_engine_drain_events(engine, events);
+    }
+    og_engine_free(engine);
+    return (int)status;
+}
+
+/* Checks the statuses of invalid calls, then submits rejected, a command the engine rejects, and writes the
+   message of the rejection into message */
+int og_harness_errors(const uint8_t *rejected, size_t rejected_len, og_buffer *message) {
+    static const uint8_t garbage[] = {0xff, 0xff, 0xff};
+    og_engine *engine = og_engine_new();
+    og_engine *restored = engine;
+    og_buffer empty = {NULL, 0};
+    og_status statuses[] = {
+        og_engine_submit(NULL, rejected, rejected_len),
+        og_engine_submit(engine, NULL, 0),
+        og_engine_drain_events(engine, NULL),
+        og_engine_snapshot(NULL, &empty),
+        og_engine_submit(engine, garbage, sizeof garbage),
+        og_engine_restore(garbage, sizeof garbage, &restored),
+    };
+    og_status expected[] = {OG_NULL_POINTER, OG_NULL_POINTER, OG_NULL_POINTER,
+                            OG_NULL_POINTER, OG_POSTCARD,     OG_POSTCARD};
+    int ok = restored == NULL && empty.data == NULL;
+    for (size_t i = 0; i < sizeof statuses / sizeof statuses[0]; i++) {
+        ok = ok && statuses[i] == expected[i];
+    }
+    og_buffer_free(empty);
+    og_engine_free(NULL);
+    if (!ok) {
+        og_engine_free(engine);
+        return HARNESS_STATUS;
+    }
+
+    og_status status = og_engine_submit(engine, rejected, rejected_len);
+    if (status == OG_REJECTED) {
+        og_engine_last_error(engine, message);
+    }
+    og_engine_free(engine);
+    return (int)status;
+}
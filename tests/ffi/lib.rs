@@ -0,0 +1,25 @@
+//! C harness of `tests/ffi.rs`, calling the ABI of `off_grid_primitives::spot::ffi` through the checked-in header.
+//! The harness is a dev-dependency of `off-grid-primitives`, so its C code is compiled for the tests only, and with
+//! the `c` feature enabled by the `ffi` feature of `off-grid-primitives` only.
+#![cfg(feature = "c")]
+
+use off_grid_primitives::spot::ffi::OgBuffer;
+
+extern "C" {
+    pub fn og_harness_abi_version() -> i32;
+    pub fn og_harness_run(
+        commands: *const *const u8,
+        lens: *const usize,
+        n: usize,
+        events: *mut OgBuffer,
+        snapshot: *mut OgBuffer,
+    ) -> i32;
+    pub fn og_harness_resume(
+        snapshot: *const u8,
+        snapshot_len: usize,
+        command: *const u8,
+        command_len: usize,
+        events: *mut OgBuffer,
+    ) -> i32;
+    pub fn og_harness_errors(rejected: *const u8, rejected_len: usize, message: *mut OgBuffer) -> i32;
+}
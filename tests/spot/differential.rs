@@ -0,0 +1,134 @@
+// Differential testing of `MatchingEngine` against the reference model in `reference.rs`.
+// The seeded command streams of `simulation.rs` run through both, and after every command the fills,
+// the taker's remainder and the resting orders must be the same. A mismatching stream is shrunk to a
+// minimal list of commands before it is reported.
+
+use off_grid_primitives::spot::event::SpotEvent;
+use off_grid_primitives::spot::orderbook::{OrderBook, OrderBookError};
+use off_grid_primitives::spot::orders::OrderId;
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+
+use super::reference::{Divergences, Fill, Outcome, RefOrder, ReferenceBook};
+use super::simulation::{apply, generate, new_engine, open_orders, shrink, Command, Failure, Rng, PAIR_ID};
+use super::EVENT_MUTEX;
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+const SCALE_8: u64 = 1_0000_0000;
+
+fn order_id(bytes: &[u8]) -> OrderId {
+    OrderId::from_bytes(bytes.try_into().expect("16 bytes"))
+}
+
+/// Outcome of a command on the engine from its events and the books before and after it.
+/// Fills without any base or quote are left out, they clear an order that cannot trade a single unit.
+fn observe(before: &OrderBook, after: &OrderBook, events: &[SpotEvent], divergences: Divergences) -> Outcome {
+    let mut outcome = Outcome::default();
+    let mut taker = None;
+    let mut spent = 0;
+    for event in events {
+        match event {
+            SpotEvent::SpotOrderPlaced { order_id: id, amnt, .. } if taker.is_none() => taker = Some((order_id(id), *amnt)),
+            SpotEvent::SpotTrade { maker_order_id, taker_is_bid, price, base_volume, quote_volume, .. } => {
+                spent += if *taker_is_bid { *quote_volume } else { *base_volume };
+                if *base_volume > 0 || *quote_volume > 0 {
+                    let maker = before.l3.get_order(order_id(maker_order_id)).expect("maker rests before the command");
+                    outcome.fills.push(Fill { maker: maker.timestamp, price: *price, base: *base_volume, quote: *quote_volume });
+                }
+            }
+            SpotEvent::SpotNewMarketPrice { price, .. } if divergences.make_price => outcome.market_price = Some(*price),
+            _ => {}
+        }
+    }
+    if let Some((id, amount)) = taker {
+        outcome.remaining = amount.saturating_sub(spent);
+        outcome.rests = after.l3.get_order(id).is_ok();
+    }
+    outcome
+}
+
+fn resting_orders(orderbook: &OrderBook) -> Vec<RefOrder> {
+    open_orders(orderbook)
+        .into_iter()
+        .map(|order| RefOrder {
+            timestamp: order.timestamp,
+            is_bid: order.is_bid,
+            owner: order.owner.as_slice()[0],
+            price: order.price,
+            cqty: order.cqty,
+            pqty: order.pqty,
+        })
+        .collect()
+}
+
+/// Runs a stream of commands on a new engine and on the reference model, comparing them after every command
+pub(crate) fn run(commands: &[Command], divergences: Divergences) -> Result<(), Failure> {
+    let mut engine = new_engine();
+    engine.set_dust(&PAIR_ID, divergences.dust);
+    let mut model = ReferenceBook::new(divergences);
+    for (step, command) in commands.iter().enumerate() {
+        let fail = |reason: String| Failure { step, reason };
+        let before = engine.orderbook(&PAIR_ID).expect("pair").clone();
+        let actual = apply(&mut engine, command, step);
+        let after = engine.orderbook(&PAIR_ID).expect("pair");
+        let actual: Result<Outcome, OrderBookError> = actual.map(|events| observe(&before, after, &events, divergences));
+        let expected = model.apply(command, step);
+        if actual != expected {
+            return Err(fail(format!("engine returned {actual:?}, the reference {expected:?}")));
+        }
+        let (actual, expected) = (resting_orders(after), model.orders());
+        if actual != expected {
+            return Err(fail(format!("engine rests {actual:?}, the reference {expected:?}")));
+        }
+    }
+    Ok(())
+}
+
+/// Runs the streams of the seeds, shrinking and reporting the first mismatch
+fn check_seeds(seeds: std::ops::Range<u64>, len: usize, divergences: Divergences) {
+    for seed in seeds {
+        let commands = generate(&mut Rng::new(seed), len);
+        if let Err(failure) = run(&commands, divergences) {
+            let minimal = shrink(&commands[..=failure.step], |candidate| run(candidate, divergences).is_err());
+            let reason = run(&minimal, divergences).expect_err("shrunk stream fails").reason;
+            panic!("seed {seed} failed at step {}: {}\nminimal stream ({reason}):\n{minimal:#?}", failure.step, failure.reason);
+        }
+    }
+}
+
+#[test]
+fn engine_matches_the_reference_model() {
+    let _guard = lock_events();
+    check_seeds(0..64, 200, Divergences { dust: 0, make_price: true });
+}
+
+#[test]
+fn engine_matches_the_reference_model_with_dust() {
+    let _guard = lock_events();
+    check_seeds(0..32, 200, Divergences { dust: 1_000_000, make_price: false });
+}
+
+#[test]
+fn dust_is_a_divergence_of_the_engine() {
+    let _guard = lock_events();
+    // an ask left with 500 base units after a partial fill
+    let commands = [
+        Command::Limit { is_bid: false, owner: 1, price: 100 * SCALE_8, amnt: SCALE_8 + 500, iqty: 0, time_in_force: TimeInForce::GoodTillCanceled },
+        Command::Limit { is_bid: true, owner: 2, price: 100 * SCALE_8, amnt: SCALE_8, iqty: 0, time_in_force: TimeInForce::GoodTillCanceled },
+    ];
+    run(&commands, Divergences { dust: 1000, make_price: false }).expect("both clear the remainder");
+    run(&commands, Divergences::default()).expect("both keep the remainder");
+
+    // the engine with a dust limit against the model without the divergence
+    let mut engine = new_engine();
+    engine.set_dust(&PAIR_ID, 1000);
+    let mut model = ReferenceBook::new(Divergences::default());
+    for (step, command) in commands.iter().enumerate() {
+        apply(&mut engine, command, step).expect("engine");
+        model.apply(command, step).expect("reference");
+    }
+    assert!(resting_orders(engine.orderbook(&PAIR_ID).expect("pair")).is_empty());
+    assert_eq!(model.orders().iter().map(|o| o.cqty).collect::<Vec<_>>(), vec![500]);
+}
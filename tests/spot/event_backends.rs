@@ -0,0 +1,117 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use off_grid_primitives::spot::event::{self, EventBackend, SpotEvent};
+use off_grid_primitives::spot::event_backends::{read_postcard, PostcardBackend, RecordingBackend};
+
+use super::simulation::{apply, generate, new_engine, Rng};
+use super::PublishGuard;
+
+fn lock_events() -> PublishGuard {
+    PublishGuard::lock()
+}
+
+/// Events of a simulated order flow, published to the process-wide event bus
+fn publish_order_flow(seed: u64) -> Vec<SpotEvent> {
+    let mut engine = new_engine();
+    let mut events = Vec::new();
+    for (step, command) in generate(&mut Rng::new(seed), 60).iter().enumerate() {
+        if let Ok(queue) = apply(&mut engine, command, step) {
+            events.extend(queue.iter().cloned());
+            event::publish_event_queue(queue).unwrap();
+        }
+    }
+    assert!(!events.is_empty());
+    events
+}
+
+/// Writer failing once `budget` bytes are written, sharing what it wrote
+#[derive(Clone)]
+struct FailingWriter {
+    written: Arc<Mutex<Vec<u8>>>,
+    budget: usize,
+}
+
+impl Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut written = self.written.lock().unwrap();
+        if written.len() + buf.len() > self.budget {
+            return Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"));
+        }
+        written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn recorder_sees_every_published_event_after_a_flush() {
+    let _guard = lock_events();
+    let recorder = RecordingBackend::new();
+    let handle = event::register_event_backend(Box::new(recorder.clone()));
+    let published = publish_order_flow(1);
+    handle.flush().unwrap();
+    assert_eq!(recorder.events(), published);
+
+    let more = publish_order_flow(2);
+    handle.shutdown().unwrap();
+    assert_eq!(recorder.take().len(), published.len() + more.len(), "shutting down handles what was published");
+    assert!(recorder.is_empty());
+}
+
+#[test]
+fn postcard_file_reads_back_the_events() {
+    let _guard = lock_events();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.bin");
+    let handle = event::register_event_backend(Box::new(PostcardBackend::create(&path).unwrap()));
+    let published = publish_order_flow(3);
+    handle.shutdown().unwrap();
+    assert_eq!(read_postcard(File::open(&path).unwrap()).unwrap(), published);
+
+    // a frame cut short is reported instead of read as an event
+    let bytes = std::fs::read(&path).unwrap();
+    let err = read_postcard(&bytes[..bytes.len() - 1]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_lines_file_reads_back_the_events() {
+    use off_grid_primitives::spot::event_backends::{read_json_lines, JsonLinesBackend};
+
+    let _guard = lock_events();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.jsonl");
+    let handle = event::register_event_backend(Box::new(JsonLinesBackend::create(&path).unwrap()));
+    let published = publish_order_flow(4);
+    handle.flush().unwrap();
+    drop(handle);
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(text.lines().count(), published.len());
+    assert!(text.lines().all(|line| line.starts_with("{\"Spot") || line.starts_with("{\"Transfer")));
+    assert_eq!(read_json_lines(io::BufReader::new(File::open(&path).unwrap())).unwrap(), published);
+}
+
+#[test]
+fn write_errors_surface_on_flush() {
+    let _guard = lock_events();
+    let writer = FailingWriter { written: Arc::default(), budget: 256 };
+    let mut backend = PostcardBackend::new(writer.clone());
+    for event in publish_order_flow(5) {
+        backend.handle_event(event);
+    }
+    assert_eq!(backend.flush().unwrap_err().kind(), io::ErrorKind::StorageFull);
+    assert!(writer.written.lock().unwrap().len() <= 256);
+
+    // the registered backend reports the error to the flushing caller
+    let handle = event::register_event_backend(Box::new(PostcardBackend::new(FailingWriter { written: Arc::default(), budget: 0 })));
+    publish_order_flow(6);
+    assert_eq!(handle.flush().unwrap_err().kind(), io::ErrorKind::StorageFull);
+    assert_eq!(handle.shutdown().unwrap_err().kind(), io::ErrorKind::StorageFull, "the events are still not written");
+}
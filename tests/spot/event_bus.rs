@@ -0,0 +1,226 @@
+use std::thread;
+use std::time::Duration;
+
+use off_grid_primitives::spot::event::{self, EventBackend, SpotEvent};
+use off_grid_primitives::spot::event_bus::{self, Backpressure, EventBusError};
+
+use super::PublishGuard;
+
+fn lock_events() -> PublishGuard {
+    PublishGuard::lock()
+}
+
+/// Event numbered by its timestamp
+fn numbered(n: i64) -> SpotEvent {
+    SpotEvent::SpotPairAdded { cid: "bus-gateway".into(), pair_id: "bus-pair".into(), timestamp: n }
+}
+
+fn number(event: &SpotEvent) -> i64 {
+    match event {
+        SpotEvent::SpotPairAdded { timestamp, .. } => *timestamp,
+        other => panic!("unexpected event {other:?}"),
+    }
+}
+
+fn numbers(events: &[SpotEvent]) -> Vec<i64> {
+    events.iter().map(number).collect()
+}
+
+#[derive(Default)]
+struct Recorder {
+    events: Vec<SpotEvent>,
+    batches: usize,
+}
+
+impl EventBackend for Recorder {
+    fn handle_event(&mut self, event: SpotEvent) {
+        self.events.push(event);
+    }
+
+    fn handle_batch(&mut self, events: std::vec::Drain<'_, SpotEvent>) {
+        self.batches += 1;
+        self.events.extend(events);
+    }
+}
+
+#[test]
+fn consumers_read_in_batches_at_their_own_cursor() {
+    let (mut producer, bus) = event_bus::bounded(8, Backpressure::Block);
+    producer.publish(numbered(-1)).unwrap();
+    let mut fast = bus.subscribe();
+    let mut slow = producer.subscribe();
+    producer.publish_all((0..6).map(numbered).collect()).unwrap();
+    assert_eq!(producer.sequence(), 7);
+
+    let mut events = Vec::new();
+    assert_eq!(fast.try_recv_batch(&mut events, 4), 4);
+    assert_eq!(fast.try_recv_batch(&mut events, 4), 2);
+    assert_eq!(fast.try_recv_batch(&mut events, 4), 0);
+    assert_eq!(numbers(&events), vec![0, 1, 2, 3, 4, 5], "a consumer only sees the events after it subscribed");
+    assert_eq!(fast.sequence(), 7);
+
+    assert_eq!(slow.try_recv().map(|event| number(&event)), Some(0));
+    assert_eq!(slow.sequence(), 2);
+}
+
+#[test]
+fn drop_oldest_skips_overwritten_events() {
+    let (mut producer, bus) = event_bus::bounded(4, Backpressure::DropOldest);
+    let mut consumer = bus.subscribe();
+    producer.publish_all((0..10).map(numbered).collect()).unwrap();
+
+    let mut events = Vec::new();
+    assert_eq!(consumer.try_recv_batch(&mut events, 100), 4);
+    assert_eq!(numbers(&events), vec![6, 7, 8, 9]);
+    assert_eq!(consumer.dropped(), 6);
+}
+
+#[test]
+fn error_rejects_what_does_not_fit() {
+    let (mut producer, bus) = event_bus::bounded(4, Backpressure::Error);
+    let mut consumer = bus.subscribe();
+    producer.publish_all((0..3).map(numbered).collect()).unwrap();
+    assert_eq!(producer.publish_all((3..5).map(numbered).collect()), Err(EventBusError::Full));
+    assert_eq!(producer.sequence(), 3, "a rejected batch publishes nothing");
+    producer.publish(numbered(3)).unwrap();
+    assert_eq!(producer.publish(numbered(4)), Err(EventBusError::Full));
+
+    let mut events = Vec::new();
+    consumer.try_recv_batch(&mut events, 2);
+    producer.publish_all((4..6).map(numbered).collect()).unwrap();
+    consumer.try_recv_batch(&mut events, 100);
+    assert_eq!(numbers(&events), vec![0, 1, 2, 3, 4, 5]);
+
+    // a dropped consumer no longer holds the producer back
+    drop(consumer);
+    producer.publish_all((6..20).map(numbered).collect()).unwrap();
+    assert_eq!(producer.sequence(), 20);
+}
+
+#[test]
+fn block_waits_for_the_slowest_consumer() {
+    let (mut producer, bus) = event_bus::bounded(16, Backpressure::Block);
+    let consumers: Vec<_> = (0..3)
+        .map(|i| {
+            let mut consumer = bus.subscribe();
+            thread::spawn(move || {
+                let mut recorder = Recorder::default();
+                let mut events = Vec::new();
+                while consumer.recv_batch(&mut events, 5).is_ok() {
+                    if i == 0 {
+                        thread::sleep(Duration::from_micros(50));
+                    }
+                    recorder.handle_batch(events.drain(..));
+                }
+                (recorder.events, consumer.dropped())
+            })
+        })
+        .collect();
+    for n in 0..2_000 {
+        producer.publish(numbered(n)).unwrap();
+    }
+    drop(producer);
+    assert!(bus.is_closed());
+
+    for consumer in consumers {
+        let (events, dropped) = consumer.join().unwrap();
+        assert_eq!(numbers(&events), (0..2_000).collect::<Vec<_>>());
+        assert_eq!(dropped, 0);
+    }
+}
+
+#[test]
+fn lagging_readers_never_see_a_torn_or_reordered_event() {
+    let (mut producer, bus) = event_bus::bounded(8, Backpressure::DropOldest);
+    let mut consumer = bus.subscribe();
+    let reader = thread::spawn(move || {
+        let mut events = Vec::new();
+        let mut last = -1;
+        let mut read = 0u64;
+        while consumer.recv_batch(&mut events, 3).is_ok() {
+            for event in events.drain(..) {
+                let n = number(&event);
+                assert!(n > last, "{n} after {last}");
+                last = n;
+                read += 1;
+            }
+        }
+        (read, consumer.dropped(), last)
+    });
+    for n in 0..50_000 {
+        producer.publish(numbered(n)).unwrap();
+    }
+    drop(producer);
+    let (read, dropped, last) = reader.join().unwrap();
+    assert_eq!(read + dropped, 50_000);
+    assert_eq!(last, 49_999);
+}
+
+#[test]
+fn consumer_runs_a_backend_until_the_bus_closes() {
+    let (mut producer, bus) = event_bus::bounded(64, Backpressure::Block);
+    let mut consumer = bus.subscribe();
+    let backend = thread::spawn(move || {
+        let mut recorder = Recorder::default();
+        consumer.run(&mut recorder, 16);
+        recorder
+    });
+    producer.publish_all((0..100).map(numbered).collect()).unwrap();
+    drop(producer);
+    let recorder = backend.join().unwrap();
+    assert_eq!(numbers(&recorder.events), (0..100).collect::<Vec<_>>());
+    assert!(recorder.batches >= 100 / 16);
+}
+
+#[test]
+fn an_idle_consumer_parks_until_an_event_is_published() {
+    let (mut producer, bus) = event_bus::bounded(4, Backpressure::Block);
+    let mut consumer = bus.subscribe();
+    let reader = thread::spawn(move || {
+        let mut events = Vec::new();
+        let read = consumer.recv_batch(&mut events, 4);
+        (read, numbers(&events), consumer.recv_batch(&mut events, 4))
+    });
+    // long enough for the reader to stop polling and park
+    thread::sleep(Duration::from_millis(50));
+    producer.publish(numbered(7)).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    drop(producer);
+    assert_eq!(reader.join().unwrap(), (Ok(1), vec![7], Err(EventBusError::Closed)));
+}
+
+#[test]
+fn published_events_reach_registered_backends() {
+    let _guard = lock_events();
+    let (handle, backend) = event::register_backend();
+    event::emit_event(numbered(1));
+    event::emit_event(numbered(2));
+    event::publish_events().unwrap();
+    assert!(event::drain_events().is_empty(), "publishing drains the queue of the thread");
+
+    // the queue of every thread is its own
+    thread::spawn(|| event::emit_event(numbered(3))).join().unwrap();
+    event::publish_event_queue(vec![numbered(4)].into()).unwrap();
+
+    let received: Vec<i64> = (0..3).map(|_| number(&backend.recv_timeout(Duration::from_secs(5)).unwrap())).collect();
+    assert_eq!(received, vec![1, 2, 4]);
+    assert_eq!(event::event_bus().map(|bus| bus.backpressure()), Some(Backpressure::Block));
+
+    // the backend thread stops with its handle, closing the receiver
+    handle.shutdown().unwrap();
+    assert!(backend.recv().is_err());
+}
+
+#[test]
+fn one_thread_at_a_time_owns_the_producer() {
+    let _guard = lock_events();
+    event::init_event_bus();
+    event::publish_event_queue(vec![numbered(1)].into()).unwrap();
+    let other = || thread::spawn(|| event::publish_event_queue(vec![numbered(2)].into())).join().unwrap();
+    assert_eq!(other(), Err(EventBusError::ProducerOwned));
+
+    // a released producer is taken by the next publisher and handed back when its thread exits
+    event::release_event_producer();
+    assert_eq!(other(), Ok(()));
+    event::publish_event_queue(vec![numbered(3)].into()).unwrap();
+}
@@ -0,0 +1,376 @@
+use off_grid_primitives::spot::command::SpotCommand;
+use off_grid_primitives::spot::event::SpotEvent;
+use off_grid_primitives::spot::ids::PairId;
+use off_grid_primitives::spot::fix::{
+    self, tag, ExecutionReporter, FixError, FixMessage, FixRequest, NewOrder, OrderEntry,
+};
+use off_grid_primitives::spot::intern::InternBudget;
+use off_grid_primitives::spot::market_order::MarketOrderSize;
+use off_grid_primitives::spot::orders::OrderRef;
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::MatchingEngine;
+
+use super::EVENT_MUTEX;
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+const SCALE_8: u64 = 1_0000_0000;
+const TRANSACT_TIME: &str = "20261019-12:00:00.000";
+const TRANSACT_MILLIS: i64 = 1_792_411_200_000;
+// end of the day of TRANSACT_TIME
+const SESSION_END: i64 = TRANSACT_MILLIS + 12 * 3_600_000;
+
+// NewOrderSingle of a client, with its session header
+const SAMPLE: &str = "8=FIX.4.4|9=141|35=D|49=CLIENT|56=OFFGRID|34=2|52=20261019-12:00:00.000|11=ord-1|1=alice|55=BTC-USD|54=2|60=20261019-12:00:00.000|38=1.5|40=2|44=101.25|59=1|10=088|";
+
+fn entry() -> OrderEntry {
+    // the Symbols of the requests are pairs known to the process
+    PairId::new(b"BTC-USD");
+    OrderEntry {
+        cid: "fix".into(),
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        session_end: Some(SESSION_END),
+        intern_budget: InternBudget::default(),
+    }
+}
+
+/// Wraps `|` delimited body fields, from MsgType on, into an encoded message
+fn message(body: &str) -> Vec<u8> {
+    let mut fields = body.trim_end_matches('|').split('|').map(|field| field.split_once('=').unwrap());
+    let (_, msg_type) = fields.next().unwrap();
+    let mut message = FixMessage::new(msg_type);
+    for (tag, value) in fields {
+        message.push(tag.parse().unwrap(), value);
+    }
+    message.encode()
+}
+
+fn sample() -> Vec<u8> {
+    SAMPLE.replace('|', "\x01").into_bytes()
+}
+
+fn request(body: &str) -> Result<FixRequest, FixError> {
+    FixRequest::parse(&FixMessage::parse(&message(body)).unwrap(), &mut entry())
+}
+
+fn new_order(body: &str) -> NewOrder {
+    match request(body).unwrap() {
+        FixRequest::NewOrder(order) => order,
+        other => panic!("not a new order: {other:?}"),
+    }
+}
+
+fn limit(side: &str, cl_ord_id: &str, qty: &str, price: &str, extra: &str) -> String {
+    format!("35=D|11={cl_ord_id}|1=alice|55=BTC-USD|54={side}|60={TRANSACT_TIME}|38={qty}|40=2|44={price}|{extra}")
+}
+
+/// Executes the commands of a request as a gateway does and renders its reports
+fn submit(engine: &mut MatchingEngine, reporter: &mut ExecutionReporter, request: &FixRequest) -> Vec<FixMessage> {
+    let mut events = Vec::new();
+    for (i, command) in request.commands().into_iter().enumerate() {
+        match engine.execute(command.clone()) {
+            Ok(queue) => events.extend(queue.into_vec()),
+            Err(err) if i == 0 => return vec![reporter.reject(request, &err.to_string())],
+            Err(err) => {
+                let FixRequest::Replace { order, .. } = request else { unreachable!() };
+                let mut reports = reporter.render(Some(request), &events);
+                reports.push(reporter.reject(&FixRequest::NewOrder(order.clone()), &err.to_string()));
+                return reports;
+            }
+        }
+    }
+    reporter.render(Some(request), &events)
+}
+
+fn engine() -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    engine
+        .execute(SpotCommand::AddPair {
+            cid: "fix".into(),
+            admin_account_id: "admin".into(),
+            fee_account_id: "fees".into(),
+            pair_id: "BTC-USD".into(),
+            timestamp: 1,
+        })
+        .unwrap();
+    engine
+}
+
+fn fields(report: &FixMessage, tags: &[u32]) -> Vec<String> {
+    tags.iter().map(|tag| report.get(*tag).unwrap_or("-").to_string()).collect()
+}
+
+#[test]
+fn sample_messages_parse_and_encode() {
+    let bytes = sample();
+    let message = FixMessage::parse(&bytes).unwrap();
+    assert_eq!(message.msg_type(), fix::msg_type::NEW_ORDER_SINGLE);
+    assert_eq!(message.get(49), Some("CLIENT"));
+    assert_eq!(message.get(tag::PRICE), Some("101.25"));
+    assert_eq!(message.encode(), bytes);
+
+    let corrupt = |from: &str, to: &str| FixMessage::parse(SAMPLE.replace(from, to).replace('|', "\x01").as_bytes());
+    assert_eq!(corrupt("10=088", "10=089"), Err(FixError::CheckSum { declared: 89, actual: 88 }));
+    assert!(matches!(corrupt("9=141", "9=140"), Err(FixError::BodyLength { declared: 140, actual: 141 })));
+    assert!(matches!(corrupt("FIX.4.4", "FIX.4.2"), Err(FixError::BeginString(_))));
+    assert!(matches!(corrupt("|10=088|", "|"), Err(FixError::Malformed(_))));
+    assert!(matches!(FixMessage::parse(b"8=FIX.4.4\x019=5\x0135=D"), Err(FixError::Malformed(_))));
+}
+
+#[test]
+fn new_order_singles_map_to_limit_and_market_commands() {
+    let FixRequest::NewOrder(order) = FixRequest::parse(&FixMessage::parse(&sample()).unwrap(), &mut entry()).unwrap() else { panic!() };
+    let SpotCommand::LimitSell(sell) = &order.command else { panic!("{:?}", order.command) };
+    assert_eq!((order.cl_ord_id.as_str(), order.order_qty, sell.post_only), ("ord-1", Some(150_000_000), false));
+    assert_eq!((sell.price, sell.amnt, sell.iqty), (10_125_000_000, 150_000_000, 0));
+    assert_eq!((sell.owner, sell.pair_id, sell.cid), ("alice".into(), "BTC-USD".into(), entry().cid));
+    assert_eq!((sell.coid.as_deref(), sell.timestamp, sell.expires_at), (Some(&b"ord-1"[..]), TRANSACT_MILLIS, i64::MAX));
+    assert_eq!((sell.maker_fee_bps, sell.taker_fee_bps, sell.time_in_force), (5, 10, TimeInForce::GoodTillCanceled));
+
+    // buys are sized in quote at the limit price, MaxFloor is the public part of icebergs
+    let order = new_order(&limit("1", "b-1", "2", "100.5", "111=0.5|"));
+    let SpotCommand::LimitBuy(buy) = &order.command else { panic!("{:?}", order.command) };
+    assert_eq!((buy.amnt, buy.iqty), (201 * SCALE_8, 201 * SCALE_8 - 5025 * SCALE_8 / 100));
+
+    let market = |extra: &str| new_order(&format!("35=D|11=m-1|1=alice|55=BTC-USD|54=1|60={TRANSACT_TIME}|40=1|{extra}"));
+    let order = market("152=250|");
+    let SpotCommand::MarketBuy(buy) = &order.command else { panic!("{:?}", order.command) };
+    assert_eq!((buy.size, buy.time_in_force, order.order_qty), (MarketOrderSize::Quote(250 * SCALE_8), TimeInForce::ImmediateOrCancel, None));
+    let SpotCommand::MarketBuy(buy) = market("38=0.1|59=4|").command else { panic!() };
+    assert_eq!((buy.size, buy.time_in_force), (MarketOrderSize::Base(SCALE_8 / 10), TimeInForce::FillOrKill));
+    assert!(matches!(request(&format!("35=D|11=m-1|1=alice|55=BTC-USD|54=1|60={TRANSACT_TIME}|40=1|")), Err(FixError::MissingTag(38))));
+}
+
+#[test]
+fn time_in_force_and_exec_inst_follow_the_mapping_table() {
+    let tif = |extra: &str| match new_order(&limit("2", "t-1", "1", "100", extra)).command {
+        SpotCommand::LimitSell(order) => (order.time_in_force, order.expires_at),
+        other => panic!("{other:?}"),
+    };
+    assert_eq!(tif(""), (TimeInForce::GoodTillCanceled, SESSION_END));
+    assert_eq!(tif("59=0|"), (TimeInForce::GoodTillCanceled, SESSION_END));
+    assert_eq!(tif("59=1|"), (TimeInForce::GoodTillCanceled, i64::MAX));
+    assert_eq!(tif("59=3|"), (TimeInForce::ImmediateOrCancel, i64::MAX));
+    assert_eq!(tif("59=4|"), (TimeInForce::FillOrKill, i64::MAX));
+    assert_eq!(tif("59=6|126=20261019-12:00:01.5|"), (TimeInForce::GoodTillCanceled, TRANSACT_MILLIS + 1500));
+
+    assert!(matches!(new_order(&limit("2", "t-1", "1", "100", "18=6|")).command, SpotCommand::LimitSell(order) if order.post_only));
+    let error = |extra: &str| request(&limit("2", "t-1", "1", "100", extra)).unwrap_err();
+    assert_eq!(error("59=2|"), FixError::Unsupported { tag: 59, value: "2".into() });
+    assert_eq!(error("59=6|"), FixError::MissingTag(126));
+    assert_eq!(error("18=6 G|"), FixError::Unsupported { tag: 18, value: "6 G".into() });
+    assert_eq!(error("18=6|59=3|"), FixError::InvalidValue { tag: 18, value: "6".into() });
+    assert_eq!(error("111=2|"), FixError::InvalidValue { tag: 111, value: "2".into() });
+    let too_precise = request(&limit("2", "t-1", "1", "1.000000001", "")).unwrap_err();
+    assert_eq!(too_precise.to_string(), "invalid value \"1.000000001\" of tag 44");
+    assert_eq!(request("35=D|11=x|1=alice|55=BTC-USD|54=5|40=2|").unwrap_err(), FixError::Unsupported { tag: 54, value: "5".into() });
+    assert_eq!(request("35=AE|11=x|").unwrap_err(), FixError::MsgType("AE".into()));
+
+    // a session without a trading day rejects Day orders
+    let mut no_day = OrderEntry { session_end: None, ..entry() };
+    let mut day = |extra: &str| FixRequest::parse(&FixMessage::parse(&message(&limit("2", "t-1", "1", "100", extra))).unwrap(), &mut no_day);
+    assert_eq!(day("").unwrap_err(), FixError::MissingTag(59));
+    assert_eq!(day("59=0|").unwrap_err(), FixError::Unsupported { tag: 59, value: "0".into() });
+    assert!(day("59=1|").is_ok());
+
+    // a session interns new accounts within its budget
+    let mut session = OrderEntry { intern_budget: InternBudget::new(1), ..entry() };
+    let mut account = |account: &str| {
+        let body = format!("35=D|11=t-1|1={account}|55=BTC-USD|54=2|60={TRANSACT_TIME}|38=1|40=2|44=100|59=1|");
+        FixRequest::parse(&FixMessage::parse(&message(&body)).unwrap(), &mut session)
+    };
+    assert!(account("fix-budget-1").is_ok());
+    assert_eq!(account("fix-budget-2").unwrap_err(), FixError::InvalidValue { tag: 1, value: "fix-budget-2".into() });
+    assert!(account("fix-budget-1").is_ok());
+    assert!(account("alice").is_ok());
+}
+
+#[test]
+fn cancels_and_replaces_map_to_cancel_commands() {
+    let FixRequest::Cancel(cancel) = request("35=F|11=c-2|41=c-1|1=alice|55=BTC-USD|54=1|60=20261019-12:00:00|").unwrap() else { panic!() };
+    let SpotCommand::CancelOrder { order, owner, pair_id, .. } = &cancel.command else { panic!() };
+    assert_eq!((order, owner, pair_id), (&OrderRef::ClientOrderId(b"c-1".to_vec()), &"alice".into(), &"BTC-USD".into()));
+    assert_eq!((cancel.cl_ord_id.as_str(), cancel.orig_cl_ord_id.as_deref()), ("c-2", Some("c-1")));
+
+    let order_id = ulid::Ulid::new();
+    let FixRequest::Cancel(cancel) = request(&format!("35=F|11=c-2|37={order_id}|1=alice|55=BTC-USD|54=1|")).unwrap() else { panic!() };
+    assert!(matches!(cancel.command, SpotCommand::CancelOrder { order: OrderRef::Id(id), .. } if id == order_id));
+    assert_eq!(request("35=F|11=c-2|1=alice|55=BTC-USD|54=1|").unwrap_err(), FixError::MissingTag(41));
+
+    // Symbols of unknown pairs and overlong Accounts are rejected without being interned
+    assert_eq!(
+        request("35=F|11=c-2|41=c-1|1=alice|55=fix-unknown-pair|54=1|").unwrap_err(),
+        FixError::InvalidValue { tag: tag::SYMBOL, value: "fix-unknown-pair".into() }
+    );
+    assert_eq!(PairId::lookup(b"fix-unknown-pair"), None);
+    let account = "a".repeat(129);
+    assert!(matches!(
+        request(&format!("35=F|11=c-2|41=c-1|1={account}|55=BTC-USD|54=1|")).unwrap_err(),
+        FixError::InvalidValue { tag: tag::ACCOUNT, .. }
+    ));
+
+    let replace = request(&format!("35=G|41=c-1|{}", limit("1", "c-3", "1", "99", "").trim_start_matches("35=D|"))).unwrap();
+    let FixRequest::Replace { cancel, order } = &replace else { panic!() };
+    assert_eq!((cancel.cl_ord_id.as_str(), order.cl_ord_id.as_str()), ("c-3", "c-3"));
+    let commands = replace.commands();
+    assert!(matches!(commands[..], [SpotCommand::CancelOrder { .. }, SpotCommand::LimitBuy(_)]));
+}
+
+#[test]
+fn events_render_as_execution_reports() {
+    let _guard = lock_events();
+    let mut engine = engine();
+    let mut reporter = ExecutionReporter::new();
+    let report_tags = [tag::CL_ORD_ID, tag::EXEC_TYPE, tag::ORD_STATUS, tag::SIDE, tag::ORDER_QTY, tag::LAST_QTY, tag::LAST_PX, tag::LEAVES_QTY, tag::CUM_QTY, tag::AVG_PX];
+
+    let reports = submit(&mut engine, &mut reporter, &request(&limit("2", "s-1", "100", "100", "")).unwrap());
+    assert_eq!(reports.len(), 1);
+    assert_eq!(fields(&reports[0], &report_tags), ["s-1", "0", "0", "2", "100", "-", "-", "100", "0", "0"]);
+    assert_eq!(reports[0].get(tag::ACCOUNT), Some("alice"));
+    let sell_id = reports[0].get(tag::ORDER_ID).unwrap().to_string();
+
+    // the buy is sized in quote at its limit price
+    let buy = limit("1", "b-1", "0.5", "101", "59=3|").replace("1=alice", "1=bob");
+    let reports = submit(&mut engine, &mut reporter, &request(&buy).unwrap());
+    let rendered: Vec<Vec<String>> = reports.iter().map(|report| fields(report, &report_tags)).collect();
+    assert_eq!(
+        rendered,
+        [
+            ["b-1", "0", "0", "1", "0.5", "-", "-", "0.5", "0", "0"],
+            ["b-1", "F", "2", "1", "0.5", "0.5", "101", "0", "0.5", "101"],
+            ["s-1", "F", "1", "2", "100", "50", "101", "50", "50", "101"],
+        ]
+    );
+    assert_eq!(reports[2].get(tag::ORDER_ID), Some(sell_id.as_str()));
+    assert_eq!(reports[0].get(tag::TRANSACT_TIME), Some(TRANSACT_TIME));
+
+    let cancel = request("35=F|11=s-2|41=s-1|1=alice|55=BTC-USD|54=2|").unwrap();
+    let reports = submit(&mut engine, &mut reporter, &cancel);
+    assert_eq!(fields(&reports[0], &[tag::CL_ORD_ID, tag::ORIG_CL_ORD_ID, tag::EXEC_TYPE, tag::ORD_STATUS, tag::LEAVES_QTY, tag::CUM_QTY]), ["s-2", "s-1", "4", "4", "0", "50"]);
+
+    // every report encodes and parses back
+    for report in reports {
+        assert_eq!(FixMessage::parse(&report.encode()).unwrap(), report);
+    }
+}
+
+#[test]
+fn buys_filling_below_their_limit_report_executions_of_their_order_qty() {
+    let _guard = lock_events();
+    let mut engine = engine();
+    let mut reporter = ExecutionReporter::new();
+    let report_tags = [tag::CL_ORD_ID, tag::EXEC_TYPE, tag::ORD_STATUS, tag::ORDER_QTY, tag::LEAVES_QTY, tag::CUM_QTY];
+    let mut rendered = Vec::new();
+    for body in [
+        limit("2", "s-1", "1", "100", "59=1|"),
+        limit("2", "s-2", "1", "100.5", "59=1|"),
+        // the buy takes both asks below its limit and rests
+        limit("1", "b-1", "2", "101", "59=1|").replace("1=alice", "1=bob"),
+        // the sell takes the resting buy
+        limit("2", "s-3", "5", "101", "59=3|"),
+    ] {
+        let reports = submit(&mut engine, &mut reporter, &request(&body).unwrap());
+        rendered.extend(reports.iter().map(|report| fields(report, &report_tags)));
+    }
+
+    let decimal = |value: &str| fix::parse_decimal(value).unwrap();
+    for report in &rendered {
+        assert_eq!(decimal(&report[3]), decimal(&report[4]) + decimal(&report[5]), "OrderQty = LeavesQty + CumQty in {report:?}");
+    }
+    let buy: Vec<&Vec<String>> = rendered.iter().filter(|report| report[0] == "b-1").collect();
+    assert_eq!(buy.iter().map(|report| report[2].as_str()).collect::<Vec<_>>(), ["0", "1", "1", "2"]);
+    assert_eq!(buy.last().unwrap()[5], "2");
+}
+
+#[test]
+fn replaces_render_a_replaced_report_of_the_new_order() {
+    let _guard = lock_events();
+    let mut engine = engine();
+    let mut reporter = ExecutionReporter::new();
+    submit(&mut engine, &mut reporter, &request(&limit("2", "r-1", "1", "105", "")).unwrap());
+
+    let replace = format!("35=G|41=r-1|{}", limit("2", "r-2", "3", "106", "").trim_start_matches("35=D|"));
+    let reports = submit(&mut engine, &mut reporter, &request(&replace).unwrap());
+    assert_eq!(reports.len(), 1);
+    assert_eq!(
+        fields(&reports[0], &[tag::CL_ORD_ID, tag::ORIG_CL_ORD_ID, tag::EXEC_TYPE, tag::ORD_STATUS, tag::ORDER_QTY, tag::PRICE]),
+        ["r-2", "r-1", "5", "0", "3", "106"]
+    );
+    let depth = engine.depth("BTC-USD", 10).unwrap();
+    assert_eq!((depth.asks.len(), depth.asks[0].price, depth.asks[0].qty), (1, 106 * SCALE_8, 3 * SCALE_8));
+
+    // the original of a replace whose new order is rejected is reported cancelled
+    let replace = format!("35=G|41=r-2|{}", limit("2", "r-3", "3", "0", "").trim_start_matches("35=D|"));
+    let reports = submit(&mut engine, &mut reporter, &request(&replace).unwrap());
+    let rendered: Vec<Vec<String>> = reports.iter().map(|report| fields(report, &[35, tag::CL_ORD_ID, tag::ORIG_CL_ORD_ID, tag::EXEC_TYPE, tag::TEXT])).collect();
+    assert_eq!(rendered, [["8", "r-3", "r-2", "4", "-"], ["8", "r-3", "-", "8", "L3 error: price is zero"]]);
+}
+
+#[test]
+fn rejections_render_rejected_reports_and_cancel_rejects() {
+    let _guard = lock_events();
+    let mut engine = engine();
+    let mut reporter = ExecutionReporter::new();
+    submit(&mut engine, &mut reporter, &request(&limit("2", "p-1", "1", "100", "")).unwrap());
+
+    // a post-only buy at the best ask would take liquidity, one below it rests
+    let post_only = request(&limit("1", "p-2", "1", "100", "18=6|")).unwrap();
+    let reports = submit(&mut engine, &mut reporter, &post_only);
+    assert_eq!(fields(&reports[0], &[tag::CL_ORD_ID, tag::EXEC_TYPE, tag::ORD_STATUS, tag::ORD_REJ_REASON, tag::TEXT]), ["p-2", "8", "8", "99", "post-only order would take liquidity"]);
+    let reports = submit(&mut engine, &mut reporter, &request(&limit("1", "p-3", "1", "99.5", "18=6|")).unwrap());
+    assert_eq!(reports[0].get(tag::EXEC_TYPE), Some("0"));
+
+    let duplicate = submit(&mut engine, &mut reporter, &request(&limit("2", "p-1", "1", "101", "")).unwrap());
+    assert_eq!(fields(&duplicate[0], &[tag::ORDER_ID, tag::EXEC_TYPE]), ["NONE", "8"]);
+
+    let cancel = request("35=F|11=p-4|41=unknown|1=alice|55=BTC-USD|54=2|").unwrap();
+    let reports = submit(&mut engine, &mut reporter, &cancel);
+    assert_eq!(
+        fields(&reports[0], &[35, tag::CL_ORD_ID, tag::ORIG_CL_ORD_ID, tag::ORD_STATUS, tag::CXL_REJ_RESPONSE_TO]),
+        ["9", "p-4", "unknown", "8", "1"]
+    );
+}
+
+#[test]
+fn expiries_of_orders_outside_fix_render_without_cl_ord_id() {
+    let order_id = ulid::Ulid::new();
+    let expired = SpotEvent::SpotOrderExpired {
+        cid: "fix".into(),
+        pair_id: Some("BTC-USD".into()),
+        order_id: order_id.to_bytes().to_vec(),
+        maker_account_id: "carol".into(),
+        is_bid: true,
+        price: 50 * SCALE_8,
+        amnt: 100 * SCALE_8,
+        iqty: 0,
+        pqty: 100 * SCALE_8,
+        cqty: 100 * SCALE_8,
+        timestamp: -1,
+        expires_at: 0,
+    };
+    let reports = ExecutionReporter::new().render(None, &[expired]);
+    assert_eq!(
+        fields(&reports[0], &[tag::ORDER_ID, tag::CL_ORD_ID, tag::EXEC_TYPE, tag::ORD_STATUS, tag::ORDER_QTY, tag::LEAVES_QTY, tag::TRANSACT_TIME]),
+        [order_id.to_string().as_str(), "-", "C", "C", "2", "0", "19691231-23:59:59.999"]
+    );
+}
+
+#[test]
+fn decimals_and_timestamps_round_trip() {
+    for (text, value) in [("0", 0), ("1", SCALE_8), ("101.25", 10_125_000_000), ("0.00000001", 1)] {
+        assert_eq!(fix::parse_decimal(text), Some(value));
+        assert_eq!(fix::format_decimal(value), text);
+    }
+    for text in ["", ".5", "-1", "1e3", "1.000000001", "184467440737.1"] {
+        assert_eq!(fix::parse_decimal(text), None, "{text}");
+    }
+    assert_eq!(fix::parse_utc_timestamp(TRANSACT_TIME), Some(TRANSACT_MILLIS));
+    assert_eq!(fix::parse_utc_timestamp("20261019-12:00:00"), Some(TRANSACT_MILLIS));
+    assert_eq!(fix::parse_utc_timestamp("20240229-00:00:00.123456"), Some(1_709_164_800_123));
+    assert_eq!(fix::parse_utc_timestamp("20261319-12:00:00"), None);
+    assert_eq!(fix::format_utc_timestamp(TRANSACT_MILLIS), TRANSACT_TIME);
+    assert_eq!(fix::format_utc_timestamp(0), "19700101-00:00:00.000");
+}
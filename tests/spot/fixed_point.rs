@@ -0,0 +1,56 @@
+use off_grid_primitives::spot::fixed_point::{mul_div, FixedPointError, Notional, Price, Quantity, Rounding};
+
+const SCALE_8: u64 = 1_0000_0000;
+
+#[test]
+fn mul_div_rounds_floor_and_ceil() {
+    assert_eq!(mul_div(10, 1, 3, Rounding::Floor), Ok(3));
+    assert_eq!(mul_div(10, 1, 3, Rounding::Ceil), Ok(4));
+    assert_eq!(mul_div(9, 1, 3, Rounding::Ceil), Ok(3));
+}
+
+#[test]
+fn mul_div_uses_a_wide_intermediate() {
+    // the product overflows u64 but the result fits
+    assert_eq!(mul_div(u64::MAX, SCALE_8, SCALE_8, Rounding::Floor), Ok(u64::MAX));
+    assert_eq!(mul_div(u64::MAX, 2, 1, Rounding::Floor), Err(FixedPointError::Overflow));
+    assert_eq!(mul_div(1, 1, 0, Rounding::Floor), Err(FixedPointError::ZeroPrice));
+}
+
+#[test]
+fn price_converts_between_base_and_quote() {
+    let price = Price(100_33333333);
+    assert_eq!(price.notional(Quantity(3 * SCALE_8), Rounding::Floor), Ok(Notional(300_99999999)));
+    assert_eq!(price.notional(Quantity(3 * SCALE_8), Rounding::Ceil), Ok(Notional(300_99999999)));
+    assert_eq!(price.notional(Quantity(SCALE_8 / 2), Rounding::Floor), Ok(Notional(50_16666666)));
+    assert_eq!(price.notional(Quantity(SCALE_8 / 2), Rounding::Ceil), Ok(Notional(50_16666667)));
+    assert_eq!(price.quantity(Notional(100 * SCALE_8), Rounding::Floor), Ok(Quantity(99667774)));
+    assert_eq!(price.quantity(Notional(100 * SCALE_8), Rounding::Ceil), Ok(Quantity(99667775)));
+    assert_eq!(Price(0).quantity(Notional(1), Rounding::Floor), Err(FixedPointError::ZeroPrice));
+}
+
+#[test]
+fn large_notionals_fail_instead_of_saturating() {
+    let price = Price(1_000_000 * SCALE_8);
+    assert_eq!(price.notional(Quantity(u64::MAX / 2), Rounding::Floor), Err(FixedPointError::Overflow));
+    assert_eq!(Quantity(u64::MAX).checked_add(Quantity(1)), Err(FixedPointError::Overflow));
+    assert_eq!(Notional(0).checked_sub(Notional(1)), Err(FixedPointError::Overflow));
+}
+
+#[test]
+fn moves_prices_and_amounts_by_basis_points() {
+    let price = Price(100 * SCALE_8);
+    assert_eq!(price.add_bps(5, Rounding::Floor), Ok(Price(100_05000000)));
+    assert_eq!(price.sub_bps(5, Rounding::Floor), Ok(Price(99_95000000)));
+    // a move down of more than 100% stops at zero
+    assert_eq!(price.sub_bps(20000, Rounding::Floor), Ok(Price(0)));
+    assert_eq!(Price::MAX.add_bps(1, Rounding::Floor), Err(FixedPointError::Overflow));
+    assert_eq!(Quantity(333).bps(25, Rounding::Floor), Ok(Quantity(0)));
+    assert_eq!(Notional(1000 * SCALE_8).bps(25, Rounding::Floor), Ok(Notional(2_50000000)));
+}
+
+#[test]
+fn serializes_as_plain_integers() {
+    let bytes = postcard::to_allocvec(&(Price(1), Quantity(2), Notional(3))).expect("serialize");
+    assert_eq!(bytes, postcard::to_allocvec(&(1u64, 2u64, 3u64)).expect("serialize"));
+}
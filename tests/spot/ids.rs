@@ -0,0 +1,247 @@
+use off_grid_primitives::spot::command::SpotCommand;
+use off_grid_primitives::spot::event::SpotEvent;
+use off_grid_primitives::spot::ids::{AccountId, AssetId, ClientId, PairId};
+use off_grid_primitives::spot::intern::{self, InternBudget, BOUNDED_LEN};
+use off_grid_primitives::spot::trades::Trade;
+use serde::{Deserialize, Serialize};
+
+/// Layout of `Trade` from when its ids were byte vectors
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct BytesTrade {
+    trade_id: u64,
+    #[serde(with = "serde_bytes")]
+    pair_id: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    base_asset_id: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    quote_asset_id: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    taker_order_id: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    maker_order_id: Vec<u8>,
+    taker_is_bid: bool,
+    price: u64,
+    base_volume: u64,
+    quote_volume: u64,
+    timestamp: i64,
+}
+
+/// Layout of `SpotEvent::SpotPairClientAccountChanged` from when its ids were byte vectors
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct BytesClientAccountChanged {
+    #[serde(with = "serde_bytes")]
+    pair_id: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    cid: Option<Vec<u8>>,
+    #[serde(with = "serde_bytes")]
+    admin_account_id: Option<Vec<u8>>,
+    #[serde(with = "serde_bytes")]
+    fee_account_id: Option<Vec<u8>>,
+    timestamp: i64,
+}
+
+#[test]
+fn ids_intern_their_bytes_once() {
+    let a = AccountId::new(b"ids-alice");
+    assert_eq!(a, AccountId::from("ids-alice"));
+    assert_eq!(a, AccountId::from(b"ids-alice".to_vec()));
+    assert_eq!(a.symbol(), AccountId::from(b"ids-alice").symbol());
+    assert_eq!(a.as_slice(), b"ids-alice");
+    assert_eq!(a.to_vec(), b"ids-alice".to_vec());
+    assert_eq!(a, b"ids-alice");
+    // ids of other kinds share the table but not the type
+    assert_eq!(ClientId::new(b"ids-alice").symbol(), a.symbol());
+}
+
+#[test]
+fn lookup_does_not_intern() {
+    assert_eq!(PairId::lookup(b"ids-never-interned"), None);
+    let pair_id = PairId::new(b"ids-interned");
+    assert_eq!(PairId::lookup(b"ids-interned"), Some(pair_id));
+}
+
+/// Layout of the first `SpotCommand`s with their ids as bytes
+#[derive(Debug, Serialize)]
+enum BytesCommand {
+    AddPair {
+        #[serde(with = "serde_bytes")]
+        cid: Vec<u8>,
+        #[serde(with = "serde_bytes")]
+        admin_account_id: Vec<u8>,
+        #[serde(with = "serde_bytes")]
+        fee_account_id: Vec<u8>,
+        #[serde(with = "serde_bytes")]
+        pair_id: Vec<u8>,
+        timestamp: i64,
+    },
+    AddPairClient {
+        #[serde(with = "serde_bytes")]
+        cid: Vec<u8>,
+        #[serde(with = "serde_bytes")]
+        pair_id: Vec<u8>,
+        #[serde(with = "serde_bytes")]
+        admin_account_id: Vec<u8>,
+        #[serde(with = "serde_bytes")]
+        fee_account_id: Vec<u8>,
+        timestamp: i64,
+    },
+}
+
+#[test]
+fn untrusted_input_interns_only_registered_ids_within_bounds() {
+    let mut budget = InternBudget::default();
+    let mut untrusted = |command: BytesCommand| {
+        let bytes = postcard::to_allocvec(&command).unwrap();
+        intern::untrusted(&mut budget, || postcard::from_bytes::<SpotCommand>(&bytes))
+    };
+    let add_client = |cid: &str, admin: &[u8]| BytesCommand::AddPairClient {
+        cid: cid.into(),
+        pair_id: b"ids-untrusted-pair".to_vec(),
+        admin_account_id: admin.to_vec(),
+        fee_account_id: b"ids-untrusted-fees".to_vec(),
+        timestamp: 2,
+    };
+
+    // the pair of a command must be known
+    assert!(untrusted(add_client("ids-untrusted-cid", b"ids-untrusted-admin")).is_err());
+    assert_eq!(PairId::lookup(b"ids-untrusted-pair"), None);
+
+    // AddPair registers its pair and client
+    let add_pair = BytesCommand::AddPair {
+        cid: b"ids-untrusted-cid".to_vec(),
+        admin_account_id: b"ids-untrusted-admin".to_vec(),
+        fee_account_id: b"ids-untrusted-fees".to_vec(),
+        pair_id: b"ids-untrusted-pair".to_vec(),
+        timestamp: 1,
+    };
+    assert!(untrusted(add_pair).is_ok());
+    assert!(PairId::lookup(b"ids-untrusted-pair").is_some());
+    assert!(ClientId::lookup(b"ids-untrusted-cid").is_some());
+
+    // accounts are interned up to the bound on their length
+    let command = untrusted(add_client("ids-untrusted-cid-2", b"ids-untrusted-admin-2")).expect("known pair");
+    assert_eq!(command.pair_id(), PairId::new(b"ids-untrusted-pair"));
+    assert!(AccountId::lookup(b"ids-untrusted-admin-2").is_some());
+    let long = vec![b'a'; BOUNDED_LEN + 1];
+    assert!(untrusted(add_client("ids-untrusted-cid-3", &long)).is_err());
+    assert_eq!(AccountId::lookup(&long), None);
+    assert_eq!(AccountId::new_bounded(&long), None);
+
+    // trusted input interns any id
+    let bytes = postcard::to_allocvec(&add_client("ids-trusted-cid", &long)).unwrap();
+    assert!(postcard::from_bytes::<SpotCommand>(&bytes).is_ok());
+    assert!(AccountId::lookup(&long).is_some());
+}
+
+#[test]
+fn untrusted_sources_intern_within_their_budget() {
+    PairId::new(b"ids-budget-pair");
+    let add_client = |admin: &str| {
+        let command = BytesCommand::AddPairClient {
+            cid: b"ids-budget-cid".to_vec(),
+            pair_id: b"ids-budget-pair".to_vec(),
+            admin_account_id: admin.into(),
+            fee_account_id: b"ids-budget-fees".to_vec(),
+            timestamp: 1,
+        };
+        postcard::to_allocvec(&command).unwrap()
+    };
+
+    // a command of 3 new ids is rejected by a budget of 2, the ids it interned stay charged
+    let mut budget = InternBudget::new(2);
+    assert!(intern::untrusted(&mut budget, || postcard::from_bytes::<SpotCommand>(&add_client("ids-budget-admin-1"))).is_err());
+    assert_eq!(budget.remaining(), 0);
+    assert!(ClientId::lookup(b"ids-budget-cid").is_some());
+    assert_eq!(AccountId::lookup(b"ids-budget-fees"), None);
+
+    // the source is rejected for new ids only, known ones still decode
+    assert!(intern::untrusted(&mut budget, || postcard::from_bytes::<SpotCommand>(&add_client("ids-budget-admin-2"))).is_err());
+    let mut other = InternBudget::new(2);
+    assert!(intern::untrusted(&mut other, || postcard::from_bytes::<SpotCommand>(&add_client("ids-budget-admin-1"))).is_ok());
+    assert_eq!(other.remaining(), 1);
+    assert!(intern::untrusted(&mut budget, || postcard::from_bytes::<SpotCommand>(&add_client("ids-budget-admin-1"))).is_ok());
+    assert!(!intern::is_untrusted());
+}
+
+#[test]
+fn ids_order_by_their_bytes() {
+    // intern in reverse order so the symbols order the other way around
+    let b = AssetId::new(b"ids-order-b");
+    let a = AssetId::new(b"ids-order-a");
+    assert!(a.symbol() > b.symbol());
+    assert!(a < b);
+    let mut ids = vec![b, AssetId::default(), a];
+    ids.sort();
+    assert_eq!(ids, vec![AssetId::new(b""), a, b]);
+    assert!(AssetId::default().is_empty());
+}
+
+#[test]
+fn trade_encodes_like_byte_ids() {
+    let trade = Trade {
+        trade_id: 7,
+        pair_id: PairId::new(b"BTC-USD"),
+        base_asset_id: AssetId::new(b"BTC"),
+        quote_asset_id: AssetId::new(b"USD"),
+        taker_order_id: vec![1; 16],
+        maker_order_id: vec![2; 16],
+        taker_is_bid: true,
+        price: 100,
+        base_volume: 3,
+        quote_volume: 300,
+        timestamp: 42,
+    };
+    let bytes = BytesTrade {
+        trade_id: 7,
+        pair_id: b"BTC-USD".to_vec(),
+        base_asset_id: b"BTC".to_vec(),
+        quote_asset_id: b"USD".to_vec(),
+        taker_order_id: vec![1; 16],
+        maker_order_id: vec![2; 16],
+        taker_is_bid: true,
+        price: 100,
+        base_volume: 3,
+        quote_volume: 300,
+        timestamp: 42,
+    };
+    let encoded = postcard::to_allocvec(&trade).expect("encode trade");
+    assert_eq!(encoded, postcard::to_allocvec(&bytes).expect("encode byte trade"));
+    assert_eq!(postcard::from_bytes::<Trade>(&encoded).expect("decode trade"), trade);
+}
+
+#[test]
+fn optional_ids_encode_like_optional_bytes() {
+    let event = SpotEvent::SpotPairClientAccountChanged {
+        pair_id: PairId::new(b"BTC-USD"),
+        cid: Some(ClientId::new(b"gateway")),
+        admin_account_id: Some(AccountId::new(b"admin")),
+        fee_account_id: None,
+        timestamp: 42,
+    };
+    let SpotEvent::SpotPairClientAccountChanged { pair_id, cid, admin_account_id, fee_account_id, timestamp } = &event else {
+        unreachable!()
+    };
+    let typed = (pair_id, cid, admin_account_id, fee_account_id, timestamp);
+    let bytes = BytesClientAccountChanged {
+        pair_id: b"BTC-USD".to_vec(),
+        cid: Some(b"gateway".to_vec()),
+        admin_account_id: Some(b"admin".to_vec()),
+        fee_account_id: None,
+        timestamp: 42,
+    };
+    let encoded = postcard::to_allocvec(&bytes).expect("encode byte event");
+    assert_eq!(postcard::to_allocvec(&typed).expect("encode typed event"), encoded);
+
+    // a queued event decodes back into the same ids
+    let decoded: SpotEvent = postcard::from_bytes(&postcard::to_allocvec(&event).expect("encode event")).expect("decode event");
+    assert_eq!(decoded, event);
+}
+
+#[test]
+fn client_id_lists_encode_like_byte_lists() {
+    let typed = vec![ClientId::new(b"a"), ClientId::new(b"bc")];
+    let bytes: Vec<Vec<u8>> = vec![b"a".to_vec(), b"bc".to_vec()];
+    let encoded = postcard::to_allocvec(&bytes).expect("encode byte list");
+    assert_eq!(postcard::to_allocvec(&typed).expect("encode id list"), encoded);
+    assert_eq!(postcard::from_bytes::<Vec<ClientId>>(&encoded).expect("decode id list"), typed);
+}
@@ -0,0 +1,298 @@
+use off_grid_primitives::spot::command::{LimitOrder, MarketOrder};
+use off_grid_primitives::spot::event::{self, SpotEvent};
+use off_grid_primitives::spot::invariants::{check_book, check_command, check_fills, InvariantError};
+use off_grid_primitives::spot::market_order::MarketOrderSize;
+use off_grid_primitives::spot::orderbook::OrderBook;
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::Pair;
+
+use super::EVENT_MUTEX;
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+const SCALE_8: u64 = 1_0000_0000;
+
+fn setup_pair() -> Pair {
+    let mut pair = Pair::new();
+    pair.pair_id = vec![1].into();
+    pair.base_asset_id = vec![2].into();
+    pair.quote_asset_id = vec![3].into();
+    let _ = event::drain_events();
+    pair
+}
+
+/// Runs a command on the pair and checks it, returning the book before it and its events
+fn checked(pair: &mut Pair, command: impl FnOnce(&mut Pair)) -> (OrderBook, event::EventQueue) {
+    let before = pair.orderbook.clone();
+    command(pair);
+    let events = event::drain_events();
+    check_command(&before, &pair.orderbook, &events).expect("invariants hold");
+    (before, events)
+}
+
+fn limit(pair: &mut Pair, is_bid: bool, owner: u8, price: u64, amnt: u64, iqty: u64) {
+    if is_bid {
+        pair.limit_buy(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [owner].into(),
+            coid: None,
+            price,
+            amnt,
+            iqty,
+            timestamp: 1,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
+            .expect("limit buy");
+    } else {
+        pair.limit_sell(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [owner].into(),
+            coid: None,
+            price,
+            amnt,
+            iqty,
+            timestamp: 1,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
+            .expect("limit sell");
+    }
+}
+
+/// Book with matched, partially filled and iceberg orders on both sides
+fn traded_pair() -> (Pair, OrderBook, event::EventQueue) {
+    let mut pair = setup_pair();
+    checked(&mut pair, |p| limit(p, false, 10, 101 * SCALE_8, 3 * SCALE_8, SCALE_8));
+    checked(&mut pair, |p| limit(p, false, 11, 103_33333333, 2 * SCALE_8, 0));
+    checked(&mut pair, |p| limit(p, true, 12, 99 * SCALE_8, 500 * SCALE_8, 100 * SCALE_8));
+    checked(&mut pair, |p| limit(p, true, 13, 98_50000000, 100 * SCALE_8, 0));
+    let (before, events) = checked(&mut pair, |p| {
+        p.market_buy(MarketOrder {
+            cid: [1].into(),
+            pair_id: p.pair_id,
+            existing_order_id: None,
+            owner: [14].into(),
+            coid: None,
+            size: MarketOrderSize::Quote(350 * SCALE_8),
+            public_amount: 0,
+            timestamp: 2,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::ImmediateOrCancel,
+        })
+            .expect("market buy");
+    });
+    (pair, before, events)
+}
+
+#[test]
+fn commands_keep_the_book_consistent() {
+    let _guard = lock_events();
+    let (mut pair, _, _) = traded_pair();
+
+    checked(&mut pair, |p| limit(p, false, 15, 98 * SCALE_8, 2 * SCALE_8, 0));
+    checked(&mut pair, |p| {
+        p.market_sell(MarketOrder {
+            cid: [1].into(),
+            pair_id: p.pair_id,
+            existing_order_id: None,
+            owner: [16].into(),
+            coid: None,
+            size: MarketOrderSize::Base(SCALE_8),
+            public_amount: 0,
+            timestamp: 3,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::ImmediateOrCancel,
+        })
+            .expect("market sell");
+    });
+    let resting = pair.orderbook.l3.orders().next().expect("resting order").clone();
+    checked(&mut pair, |p| {
+        p.cancel_order(vec![1], vec![1], resting.id, resting.owner.clone()).expect("cancel");
+    });
+    check_book(&pair.orderbook).expect("book is consistent");
+}
+
+#[test]
+fn detects_l2_levels_out_of_sync_with_l3() {
+    let _guard = lock_events();
+    let (pair, _, _) = traded_pair();
+    let bid_head = pair.orderbook.l2.bid_head().expect("bid head");
+    let ask_head = pair.orderbook.l2.ask_head().expect("ask head");
+
+    let mut book = pair.orderbook.clone();
+    let level = book.l2.current_bid_level(bid_head).expect("level");
+    book.l2.set_current_bid_level(bid_head, level - 1).expect("set level");
+    assert!(matches!(
+        check_book(&book),
+        Err(InvariantError::LevelMismatch { is_bid: true, price, .. }) if price == bid_head
+    ));
+
+    let mut book = pair.orderbook.clone();
+    book.l2.remove_price(false, ask_head).expect("remove price");
+    assert!(matches!(
+        check_book(&book),
+        Err(InvariantError::MissingLevel { is_bid: false, price, .. }) if price == ask_head
+    ));
+
+    let mut book = pair.orderbook.clone();
+    book.l2.insert_price(false, 200 * SCALE_8).expect("insert price");
+    assert_eq!(check_book(&book), Err(InvariantError::EmptyLevel { is_bid: false, price: 200 * SCALE_8 }));
+
+    let mut book = pair.orderbook.clone();
+    book.l2.bid_prices.remove(&bid_head);
+    assert_eq!(check_book(&book), Err(InvariantError::PriceListBroken { is_bid: true, price: bid_head }));
+
+    let mut book = pair.orderbook.clone();
+    book.l2.current_ask_level_map.insert(ask_head + 1, 0);
+    assert_eq!(check_book(&book), Err(InvariantError::PriceListBroken { is_bid: false, price: ask_head + 1 }));
+}
+
+#[test]
+fn detects_broken_l3_order_lists() {
+    let _guard = lock_events();
+    let (pair, _, _) = traded_pair();
+    let bid_head = pair.orderbook.l2.bid_head().expect("bid head");
+    let order_id = pair.orderbook.l3.head(bid_head).expect("head order");
+
+    let mut book = pair.orderbook.clone();
+    book.l3.levels.remove(&bid_head);
+    assert_eq!(check_book(&book), Err(InvariantError::OrderUnreachable { order_id }));
+
+    let mut book = pair.orderbook.clone();
+    book.l3.get_order_mut(order_id).expect("order").price += 1;
+    assert_eq!(check_book(&book), Err(InvariantError::OrderListBroken { price: bid_head, order_id }));
+}
+
+#[test]
+fn detects_fills_that_do_not_conserve_value() {
+    let _guard = lock_events();
+    let (pair, before, events) = traded_pair();
+    assert!(events.iter().any(|e| matches!(e, SpotEvent::SpotOrderPartiallyFilled { .. })));
+    check_fills(&before, &pair.orderbook, &events).expect("fills conserve value");
+
+    // a maker paid more than the price of the base it sold
+    let mut tampered = events.clone();
+    for event in tampered.iter_mut() {
+        if let SpotEvent::SpotOrderFullyFilled { is_taker_event: false, quote_volume, .. } = event {
+            *quote_volume += 1;
+            break;
+        }
+    }
+    assert!(matches!(check_fills(&before, &pair.orderbook, &tampered), Err(InvariantError::FillValue { .. })));
+
+    // fees above the fill
+    let mut tampered = events.clone();
+    for event in tampered.iter_mut() {
+        if let SpotEvent::SpotOrderFullyFilled { base_volume, base_fee, .. } = event {
+            *base_fee = *base_volume + 1;
+            break;
+        }
+    }
+    assert!(matches!(check_fills(&before, &pair.orderbook, &tampered), Err(InvariantError::FeeExceedsFill { .. })));
+
+    // fees off the bps of the orders
+    let mut tampered = events.clone();
+    for event in tampered.iter_mut() {
+        if let SpotEvent::SpotOrderFullyFilled { quote_fee, .. } = event {
+            *quote_fee += 1;
+            break;
+        }
+    }
+    assert!(matches!(check_fills(&before, &pair.orderbook, &tampered), Err(InvariantError::FeeMismatch { .. })));
+
+    // a maker charged at other bps than it rests with
+    let mut tampered = events.clone();
+    for event in tampered.iter_mut() {
+        if let SpotEvent::SpotOrderFullyFilled { maker_fee_bps, .. } = event {
+            *maker_fee_bps += 1;
+            break;
+        }
+    }
+    assert!(matches!(
+        check_fills(&before, &pair.orderbook, &tampered),
+        Err(InvariantError::FeeBpsMismatch { bps, order_bps, .. }) if bps == order_bps + 1
+    ));
+
+    // a partial fill that decreased the order by more than the fill
+    let mut tampered = events.clone();
+    for event in tampered.iter_mut() {
+        if let SpotEvent::SpotOrderPartiallyFilled { cqty, .. } = event {
+            *cqty -= 1;
+            break;
+        }
+    }
+    assert!(matches!(check_fills(&before, &pair.orderbook, &tampered), Err(InvariantError::FillDecrement { .. })));
+
+    // the book disagrees with the events
+    let mut after = pair.orderbook.clone();
+    let partial = events
+        .iter()
+        .find_map(|e| match e {
+            SpotEvent::SpotOrderPartiallyFilled { is_taker_event: false, maker_order_id, .. } => Some(maker_order_id.clone()),
+            _ => None,
+        })
+        .expect("partially filled maker");
+    let order_id = off_grid_primitives::spot::orders::OrderId::from_bytes(partial.try_into().expect("16 bytes"));
+    after.l3.get_order_mut(order_id).expect("order").cqty -= 1;
+    assert!(matches!(
+        check_fills(&before, &after, &events),
+        Err(InvariantError::StateMismatch { actual: Some(_), .. })
+    ));
+}
+
+#[cfg(feature = "invariants")]
+#[test]
+fn engine_rolls_back_commands_violating_invariants() {
+    use off_grid_primitives::spot::orderbook::OrderBookError;
+    use off_grid_primitives::spot::MatchingEngine;
+    use std::collections::HashMap;
+
+    let _guard = lock_events();
+    let (mut pair, _, _) = traded_pair();
+    // a level without orders the next command leaves in the book
+    pair.orderbook.l2.insert_price(false, 200 * SCALE_8).expect("insert price");
+    let pairs = HashMap::from([(pair.pair_id, pair)]);
+    let bytes = postcard::to_allocvec(&(pairs, 1u32)).expect("serialize engine");
+    let mut engine: MatchingEngine = postcard::from_bytes(&bytes).expect("deserialize engine");
+    let before = engine.clone();
+
+    let result = engine.limit_sell(LimitOrder {
+        cid: [1].into(),
+        pair_id: [1].into(),
+        existing_order_id: None,
+        owner: [17].into(),
+        coid: None,
+        price: 150 * SCALE_8,
+        amnt: SCALE_8,
+        iqty: 0,
+        timestamp: 4,
+        expires_at: i64::MAX,
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        time_in_force: TimeInForce::GoodTillCanceled,
+        post_only: false,
+    });
+    assert!(matches!(
+        result,
+        Err(OrderBookError::Invariant(InvariantError::EmptyLevel { is_bid: false, price })) if price == 200 * SCALE_8
+    ));
+    assert_eq!(engine, before);
+    assert!(event::drain_events().is_empty());
+}
@@ -1,4 +1,4 @@
-use off_grid_primitives::spot::market::L1;
+use off_grid_primitives::spot::market::{L1, DEFAULT_MARKET_PROTECTION_BPS};
 
 #[test]
 fn updates_fields() {
@@ -51,6 +51,21 @@ fn updates_slippage_limits() {
     assert_eq!(l1.market_sell_slippage_limit, Some(12));
 }
 
+#[test]
+fn make_prices_clamp_instead_of_overflowing() {
+    let mut l1 = L1::new();
+    l1.lmp = Some(u64::MAX);
+    assert_eq!(l1.det_market_buy_make_price(0, 0, 5), u64::MAX);
+    l1.market_buy_protection_bps = Some(500);
+    assert_eq!(l1.det_market_protection_price(true, 100), Some(u64::MAX));
+
+    l1.lmp = Some(100 * 1_0000_0000);
+    assert_eq!(l1.det_market_buy_make_price(0, 0, 5), 100_05000000);
+    assert_eq!(l1.det_market_sell_make_price(0, 0, 5), 99_95000000);
+    // a spread of 100% or more gives the minimum price instead of underflowing
+    assert_eq!(l1.det_market_sell_make_price(0, 0, 20000), 1);
+}
+
 #[test]
 fn serialize_and_deserialize_l1() {
     let mut l1 = L1::new();
@@ -90,6 +105,8 @@ fn serialize_and_deserialize_l1_with_none_values() {
         limit_sell_slippage_limit: None,
         market_buy_slippage_limit: None,
         market_sell_slippage_limit: None,
+        market_buy_protection_bps: None,
+        market_sell_protection_bps: None,
     };
 
     // Serialize to binary format
@@ -122,4 +139,6 @@ fn serialize_and_deserialize_l1_default() {
     assert_eq!(decoded.limit_sell_slippage_limit, Some(10000u64));
     assert_eq!(decoded.market_buy_slippage_limit, Some(10000u64));
     assert_eq!(decoded.market_sell_slippage_limit, Some(10000u64));
+    assert_eq!(decoded.market_buy_protection_bps, Some(DEFAULT_MARKET_PROTECTION_BPS));
+    assert_eq!(decoded.market_sell_protection_bps, Some(DEFAULT_MARKET_PROTECTION_BPS));
 }
@@ -1,12 +1,19 @@
 use off_grid_primitives::spot::prices::{L2, PriceNode, Level};
+use off_grid_primitives::spot::snapshot;
 use std::collections::BTreeMap;
 
+/// Price list of a side as a map of price -> neighbours
+fn price_nodes(l2: &L2, is_bid: bool) -> BTreeMap<u64, PriceNode> {
+    let prices = if is_bid { l2.collect_bid_prices() } else { l2.collect_ask_prices() };
+    prices.into_iter().map(|price| (price, l2.price_node(is_bid, price).expect("listed price"))).collect()
+}
+
 // price linked list tests
 #[test]
 fn insert_bid_price() {
     let mut l2 = L2::new();
     l2.insert_price(true, 100).expect("insert bid price 100");
-    assert_eq!(l2.bid_price_head, Some(100));
+    assert_eq!(l2.bid_head(), Some(100));
 }
 
 // inserting bid price with nothing places the price at bid head
@@ -14,7 +21,7 @@ fn insert_bid_price() {
 fn insert_bid_price_with_nothing() {
     let mut l2 = L2::new();
     l2.insert_price(true, 100).expect("insert bid price 100");
-    assert_eq!(l2.bid_price_head, Some(100));
+    assert_eq!(l2.bid_head(), Some(100));
 }
 
 // inserting bid price with something places the price at descending order
@@ -24,11 +31,11 @@ fn insert_bid_price_in_descending_order() {
     l2.insert_price(true, 100).expect("insert bid price 100");
     l2.insert_price(true, 90).expect("insert bid price 90");
     // check if the bid price is in descending order
-    assert_eq!(l2.bid_price_nodes, BTreeMap::from([(100, PriceNode { prev: None, next: Some(90) }), (90, PriceNode { prev: Some(100), next: None })]));
+    assert_eq!(price_nodes(&l2, true), BTreeMap::from([(100, PriceNode { prev: None, next: Some(90) }), (90, PriceNode { prev: Some(100), next: None })]));
     // check the bid price head
-    assert_eq!(l2.bid_price_head, Some(100));
+    assert_eq!(l2.bid_head(), Some(100));
     // check the bid price tail
-    assert_eq!(l2.bid_price_tail, Some(90));
+    assert_eq!(l2.bid_tail(), Some(90));
 }
 
 // inserting bid price with something places the price at descending order
@@ -40,15 +47,15 @@ fn insert_bid_price_in_middle_of_list() {
     l2.insert_price(true, 80).expect("insert bid price 80");
     l2.insert_price(true, 90).expect("insert bid price 90");
     // check if the bid price is in descending order
-    assert_eq!(l2.bid_price_nodes, BTreeMap::from([
+    assert_eq!(price_nodes(&l2, true), BTreeMap::from([
         (100, PriceNode { prev: None, next: Some(90) }), 
         (90, PriceNode { prev: Some(100), next: Some(80) }),
         (80, PriceNode { prev: Some(90), next: None })
     ]));
     // check the bid price head
-    assert_eq!(l2.bid_price_head, Some(100));
+    assert_eq!(l2.bid_head(), Some(100));
     // check the bid price tail
-    assert_eq!(l2.bid_price_tail, Some(80));
+    assert_eq!(l2.bid_tail(), Some(80));
 }
 
 // ask price linked list tests
@@ -56,14 +63,14 @@ fn insert_bid_price_in_middle_of_list() {
 fn insert_ask_price() {
     let mut l2 = L2::new();
     l2.insert_price(false, 100).expect("insert ask price 100");
-    assert_eq!(l2.ask_price_head, Some(100));
+    assert_eq!(l2.ask_head(), Some(100));
 }
 
 #[test]
 fn insert_ask_price_with_nothing() {
     let mut l2 = L2::new();
     l2.insert_price(false, 100).expect("insert ask price 100");
-    assert_eq!(l2.ask_price_head, Some(100));
+    assert_eq!(l2.ask_head(), Some(100));
 }
 
 #[test]
@@ -71,14 +78,14 @@ fn insert_ask_price_in_ascending_order() {
     let mut l2 = L2::new();
     l2.insert_price(false, 100).expect("insert ask price 100");
     l2.insert_price(false, 110).expect("insert ask price 110");
-    assert_eq!(l2.ask_price_nodes, BTreeMap::from([
+    assert_eq!(price_nodes(&l2, false), BTreeMap::from([
         (100, PriceNode { prev: None, next: Some(110) }),
         (110, PriceNode { prev: Some(100), next: None })
     ]));
     // check the ask price head
-    assert_eq!(l2.ask_price_head, Some(100));
+    assert_eq!(l2.ask_head(), Some(100));
     // check the ask price tail
-    assert_eq!(l2.ask_price_tail, Some(110));
+    assert_eq!(l2.ask_tail(), Some(110));
 }
 
 
@@ -88,15 +95,15 @@ fn insert_ask_price_in_middle_of_list() {
     l2.insert_price(false, 100).expect("insert ask price 100");
     l2.insert_price(false, 80).expect("insert ask price 80");
     l2.insert_price(false, 90).expect("insert ask price 90");
-    assert_eq!(l2.ask_price_nodes, BTreeMap::from([
+    assert_eq!(price_nodes(&l2, false), BTreeMap::from([
         (80, PriceNode { prev: None, next: Some(90) }),
         (90, PriceNode { prev: Some(80), next: Some(100) }),
         (100, PriceNode { prev: Some(90), next: None })
     ]));
     // check the ask price head (lowest price for ask prices in ascending order)
-    assert_eq!(l2.ask_price_head, Some(80));
+    assert_eq!(l2.ask_head(), Some(80));
     // check the ask price tail (highest price for ask prices in ascending order)
-    assert_eq!(l2.ask_price_tail, Some(100));
+    assert_eq!(l2.ask_tail(), Some(100));
 }
 
 #[test]
@@ -107,8 +114,8 @@ fn clear_bid_head_clears_head_price() {
     l2.insert_price(true, 80).expect("insert bid price 80");
     
     // Verify initial state: head is 100, tail is 80
-    assert_eq!(l2.bid_price_head, Some(100));
-    assert_eq!(l2.bid_price_tail, Some(80));
+    assert_eq!(l2.bid_head(), Some(100));
+    assert_eq!(l2.bid_tail(), Some(80));
     
     // Clear the head (100), should move to next (90)
     let result = l2.clear_head(true);
@@ -116,8 +123,8 @@ fn clear_bid_head_clears_head_price() {
     assert_eq!(result.unwrap(), Some(90));
     
     // Verify head moved to 90, tail remains 80
-    assert_eq!(l2.bid_price_head, Some(90));
-    assert_eq!(l2.bid_price_tail, Some(80));
+    assert_eq!(l2.bid_head(), Some(90));
+    assert_eq!(l2.bid_tail(), Some(80));
 }
 
 #[test]
@@ -128,8 +135,8 @@ fn clear_ask_head_clears_head_price() {
     l2.insert_price(false, 100).expect("insert ask price 100");
     
     // Verify initial state: head is 80, tail is 100
-    assert_eq!(l2.ask_price_head, Some(80));
-    assert_eq!(l2.ask_price_tail, Some(100));
+    assert_eq!(l2.ask_head(), Some(80));
+    assert_eq!(l2.ask_tail(), Some(100));
     
     // Clear the head (80), should move to next (90)
     let result = l2.clear_head(false);
@@ -137,8 +144,8 @@ fn clear_ask_head_clears_head_price() {
     assert_eq!(result.unwrap(), Some(90));
     
     // Verify head moved to 90, tail remains 100
-    assert_eq!(l2.ask_price_head, Some(90));
-    assert_eq!(l2.ask_price_tail, Some(100));
+    assert_eq!(l2.ask_head(), Some(90));
+    assert_eq!(l2.ask_tail(), Some(100));
 }
 
 #[test]
@@ -147,8 +154,8 @@ fn clear_bid_head_clears_last_price() {
     l2.insert_price(true, 100).expect("insert bid price 100");
     
     // Verify initial state: head is 100, tail is 100 (same when only one price)
-    assert_eq!(l2.bid_price_head, Some(100));
-    assert_eq!(l2.bid_price_tail, Some(100));
+    assert_eq!(l2.bid_head(), Some(100));
+    assert_eq!(l2.bid_tail(), Some(100));
     
     // Clear the head (100), should become None since there's no next
     let result = l2.clear_head(true);
@@ -156,8 +163,8 @@ fn clear_bid_head_clears_last_price() {
     assert_eq!(result.unwrap(), None);
     
     // Verify both head and tail are now None
-    assert_eq!(l2.bid_price_head, None);
-    assert_eq!(l2.bid_price_tail, None);
+    assert_eq!(l2.bid_head(), None);
+    assert_eq!(l2.bid_tail(), None);
 }
 
 #[test]
@@ -166,8 +173,8 @@ fn clear_ask_head_clears_last_price() {
     l2.insert_price(false, 100).expect("insert ask price 100");
     
     // Verify initial state: head is 100, tail is 100 (same when only one price)
-    assert_eq!(l2.ask_price_head, Some(100));
-    assert_eq!(l2.ask_price_tail, Some(100));
+    assert_eq!(l2.ask_head(), Some(100));
+    assert_eq!(l2.ask_tail(), Some(100));
     
     // Clear the head (100), should become None since there's no next
     let result = l2.clear_head(false);
@@ -175,8 +182,8 @@ fn clear_ask_head_clears_last_price() {
     assert_eq!(result.unwrap(), None);
     
     // Verify both head and tail are now None
-    assert_eq!(l2.ask_price_head, None);
-    assert_eq!(l2.ask_price_tail, None);
+    assert_eq!(l2.ask_head(), None);
+    assert_eq!(l2.ask_tail(), None);
 }
 
 // remove price tests
@@ -191,17 +198,17 @@ fn remove_bid_price_head() {
 
     // Verify initial order: [100, 90, 80, 70]
     assert_eq!(l2.collect_bid_prices(), vec![100, 90, 80, 70]);
-    assert_eq!(l2.bid_price_head, Some(100));
-    assert_eq!(l2.bid_price_tail, Some(70));
+    assert_eq!(l2.bid_head(), Some(100));
+    assert_eq!(l2.bid_tail(), Some(70));
 
     // Remove head (100)
     l2.remove_price(true, 100).expect("remove bid price 100");
 
     // Verify order is maintained: [90, 80, 70]
     assert_eq!(l2.collect_bid_prices(), vec![90, 80, 70]);
-    assert_eq!(l2.bid_price_head, Some(90));
-    assert_eq!(l2.bid_price_tail, Some(70));
-    assert!(l2.bid_price_nodes.get(&100).is_none());
+    assert_eq!(l2.bid_head(), Some(90));
+    assert_eq!(l2.bid_tail(), Some(70));
+    assert!(l2.price_node(true, 100).is_none());
 }
 
 #[test]
@@ -218,9 +225,9 @@ fn remove_bid_price_tail() {
 
     // Verify order is maintained: [100, 90, 80]
     assert_eq!(l2.collect_bid_prices(), vec![100, 90, 80]);
-    assert_eq!(l2.bid_price_head, Some(100));
-    assert_eq!(l2.bid_price_tail, Some(80));
-    assert!(l2.bid_price_nodes.get(&70).is_none());
+    assert_eq!(l2.bid_head(), Some(100));
+    assert_eq!(l2.bid_tail(), Some(80));
+    assert!(l2.price_node(true, 70).is_none());
 }
 
 #[test]
@@ -237,14 +244,14 @@ fn remove_bid_price_middle() {
 
     // Verify order is maintained: [100, 80, 70]
     assert_eq!(l2.collect_bid_prices(), vec![100, 80, 70]);
-    assert_eq!(l2.bid_price_head, Some(100));
-    assert_eq!(l2.bid_price_tail, Some(70));
-    assert!(l2.bid_price_nodes.get(&90).is_none());
+    assert_eq!(l2.bid_head(), Some(100));
+    assert_eq!(l2.bid_tail(), Some(70));
+    assert!(l2.price_node(true, 90).is_none());
     
     // Verify links are correct
-    let node_100 = l2.bid_price_nodes.get(&100).unwrap();
+    let node_100 = l2.price_node(true, 100).unwrap();
     assert_eq!(node_100.next, Some(80));
-    let node_80 = l2.bid_price_nodes.get(&80).unwrap();
+    let node_80 = l2.price_node(true, 80).unwrap();
     assert_eq!(node_80.prev, Some(100));
     assert_eq!(node_80.next, Some(70));
 }
@@ -273,9 +280,9 @@ fn remove_bid_price_multiple() {
     assert_eq!(l2.collect_bid_prices(), vec![70]);
     
     l2.remove_price(true, 70).expect("remove bid price 70");
-    assert_eq!(l2.collect_bid_prices(), vec![]);
-    assert_eq!(l2.bid_price_head, None);
-    assert_eq!(l2.bid_price_tail, None);
+    assert_eq!(l2.collect_bid_prices(), Vec::<u64>::new());
+    assert_eq!(l2.bid_head(), None);
+    assert_eq!(l2.bid_tail(), None);
 }
 
 #[test]
@@ -302,17 +309,17 @@ fn remove_ask_price_head() {
 
     // Verify initial order: [70, 80, 90, 100]
     assert_eq!(l2.collect_ask_prices(), vec![70, 80, 90, 100]);
-    assert_eq!(l2.ask_price_head, Some(70));
-    assert_eq!(l2.ask_price_tail, Some(100));
+    assert_eq!(l2.ask_head(), Some(70));
+    assert_eq!(l2.ask_tail(), Some(100));
 
     // Remove head (70)
     l2.remove_price(false, 70).expect("remove ask price 70");
 
     // Verify order is maintained: [80, 90, 100]
     assert_eq!(l2.collect_ask_prices(), vec![80, 90, 100]);
-    assert_eq!(l2.ask_price_head, Some(80));
-    assert_eq!(l2.ask_price_tail, Some(100));
-    assert!(l2.ask_price_nodes.get(&70).is_none());
+    assert_eq!(l2.ask_head(), Some(80));
+    assert_eq!(l2.ask_tail(), Some(100));
+    assert!(l2.price_node(false, 70).is_none());
 }
 
 #[test]
@@ -329,9 +336,9 @@ fn remove_ask_price_tail() {
 
     // Verify order is maintained: [70, 80, 90]
     assert_eq!(l2.collect_ask_prices(), vec![70, 80, 90]);
-    assert_eq!(l2.ask_price_head, Some(70));
-    assert_eq!(l2.ask_price_tail, Some(90));
-    assert!(l2.ask_price_nodes.get(&100).is_none());
+    assert_eq!(l2.ask_head(), Some(70));
+    assert_eq!(l2.ask_tail(), Some(90));
+    assert!(l2.price_node(false, 100).is_none());
 }
 
 #[test]
@@ -348,14 +355,14 @@ fn remove_ask_price_middle() {
 
     // Verify order is maintained: [70, 90, 100]
     assert_eq!(l2.collect_ask_prices(), vec![70, 90, 100]);
-    assert_eq!(l2.ask_price_head, Some(70));
-    assert_eq!(l2.ask_price_tail, Some(100));
-    assert!(l2.ask_price_nodes.get(&80).is_none());
+    assert_eq!(l2.ask_head(), Some(70));
+    assert_eq!(l2.ask_tail(), Some(100));
+    assert!(l2.price_node(false, 80).is_none());
     
     // Verify links are correct
-    let node_70 = l2.ask_price_nodes.get(&70).unwrap();
+    let node_70 = l2.price_node(false, 70).unwrap();
     assert_eq!(node_70.next, Some(90));
-    let node_90 = l2.ask_price_nodes.get(&90).unwrap();
+    let node_90 = l2.price_node(false, 90).unwrap();
     assert_eq!(node_90.prev, Some(70));
     assert_eq!(node_90.next, Some(100));
 }
@@ -384,9 +391,9 @@ fn remove_ask_price_multiple() {
     assert_eq!(l2.collect_ask_prices(), vec![90]);
     
     l2.remove_price(false, 90).expect("remove ask price 90");
-    assert_eq!(l2.collect_ask_prices(), vec![]);
-    assert_eq!(l2.ask_price_head, None);
-    assert_eq!(l2.ask_price_tail, None);
+    assert_eq!(l2.collect_ask_prices(), Vec::<u64>::new());
+    assert_eq!(l2.ask_head(), None);
+    assert_eq!(l2.ask_tail(), None);
 }
 
 #[test]
@@ -409,16 +416,16 @@ fn remove_single_price() {
     // Test bid: single price
     l2.insert_price(true, 100).expect("insert bid price 100");
     l2.remove_price(true, 100).expect("remove bid price 100");
-    assert_eq!(l2.collect_bid_prices(), vec![]);
-    assert_eq!(l2.bid_price_head, None);
-    assert_eq!(l2.bid_price_tail, None);
+    assert_eq!(l2.collect_bid_prices(), Vec::<u64>::new());
+    assert_eq!(l2.bid_head(), None);
+    assert_eq!(l2.bid_tail(), None);
     
     // Test ask: single price
     l2.insert_price(false, 100).expect("insert ask price 100");
     l2.remove_price(false, 100).expect("remove ask price 100");
-    assert_eq!(l2.collect_ask_prices(), vec![]);
-    assert_eq!(l2.ask_price_head, None);
-    assert_eq!(l2.ask_price_tail, None);
+    assert_eq!(l2.collect_ask_prices(), Vec::<u64>::new());
+    assert_eq!(l2.ask_head(), None);
+    assert_eq!(l2.ask_tail(), None);
 }
 
 #[test]
@@ -742,3 +749,159 @@ fn get_snapshot_formatted_strings_edge_cases() {
     assert_eq!(snapshot[1], vec!["0.00000001".to_string(), "0.00000001".to_string(), "0.00000001".to_string()]);
     assert_eq!(snapshot[2], vec!["10.00000000".to_string(), "5.00000000".to_string(), "5.00000000".to_string()]);
 }
+
+// scaled level tests
+#[test]
+fn scaled_levels_bucket_bids_down_and_asks_up() {
+    let mut l2 = L2::new();
+    let scale = 100_000_000; // 1.00000000 in 8 decimals
+    l2.set_scales(&[scale]);
+
+    for (price, qty) in [(99_500_000, 10), (99_100_000, 20), (98_000_000, 5)] {
+        l2.insert_price(true, price).expect("insert bid price");
+        l2.set_public_bid_level(price, qty).expect("set public bid level");
+        l2.set_current_bid_level(price, qty * 2).expect("set current bid level");
+    }
+    for (price, qty) in [(100_200_000, 7), (100_900_000, 3), (102_000_000, 1)] {
+        l2.insert_price(false, price).expect("insert ask price");
+        l2.set_public_ask_level(price, qty).expect("set public ask level");
+        l2.set_current_ask_level(price, qty).expect("set current ask level");
+    }
+
+    // 0.995, 0.991 and 0.98 are all rounded down to 0
+    assert_eq!(
+        l2.get_snapshot_raw(true, scale, 10).expect("bid snapshot"),
+        vec![vec![0, 35, 70]]
+    );
+    // 1.002 and 1.009 are rounded up to 2, 1.02 as well
+    assert_eq!(
+        l2.get_snapshot_raw(false, scale, 10).expect("ask snapshot"),
+        vec![vec![200_000_000, 11, 11]]
+    );
+
+    l2.set_scales(&[1_000_000, scale]);
+    assert_eq!(
+        l2.scale_bid_levels(1_000_000, 10),
+        vec![
+            Level { price: 99_000_000, pqty: 30, cqty: 60 },
+            Level { price: 98_000_000, pqty: 5, cqty: 10 },
+        ]
+    );
+    assert_eq!(
+        l2.scale_ask_levels(1_000_000, 10),
+        vec![
+            Level { price: 101_000_000, pqty: 10, cqty: 10 },
+            Level { price: 102_000_000, pqty: 1, cqty: 1 },
+        ]
+    );
+}
+
+#[test]
+fn scaled_levels_drop_empty_buckets_on_removal() {
+    let mut l2 = L2::new();
+    let scale = 1_000_000; // 0.01000000 in 8 decimals
+    l2.set_scales(&[scale]);
+
+    l2.insert_price(true, 99_500_000).expect("insert bid price");
+    l2.set_public_bid_level(99_500_000, 10).expect("set public bid level");
+    l2.set_current_bid_level(99_500_000, 10).expect("set current bid level");
+    l2.insert_price(true, 98_100_000).expect("insert bid price");
+    l2.set_public_bid_level(98_100_000, 20).expect("set public bid level");
+    l2.set_current_bid_level(98_100_000, 20).expect("set current bid level");
+
+    l2.set_public_bid_level(99_500_000, 4).expect("decrease public bid level");
+    l2.set_current_bid_level(99_500_000, 4).expect("decrease current bid level");
+    assert_eq!(
+        l2.scale_bid_levels(scale, 10),
+        vec![
+            Level { price: 99_000_000, pqty: 4, cqty: 4 },
+            Level { price: 98_000_000, pqty: 20, cqty: 20 },
+        ]
+    );
+
+    l2.remove_price(true, 99_500_000).expect("remove bid price");
+    assert_eq!(
+        l2.scale_bid_levels(scale, 10),
+        vec![Level { price: 98_000_000, pqty: 20, cqty: 20 }]
+    );
+
+    // incremental maintenance matches a full rebuild
+    let incremental = l2.bid_level_list.clone();
+    l2.rebuild_scaled_levels();
+    assert_eq!(l2.bid_level_list, incremental);
+}
+
+/// `L2` as it was serialized with linked price lists in 0.1.0
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct LinkedL2 {
+    bid_price_head: Option<u64>,
+    ask_price_head: Option<u64>,
+    bid_price_tail: Option<u64>,
+    ask_price_tail: Option<u64>,
+    bid_price_nodes: BTreeMap<u64, PriceNode>,
+    ask_price_nodes: BTreeMap<u64, PriceNode>,
+    public_bid_level_map: BTreeMap<u64, u64>,
+    public_ask_level_map: BTreeMap<u64, u64>,
+    current_bid_level_map: BTreeMap<u64, u64>,
+    current_ask_level_map: BTreeMap<u64, u64>,
+    bid_level_list: BTreeMap<u64, Vec<Level>>,
+    ask_level_list: BTreeMap<u64, Vec<Level>>,
+}
+
+#[test]
+fn linked_price_list_snapshot_migrates_to_price_sets() {
+    let linked = LinkedL2 {
+        bid_price_head: Some(100),
+        ask_price_head: Some(110),
+        bid_price_tail: Some(80),
+        ask_price_tail: Some(120),
+        bid_price_nodes: BTreeMap::from([
+            (100, PriceNode { prev: None, next: Some(90) }),
+            (90, PriceNode { prev: Some(100), next: Some(80) }),
+            (80, PriceNode { prev: Some(90), next: None }),
+        ]),
+        ask_price_nodes: BTreeMap::from([
+            (110, PriceNode { prev: None, next: Some(120) }),
+            (120, PriceNode { prev: Some(110), next: None }),
+        ]),
+        public_bid_level_map: BTreeMap::from([(100, 1), (90, 2), (80, 3)]),
+        public_ask_level_map: BTreeMap::from([(110, 4), (120, 5)]),
+        current_bid_level_map: BTreeMap::from([(100, 1), (90, 2), (80, 3)]),
+        current_ask_level_map: BTreeMap::from([(110, 4), (120, 5)]),
+        bid_level_list: BTreeMap::new(),
+        ask_level_list: BTreeMap::new(),
+    };
+    let encoded = postcard::to_allocvec(&linked).expect("serialize linked L2");
+
+    let l2: L2 = snapshot::decode(&encoded).expect("decode linked L2");
+    assert_eq!(l2.collect_bid_prices(), vec![100, 90, 80]);
+    assert_eq!(l2.collect_ask_prices(), vec![110, 120]);
+    assert_eq!((l2.bid_head(), l2.bid_tail()), (Some(100), Some(80)));
+    assert_eq!((l2.ask_head(), l2.ask_tail()), (Some(110), Some(120)));
+    assert_eq!(l2.public_bid_level(90), Some(2));
+    assert_eq!(l2.current_ask_level(120), Some(5));
+
+    assert!(l2.scales.is_empty());
+
+    // written in the same layout followed by the empty scaled depth state
+    let written = postcard::to_allocvec(&l2).expect("serialize L2");
+    assert_eq!(written, [encoded.as_slice(), &[0, 0, 0]].concat());
+    let decoded: LinkedL2 = postcard::from_bytes(&written).expect("deserialize as linked L2");
+    assert_eq!(decoded, linked);
+}
+
+#[test]
+fn next_price_walks_the_price_sets() {
+    let mut l2 = L2::new();
+    for price in [90, 110, 100] {
+        l2.insert_price(true, price).expect("insert bid price");
+        l2.insert_price(false, price).expect("insert ask price");
+    }
+    assert_eq!(l2.next_price(true, 110), Some(100));
+    assert_eq!(l2.next_price(true, 90), None);
+    assert_eq!(l2.next_price(false, 90), Some(100));
+    assert_eq!(l2.next_price(false, 110), None);
+    // prices not in the list have no next price
+    assert_eq!(l2.next_price(true, 95), None);
+    assert_eq!(l2.price_node(false, 95), None);
+}
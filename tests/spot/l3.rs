@@ -1,6 +1,12 @@
+use off_grid_primitives::spot::ids::AccountId;
 use off_grid_primitives::spot::orders::{L3, L3Error, Node, Order, OrderId};
 use std::collections::HashMap;
 
+/// Links of every order in the storage by order id
+fn order_nodes(storage: &L3) -> HashMap<OrderId, Node> {
+    storage.orders().map(|order| (order.id, storage.node(order.id).expect("stored order"))).collect()
+}
+
 fn setup_orders() -> L3 {
     let mut storage = L3::new();
     let _id1 = storage
@@ -376,9 +382,9 @@ fn insert_id_creates_single_order_node() {
     
     // check if the order node is created correctly
     let expected_nodes = HashMap::from([(id, Node { prev: None, next: None })]);
-    assert_eq!(storage.order_nodes, expected_nodes);
-    assert_eq!(storage.price_head.get(&100), Some(&id));
-    assert_eq!(storage.price_tail.get(&100), Some(&id));
+    assert_eq!(order_nodes(&storage), expected_nodes);
+    assert_eq!(storage.head(100), Some(id));
+    assert_eq!(storage.tail(100), Some(id));
 }
 
 #[test]
@@ -405,9 +411,9 @@ fn insert_id_creates_fifo_linked_list() {
         (id2, Node { prev: Some(id1), next: Some(id3) }),
         (id3, Node { prev: Some(id2), next: None }),
     ]);
-    assert_eq!(storage.order_nodes, expected_nodes);
-    assert_eq!(storage.price_head.get(&100), Some(&id1));
-    assert_eq!(storage.price_tail.get(&100), Some(&id3));
+    assert_eq!(order_nodes(&storage), expected_nodes);
+    assert_eq!(storage.head(100), Some(id1));
+    assert_eq!(storage.tail(100), Some(id3));
 }
 
 #[test]
@@ -415,26 +421,30 @@ fn delete_order_updates_linked_list_in_middle() {
     let (mut storage, ids) = setup_orders_with_ids();
     // Before deletion: 1 -> 2 -> 3
     // After deleting 2: 1 -> 3
-    // Note: node 2 is removed from linked list but may still exist in order_nodes
     let result = storage.delete_order(ids[1]);
     assert!(result.is_ok());
     
     // Check that the linked list is correct
-    assert_eq!(storage.price_head.get(&100), Some(&ids[0]));
-    assert_eq!(storage.price_tail.get(&100), Some(&ids[2]));
-    // Check that node 1 points to node 3
+    assert_eq!(storage.head(100), Some(ids[0]));
+    assert_eq!(storage.tail(100), Some(ids[2]));
+    // Check that node 1 points to node 3 and node 3 back to node 1
     assert_eq!(
-        storage.order_nodes.get(&ids[0]),
-        Some(&Node {
+        storage.node(ids[0]),
+        Some(Node {
             prev: None,
             next: Some(ids[2])
         })
     );
-    // Note: node 3's prev may not be updated correctly by delete_order when deleting in the middle
-    // This is a known issue - the next node's prev pointer should be updated to point to the prev node
-    // For now, we just verify that the forward link (1 -> 3) is correct
-    // Node 2 should be removed from orders but may still be in order_nodes
-    assert!(!storage.orders.contains_key(&ids[1]));
+    assert_eq!(
+        storage.node(ids[2]),
+        Some(Node {
+            prev: Some(ids[0]),
+            next: None
+        })
+    );
+    // Node 2 should be removed from both orders and order_nodes
+    assert!(storage.node(ids[1]).is_none());
+    assert!(!storage.contains(ids[1]));
 }
 
 #[test]
@@ -447,25 +457,25 @@ fn delete_order_updates_linked_list_at_end() {
     assert!(result.is_ok());
     
     // Check that the linked list is correct
-    assert_eq!(storage.price_head.get(&100), Some(&ids[0]));
-    assert_eq!(storage.price_tail.get(&100), Some(&ids[1]));
+    assert_eq!(storage.head(100), Some(ids[0]));
+    assert_eq!(storage.tail(100), Some(ids[1]));
     // Check that node 1 and 2 are correctly linked
     assert_eq!(
-        storage.order_nodes.get(&ids[0]),
-        Some(&Node {
+        storage.node(ids[0]),
+        Some(Node {
             prev: None,
             next: Some(ids[1])
         })
     );
     assert_eq!(
-        storage.order_nodes.get(&ids[1]),
-        Some(&Node {
+        storage.node(ids[1]),
+        Some(Node {
             prev: Some(ids[0]),
             next: None
         })
     );
     // Node 3 should be removed from orders but may still be in order_nodes
-    assert!(!storage.orders.contains_key(&ids[2]));
+    assert!(!storage.contains(ids[2]));
 }
 
 #[test]
@@ -478,25 +488,25 @@ fn delete_order_updates_linked_list_at_head() {
     assert!(result.is_ok());
     
     // Check that the linked list is correct
-    assert_eq!(storage.price_head.get(&100), Some(&ids[1]));
-    assert_eq!(storage.price_tail.get(&100), Some(&ids[2]));
+    assert_eq!(storage.head(100), Some(ids[1]));
+    assert_eq!(storage.tail(100), Some(ids[2]));
     // Check that node 2 and 3 are correctly linked
     assert_eq!(
-        storage.order_nodes.get(&ids[1]),
-        Some(&Node {
+        storage.node(ids[1]),
+        Some(Node {
             prev: None,
             next: Some(ids[2])
         })
     );
     assert_eq!(
-        storage.order_nodes.get(&ids[2]),
-        Some(&Node {
+        storage.node(ids[2]),
+        Some(Node {
             prev: Some(ids[1]),
             next: None
         })
     );
     // Node 1 should be removed from orders but may still be in order_nodes
-    assert!(!storage.orders.contains_key(&ids[0]));
+    assert!(!storage.contains(ids[0]));
 }
 
 #[test]
@@ -504,7 +514,6 @@ fn pop_front_updates_linked_list() {
     let (mut storage, ids) = setup_orders_with_ids();
     // Before pop: 1 -> 2 -> 3
     // After pop: 2 -> 3
-    // Note: node 1 is removed from linked list but may still exist in order_nodes
     let result = storage.pop_front(100);
     assert!(result.is_ok());
     let (order, emptied) = result.unwrap();
@@ -512,25 +521,26 @@ fn pop_front_updates_linked_list() {
     assert!(!emptied);
     
     // Check that the linked list is correct
-    assert_eq!(storage.price_head.get(&100), Some(&ids[1]));
-    assert_eq!(storage.price_tail.get(&100), Some(&ids[2]));
+    assert_eq!(storage.head(100), Some(ids[1]));
+    assert_eq!(storage.tail(100), Some(ids[2]));
     // Check that node 2 and 3 are correctly linked
     assert_eq!(
-        storage.order_nodes.get(&ids[1]),
-        Some(&Node {
+        storage.node(ids[1]),
+        Some(Node {
             prev: None,
             next: Some(ids[2])
         })
     );
     assert_eq!(
-        storage.order_nodes.get(&ids[2]),
-        Some(&Node {
+        storage.node(ids[2]),
+        Some(Node {
             prev: Some(ids[1]),
             next: None
         })
     );
-    // Node 1 may still be in order_nodes (pop_front doesn't remove it)
-    // but it's no longer part of the linked list
+    // Node 1 is moved out of the storage
+    assert!(!storage.contains(ids[0]));
+    assert_eq!(storage.node(ids[0]), None);
 }
 
 #[test]
@@ -544,9 +554,146 @@ fn pop_front_removes_last_order_and_clears_price_level() {
     assert_eq!(order.unwrap().id, id);
     assert!(emptied);
     
-    // Node should still exist in order_nodes (not removed by pop_front)
-    // but price level should be empty
-    assert!(storage.order_nodes.contains_key(&id));
-    assert_eq!(storage.price_head.get(&100), None);
-    assert_eq!(storage.price_tail.get(&100), None);
+    // the order is moved out of the storage and the price level is empty
+    assert!(storage.node(id).is_none());
+    assert!(!storage.contains(id));
+    assert_eq!(storage.head(100), None);
+    assert_eq!(storage.tail(100), None);
+}
+
+#[test]
+fn indexes_orders_by_owner_until_deleted() {
+    let mut storage = L3::new();
+    let first = storage
+        .create_order("1", "alice", true, 100, 50, 0, 0, 10000, 1000)
+        .expect("create order 1")
+        .id;
+    let _other = storage
+        .create_order("1", "bob", false, 110, 75, 0, 0, 10000, 1000)
+        .expect("create order 2")
+        .id;
+    let second = storage
+        .create_order("1", "alice", false, 120, 20, 0, 0, 10000, 1000)
+        .expect("create order 3")
+        .id;
+
+    let mut alice_ids = storage.get_order_ids_by_owner(b"alice");
+    alice_ids.sort();
+    let mut expected = vec![first, second];
+    expected.sort();
+    assert_eq!(alice_ids, expected);
+
+    storage.delete_order(first).expect("delete order 1");
+    assert_eq!(storage.get_order_ids_by_owner(b"alice"), vec![second]);
+
+    storage.delete_order(second).expect("delete order 3");
+    assert!(storage.get_orders_by_owner(b"alice").is_empty());
+    let alice = AccountId::lookup(b"alice").expect("interned owner");
+    assert!(!storage.owner_orders.contains_key(&alice));
+}
+
+#[test]
+fn client_order_ids_are_unique_per_owner() {
+    let mut storage = L3::new();
+    let alice_order = storage
+        .create_order("1", "alice", true, 100, 50, 0, 0, 10000, 1000)
+        .expect("create alice order")
+        .id;
+    let alice_other = storage
+        .create_order("1", "alice", true, 100, 50, 0, 0, 10000, 1000)
+        .expect("create alice order")
+        .id;
+    let bob_order = storage
+        .create_order("1", "bob", true, 100, 50, 0, 0, 10000, 1000)
+        .expect("create bob order")
+        .id;
+
+    storage.set_client_order_id(alice_order, "my-ref").expect("set alice coid");
+    // the same client order id is free for another owner
+    storage.set_client_order_id(bob_order, "my-ref").expect("set bob coid");
+    assert_eq!(
+        storage.set_client_order_id(alice_other, "my-ref"),
+        Err(L3Error::DuplicateClientOrderId)
+    );
+
+    let found = storage
+        .get_order_by_client_order_id(b"alice", b"my-ref")
+        .expect("alice order by coid");
+    assert_eq!(found.id, alice_order);
+    assert_eq!(found.coid, Some(b"my-ref".into()));
+
+    // deleting the order releases its client order id
+    storage.delete_order(alice_order).expect("delete alice order");
+    assert_eq!(
+        storage.get_order_by_client_order_id(b"alice", b"my-ref"),
+        Err(L3Error::ClientOrderIdDoesNotExist)
+    );
+    storage.set_client_order_id(alice_other, "my-ref").expect("reuse released coid");
+}
+
+/// `L3` as it was serialized with orders and links in maps keyed by order id
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct LinkedL3 {
+    price_head: std::collections::BTreeMap<u64, OrderId>,
+    price_tail: std::collections::BTreeMap<u64, OrderId>,
+    order_nodes: HashMap<OrderId, Node>,
+    orders: HashMap<OrderId, Order>,
+    dust: u64,
+    dormant_order: Option<OrderId>,
+}
+
+#[test]
+fn snapshot_keeps_the_linked_layout() {
+    let (mut storage, ids) = setup_orders_with_ids();
+    storage.set_client_order_id(ids[1], b"b-1".to_vec()).expect("set client order id");
+    storage
+        .create_order("4", "alice", false, 120, 10, 0, 0, 10000, 1000)
+        .expect("create order 4");
+
+    let encoded = postcard::to_allocvec(&storage).expect("serialize storage");
+    let linked: LinkedL3 = postcard::from_bytes(&encoded).expect("deserialize as linked L3");
+    assert_eq!(linked.price_head.get(&100), Some(&ids[0]));
+    assert_eq!(linked.price_tail.get(&100), Some(&ids[2]));
+    assert_eq!(linked.order_nodes, order_nodes(&storage));
+    assert_eq!(linked.orders.len(), 4);
+    assert_eq!(linked.orders[&ids[1]].coid.as_deref(), Some(b"b-1".as_slice()));
+
+    // a snapshot of the linked layout migrates into the arena, rebuilding the owner and client order id indexes
+    let encoded = postcard::to_allocvec(&linked).expect("serialize linked L3");
+    let migrated: L3 = postcard::from_bytes(&encoded).expect("deserialize linked L3");
+    assert_eq!(migrated, storage);
+    assert_eq!(migrated.get_order_ids(100, 3), ids);
+    assert_eq!(migrated.order_id_by_client_order_id(b"bob", b"b-1"), Some(ids[1]));
+    assert_eq!(migrated.get_orders_by_owner(b"alice").len(), 2);
+}
+
+#[test]
+fn deleted_order_slots_are_reused() {
+    let (mut storage, ids) = setup_orders_with_ids();
+    assert_eq!(storage.slots.len(), 3);
+
+    storage.delete_order(ids[1]).expect("delete order 2");
+    assert_eq!(storage.free.len(), 1);
+    let id = storage
+        .create_order("4", "dave", true, 100, 10, 0, 0, 10000, 1000)
+        .expect("create order 4")
+        .id;
+    assert_eq!(storage.slots.len(), 3);
+    assert!(storage.free.is_empty());
+    assert_eq!(storage.get_order_ids(100, 4), vec![ids[0], ids[2], id]);
+    assert_eq!(storage.get_order(id).map(|order| order.owner), Ok(AccountId::new(b"dave")));
+}
+
+#[test]
+fn popping_an_order_frees_its_slot() {
+    let (mut storage, ids) = setup_orders_with_ids();
+    let (order, emptied) = storage.pop_front(100).expect("pop front");
+    assert_eq!(order.map(|order| order.id), Some(ids[0]));
+    assert!(!emptied);
+
+    // the popped order is gone, deleting it fails and does not touch the level
+    assert_eq!(storage.delete_order(ids[0]), Err(L3Error::OrderDoesNotExist(ids[0])));
+    assert_eq!(storage.free.len(), 1);
+    assert_eq!(storage.get_order_ids(100, 3), vec![ids[1], ids[2]]);
+    assert_eq!(storage.head(100), Some(ids[1]));
 }
@@ -44,7 +44,7 @@ fn add_pair_client_emits_spot_pair_client_account_changed() {
     let _ = event::drain_events();
 
     let events = engine
-        .add_pair_client(vec![2], pair_id.clone(), vec![20], vec![21])
+        .add_pair_client(vec![2], pair_id.clone(), vec![20], vec![21], 2000)
         .expect("add_pair_client");
 
     assert!(
@@ -1,5 +1,8 @@
+use off_grid_primitives::spot::command::LimitOrder;
 use off_grid_primitives::spot::event::{self, SpotEvent};
 use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::orderbook::OrderBookError;
+use off_grid_primitives::spot::orders::{L3Error, OrderRef};
 use off_grid_primitives::spot::MatchingEngine;
 
 use super::EVENT_MUTEX;
@@ -22,38 +25,42 @@ fn cancel_order_emits_spot_order_cancelled() {
 
     // Place two bids at same price so cancelling one leaves the level (avoids PriceMissing after remove_price)
     engine
-        .limit_buy(
-            vec![1],
-            pair_id.clone(),
-            None,
-            vec![10],
-            100 * SCALE_8,
-            5 * SCALE_8,
-            0,
-            123,
-            i64::MAX,
-            5,
-            10,
-            TimeInForce::GoodTillCanceled,
-        )
+        .limit_buy(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair_id.as_slice().into(),
+            existing_order_id: None,
+            owner: [10].into(),
+            coid: None,
+            price: 100 * SCALE_8,
+            amnt: 5 * SCALE_8,
+            iqty: 0,
+            timestamp: 123,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
         .expect("limit_buy");
     let _ = event::drain_events();
 
     let events = engine
-        .limit_buy(
-            vec![2],
-            pair_id.clone(),
-            None,
-            vec![20],
-            100 * SCALE_8,
-            10 * SCALE_8,
-            0,
-            124,
-            i64::MAX,
-            5,
-            10,
-            TimeInForce::GoodTillCanceled,
-        )
+        .limit_buy(LimitOrder {
+            cid: [2].into(),
+            pair_id: pair_id.as_slice().into(),
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: None,
+            price: 100 * SCALE_8,
+            amnt: 10 * SCALE_8,
+            iqty: 0,
+            timestamp: 124,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
         .expect("limit_buy");
 
     // Use last matching SpotOrderPlaced so we get the order from this limit_buy, not a stale one.
@@ -77,7 +84,7 @@ fn cancel_order_emits_spot_order_cancelled() {
     let id: OrderId = Ulid::from_bytes(arr);
 
     let events = engine
-        .cancel_order(vec![2], pair_id.clone(), id, vec![20], true)
+        .cancel_order(vec![2], pair_id.clone(), id, vec![20])
         .expect("cancel_order");
 
     assert!(
@@ -86,3 +93,84 @@ fn cancel_order_emits_spot_order_cancelled() {
         events
     );
 }
+
+#[test]
+fn cancel_order_by_client_order_id() {
+    let _guard = lock_events();
+    let mut engine = MatchingEngine::new();
+    let _ = event::drain_events();
+
+    let pair_id = vec![1];
+    engine.add_pair(vec![1], vec![10], vec![11], pair_id.clone(), 1000);
+    let _ = event::drain_events();
+
+    engine
+        .limit_sell(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair_id.as_slice().into(),
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: Some(b"ask-1".into()),
+            price: 100 * SCALE_8,
+            amnt: 5 * SCALE_8,
+            iqty: 0,
+            timestamp: 123,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
+        .expect("limit_sell");
+    let _ = event::drain_events();
+
+    // the client order id is unique among the owner's resting orders
+    let duplicate = engine.limit_sell(LimitOrder {
+        cid: [1].into(),
+        pair_id: pair_id.as_slice().into(),
+        existing_order_id: None,
+        owner: [20].into(),
+        coid: Some(b"ask-1".into()),
+        price: 101 * SCALE_8,
+        amnt: 5 * SCALE_8,
+        iqty: 0,
+        timestamp: 124,
+        expires_at: i64::MAX,
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        time_in_force: TimeInForce::GoodTillCanceled,
+        post_only: false,
+    });
+    assert_eq!(
+        duplicate,
+        Err(OrderBookError::L3(L3Error::DuplicateClientOrderId))
+    );
+    let _ = event::drain_events();
+
+    let order = engine
+        .order_by_client_order_id(&pair_id, &[20], b"ask-1")
+        .expect("order by client order id")
+        .clone();
+    assert!(!order.is_bid);
+    assert_eq!(engine.orders_by_owner(&pair_id, &[20]), vec![&order]);
+
+    let events = engine
+        .cancel_order(
+            vec![1],
+            pair_id.clone(),
+            OrderRef::ClientOrderId(b"ask-1".to_vec()),
+            vec![20],
+        )
+        .expect("cancel_order");
+
+    let order_id_bytes = order.id.to_bytes().to_vec();
+    assert!(
+        events.iter().any(|e| matches!(e, SpotEvent::SpotOrderCancelled { order_id: o, is_bid: false, .. } if o == &order_id_bytes)),
+        "expected SpotOrderCancelled: {:?}",
+        events
+    );
+    assert!(engine.orders_by_owner(&pair_id, &[20]).is_empty());
+    assert!(engine
+        .order_by_client_order_id(&pair_id, &[20], b"ask-1")
+        .is_none());
+}
@@ -1,3 +1,4 @@
+use off_grid_primitives::spot::command::LimitOrder;
 use off_grid_primitives::spot::event::{self, SpotEvent};
 use off_grid_primitives::spot::time_in_force::TimeInForce;
 use off_grid_primitives::spot::MatchingEngine;
@@ -22,20 +23,22 @@ fn limit_buy_emits_spot_order_placed() {
     let _ = event::drain_events();
 
     let events = engine
-        .limit_buy(
-            vec![2],
-            pair_id.clone(),
-            None,
-            vec![20],
-            100 * SCALE_8,
-            10 * SCALE_8,
-            0,
-            124,
-            i64::MAX,
-            5,
-            10,
-            TimeInForce::GoodTillCanceled,
-        )
+        .limit_buy(LimitOrder {
+            cid: [2].into(),
+            pair_id: pair_id.as_slice().into(),
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: None,
+            price: 100 * SCALE_8,
+            amnt: 10 * SCALE_8,
+            iqty: 0,
+            timestamp: 124,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
         .expect("limit_buy");
 
     let has_order_placed = events.iter().any(|e| matches!(e, SpotEvent::SpotOrderPlaced { pair_id: p, is_bid: true, price: pr, amnt: a, .. } if p == &pair_id && *pr == 100 * SCALE_8 && *a == 10 * SCALE_8));
@@ -1,4 +1,6 @@
+use off_grid_primitives::spot::command::{LimitOrder, MarketOrder};
 use off_grid_primitives::spot::event::{self, SpotEvent};
+use off_grid_primitives::spot::market_order::MarketOrderSize;
 use off_grid_primitives::spot::time_in_force::TimeInForce;
 use off_grid_primitives::spot::MatchingEngine;
 
@@ -23,37 +25,40 @@ fn market_buy_emits_fill_and_market_price_events() {
 
     // Place an ask so market_buy can match
     engine
-        .limit_sell(
-            vec![1],
-            pair_id.clone(),
-            None,
-            vec![10],
-            90 * SCALE_8,
-            10 * SCALE_8,
-            0,
-            123,
-            i64::MAX,
-            5,
-            10,
-            TimeInForce::GoodTillCanceled,
-        )
+        .limit_sell(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair_id.as_slice().into(),
+            existing_order_id: None,
+            owner: [10].into(),
+            coid: None,
+            price: 90 * SCALE_8,
+            amnt: 10 * SCALE_8,
+            iqty: 0,
+            timestamp: 123,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
         .expect("limit_sell");
     let _ = event::drain_events();
 
     let events = engine
-        .market_buy(
-            vec![2],
-            pair_id.clone(),
-            None,
-            vec![20],
-            5 * SCALE_8,
-            0,
-            124,
-            i64::MAX,
-            5,
-            10,
-            TimeInForce::GoodTillCanceled,
-        )
+        .market_buy(MarketOrder {
+            cid: [2].into(),
+            pair_id: pair_id.as_slice().into(),
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: None,
+            size: MarketOrderSize::Base(5 * SCALE_8),
+            public_amount: 0,
+            timestamp: 124,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+        })
         .expect("market_buy");
 
     assert!(
@@ -0,0 +1,200 @@
+use off_grid_primitives::spot::command::LimitOrder;
+use off_grid_primitives::spot::event;
+use off_grid_primitives::spot::market_data::{L2Replica, MarketDataError};
+use off_grid_primitives::spot::prices::DEFAULT_SCALES;
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::MatchingEngine;
+
+use super::EVENT_MUTEX;
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+const SCALE_8: u64 = 1_0000_0000;
+
+fn setup_engine(pair_id: &[u8]) -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    engine.add_pair(vec![1], vec![10], vec![11], pair_id.to_vec(), 1000);
+    let _ = event::drain_events();
+    engine
+}
+
+/// Deterministic xorshift generator so failures are reproducible
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/// Places, matches or cancels a random order on the pair.
+fn random_step(engine: &mut MatchingEngine, pair_id: &[u8], rng: &mut Rng) {
+    let owner = vec![20 + rng.below(4) as u8];
+    // prices between 95.00 and 104.99 so every scale gets several buckets
+    let price = (9500 + rng.below(1000)) * 100_0000;
+    let qty = (1 + rng.below(5)) * SCALE_8;
+    let iqty = if rng.below(4) == 0 { qty / 2 } else { 0 };
+    let _ = match rng.below(10) {
+        0..=3 => engine.limit_buy(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair_id.into(),
+            existing_order_id: None,
+            owner: owner.into(),
+            coid: None,
+            price,
+            // bid amounts are in quote terms
+            amnt: qty / SCALE_8 * price,
+            iqty: iqty / SCALE_8 * price,
+            timestamp: 123,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        }),
+        4..=7 => engine.limit_sell(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair_id.into(),
+            existing_order_id: None,
+            owner: owner.into(),
+            coid: None,
+            price,
+            amnt: qty,
+            iqty,
+            timestamp: 123,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        }),
+        _ => {
+            let orders = engine.orders_by_owner(pair_id, &owner);
+            if orders.is_empty() {
+                return;
+            }
+            let id = orders[rng.below(orders.len() as u64) as usize].id;
+            engine.cancel_order(vec![1], pair_id.to_vec(), id, owner)
+        }
+    };
+    let _ = event::drain_events();
+}
+
+fn assert_replica_matches(engine: &MatchingEngine, pair_id: &[u8], replica: &L2Replica) {
+    let l2 = &engine.orderbook(pair_id).expect("orderbook").l2;
+    assert_eq!(&replica.bids(), l2.bid_levels(replica.scale), "bids at scale {}", replica.scale);
+    assert_eq!(&replica.asks(), l2.ask_levels(replica.scale), "asks at scale {}", replica.scale);
+}
+
+#[test]
+fn replicas_follow_random_order_flow() {
+    let _guard = lock_events();
+    let pair_id = vec![1];
+    let mut engine = setup_engine(&pair_id);
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+
+    let mut replicas: Vec<L2Replica> = DEFAULT_SCALES
+        .iter()
+        .map(|scale| {
+            let mut replica = L2Replica::new(pair_id.clone(), *scale);
+            let snapshot = engine.depth_snapshot(&pair_id, *scale).expect("snapshot");
+            replica.apply_snapshot(&snapshot).expect("apply snapshot");
+            replica
+        })
+        .collect();
+    let mut late: Option<L2Replica> = None;
+
+    for step in 0..400 {
+        random_step(&mut engine, &pair_id, &mut rng);
+        // a client joining mid-stream starts from a snapshot taken before the pending deltas are published
+        if step == 200 {
+            let mut replica = L2Replica::new(pair_id.clone(), DEFAULT_SCALES[1]);
+            let snapshot = engine.depth_snapshot(&pair_id, DEFAULT_SCALES[1]).expect("snapshot");
+            replica.apply_snapshot(&snapshot).expect("apply snapshot");
+            late = Some(replica);
+        }
+        let deltas = engine.depth_deltas(&pair_id).expect("deltas");
+        for delta in &deltas {
+            for replica in replicas.iter_mut() {
+                replica.apply_delta(delta).expect("apply delta");
+            }
+            if let Some(replica) = late.as_mut() {
+                replica.apply_delta(delta).expect("apply delta");
+            }
+        }
+        if step % 50 == 0 {
+            for replica in &replicas {
+                assert_replica_matches(&engine, &pair_id, replica);
+            }
+        }
+    }
+
+    for replica in &replicas {
+        assert!(replica.is_synced());
+        assert_replica_matches(&engine, &pair_id, replica);
+    }
+    let late = late.expect("late replica");
+    assert_replica_matches(&engine, &pair_id, &late);
+    assert_eq!(late.seq(), replicas[1].seq());
+    assert!(replicas[0].seq().unwrap() > 100, "order flow produced deltas");
+}
+
+#[test]
+fn replica_detects_gaps_and_resyncs_from_snapshot() {
+    let _guard = lock_events();
+    let pair_id = vec![1];
+    let mut engine = setup_engine(&pair_id);
+    let mut rng = Rng(42);
+    let scale = DEFAULT_SCALES[2];
+
+    assert_eq!(
+        engine.depth_snapshot(&pair_id, 7).unwrap_err(),
+        MarketDataError::ScaleNotConfigured(7)
+    );
+    assert_eq!(
+        engine.depth_snapshot(&[9], scale).unwrap_err(),
+        MarketDataError::PairNotFound
+    );
+
+    let mut replica = L2Replica::new(pair_id.clone(), scale);
+    let snapshot = engine.depth_snapshot(&pair_id, scale).expect("snapshot");
+    replica.apply_snapshot(&snapshot).expect("apply snapshot");
+
+    let mut deltas = Vec::new();
+    while deltas.len() < 3 {
+        random_step(&mut engine, &pair_id, &mut rng);
+        deltas.extend(engine.depth_deltas(&pair_id).expect("deltas"));
+    }
+
+    // skip the first delta
+    assert_eq!(
+        replica.apply_delta(&deltas[1]),
+        Err(MarketDataError::SequenceGap {
+            expected: snapshot.seq + 1,
+            got: deltas[1].seq,
+        })
+    );
+    assert!(!replica.is_synced());
+    assert_eq!(replica.apply_delta(&deltas[2]), Err(MarketDataError::NotSynced));
+
+    // resync and replay, deltas already in the snapshot are ignored
+    let snapshot = engine.depth_snapshot(&pair_id, scale).expect("snapshot");
+    replica.apply_snapshot(&snapshot).expect("apply snapshot");
+    for delta in &deltas {
+        replica.apply_delta(delta).expect("apply delta");
+    }
+    assert_eq!(replica.seq(), Some(snapshot.seq));
+    assert_replica_matches(&engine, &pair_id, &replica);
+
+    let mismatched = engine.depth_snapshot(&pair_id, DEFAULT_SCALES[0]).expect("snapshot");
+    assert_eq!(replica.apply_snapshot(&mismatched), Err(MarketDataError::SnapshotMismatch));
+}
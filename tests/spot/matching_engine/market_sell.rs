@@ -1,4 +1,6 @@
+use off_grid_primitives::spot::command::{LimitOrder, MarketOrder};
 use off_grid_primitives::spot::event::{self, SpotEvent};
+use off_grid_primitives::spot::market_order::MarketOrderSize;
 use off_grid_primitives::spot::time_in_force::TimeInForce;
 use off_grid_primitives::spot::MatchingEngine;
 use ulid::Ulid;
@@ -24,20 +26,22 @@ fn market_sell_emits_fill_and_market_price_events() {
 
     // Place a bid so market_sell can match; capture maker (bid) order id for orderbook assertion.
     let limit_buy_events = engine
-        .limit_buy(
-            vec![1],
-            pair_id.clone(),
-            None,
-            vec![10],
-            110 * SCALE_8,
-            10 * SCALE_8,
-            0,
-            123,
-            i64::MAX,
-            5,
-            10,
-            TimeInForce::GoodTillCanceled,
-        )
+        .limit_buy(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair_id.as_slice().into(),
+            existing_order_id: None,
+            owner: [10].into(),
+            coid: None,
+            price: 110 * SCALE_8,
+            amnt: 1100 * SCALE_8,
+            iqty: 0,
+            timestamp: 123,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
         .expect("limit_buy");
     // Use last matching SpotOrderPlaced (bid) so we get the order from this limit_buy, not a stale one.
     let maker_order_id = limit_buy_events
@@ -56,19 +60,20 @@ fn market_sell_emits_fill_and_market_price_events() {
     let _ = event::drain_events();
 
     let events = engine
-        .market_sell(
-            vec![2],
-            pair_id.clone(),
-            None,
-            vec![20],
-            5 * SCALE_8,
-            0,
-            124,
-            i64::MAX,
-            5,
-            10,
-            TimeInForce::GoodTillCanceled,
-        )
+        .market_sell(MarketOrder {
+            cid: [2].into(),
+            pair_id: pair_id.as_slice().into(),
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: None,
+            size: MarketOrderSize::Base(5 * SCALE_8),
+            public_amount: 0,
+            timestamp: 124,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+        })
         .expect("market_sell");
 
     assert!(
@@ -89,7 +94,7 @@ fn market_sell_emits_fill_and_market_price_events() {
     // After matching: when maker remains in L3 and L2 has bid head, assert order info and price level.
     let orderbook = engine.orderbook(&pair_id).expect("pair exists");
     let price = 110 * SCALE_8;
-    let remaining = 5 * SCALE_8;
+    let remaining = 550 * SCALE_8;
     let maker_still_in_l3 = orderbook.l3.get_order(maker_order_id).is_ok();
     if maker_still_in_l3 && orderbook.l2.bid_head().is_some() {
         event_assertion::assert_orderbook_has_bid_level(
@@ -4,5 +4,7 @@ pub mod add_pair;
 pub mod cancel_order;
 pub mod limit_buy;
 pub mod limit_sell;
+pub mod market_data;
 pub mod market_buy;
 pub mod market_sell;
+pub mod query;
@@ -0,0 +1,226 @@
+use off_grid_primitives::spot::command::LimitOrder;
+use off_grid_primitives::spot::event;
+use off_grid_primitives::spot::query::{DepthLevel, OrdersPage, QueryError};
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::MatchingEngine;
+
+use super::EVENT_MUTEX;
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+const SCALE_8: u64 = 1_0000_0000;
+
+fn setup_engine(pair_id: &[u8]) -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    engine.add_pair(vec![1], vec![10], vec![11], pair_id.to_vec(), 1000);
+    let _ = event::drain_events();
+    engine
+}
+
+fn place_bid(engine: &mut MatchingEngine, pair_id: &[u8], owner: u8, price: u64, amnt: u64, iqty: u64) {
+    engine
+        .limit_buy(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair_id.into(),
+            existing_order_id: None,
+            owner: [owner].into(),
+            coid: None,
+            price,
+            amnt,
+            iqty,
+            timestamp: 123,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
+        .expect("limit_buy");
+    let _ = event::drain_events();
+}
+
+fn place_ask(engine: &mut MatchingEngine, pair_id: &[u8], owner: u8, price: u64, amnt: u64, iqty: u64) {
+    engine
+        .limit_sell(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair_id.into(),
+            existing_order_id: None,
+            owner: [owner].into(),
+            coid: None,
+            price,
+            amnt,
+            iqty,
+            timestamp: 123,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
+        .expect("limit_sell");
+    let _ = event::drain_events();
+}
+
+#[test]
+fn open_orders_are_paginated_with_cursor() {
+    let _guard = lock_events();
+    let pair_id = vec![1];
+    let mut engine = setup_engine(&pair_id);
+
+    for i in 0..5 {
+        place_bid(&mut engine, &pair_id, 20, (90 - i) * SCALE_8, SCALE_8, 0);
+    }
+    place_bid(&mut engine, &pair_id, 30, 80 * SCALE_8, SCALE_8, 0);
+
+    let mut seen = Vec::new();
+    let mut cursor = None;
+    let mut pages = 0;
+    loop {
+        let page = engine
+            .open_orders(&pair_id, &[20], cursor, 2)
+            .expect("open orders");
+        assert!(page.orders.len() <= 2);
+        seen.extend(page.orders.iter().map(|o| o.id));
+        pages += 1;
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    assert_eq!(pages, 3);
+    assert_eq!(seen.len(), 5);
+    let mut sorted = seen.clone();
+    sorted.sort();
+    assert_eq!(seen, sorted, "pages are ordered by order id");
+    assert!(seen.iter().all(|id| engine.order(&pair_id, *id).expect("order").owner == vec![20]));
+
+    let empty = engine.open_orders(&pair_id, &[99], None, 10).expect("open orders");
+    assert_eq!(empty, OrdersPage::default());
+}
+
+#[test]
+fn depth_and_best_bid_ask_show_public_quantity() {
+    let _guard = lock_events();
+    let pair_id = vec![1];
+    let mut engine = setup_engine(&pair_id);
+
+    place_bid(&mut engine, &pair_id, 20, 99 * SCALE_8, 2 * SCALE_8, 0);
+    place_bid(&mut engine, &pair_id, 21, 98 * SCALE_8, 3 * SCALE_8, 0);
+    // iceberg ask hides 4 of 5
+    place_ask(&mut engine, &pair_id, 22, 101 * SCALE_8, 5 * SCALE_8, 4 * SCALE_8);
+    place_ask(&mut engine, &pair_id, 23, 102 * SCALE_8, SCALE_8, 0);
+
+    let depth = engine.depth(&pair_id, 1).expect("depth");
+    assert_eq!(depth.bids, vec![DepthLevel { price: 99 * SCALE_8, qty: 2 * SCALE_8 }]);
+    assert_eq!(depth.asks, vec![DepthLevel { price: 101 * SCALE_8, qty: SCALE_8 }]);
+
+    let depth = engine.depth(&pair_id, 10).expect("depth");
+    assert_eq!(depth.bids.len(), 2);
+    assert_eq!(depth.asks.len(), 2);
+    assert_eq!(depth.bids[1], DepthLevel { price: 98 * SCALE_8, qty: 3 * SCALE_8 });
+    assert_eq!(depth.asks[1], DepthLevel { price: 102 * SCALE_8, qty: SCALE_8 });
+
+    let top = engine.best_bid_ask(&pair_id).expect("best bid ask");
+    assert_eq!(top.bid, Some(depth.bids[0]));
+    assert_eq!(top.ask, Some(depth.asks[0]));
+    assert_eq!(engine.last_price(&pair_id), Ok(None));
+
+    // DTOs are serializable for the RPC layer
+    let bytes = postcard::to_allocvec(&depth).expect("serialize depth");
+    let decoded: off_grid_primitives::spot::query::Depth =
+        postcard::from_bytes(&bytes).expect("deserialize depth");
+    assert_eq!(decoded, depth);
+}
+
+#[test]
+fn queries_on_unknown_pair_or_order_fail() {
+    let _guard = lock_events();
+    let pair_id = vec![1];
+    let engine = setup_engine(&pair_id);
+
+    assert_eq!(engine.depth(&[9], 5), Err(QueryError::PairNotFound));
+    assert_eq!(engine.last_price(&[9]), Err(QueryError::PairNotFound));
+    let id = ulid::Ulid::new();
+    assert_eq!(engine.order(&pair_id, id), Err(QueryError::OrderNotFound(id)));
+}
+
+#[test]
+fn preview_leaves_the_engine_and_the_event_queue_untouched() {
+    use off_grid_primitives::spot::command::SpotCommand;
+    use off_grid_primitives::spot::event::SpotEvent;
+
+    let _guard = lock_events();
+    let pair_id = vec![1];
+    let mut engine = setup_engine(&pair_id);
+    place_bid(&mut engine, &pair_id, 20, 99 * SCALE_8, 990 * SCALE_8, 0);
+    let before = engine.clone();
+    let sell = SpotCommand::LimitSell(LimitOrder {
+        cid: [1].into(),
+        pair_id: pair_id.as_slice().into(),
+        existing_order_id: None,
+        owner: [21].into(),
+        coid: None,
+        price: 99 * SCALE_8,
+        amnt: 4 * SCALE_8,
+        iqty: 0,
+        timestamp: 124,
+        expires_at: i64::MAX,
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        time_in_force: TimeInForce::GoodTillCanceled,
+        post_only: false,
+    });
+
+    event::emit_event(SpotEvent::SpotPairAdded { cid: [1].into(), pair_id: [7].into(), timestamp: 0 });
+    let preview = engine.preview(sell.clone()).expect("preview");
+    assert_eq!(engine, before);
+    assert_eq!(event::drain_events().len(), 1, "the pending event is kept and the preview's events are not added");
+    // the same events, but for the id of the new order
+    let kinds = |events: &event::EventQueue| events.iter().map(std::mem::discriminant).collect::<Vec<_>>();
+    assert_eq!(kinds(&engine.execute(sell).expect("execute")), kinds(&preview));
+    assert!(engine.preview(SpotCommand::SetDust { pair_id: [9].into(), dust: 1 }).is_err());
+}
+
+#[test]
+fn post_only_orders_crossing_the_book_are_rejected() {
+    use off_grid_primitives::spot::command::SpotCommand;
+    use off_grid_primitives::spot::orderbook::OrderBookError;
+
+    let _guard = lock_events();
+    let pair_id = vec![1];
+    let mut engine = setup_engine(&pair_id);
+    place_bid(&mut engine, &pair_id, 20, 99 * SCALE_8, SCALE_8, 0);
+    let sell = |price| {
+        SpotCommand::LimitSell(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair_id.as_slice().into(),
+            existing_order_id: None,
+            owner: [21].into(),
+            coid: None,
+            price,
+            amnt: SCALE_8,
+            iqty: 0,
+            timestamp: 124,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: true,
+        })
+    };
+
+    let before = engine.clone();
+    let err = engine.execute(sell(99 * SCALE_8)).unwrap_err();
+    assert!(matches!(err, OrderBookError::PostOnlyWouldTake), "{err:?}");
+    assert_eq!(engine, before);
+    assert!(event::drain_events().is_empty());
+
+    engine.execute(sell(100 * SCALE_8)).expect("post-only order rests");
+    let _ = event::drain_events();
+    let top = engine.best_bid_ask(&pair_id).expect("best bid ask");
+    assert_eq!(top.bid, Some(DepthLevel { price: 99 * SCALE_8, qty: SCALE_8 }));
+    assert_eq!(top.ask, Some(DepthLevel { price: 100 * SCALE_8, qty: SCALE_8 }));
+}
@@ -1,4 +1,5 @@
 use off_grid_primitives::spot::event::{self, SpotEvent};
+use off_grid_primitives::spot::fixed_point::{Notional, Price, Quantity, Rounding};
 use off_grid_primitives::spot::orderbook::OrderBook;
 use super::EVENT_MUTEX;
 
@@ -42,12 +43,14 @@ fn assert_order_placed(
             quote_asset_id,
             order_id,
             maker_account_id,
+            coid,
             is_bid,
             price,
             amnt,
             iqty,
             pqty,
             cqty,
+            fee_bps: _,
             timestamp,
             expires_at,
         } => {
@@ -57,6 +60,7 @@ fn assert_order_placed(
             assert_eq!(base_asset_id.as_slice(), expected_base_asset_id.as_slice(), "base_asset_id");
             assert_eq!(quote_asset_id.as_slice(), expected_quote_asset_id.as_slice(), "quote_asset_id");
             assert_eq!(maker_account_id.as_slice(), expected_owner.as_slice(), "maker_account_id");
+            assert_eq!(*coid, None, "orders placed on the book directly have no client order id");
             assert_eq!(*is_bid, expected_is_bid, "is_bid");
             assert_eq!(*price, expected_price, "price");
             assert_eq!(*amnt, expected_amnt, "amnt");
@@ -143,15 +147,15 @@ fn assert_no_rebate_event(
 
 fn matching_amounts(orderbook: &OrderBook, taker: &off_grid_primitives::spot::orders::Order, maker: &off_grid_primitives::spot::orders::Order) -> (u64, u64) {
     let taker_converted_matching_cqty = if taker.is_bid {
-        taker
-            .cqty
-            .saturating_mul(SCALE_8)
-            .saturating_div(taker.price)
+        Price(taker.price)
+            .quantity(Notional(taker.cqty), Rounding::Floor)
+            .expect("base of the taker quote")
+            .0
     } else {
-        taker
-            .cqty
-            .saturating_mul(taker.price)
-            .saturating_div(SCALE_8)
+        Price(taker.price)
+            .notional(Quantity(taker.cqty), Rounding::Floor)
+            .expect("quote of the taker base")
+            .0
     };
 
     let matching_amount = if taker_converted_matching_cqty >= maker.cqty {
@@ -167,24 +171,25 @@ fn matching_amounts(orderbook: &OrderBook, taker: &off_grid_primitives::spot::or
     };
 
     let matching_base_amount = if taker.is_bid {
-        matching_amount
-            .saturating_mul(SCALE_8)
-            .saturating_div(taker.price)
+        Price(taker.price)
+            .quantity(Notional(matching_amount), Rounding::Floor)
+            .expect("base of the matching quote")
+            .0
     } else {
         matching_amount
     };
     let matching_quote_amount = if taker.is_bid {
         matching_amount
     } else {
-        matching_amount
-            .saturating_mul(taker.price)
-            .saturating_div(SCALE_8)
+        Price(taker.price)
+            .notional(Quantity(matching_amount), Rounding::Floor)
+            .expect("quote of the matching base")
+            .0
     };
 
     (matching_base_amount, matching_quote_amount)
 }
 
-
 #[test]
 fn place_ask_automatically_inserts_price_without_expiration() {
     let _guard = lock_events();
@@ -1060,7 +1065,7 @@ fn rebate_event_emitted_for_negative_maker_fee() {
         &quote_asset_id,
         maker_order.price,
         maker_order.is_bid,
-        &maker_order.owner,
+        maker_order.owner.as_slice(),
         true,
         maker_order.fee_bps,
         matching_base_amount,
@@ -1073,7 +1078,7 @@ fn rebate_event_emitted_for_negative_maker_fee() {
         &quote_asset_id,
         taker_order.price,
         taker_order.is_bid,
-        &taker_order.owner,
+        taker_order.owner.as_slice(),
         false,
     );
 }
@@ -1144,7 +1149,7 @@ fn rebate_event_emitted_for_negative_taker_fee() {
         &quote_asset_id,
         taker_order.price,
         taker_order.is_bid,
-        &taker_order.owner,
+        taker_order.owner.as_slice(),
         false,
         taker_order.fee_bps,
         matching_base_amount,
@@ -1157,7 +1162,7 @@ fn rebate_event_emitted_for_negative_taker_fee() {
         &quote_asset_id,
         maker_order.price,
         maker_order.is_bid,
-        &maker_order.owner,
+        maker_order.owner.as_slice(),
         true,
     );
 }
@@ -1228,7 +1233,7 @@ fn rebate_event_emitted_for_negative_maker_and_taker_fee() {
         &quote_asset_id,
         maker_order.price,
         maker_order.is_bid,
-        &maker_order.owner,
+        maker_order.owner.as_slice(),
         true,
         maker_order.fee_bps,
         matching_base_amount,
@@ -1241,7 +1246,7 @@ fn rebate_event_emitted_for_negative_maker_and_taker_fee() {
         &quote_asset_id,
         taker_order.price,
         taker_order.is_bid,
-        &taker_order.owner,
+        taker_order.owner.as_slice(),
         false,
         taker_order.fee_bps,
         matching_base_amount,
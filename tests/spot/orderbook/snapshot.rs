@@ -117,8 +117,8 @@ fn serialize_and_deserialize_orderbook_with_orders_without_expiration() {
     // Verify L2 price levels are preserved
     assert_eq!(decoded.l2.bid_head(), orderbook.l2.bid_head());
     assert_eq!(decoded.l2.ask_head(), orderbook.l2.ask_head());
-    assert_eq!(decoded.l2.bid_price_tail, orderbook.l2.bid_price_tail);
-    assert_eq!(decoded.l2.ask_price_tail, orderbook.l2.ask_price_tail);
+    assert_eq!(decoded.l2.bid_tail(), orderbook.l2.bid_tail());
+    assert_eq!(decoded.l2.ask_tail(), orderbook.l2.ask_tail());
     
     // Verify complete equality
     assert_eq!(decoded, orderbook);
@@ -232,13 +232,13 @@ fn serialize_and_deserialize_orderbook_after_execution_without_expiration() {
     // Configure fee recipients for all clients so fee emissions succeed
     orderbook
         .fee_recipients
-        .insert(bid_order.cid.clone(), b"bid_admin".to_vec());
+        .insert(bid_order.cid, b"bid_admin".into());
     orderbook
         .fee_recipients
-        .insert(ask_order.cid.clone(), b"ask_admin".to_vec());
+        .insert(ask_order.cid, b"ask_admin".into());
     orderbook
         .fee_recipients
-        .insert(taker_order.cid.clone(), b"taker_admin".to_vec());
+        .insert(taker_order.cid, b"taker_admin".into());
 
     // Execute a trade (decreases the ask order) – rely on events + book state
     orderbook
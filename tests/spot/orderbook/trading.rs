@@ -1,5 +1,6 @@
 use super::EVENT_MUTEX;
 use off_grid_primitives::spot::event::{self, SpotEvent};
+use off_grid_primitives::spot::fixed_point::{Notional, Price, Quantity, Rounding};
 use off_grid_primitives::spot::orderbook::OrderBook;
 use off_grid_primitives::spot::orderbook::OrderBookError;
 use off_grid_primitives::spot::orders::{Order, OrderId};
@@ -176,6 +177,7 @@ fn assert_order_expired(
         e,
         SpotEvent::SpotOrderExpired {
             cid,
+            pair_id: _,
             order_id,
             maker_account_id,
             is_bid,
@@ -218,6 +220,7 @@ fn assert_order_expired_without_timestamp(
         e,
         SpotEvent::SpotOrderExpired {
             cid,
+            pair_id: _,
             order_id,
             maker_account_id,
             is_bid,
@@ -244,15 +247,15 @@ fn assert_order_expired_without_timestamp(
 
 fn matching_amounts(orderbook: &OrderBook, taker: &Order, maker: &Order) -> (u64, u64, u64) {
     let taker_converted_matching_cqty = if taker.is_bid {
-        taker
-            .cqty
-            .saturating_mul(1_0000_0000)
-            .saturating_div(taker.price)
+        Price(taker.price)
+            .quantity(Notional(taker.cqty), Rounding::Floor)
+            .expect("base of the taker quote")
+            .0
     } else {
-        taker
-            .cqty
-            .saturating_mul(taker.price)
-            .saturating_div(1_0000_0000)
+        Price(taker.price)
+            .notional(Quantity(taker.cqty), Rounding::Floor)
+            .expect("quote of the taker base")
+            .0
     };
 
     let matching_amount = if taker_converted_matching_cqty >= maker.cqty {
@@ -268,18 +271,20 @@ fn matching_amounts(orderbook: &OrderBook, taker: &Order, maker: &Order) -> (u64
     };
 
     let matching_base_amount = if taker.is_bid {
-        matching_amount
-            .saturating_mul(1_0000_0000)
-            .saturating_div(taker.price)
+        Price(taker.price)
+            .quantity(Notional(matching_amount), Rounding::Floor)
+            .expect("base of the matching quote")
+            .0
     } else {
         matching_amount
     };
     let matching_quote_amount = if taker.is_bid {
         matching_amount
     } else {
-        matching_amount
-            .saturating_mul(taker.price)
-            .saturating_div(1_0000_0000)
+        Price(taker.price)
+            .notional(Quantity(matching_amount), Rounding::Floor)
+            .expect("quote of the matching base")
+            .0
     };
 
     (matching_amount, matching_base_amount, matching_quote_amount)
@@ -332,20 +337,20 @@ fn expired_order_on_pop_front_moves_to_next_price_level() {
 
     // check order is in price level
     assert_eq!(
-        orderbook.l3.price_head.get(&(110 * 1_0000_0000)),
-        Some(&expired_id)
+        orderbook.l3.head(110 * 1_0000_0000),
+        Some(expired_id)
     );
     assert_eq!(
-        orderbook.l3.price_tail.get(&(110 * 1_0000_0000)),
-        Some(&expired_id)
+        orderbook.l3.tail(110 * 1_0000_0000),
+        Some(expired_id)
     );
     assert_eq!(
-        orderbook.l3.price_head.get(&(100 * 1_0000_0000)),
-        Some(&active_id)
+        orderbook.l3.head(100 * 1_0000_0000),
+        Some(active_id)
     );
     assert_eq!(
-        orderbook.l3.price_tail.get(&(100 * 1_0000_0000)),
-        Some(&active_id)
+        orderbook.l3.tail(100 * 1_0000_0000),
+        Some(active_id)
     );
 
     // check public/current levels in l2
@@ -451,10 +456,10 @@ fn execute_trade_from_ask_order_to_bid_order_and_check_ask_price_level_without_e
     // Configure fee recipients so fee events can be emitted without panicking
     orderbook
         .fee_recipients
-        .insert(ask_order.cid.clone(), b"ask_admin".to_vec());
+        .insert(ask_order.cid, b"ask_admin".into());
     orderbook
         .fee_recipients
-        .insert(taker_order.cid.clone(), b"taker_admin".to_vec());
+        .insert(taker_order.cid, b"taker_admin".into());
 
     // Execute the trade
     orderbook
@@ -622,10 +627,10 @@ fn execute_trade_from_bid_order_to_ask_order_and_check_bid_price_level_without_e
     // Configure fee recipients for taker and maker so fee events can be emitted
     orderbook
         .fee_recipients
-        .insert(bid_order.cid.clone(), b"bid_admin".to_vec());
+        .insert(bid_order.cid, b"bid_admin".into());
     orderbook
         .fee_recipients
-        .insert(taker_order.cid.clone(), b"taker_admin".to_vec());
+        .insert(taker_order.cid, b"taker_admin".into());
 
     // Execute the trade via events (no OrderMatch return any more)
     orderbook
@@ -915,4 +920,46 @@ fn expired_order_on_pop_front_moves_to_next_price_level_when_the_best_price_is_e
         1000 * 1_0000_0000,
         0,
     );
-}
\ No newline at end of file
+}
+#[test]
+fn execute_fails_on_notional_overflow_instead_of_saturating() {
+    let _guard = lock_events();
+    let mut orderbook = OrderBook::new();
+    let price = 1_000_000 * 1_0000_0000;
+
+    let ask_order = orderbook
+        .place_ask(vec![1], vec![0], vec![0], vec![0], vec![10], price, u64::MAX / 2, 0, 1, i64::MAX, 0)
+        .expect("place ask order");
+    let taker_order = orderbook
+        .place_bid(vec![2], vec![0], vec![0], vec![0], vec![20], price, u64::MAX, 0, 2, i64::MAX, 0)
+        .expect("place taker bid");
+    let _ = event::drain_events();
+
+    let result = orderbook.execute(taker_order.clone(), ask_order.clone(), vec![0], vec![0], vec![0], 0);
+    assert!(matches!(result, Err(OrderBookError::FixedPoint(_))));
+    // nothing is matched
+    assert_eq!(orderbook.l3.get_order(ask_order.id).expect("ask").cqty, u64::MAX / 2);
+    assert_eq!(orderbook.l3.get_order(taker_order.id).expect("bid").cqty, u64::MAX);
+    assert!(event::drain_events().is_empty());
+}
+
+#[test]
+fn clear_empty_head_skips_levels_holding_only_the_other_side() {
+    let _guard = lock_events();
+    let mut orderbook = OrderBook::new();
+    let price = 100 * 1_0000_0000;
+
+    let bid_order = orderbook
+        .place_bid(vec![1], vec![0], vec![0], vec![0], vec![10], price, 100 * 1_0000_0000, 0, 1, i64::MAX, 0)
+        .expect("place bid order");
+    orderbook
+        .place_ask(vec![2], vec![0], vec![0], vec![0], vec![20], price, 1_0000_0000, 0, 2, i64::MAX, 0)
+        .expect("place ask order");
+    let _ = event::drain_events();
+
+    // the bid leaves the shared L3 level without its L2 level, which still holds the ask
+    orderbook.l3.delete_order(bid_order.id).expect("delete bid");
+    assert_eq!(orderbook.clear_empty_head(true), Err(OrderBookError::PriceIsZero));
+    assert_eq!(orderbook.l2.bid_head(), None);
+    assert_eq!(orderbook.clear_empty_head(false), Ok(price));
+}
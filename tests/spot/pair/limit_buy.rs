@@ -1,3 +1,4 @@
+use off_grid_primitives::spot::command::LimitOrder;
 use off_grid_primitives::spot::event;
 use off_grid_primitives::spot::time_in_force::TimeInForce;
 use off_grid_primitives::spot::Pair;
@@ -15,9 +16,9 @@ const SCALE_8: u64 = 1_0000_0000;
 fn limit_buy_moves_lmp_to_best_ask() {
     let _guard = lock_events();
     let mut pair = Pair::new();
-    pair.pair_id = vec![1];
-    pair.base_asset_id = vec![2];
-    pair.quote_asset_id = vec![3];
+    pair.pair_id = vec![1].into();
+    pair.base_asset_id = vec![2].into();
+    pair.quote_asset_id = vec![3].into();
 
     let _ = event::drain_events();
 
@@ -41,19 +42,22 @@ fn limit_buy_moves_lmp_to_best_ask() {
 
     assert_eq!(pair.orderbook.l2.ask_head(), Some(ask_price));
 
-    pair.limit_buy(
-        vec![2],
-        None,
-        vec![20],
-        100 * SCALE_8,
-        100 * SCALE_8,
-        0,
-        124,
-        i64::MAX,
-        5,
-        10,
-        TimeInForce::GoodTillCanceled,
-    )
+    pair.limit_buy(LimitOrder {
+        cid: [2].into(),
+        pair_id: pair.pair_id,
+        existing_order_id: None,
+        owner: [20].into(),
+        coid: None,
+        price: 100 * SCALE_8,
+        amnt: 100 * SCALE_8,
+        iqty: 0,
+        timestamp: 124,
+        expires_at: i64::MAX,
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        time_in_force: TimeInForce::GoodTillCanceled,
+        post_only: false,
+    })
     .expect("limit buy");
 
     assert_eq!(pair.orderbook.l2.ask_head(), None);
@@ -65,9 +69,9 @@ fn limit_buy_moves_lmp_to_best_ask() {
     // SpotOrderPlaced for resting bid is from initial place_bid (full amnt/cqty/pqty)
     event_assertion::assert_limit_buy_events_matched(
         &events,
-        &pair.pair_id,
-        &pair.base_asset_id,
-        &pair.quote_asset_id,
+        pair.pair_id.as_slice(),
+        pair.base_asset_id.as_slice(),
+        pair.quote_asset_id.as_slice(),
         &[20],
         100 * SCALE_8,
         100 * SCALE_8,
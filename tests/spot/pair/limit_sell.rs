@@ -1,3 +1,4 @@
+use off_grid_primitives::spot::command::LimitOrder;
 use off_grid_primitives::spot::event;
 use off_grid_primitives::spot::time_in_force::TimeInForce;
 use off_grid_primitives::spot::Pair;
@@ -15,9 +16,9 @@ const SCALE_8: u64 = 1_0000_0000;
 fn limit_sell_moves_lmp_to_best_bid_when_matching() {
     let _guard = lock_events();
     let mut pair = Pair::new();
-    pair.pair_id = vec![1];
-    pair.base_asset_id = vec![2];
-    pair.quote_asset_id = vec![3];
+    pair.pair_id = vec![1].into();
+    pair.base_asset_id = vec![2].into();
+    pair.quote_asset_id = vec![3].into();
 
     let _ = event::drain_events();
 
@@ -41,19 +42,22 @@ fn limit_sell_moves_lmp_to_best_bid_when_matching() {
 
     assert_eq!(pair.orderbook.l2.bid_head(), Some(bid_price));
 
-    pair.limit_sell(
-        vec![2],
-        None,
-        vec![20],
-        100 * SCALE_8,
-        100 * SCALE_8,
-        0,
-        124,
-        i64::MAX,
-        5,
-        10,
-        TimeInForce::GoodTillCanceled,
-    )
+    pair.limit_sell(LimitOrder {
+        cid: [2].into(),
+        pair_id: pair.pair_id,
+        existing_order_id: None,
+        owner: [20].into(),
+        coid: None,
+        price: 100 * SCALE_8,
+        amnt: 100 * SCALE_8,
+        iqty: 0,
+        timestamp: 124,
+        expires_at: i64::MAX,
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        time_in_force: TimeInForce::GoodTillCanceled,
+        post_only: false,
+    })
     .expect("limit sell");
 
     assert_eq!(pair.orderbook.l2.bid_head(), None);
@@ -65,9 +69,9 @@ fn limit_sell_moves_lmp_to_best_bid_when_matching() {
     // SpotOrderPlaced for resting ask is from initial place_ask (full amnt/cqty/pqty)
     event_assertion::assert_limit_sell_events_matched(
         &events,
-        &pair.pair_id,
-        &pair.base_asset_id,
-        &pair.quote_asset_id,
+        pair.pair_id.as_slice(),
+        pair.base_asset_id.as_slice(),
+        pair.quote_asset_id.as_slice(),
         &[20],
         100 * SCALE_8,
         100 * SCALE_8,
@@ -118,9 +122,9 @@ fn limit_sell_moves_lmp_to_best_bid_when_matching() {
 fn limit_sell_moves_lmp_to_best_bid() {
     let _guard = lock_events();
     let mut pair = Pair::new();
-    pair.pair_id = vec![4];
-    pair.base_asset_id = vec![5];
-    pair.quote_asset_id = vec![6];
+    pair.pair_id = vec![4].into();
+    pair.base_asset_id = vec![5].into();
+    pair.quote_asset_id = vec![6].into();
 
     let _ = event::drain_events();
 
@@ -144,19 +148,22 @@ fn limit_sell_moves_lmp_to_best_bid() {
 
     assert_eq!(pair.orderbook.l2.bid_head(), Some(bid_price));
 
-    pair.limit_sell(
-        vec![4],
-        None,
-        vec![40],
-        100 * SCALE_8,
-        100 * SCALE_8,
-        0,
-        224,
-        i64::MAX,
-        5,
-        10,
-        TimeInForce::GoodTillCanceled,
-    )
+    pair.limit_sell(LimitOrder {
+        cid: [4].into(),
+        pair_id: pair.pair_id,
+        existing_order_id: None,
+        owner: [40].into(),
+        coid: None,
+        price: 100 * SCALE_8,
+        amnt: 100 * SCALE_8,
+        iqty: 0,
+        timestamp: 224,
+        expires_at: i64::MAX,
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        time_in_force: TimeInForce::GoodTillCanceled,
+        post_only: false,
+    })
     .expect("limit sell");
 
     assert_eq!(pair.orderbook.l2.bid_head(), None);
@@ -166,9 +173,9 @@ fn limit_sell_moves_lmp_to_best_bid() {
     let events = event::drain_events();
     event_assertion::assert_limit_sell_events_matched(
         &events,
-        &pair.pair_id,
-        &pair.base_asset_id,
-        &pair.quote_asset_id,
+        pair.pair_id.as_slice(),
+        pair.base_asset_id.as_slice(),
+        pair.quote_asset_id.as_slice(),
         &[40],
         100 * SCALE_8,
         100 * SCALE_8,
@@ -1,5 +1,7 @@
+use off_grid_primitives::spot::command::MarketOrder;
 use off_grid_primitives::spot::event;
 use off_grid_primitives::spot::market::L1;
+use off_grid_primitives::spot::market_order::MarketOrderSize;
 use off_grid_primitives::spot::time_in_force::TimeInForce;
 use off_grid_primitives::spot::Pair;
 
@@ -16,9 +18,9 @@ const SCALE_8: u64 = 1_0000_0000;
 fn market_buy_sets_market_price_after_matching() {
     let _guard = lock_events();
     let mut pair = Pair::new();
-    pair.pair_id = vec![1];
-    pair.base_asset_id = vec![2];
-    pair.quote_asset_id = vec![3];
+    pair.pair_id = vec![1].into();
+    pair.base_asset_id = vec![2].into();
+    pair.quote_asset_id = vec![3].into();
 
     let _ = event::drain_events();
 
@@ -40,25 +42,27 @@ fn market_buy_sets_market_price_after_matching() {
         )
         .expect("place ask");
 
-    pair.market_buy(
-        vec![2],
-        None,
-        vec![20],
-        5 * SCALE_8,
-        0,
-        124,
-        i64::MAX,
-        5,
-        10,
-        TimeInForce::GoodTillCanceled,
-    )
+    pair.market_buy(MarketOrder {
+        cid: [2].into(),
+        pair_id: pair.pair_id,
+        existing_order_id: None,
+        owner: [20].into(),
+        coid: None,
+        size: MarketOrderSize::Base(5 * SCALE_8),
+        public_amount: 0,
+        timestamp: 124,
+        expires_at: i64::MAX,
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        time_in_force: TimeInForce::GoodTillCanceled,
+    })
     .expect("market buy");
 
     // After match: buy fully filled, lmp=110, bid_head=0, ask_head=110 (remaining ask). make_price = min(ask_head, up) = 110*SCALE_8
     assert_eq!(pair.market_price, Some(110 * SCALE_8));
 
     let events = event::drain_events();
-    event_assertion::assert_market_buy_events_matched(&events, &pair.pair_id, 110 * SCALE_8);
+    event_assertion::assert_market_buy_events_matched(&events, pair.pair_id.as_slice(), 110 * SCALE_8);
     // Remaining ask (maker) is still in the orderbook: 10 - 5 = 5 at 110.
     event_assertion::assert_orderbook_has_ask_level(&pair.orderbook, 110 * SCALE_8, 5 * SCALE_8);
 }
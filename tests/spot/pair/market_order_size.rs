@@ -0,0 +1,306 @@
+use off_grid_primitives::spot::command::MarketOrder;
+use off_grid_primitives::spot::event::{self, SpotEvent};
+use off_grid_primitives::spot::market_order::{MarketOrderFill, MarketOrderSize};
+use off_grid_primitives::spot::orderbook::OrderBookError;
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::Pair;
+
+use super::event_assertion;
+use super::EVENT_MUTEX;
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+const SCALE_8: u64 = 1_0000_0000;
+
+fn setup_pair() -> Pair {
+    let mut pair = Pair::new();
+    pair.pair_id = vec![1].into();
+    pair.base_asset_id = vec![2].into();
+    pair.quote_asset_id = vec![3].into();
+    let _ = event::drain_events();
+    pair
+}
+
+/// asks in base terms: 1 @ 100.33333333, 2 @ 101.66666667, 5 @ 103
+fn setup_asks() -> Pair {
+    let mut pair = setup_pair();
+    for (owner, price, amnt) in [(10u8, 100_33333333, SCALE_8), (11, 101_66666667, 2 * SCALE_8), (12, 103 * SCALE_8, 5 * SCALE_8)] {
+        pair.orderbook
+            .place_ask(
+                vec![1], pair.pair_id.clone(), pair.base_asset_id.clone(), pair.quote_asset_id.clone(),
+                vec![owner], price, amnt, 0, 1, i64::MAX, 5,
+            )
+            .expect("place ask");
+    }
+    let _ = event::drain_events();
+    pair
+}
+
+/// bids in quote terms: 150 @ 100.33333333, 1000 @ 99.5
+fn setup_bids() -> Pair {
+    let mut pair = setup_pair();
+    for (owner, price, amnt) in [(10u8, 100_33333333, 150 * SCALE_8), (11, 99_50000000, 1000 * SCALE_8)] {
+        pair.orderbook
+            .place_bid(
+                vec![1], pair.pair_id.clone(), pair.base_asset_id.clone(), pair.quote_asset_id.clone(),
+                vec![owner], price, amnt, 0, 1, i64::MAX, 5,
+            )
+            .expect("place bid");
+    }
+    let _ = event::drain_events();
+    pair
+}
+
+fn market(pair: &mut Pair, is_bid: bool, size: MarketOrderSize, time_in_force: TimeInForce) -> Result<MarketOrderFill, OrderBookError> {
+    if is_bid {
+        pair.market_buy(MarketOrder {
+            cid: [2].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: None,
+            size,
+            public_amount: 0,
+            timestamp: 2,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force,
+        })
+    } else {
+        pair.market_sell(MarketOrder {
+            cid: [2].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: None,
+            size,
+            public_amount: 0,
+            timestamp: 2,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force,
+        })
+    }
+}
+
+/// (price, base, quote) of the trades in the events
+fn trades(events: &event::EventQueue) -> Vec<(u64, u64, u64)> {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            SpotEvent::SpotTrade { price, base_volume, quote_volume, .. } => Some((*price, *base_volume, *quote_volume)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// current quantity of the cancelled taker order
+fn refund(events: &event::EventQueue) -> Option<u64> {
+    events.iter().find_map(|e| match e {
+        SpotEvent::SpotOrderCancelled { maker_account_id, cqty, .. } if maker_account_id == &vec![20] => Some(*cqty),
+        _ => None,
+    })
+}
+
+#[test]
+fn quote_buy_spends_exactly_and_refunds_rounding() {
+    let _guard = lock_events();
+    let mut pair = setup_asks();
+
+    let fill = market(&mut pair, true, MarketOrderSize::Quote(350 * SCALE_8), TimeInForce::GoodTillCanceled)
+        .expect("market buy");
+    let events = event::drain_events();
+
+    // the last level only gets the base amount the rest of the quote can pay for, rounded down
+    assert_eq!(
+        trades(&events),
+        vec![
+            (100_33333333, SCALE_8, 100_33333333),
+            (101_66666667, 2 * SCALE_8, 203_33333334),
+            (103 * SCALE_8, 44983818, 46_33333254),
+        ]
+    );
+    assert_eq!(fill.base_filled, 3_44983818);
+    assert_eq!(fill.quote_filled, 349_99999921);
+    assert_eq!(fill.remaining, 79);
+    assert!(!fill.is_resting);
+    assert_eq!(fill.quote_filled + fill.remaining, 350 * SCALE_8);
+    assert_eq!(refund(&events), Some(79));
+    assert!(pair.orderbook.l3.get_order(fill.order_id).is_err());
+
+    event_assertion::assert_orderbook_has_ask_level(&pair.orderbook, 103 * SCALE_8, 5 * SCALE_8 - 44983818);
+    assert_eq!(pair.orderbook.l2.ask_head(), Some(103 * SCALE_8));
+    assert_eq!(pair.orderbook.l2.bid_head(), None);
+    assert_eq!(pair.l1.lmp(), Some(103 * SCALE_8));
+}
+
+#[test]
+fn base_buy_pays_each_level_rounded_up() {
+    let _guard = lock_events();
+    let mut pair = setup_asks();
+
+    let fill = market(&mut pair, true, MarketOrderSize::Base(3 * SCALE_8 + SCALE_8 / 2), TimeInForce::GoodTillCanceled)
+        .expect("market buy");
+    let events = event::drain_events();
+
+    assert_eq!(
+        trades(&events),
+        vec![
+            (100_33333333, SCALE_8, 100_33333333),
+            (101_66666667, 2 * SCALE_8, 203_33333334),
+            (103 * SCALE_8, SCALE_8 / 2, 51_50000000),
+        ]
+    );
+    assert_eq!(fill.base_filled, 3 * SCALE_8 + SCALE_8 / 2);
+    assert_eq!(fill.quote_filled, 355_16666667);
+    // the taker only locks what the fills cost
+    assert_eq!(fill.remaining, 0);
+    assert_eq!(refund(&events), None);
+    event_assertion::assert_orderbook_has_ask_level(&pair.orderbook, 103 * SCALE_8, 4 * SCALE_8 + SCALE_8 / 2);
+}
+
+#[test]
+fn base_sell_receives_each_level_rounded_down() {
+    let _guard = lock_events();
+    let mut pair = setup_bids();
+
+    let fill = market(&mut pair, false, MarketOrderSize::Base(2 * SCALE_8), TimeInForce::GoodTillCanceled)
+        .expect("market sell");
+    let events = event::drain_events();
+
+    // the first bid can only buy 1.49501661 base, its 14 quote units left are cleared with it
+    assert_eq!(
+        trades(&events),
+        vec![(100_33333333, 1_49501661, 149_99999986), (99_50000000, 50498339, 50_24584730)]
+    );
+    assert_eq!(fill.base_filled, 2 * SCALE_8);
+    assert_eq!(fill.quote_filled, 200_24584716);
+    assert_eq!(fill.remaining, 0);
+    assert_eq!(pair.orderbook.l2.bid_head(), Some(99_50000000));
+    event_assertion::assert_orderbook_has_bid_level(&pair.orderbook, 99_50000000, 949_75415270, None);
+    assert_eq!(pair.orderbook.l2.ask_head(), None);
+}
+
+#[test]
+fn quote_sell_never_receives_more_than_the_size() {
+    let _guard = lock_events();
+    let mut pair = setup_bids();
+
+    let fill = market(&mut pair, false, MarketOrderSize::Quote(250 * SCALE_8), TimeInForce::GoodTillCanceled)
+        .expect("market sell");
+    let events = event::drain_events();
+
+    assert_eq!(
+        trades(&events),
+        vec![(100_33333333, 1_49501661, 149_99999986), (99_50000000, 1_00502512, 99_99999944)]
+    );
+    assert_eq!(fill.base_filled, 2_50004173);
+    assert_eq!(fill.quote_filled, 249_99999930);
+    assert!(fill.quote_filled <= 250 * SCALE_8);
+    // the taker only locks the base the fills need
+    assert_eq!(fill.remaining, 0);
+    assert_eq!(refund(&events), None);
+    event_assertion::assert_orderbook_has_bid_level(&pair.orderbook, 99_50000000, 900_00000056, None);
+}
+
+#[test]
+fn remainder_after_exhausting_the_book_follows_time_in_force() {
+    let _guard = lock_events();
+
+    // FOK is rejected before anything is placed
+    let mut pair = setup_asks();
+    let result = market(&mut pair, true, MarketOrderSize::Base(9 * SCALE_8), TimeInForce::FillOrKill);
+    assert_eq!(result, Err(OrderBookError::OrderNotFullyFilled));
+    assert!(event::drain_events().is_empty());
+    assert_eq!(pair.orderbook.l2.ask_head(), Some(100_33333333));
+
+    // FOK fills when the book is deep enough
+    let fill = market(&mut pair, true, MarketOrderSize::Base(8 * SCALE_8), TimeInForce::FillOrKill)
+        .expect("market buy fok");
+    assert_eq!(fill.base_filled, 8 * SCALE_8);
+    assert_eq!(fill.remaining, 0);
+    assert_eq!(pair.orderbook.l2.ask_head(), None);
+    let _ = event::drain_events();
+
+    // IOC cancels the quote locked for the unfilled base at the last price
+    let mut pair = setup_asks();
+    let fill = market(&mut pair, true, MarketOrderSize::Base(9 * SCALE_8), TimeInForce::ImmediateOrCancel)
+        .expect("market buy ioc");
+    let events = event::drain_events();
+    assert_eq!(fill.base_filled, 8 * SCALE_8);
+    assert_eq!(fill.remaining, 103 * SCALE_8);
+    assert!(!fill.is_resting);
+    assert_eq!(refund(&events), Some(103 * SCALE_8));
+    assert!(pair.orderbook.l3.get_order(fill.order_id).is_err());
+
+    // GTC rests the rest of the quote
+    let mut pair = setup_asks();
+    let fill = market(&mut pair, true, MarketOrderSize::Quote(1000 * SCALE_8), TimeInForce::GoodTillCanceled)
+        .expect("market buy gtc");
+    let events = event::drain_events();
+    assert_eq!(fill.base_filled, 8 * SCALE_8);
+    assert_eq!(fill.quote_filled, 818_66666667);
+    assert_eq!(fill.remaining, 181_33333333);
+    assert!(fill.is_resting);
+    assert_eq!(refund(&events), None);
+    let order = pair.orderbook.l3.get_order(fill.order_id).expect("resting order");
+    assert_eq!((order.price, order.cqty), (103 * SCALE_8, 181_33333333));
+    assert_eq!(pair.orderbook.l2.ask_head(), None);
+
+    // GTC rests the quote locked for the unfilled base at the last price, at that price
+    let mut pair = setup_asks();
+    let fill = market(&mut pair, true, MarketOrderSize::Base(9 * SCALE_8), TimeInForce::GoodTillCanceled)
+        .expect("market buy gtc of base");
+    let _ = event::drain_events();
+    assert!(fill.is_resting);
+    let order = pair.orderbook.l3.get_order(fill.order_id).expect("resting order");
+    assert_eq!((order.price, order.cqty), (103 * SCALE_8, 103 * SCALE_8));
+    assert_eq!(pair.orderbook.l2.bid_head(), Some(103 * SCALE_8));
+}
+
+#[test]
+fn market_orders_match_at_their_own_timestamp() {
+    let _guard = lock_events();
+    let mut pair = setup_pair();
+    // the first ask expires before the market order arrives
+    for (owner, price, expires_at) in [(10u8, 100 * SCALE_8, 50), (11, 101 * SCALE_8, i64::MAX)] {
+        pair.orderbook
+            .place_ask(
+                vec![1], pair.pair_id.clone(), pair.base_asset_id.clone(), pair.quote_asset_id.clone(),
+                vec![owner], price, SCALE_8, 0, 1, expires_at, 5,
+            )
+            .expect("place ask");
+    }
+    let _ = event::drain_events();
+
+    let fill = pair
+        .market_buy(MarketOrder {
+            cid: [2].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: None,
+            size: MarketOrderSize::Base(SCALE_8),
+            public_amount: 0,
+            timestamp: 100,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::ImmediateOrCancel,
+        })
+        .expect("market buy");
+    let events = event::drain_events();
+    assert_eq!(fill.quote_filled, 101 * SCALE_8);
+    let trades: Vec<(u64, i64)> = events
+        .iter()
+        .filter_map(|e| match e {
+            SpotEvent::SpotTrade { price, timestamp, .. } => Some((*price, *timestamp)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(trades, vec![(101 * SCALE_8, 100)]);
+}
@@ -0,0 +1,207 @@
+use off_grid_primitives::spot::command::MarketOrder;
+use off_grid_primitives::spot::event::{self, SpotEvent};
+use off_grid_primitives::spot::market_order::MarketOrderSize;
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::Pair;
+
+use super::event_assertion;
+use super::EVENT_MUTEX;
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+const SCALE_8: u64 = 1_0000_0000;
+
+fn setup_pair() -> Pair {
+    let mut pair = Pair::new();
+    pair.pair_id = vec![1].into();
+    pair.base_asset_id = vec![2].into();
+    pair.quote_asset_id = vec![3].into();
+    let _ = event::drain_events();
+    pair
+}
+
+/// one base ask at each of 100, 104 and 110
+fn setup_asks() -> Pair {
+    let mut pair = setup_pair();
+    for (owner, price) in [(10u8, 100 * SCALE_8), (11, 104 * SCALE_8), (12, 110 * SCALE_8)] {
+        pair.orderbook
+            .place_ask(
+                vec![1], pair.pair_id.clone(), pair.base_asset_id.clone(), pair.quote_asset_id.clone(),
+                vec![owner], price, SCALE_8, 0, 1, i64::MAX, 5,
+            )
+            .expect("place ask");
+    }
+    let _ = event::drain_events();
+    pair
+}
+
+/// (protection price, is quote size, unfilled) of the protection event
+fn protection(events: &event::EventQueue) -> Option<(u64, bool, u64)> {
+    events.iter().find_map(|e| match e {
+        SpotEvent::SpotMarketOrderProtected { protection_price, is_quote_size, unfilled, maker_account_id, .. } => {
+            assert_eq!(maker_account_id, &vec![20]);
+            Some((*protection_price, *is_quote_size, *unfilled))
+        }
+        _ => None,
+    })
+}
+
+fn refund(events: &event::EventQueue) -> Option<u64> {
+    events.iter().find_map(|e| match e {
+        SpotEvent::SpotOrderCancelled { maker_account_id, cqty, .. } if maker_account_id == &vec![20] => Some(*cqty),
+        _ => None,
+    })
+}
+
+#[test]
+fn market_buy_stops_at_the_band_above_the_best_ask() {
+    let _guard = lock_events();
+    let mut pair = setup_asks();
+    // 5% above the best ask before the first match
+    pair.l1.set_market_buy_protection_bps(Some(500));
+
+    let fill = pair
+        .market_buy(MarketOrder {
+            cid: [2].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: None,
+            size: MarketOrderSize::Base(3 * SCALE_8),
+            public_amount: 0,
+            timestamp: 2,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+        })
+        .expect("market buy");
+    let events = event::drain_events();
+
+    assert_eq!(fill.base_filled, 2 * SCALE_8);
+    assert_eq!(fill.quote_filled, 204 * SCALE_8);
+    assert_eq!(fill.protection_price, Some(105 * SCALE_8));
+    assert_eq!(fill.unfilled, SCALE_8);
+    // the quote locked for the unfilled base at the last price reached is refunded even though the order is GTC
+    assert_eq!(fill.remaining, 104 * SCALE_8);
+    assert!(!fill.is_resting);
+    assert_eq!(refund(&events), Some(104 * SCALE_8));
+    assert_eq!(protection(&events), Some((105 * SCALE_8, false, SCALE_8)));
+    event_assertion::assert_orderbook_has_ask_level(&pair.orderbook, 110 * SCALE_8, SCALE_8);
+    assert_eq!(pair.orderbook.l2.bid_head(), None);
+}
+
+#[test]
+fn market_sell_stops_at_the_band_below_lmp() {
+    let _guard = lock_events();
+    let mut pair = setup_pair();
+    // bids in quote terms, one base each
+    for (owner, price) in [(10u8, 99 * SCALE_8), (11, 97 * SCALE_8), (12, 90 * SCALE_8)] {
+        pair.orderbook
+            .place_bid(
+                vec![1], pair.pair_id.clone(), pair.base_asset_id.clone(), pair.quote_asset_id.clone(),
+                vec![owner], price, price, 0, 1, i64::MAX, 5,
+            )
+            .expect("place bid");
+    }
+    let _ = event::drain_events();
+    pair.l1.set_lmp(100 * SCALE_8);
+    pair.l1.set_market_sell_protection_bps(Some(500));
+
+    let fill = pair
+        .market_sell(MarketOrder {
+            cid: [2].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: None,
+            size: MarketOrderSize::Quote(300 * SCALE_8),
+            public_amount: 0,
+            timestamp: 2,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::ImmediateOrCancel,
+        })
+        .expect("market sell");
+    let events = event::drain_events();
+
+    assert_eq!(fill.base_filled, 2 * SCALE_8);
+    assert_eq!(fill.quote_filled, 196 * SCALE_8);
+    assert_eq!(fill.protection_price, Some(95 * SCALE_8));
+    assert_eq!(fill.unfilled, 104 * SCALE_8);
+    // base locked for the unfilled quote at 97, rounded up
+    assert_eq!(fill.remaining, 1_07216495);
+    assert_eq!(refund(&events), Some(1_07216495));
+    assert_eq!(protection(&events), Some((95 * SCALE_8, true, 104 * SCALE_8)));
+    event_assertion::assert_orderbook_has_bid_level(&pair.orderbook, 90 * SCALE_8, 90 * SCALE_8, None);
+    assert_eq!(pair.orderbook.l2.ask_head(), None);
+}
+
+#[test]
+fn band_before_the_best_price_cancels_the_whole_order() {
+    let _guard = lock_events();
+    let mut pair = setup_asks();
+    pair.l1.set_lmp(50 * SCALE_8);
+    pair.l1.set_market_buy_protection_bps(Some(500));
+
+    let fill = pair
+        .market_buy(MarketOrder {
+            cid: [2].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: None,
+            size: MarketOrderSize::Quote(100 * SCALE_8),
+            public_amount: 0,
+            timestamp: 2,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+        })
+        .expect("market buy");
+    let events = event::drain_events();
+
+    assert_eq!(fill.base_filled, 0);
+    assert_eq!(fill.unfilled, 100 * SCALE_8);
+    assert_eq!(fill.remaining, 100 * SCALE_8);
+    assert!(!events.iter().any(|e| matches!(e, SpotEvent::SpotTrade { .. })));
+    assert_eq!(protection(&events), Some((52_50000000, true, 100 * SCALE_8)));
+    assert_eq!(refund(&events), Some(100 * SCALE_8));
+    event_assertion::assert_orderbook_has_ask_level(&pair.orderbook, 100 * SCALE_8, SCALE_8);
+}
+
+#[test]
+fn no_slippage_limit_sweeps_the_book() {
+    let _guard = lock_events();
+    let mut pair = setup_asks();
+    pair.l1.set_market_buy_protection_bps(None);
+
+    let fill = pair
+        .market_buy(MarketOrder {
+            cid: [2].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: None,
+            size: MarketOrderSize::Base(3 * SCALE_8),
+            public_amount: 0,
+            timestamp: 2,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::ImmediateOrCancel,
+        })
+        .expect("market buy");
+    let events = event::drain_events();
+
+    assert_eq!(fill.base_filled, 3 * SCALE_8);
+    assert_eq!(fill.quote_filled, 314 * SCALE_8);
+    assert_eq!(fill.protection_price, None);
+    assert_eq!(fill.unfilled, 0);
+    assert_eq!(protection(&events), None);
+    assert_eq!(pair.orderbook.l2.ask_head(), None);
+}
@@ -1,5 +1,7 @@
+use off_grid_primitives::spot::command::MarketOrder;
 use off_grid_primitives::spot::event;
 use off_grid_primitives::spot::market::L1;
+use off_grid_primitives::spot::market_order::MarketOrderSize;
 use off_grid_primitives::spot::time_in_force::TimeInForce;
 use off_grid_primitives::spot::Pair;
 
@@ -16,9 +18,9 @@ const SCALE_8: u64 = 1_0000_0000;
 fn market_sell_sets_market_price_after_matching() {
     let _guard = lock_events();
     let mut pair = Pair::new();
-    pair.pair_id = vec![1];
-    pair.base_asset_id = vec![2];
-    pair.quote_asset_id = vec![3];
+    pair.pair_id = vec![1].into();
+    pair.base_asset_id = vec![2].into();
+    pair.quote_asset_id = vec![3].into();
 
     let _ = event::drain_events();
 
@@ -32,7 +34,7 @@ fn market_sell_sets_market_price_after_matching() {
             pair.quote_asset_id.clone(),
             vec![10],
             bid_price,
-            10 * SCALE_8,
+            1100 * SCALE_8,
             0,
             123,
             i64::MAX,
@@ -40,29 +42,31 @@ fn market_sell_sets_market_price_after_matching() {
         )
         .expect("place bid");
 
-    pair.market_sell(
-        vec![2],
-        None,
-        vec![20],
-        5 * SCALE_8,
-        0,
-        124,
-        i64::MAX,
-        5,
-        10,
-        TimeInForce::GoodTillCanceled,
-    )
+    pair.market_sell(MarketOrder {
+        cid: [2].into(),
+        pair_id: pair.pair_id,
+        existing_order_id: None,
+        owner: [20].into(),
+        coid: None,
+        size: MarketOrderSize::Base(5 * SCALE_8),
+        public_amount: 0,
+        timestamp: 124,
+        expires_at: i64::MAX,
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        time_in_force: TimeInForce::GoodTillCanceled,
+    })
     .expect("market sell");
 
-    // After match: lmp=110, bid_head=110, ask_head=0, spread=5 => down = 110*9995/10000
-    let expected_make_price = (110 * SCALE_8 * 9995) / 10000;
+    // After match: lmp=110, bid_head=110 (remaining bid), ask_head=0, spread=5 => down = 110*9995/10000 is below bid_head => bid_head
+    let expected_make_price = 110 * SCALE_8;
     assert_eq!(pair.market_price, Some(expected_make_price));
 
     let events = event::drain_events();
-    event_assertion::assert_market_sell_events_matched(&events, &pair.pair_id, expected_make_price);
+    event_assertion::assert_market_sell_events_matched(&events, pair.pair_id.as_slice(), expected_make_price);
     // After matching: when maker remains in L3 and L2 has bid head, assert order info and price level.
     let price = 110 * SCALE_8;
-    let remaining = 5 * SCALE_8;
+    let remaining = 550 * SCALE_8;
     let maker_still_in_l3 = pair.orderbook.l3.get_order(bid_order.id).is_ok();
     if maker_still_in_l3 && pair.orderbook.l2.bid_head().is_some() {
         event_assertion::assert_orderbook_has_bid_level(
@@ -86,9 +90,9 @@ fn market_sell_sets_market_price_after_matching() {
 fn market_sell_partial_fill_orderbook_has_bid_level_and_maker_in_l3() {
     let _guard = lock_events();
     let mut pair = Pair::new();
-    pair.pair_id = vec![1];
-    pair.base_asset_id = vec![2];
-    pair.quote_asset_id = vec![3];
+    pair.pair_id = vec![1].into();
+    pair.base_asset_id = vec![2].into();
+    pair.quote_asset_id = vec![3].into();
 
     let _ = event::drain_events();
 
@@ -5,5 +5,9 @@ pub mod limit_buy;
 pub mod limit_sell;
 pub mod market_buy;
 pub mod market_sell;
+pub mod market_order_size;
+pub mod market_protection;
 pub mod snapshot;
+pub mod ticker;
 pub mod slippage;
+pub mod trades;
@@ -1,4 +1,6 @@
+use off_grid_primitives::spot::command::LimitOrder;
 use off_grid_primitives::spot::event;
+use off_grid_primitives::spot::time_in_force::TimeInForce;
 use off_grid_primitives::spot::Pair;
 use super::EVENT_MUTEX;
 
@@ -19,12 +21,12 @@ fn serialize_and_deserialize_empty_pair() {
 fn serialize_and_deserialize_pair_with_orders() {
     let _guard = lock_events();
     let mut pair = Pair::new();
-    pair.pair_id = vec![1];
-    pair.base_asset_id = vec![2];
-    pair.quote_asset_id = vec![3];
+    pair.pair_id = vec![1].into();
+    pair.base_asset_id = vec![2].into();
+    pair.quote_asset_id = vec![3].into();
 
-    pair.add_client(vec![9], vec![10], vec![11]);
-    pair.add_client(vec![8], vec![12], vec![13]);
+    pair.add_client(vec![9], vec![10], vec![11], 1);
+    pair.add_client(vec![8], vec![12], vec![13], 1);
 
     let _ = event::drain_events();
 
@@ -86,4 +88,73 @@ fn serialize_and_deserialize_pair_with_orders() {
     assert_eq!(decoded_bid.cqty, 1000);
     assert_eq!(decoded_ask.price, 110);
     assert_eq!(decoded_ask.cqty, 800);
-}
\ No newline at end of file
+}
+#[test]
+fn scaled_depth_follows_matching_and_cancels() {
+    let _guard = lock_events();
+    let mut pair = Pair::new();
+    pair.pair_id = vec![1].into();
+    pair.base_asset_id = vec![2].into();
+    pair.quote_asset_id = vec![3].into();
+    let _ = event::drain_events();
+
+    const SCALE_8: u64 = 1_0000_0000;
+    let one = SCALE_8; // scale 1.00000000
+
+    for (owner, price) in [(20u8, 101 * SCALE_8 + 2_000_000), (21, 101 * SCALE_8 + 7_000_000), (22, 103 * SCALE_8)] {
+        pair.orderbook
+            .place_ask(vec![1], pair.pair_id.clone(), vec![2], vec![3], vec![owner], price, 2 * SCALE_8, 0, 1, i64::MAX, 5)
+            .expect("place ask");
+    }
+    assert_eq!(
+        pair.orderbook.l2.get_snapshot_raw(false, one, 10).expect("ask snapshot"),
+        vec![vec![102 * SCALE_8, 4 * SCALE_8, 4 * SCALE_8], vec![103 * SCALE_8, 2 * SCALE_8, 2 * SCALE_8]]
+    );
+
+    // buy with 100 quote takes part of the first ask
+    pair.limit_buy(LimitOrder {
+        cid: [1].into(),
+        pair_id: pair.pair_id,
+        existing_order_id: None,
+        owner: [30].into(),
+        coid: None,
+        price: 102 * SCALE_8,
+        amnt: 100 * SCALE_8,
+        iqty: 0,
+        timestamp: 2,
+        expires_at: i64::MAX,
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        time_in_force: TimeInForce::ImmediateOrCancel,
+        post_only: false,
+    })
+    .expect("limit buy");
+
+    // the 102 bucket holds what is left at 101.02 and 101.07
+    let left: u64 = pair
+        .orderbook
+        .l2
+        .current_ask_level_map
+        .range(101 * SCALE_8 + 1..=102 * SCALE_8)
+        .map(|(_, cqty)| *cqty)
+        .sum();
+    assert!(left < 4 * SCALE_8);
+    let asks = pair.orderbook.l2.get_snapshot_raw(false, one, 10).expect("ask snapshot");
+    assert_eq!(asks.len(), 2);
+    assert_eq!(asks[0][0], 102 * SCALE_8);
+    assert_eq!(asks[0][2], left);
+    assert_eq!(asks[1], vec![103 * SCALE_8, 2 * SCALE_8, 2 * SCALE_8]);
+    assert!(pair.orderbook.l2.get_snapshot_raw(true, one, 10).expect("bid snapshot").is_empty());
+
+    // incremental maintenance matches a full rebuild from the price levels
+    let incremental = (pair.orderbook.l2.bid_level_list.clone(), pair.orderbook.l2.ask_level_list.clone());
+    pair.orderbook.l2.rebuild_scaled_levels();
+    assert_eq!((pair.orderbook.l2.bid_level_list.clone(), pair.orderbook.l2.ask_level_list.clone()), incremental);
+
+    let owner_orders = pair.orderbook.orders_by_owner(&[22]);
+    pair.cancel_order(vec![1], pair.pair_id.clone(), owner_orders[0].id, vec![22])
+        .expect("cancel ask");
+    let asks = pair.orderbook.l2.get_snapshot_raw(false, one, 10).expect("ask snapshot");
+    assert_eq!(asks.len(), 1);
+    let _ = event::drain_events();
+}
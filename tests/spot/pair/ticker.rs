@@ -0,0 +1,155 @@
+use off_grid_primitives::spot::command::LimitOrder;
+use off_grid_primitives::spot::event;
+use off_grid_primitives::spot::query::QueryError;
+use off_grid_primitives::spot::ticker::{RollingTicker, TICKER_BUCKET_MS, TICKER_WINDOW_MS};
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::trades::Trade;
+use off_grid_primitives::spot::MatchingEngine;
+use super::EVENT_MUTEX;
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+const SCALE_8: u64 = 1_0000_0000;
+const HOUR: i64 = 60 * 60_000;
+
+fn trade(price: u64, timestamp: i64) -> Trade {
+    Trade {
+        price,
+        base_volume: SCALE_8,
+        quote_volume: price,
+        timestamp,
+        ..Trade::default()
+    }
+}
+
+#[test]
+fn ticker_rolls_over_the_window() {
+    let _guard = lock_events();
+    let mut ticker = RollingTicker::new(TICKER_WINDOW_MS, TICKER_BUCKET_MS);
+    let t0 = 1_700_000_000_000;
+
+    let empty = ticker.ticker(t0);
+    assert_eq!(empty.last, None);
+    assert_eq!(empty.open, None);
+    assert_eq!(empty.trades, 0);
+
+    ticker.apply_trade(&trade(100 * SCALE_8, t0));
+    ticker.apply_trade(&trade(120 * SCALE_8, t0 + 10));
+    ticker.apply_trade(&trade(90 * SCALE_8, t0 + HOUR));
+    ticker.apply_trade(&trade(110 * SCALE_8, t0 + 2 * HOUR));
+
+    let stats = ticker.ticker(t0 + 2 * HOUR);
+    assert_eq!(stats.last, Some(110 * SCALE_8));
+    assert_eq!(stats.open, Some(100 * SCALE_8));
+    assert_eq!(stats.high, Some(120 * SCALE_8));
+    assert_eq!(stats.low, Some(90 * SCALE_8));
+    assert_eq!(stats.base_volume, 4 * SCALE_8);
+    assert_eq!(stats.quote_volume, 420 * SCALE_8);
+    assert_eq!(stats.trades, 4);
+    assert_eq!(stats.change_bps, 1000);
+
+    // the first bucket leaves the window a day later
+    let later = ticker.ticker(t0 + TICKER_WINDOW_MS + TICKER_BUCKET_MS);
+    assert_eq!(later.open, Some(90 * SCALE_8));
+    assert_eq!(later.high, Some(110 * SCALE_8));
+    assert_eq!(later.trades, 2);
+    assert_eq!(later.change_bps, 2222);
+
+    // after a day without trades only the last price is left
+    ticker.apply_trade(&trade(80 * SCALE_8, t0 + 3 * HOUR));
+    let idle = ticker.ticker(t0 + 3 * HOUR + 2 * TICKER_WINDOW_MS);
+    assert_eq!(idle.last, Some(80 * SCALE_8));
+    assert_eq!(idle.open, None);
+    assert_eq!(idle.trades, 0);
+    assert_eq!(idle.change_bps, 0);
+
+    // buckets out of the window are dropped as trades come in
+    ticker.apply_trade(&trade(70 * SCALE_8, t0 + 3 * HOUR + TICKER_WINDOW_MS));
+    assert_eq!(ticker.buckets.len(), 2);
+    assert_eq!(ticker.ticker(t0 + 3 * HOUR + TICKER_WINDOW_MS).change_bps, -1250);
+
+    let encoded = postcard::to_allocvec(&ticker).expect("serialize ticker");
+    let restored: RollingTicker = postcard::from_bytes(&encoded).expect("deserialize ticker");
+    assert_eq!(restored, ticker);
+}
+
+#[test]
+fn late_trades_open_their_bucket_in_order() {
+    let mut ticker = RollingTicker::new(TICKER_WINDOW_MS, TICKER_BUCKET_MS);
+    // at the start of a bucket
+    let t0 = 1_699_999_980_000;
+    ticker.apply_trade(&trade(100 * SCALE_8, t0));
+    ticker.apply_trade(&trade(110 * SCALE_8, t0 + 2 * HOUR));
+
+    // a late trade between the two buckets opens its own bucket without moving the last price
+    ticker.apply_trade(&trade(90 * SCALE_8, t0 + HOUR));
+    let starts: Vec<i64> = ticker.buckets.iter().map(|bucket| bucket.start - t0).collect();
+    assert_eq!(starts, vec![0, HOUR, 2 * HOUR]);
+    let stats = ticker.ticker(t0 + 2 * HOUR);
+    assert_eq!(stats.last, Some(110 * SCALE_8));
+    assert_eq!(stats.low, Some(90 * SCALE_8));
+    assert_eq!(stats.trades, 3);
+
+    // a late trade older than the window is dropped
+    ticker.apply_trade(&trade(50 * SCALE_8, t0 + 2 * HOUR - TICKER_WINDOW_MS - TICKER_BUCKET_MS));
+    assert_eq!(ticker.buckets.len(), 3);
+    assert_eq!(ticker.ticker(t0 + 2 * HOUR).low, Some(90 * SCALE_8));
+}
+
+#[test]
+fn engine_serves_ticker_from_matches() {
+    let _guard = lock_events();
+    let pair_id = vec![1];
+    let mut engine = MatchingEngine::new();
+    engine.add_pair(vec![1], vec![10], vec![11], pair_id.clone(), 1000);
+
+    engine
+        .limit_buy(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair_id.as_slice().into(),
+            existing_order_id: None,
+            owner: [20].into(),
+            coid: None,
+            price: 100 * SCALE_8,
+            amnt: 100 * SCALE_8,
+            iqty: 0,
+            timestamp: 1,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
+        .expect("limit buy");
+    engine
+        .limit_sell(LimitOrder {
+            cid: [1].into(),
+            pair_id: pair_id.as_slice().into(),
+            existing_order_id: None,
+            owner: [21].into(),
+            coid: None,
+            price: 100 * SCALE_8,
+            amnt: SCALE_8,
+            iqty: 0,
+            timestamp: 2,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
+        .expect("limit sell");
+    let _ = event::drain_events();
+
+    // the trade is stamped with the taker's timestamp
+    let now = 2;
+    let ticker = engine.ticker(&pair_id, now).expect("ticker");
+    assert_eq!(ticker.last, Some(100 * SCALE_8));
+    assert_eq!(ticker.open, Some(100 * SCALE_8));
+    assert_eq!(ticker.trades, 1);
+    assert_eq!(ticker.change_bps, 0);
+
+    assert_eq!(engine.ticker(&[9], now), Err(QueryError::PairNotFound));
+}
@@ -0,0 +1,168 @@
+use off_grid_primitives::spot::command::LimitOrder;
+use off_grid_primitives::spot::event::{self, SpotEvent};
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::trades::{CandleAggregator, CandleInterval, Trade};
+use off_grid_primitives::spot::Pair;
+use super::EVENT_MUTEX;
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+const SCALE_8: u64 = 1_0000_0000;
+
+fn setup_pair() -> Pair {
+    let mut pair = Pair::new();
+    pair.pair_id = vec![1].into();
+    pair.base_asset_id = vec![2].into();
+    pair.quote_asset_id = vec![3].into();
+    pair.add_client(vec![9], vec![10], vec![11], 1);
+    let _ = event::drain_events();
+    pair
+}
+
+fn trade(price: u64, base_volume: u64, timestamp: i64) -> Trade {
+    Trade {
+        price,
+        base_volume,
+        quote_volume: base_volume / SCALE_8 * price,
+        timestamp,
+        ..Trade::default()
+    }
+}
+
+#[test]
+fn limit_sell_emits_one_trade_per_match() {
+    let _guard = lock_events();
+    let mut pair = setup_pair();
+    for (owner, price) in [(21u8, 101 * SCALE_8), (22u8, 100 * SCALE_8), (24u8, 100 * SCALE_8)] {
+        // bid amounts are in quote terms
+        pair.limit_buy(LimitOrder {
+            cid: [9].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [owner].into(),
+            coid: None,
+            price,
+            amnt: price,
+            iqty: 0,
+            timestamp: 1,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
+        .expect("limit buy");
+    }
+    let _ = event::drain_events();
+
+    let mut events = Vec::new();
+    for price in [101 * SCALE_8, 100 * SCALE_8, 100 * SCALE_8] {
+        pair.limit_sell(LimitOrder {
+            cid: [9].into(),
+            pair_id: pair.pair_id,
+            existing_order_id: None,
+            owner: [23].into(),
+            coid: None,
+            price,
+            amnt: SCALE_8,
+            iqty: 0,
+            timestamp: 2,
+            expires_at: i64::MAX,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
+        .expect("limit sell");
+        events.extend(event::drain_events().into_vec());
+    }
+
+    let trades: Vec<(u64, bool, u64, u64)> = events
+        .iter()
+        .filter_map(|e| match e {
+            SpotEvent::SpotTrade { trade_id, taker_is_bid, price, base_volume, pair_id, .. } => {
+                assert_eq!(pair_id, &vec![1]);
+                Some((*trade_id, *taker_is_bid, *price, *base_volume))
+            }
+            _ => None,
+        })
+        .collect();
+    let fills = events
+        .iter()
+        .filter(|e| matches!(e, SpotEvent::SpotOrderPartiallyFilled { .. } | SpotEvent::SpotOrderFullyFilled { .. }))
+        .count();
+
+    // one trade per match, fills carry the taker and maker views
+    assert_eq!(trades.len(), 3);
+    assert_eq!(fills, 2 * trades.len());
+    assert_eq!(trades.iter().map(|t| t.0).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert!(trades.iter().all(|t| !t.1), "taker is selling");
+    assert_eq!(trades.iter().map(|t| t.2).collect::<Vec<_>>(), vec![101 * SCALE_8, 100 * SCALE_8, 100 * SCALE_8]);
+    assert_eq!(pair.orderbook.last_trade_id, 3);
+
+    let candle = pair.candles.current(CandleInterval::OneDay).expect("candle");
+    assert_eq!(candle.trades, 3);
+    assert_eq!(candle.open, 101 * SCALE_8);
+    assert_eq!(candle.close, 100 * SCALE_8);
+    assert_eq!(candle.high, 101 * SCALE_8);
+    assert_eq!(candle.low, 100 * SCALE_8);
+    assert_eq!(candle.base_volume, trades.iter().map(|t| t.3).sum::<u64>());
+    let minutes = pair.candles.candles(CandleInterval::OneMinute, 10);
+    assert_eq!(minutes.iter().map(|c| c.trades).sum::<u64>(), 3);
+
+    // trades and candles are stamped with the taker's timestamp
+    assert!(events.iter().all(|e| !matches!(e, SpotEvent::SpotTrade { timestamp, .. } if *timestamp != 2)));
+    assert_eq!(minutes.iter().map(|c| c.open_time).collect::<Vec<_>>(), vec![0]);
+}
+
+#[test]
+fn candles_roll_over_intervals_and_restore() {
+    let _guard = lock_events();
+    let mut candles = CandleAggregator::new(&[CandleInterval::OneMinute, CandleInterval::FiveMinutes], 2);
+
+    candles.apply_trade(&trade(100 * SCALE_8, SCALE_8, 60_000));
+    candles.apply_trade(&trade(105 * SCALE_8, 2 * SCALE_8, 60_500));
+    candles.apply_trade(&trade(98 * SCALE_8, SCALE_8, 119_999));
+    candles.apply_trade(&trade(99 * SCALE_8, SCALE_8, 120_000));
+    // gaps without trades produce no candle
+    candles.apply_trade(&trade(101 * SCALE_8, SCALE_8, 240_000));
+    // late trade for a kept candle
+    candles.apply_trade(&trade(97 * SCALE_8, SCALE_8, 125_000));
+
+    let minutes = candles.candles(CandleInterval::OneMinute, 10);
+    assert_eq!(
+        minutes.iter().map(|c| c.open_time).collect::<Vec<_>>(),
+        vec![60_000, 120_000, 240_000]
+    );
+    assert_eq!(minutes[0].open, 100 * SCALE_8);
+    assert_eq!(minutes[0].high, 105 * SCALE_8);
+    assert_eq!(minutes[0].low, 98 * SCALE_8);
+    assert_eq!(minutes[0].close, 98 * SCALE_8);
+    assert_eq!(minutes[0].base_volume, 4 * SCALE_8);
+    assert_eq!(minutes[0].quote_volume, 408 * SCALE_8);
+    assert_eq!(minutes[0].trades, 3);
+    assert_eq!(minutes[1].low, 97 * SCALE_8);
+    assert_eq!(minutes[1].trades, 2);
+
+    let five = candles.candles(CandleInterval::FiveMinutes, 10);
+    assert_eq!(five.len(), 1);
+    assert_eq!(five[0].open_time, 0);
+    assert_eq!(five[0].trades, 6);
+    assert_eq!(five[0].close, 97 * SCALE_8);
+
+    // only `history` closed candles are kept before the open one
+    candles.apply_trade(&trade(102 * SCALE_8, SCALE_8, 300_000));
+    let minutes = candles.candles(CandleInterval::OneMinute, 10);
+    assert_eq!(
+        minutes.iter().map(|c| c.open_time).collect::<Vec<_>>(),
+        vec![120_000, 240_000, 300_000]
+    );
+    assert_eq!(candles.candles(CandleInterval::OneMinute, 1)[0].open_time, 300_000);
+    assert!(candles.candles(CandleInterval::OneHour, 10).is_empty());
+
+    let encoded = postcard::to_allocvec(&candles).expect("serialize candles");
+    let restored: CandleAggregator = postcard::from_bytes(&encoded).expect("deserialize candles");
+    assert_eq!(restored, candles);
+}
@@ -0,0 +1,172 @@
+use off_grid_primitives::spot::command::LimitOrder;
+use off_grid_primitives::spot::event::{self, SpotEvent};
+use off_grid_primitives::spot::orderbook::OrderBook;
+use off_grid_primitives::spot::orders::OrderRef;
+use off_grid_primitives::spot::prices::DEFAULT_SCALES;
+use off_grid_primitives::spot::projection::{OrderBookProjection, ProjectionError};
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::MatchingEngine;
+
+use super::simulation::{apply, generate, new_engine, Rng, MAKER_FEE_BPS, PAIR_ID, TAKER_FEE_BPS};
+use super::EVENT_MUTEX;
+
+const SCALE_8: u64 = 1_0000_0000;
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[test]
+fn projection_follows_simulated_order_flows() {
+    let _guard = lock_events();
+    for seed in 0..8 {
+        let mut engine = new_engine();
+        let mut projection = OrderBookProjection::new(&PAIR_ID);
+        for (step, command) in generate(&mut Rng::new(seed), 150).iter().enumerate() {
+            let Ok(events) = apply(&mut engine, command, step) else { continue };
+            projection.apply_all(events.iter()).unwrap_or_else(|err| panic!("seed {seed} step {step}: {err}"));
+            let pair = engine.pair(&PAIR_ID).expect("pair");
+            projection.verify(pair).unwrap_or_else(|err| panic!("seed {seed} step {step}: {err}"));
+        }
+        assert_eq!(projection.orderbook.l3.orders().count(), engine.orderbook(&PAIR_ID).unwrap().l3.orders().count());
+    }
+}
+
+#[test]
+fn projection_keeps_client_order_ids_and_skips_other_pairs() {
+    let _guard = lock_events();
+    let _ = event::drain_events();
+    let mut engine = MatchingEngine::new();
+    let (btc, eth) = (b"BTC-USD".to_vec(), b"ETH-USD".to_vec());
+    engine.add_pair([1], [10], [11], btc.clone(), 0);
+    engine.add_pair([1], [10], [11], eth.clone(), 0);
+    let mut stream: Vec<SpotEvent> = event::drain_events().into_vec();
+
+    for (i, pair_id) in [&btc, &eth, &btc].into_iter().enumerate() {
+        let coid = Some(format!("bid-{i}").into_bytes().into());
+        let events = engine
+            .limit_buy(LimitOrder {
+                cid: [1].into(),
+                pair_id: pair_id.as_slice().into(),
+                existing_order_id: None,
+                owner: [20].into(),
+                coid,
+                price: (99 - i as u64) * SCALE_8,
+                amnt: 1000 * SCALE_8,
+                iqty: 400 * SCALE_8,
+                timestamp: 1,
+                expires_at: i64::MAX,
+                maker_fee_bps: MAKER_FEE_BPS,
+                taker_fee_bps: TAKER_FEE_BPS,
+                time_in_force: TimeInForce::GoodTillCanceled,
+                post_only: false,
+            })
+            .unwrap();
+        stream.extend(events.into_vec());
+    }
+    let events = engine
+        .limit_sell(LimitOrder {
+            cid: [1].into(),
+            pair_id: btc.as_slice().into(),
+            existing_order_id: None,
+            owner: [21].into(),
+            coid: None,
+            price: 99 * SCALE_8,
+            amnt: 3 * SCALE_8,
+            iqty: 0,
+            timestamp: 2,
+            expires_at: i64::MAX,
+            maker_fee_bps: MAKER_FEE_BPS,
+            taker_fee_bps: TAKER_FEE_BPS,
+            time_in_force: TimeInForce::GoodTillCanceled,
+            post_only: false,
+        })
+        .unwrap();
+    stream.extend(events.into_vec());
+    let events = engine.cancel_order([1], btc.clone(), OrderRef::ClientOrderId(b"bid-2".to_vec()), [20]).unwrap();
+    stream.extend(events.into_vec());
+
+    for pair_id in [&btc, &eth] {
+        let mut projection = OrderBookProjection::new(pair_id.clone());
+        projection.apply_all(&stream).unwrap();
+        projection.verify(engine.pair(pair_id.clone()).unwrap()).unwrap();
+    }
+    let mut projection = OrderBookProjection::new(btc.clone());
+    projection.apply_all(&stream).unwrap();
+    let order = projection.orderbook.l3.get_order_by_client_order_id([20], b"bid-0").expect("coid of the resting bid");
+    assert_eq!((order.cqty, order.fee_bps), (996_99999904, TAKER_FEE_BPS));
+    assert!(!projection.orderbook.l3.has_client_order_id([20], b"bid-2"), "the cancelled bid released its coid");
+    assert_eq!(projection.l1.lmp(), Some(99 * SCALE_8));
+}
+
+#[test]
+fn projection_follows_icebergs_and_expiries() {
+    let _guard = lock_events();
+    let _ = event::drain_events();
+    let pair_id = &PAIR_ID;
+    let mut orderbook = OrderBook::new();
+    orderbook.l2.set_scales(&DEFAULT_SCALES);
+    let now = 1_000;
+    let place = |orderbook: &mut OrderBook, is_bid: bool, price: u64, iqty: u64, expires_at: i64| {
+        orderbook
+            .place_order([1], pair_id, [2], [3], [20], None, is_bid, price * SCALE_8, 10 * SCALE_8, iqty, 1, expires_at, MAKER_FEE_BPS)
+            .unwrap()
+    };
+    let iceberg = place(&mut orderbook, false, 101, 6 * SCALE_8, i64::MAX);
+    place(&mut orderbook, false, 101, 0, now - 1);
+    place(&mut orderbook, false, 102, 0, now - 1);
+    place(&mut orderbook, true, 99, 0, i64::MAX);
+    orderbook.set_iceberg_quantity([1], pair_id, false, iceberg.id, 2 * SCALE_8).unwrap();
+    orderbook.set_iceberg_quantity([1], pair_id, false, iceberg.id, 8 * SCALE_8).unwrap();
+    orderbook.expire_orders(false, pair_id, [2], [3], [10], now).unwrap();
+
+    let mut projection = OrderBookProjection::new(pair_id);
+    projection.apply_all(event::drain_events().iter()).unwrap();
+    projection.verify_orderbook(&orderbook).unwrap();
+    assert_eq!(projection.orderbook.l2.public_ask_level(101 * SCALE_8), Some(2 * SCALE_8));
+    assert!(!projection.orderbook.l2.price_exists(false, 102 * SCALE_8));
+}
+
+#[test]
+fn verification_reports_the_first_difference() {
+    let _guard = lock_events();
+    let mut engine = new_engine();
+    let events: Vec<SpotEvent> = generate(&mut Rng::new(3), 60)
+        .iter()
+        .enumerate()
+        .filter_map(|(step, command)| apply(&mut engine, command, step).ok())
+        .flat_map(|events| events.into_vec())
+        .collect();
+    let pair = engine.pair(&PAIR_ID).unwrap();
+
+    // a replica missing the last change of a resting level is off by that level
+    let level_change = events
+        .iter()
+        .rposition(|event| matches!(event, SpotEvent::SpotPriceLevelChanged { is_bid, price, .. } if pair.orderbook.l2.price_exists(*is_bid, *price)))
+        .expect("a level change");
+    let mut projection = OrderBookProjection::new(&PAIR_ID);
+    projection.apply_all(events[..level_change].iter().chain(&events[level_change + 1..])).unwrap();
+    assert!(matches!(projection.verify(pair), Err(ProjectionError::LevelMismatch { .. })));
+
+    let mut projection = OrderBookProjection::new(&PAIR_ID);
+    projection.apply_all(&events).unwrap();
+    projection.verify(pair).unwrap();
+    projection.market_price = None;
+    assert!(matches!(projection.verify(pair), Err(ProjectionError::MarketPriceMismatch { book: Some(_), .. })));
+
+    // fills of an order the replica never saw placed cannot be applied
+    let maker_id = events
+        .iter()
+        .find_map(|event| match event {
+            SpotEvent::SpotOrderFullyFilled { is_taker_event: false, maker_order_id, .. } => Some(maker_order_id),
+            _ => None,
+        })
+        .expect("a filled maker");
+    let unseen: Vec<&SpotEvent> = events
+        .iter()
+        .filter(|event| !matches!(event, SpotEvent::SpotOrderPlaced { order_id, .. } if order_id == maker_id))
+        .collect();
+    let mut projection = OrderBookProjection::new(&PAIR_ID);
+    let err = projection.apply_all(unseen).unwrap_err();
+    assert!(matches!(err, ProjectionError::L3(_)), "{err}");
+}
@@ -0,0 +1,404 @@
+// Reference order book for differential testing of the matching engine.
+// Each side is a vector of orders kept sorted by price then time, and every command walks it from the
+// front. There are no price lists, levels or shared L3 lists to keep in sync, so the model is slow but
+// easy to check by reading. Its arithmetic is written out with u128 instead of `spot::fixed_point`.
+//
+// The model follows the engine's pricing rules: a limit match is sized by `OrderBook::get_required` and
+// converted at the taker's price, clearing the order it fills up, while market orders fill at each
+// maker's price. Behaviours of the engine that are not plain price-time matching are `Divergences` and
+// stay off unless turned on.
+
+use off_grid_primitives::spot::market::{L1, DEFAULT_MARKET_PROTECTION_BPS};
+use off_grid_primitives::spot::market_order::MarketOrderSize;
+use off_grid_primitives::spot::orderbook::OrderBookError;
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+
+use super::simulation::{Command, MAKER_FEE_BPS};
+
+const SCALE_8: u64 = 1_0000_0000;
+const BPS_DENOM: u64 = 10_000;
+/// Market protection band of a new pair, in basis points from the last match price or the best price
+const MARKET_PROTECTION_BPS: u64 = DEFAULT_MARKET_PROTECTION_BPS;
+
+/// Engine behaviours the model only applies when turned on.
+/// - `dust` clears an order whose remainder after a fill is at or below it, as the engine does with
+///   `OrderBook::dust` for makers and limit takers. Market takers keep their exact remainder to refund it.
+/// - `make_price` reports the make price of `L1` for every placed order as the market price, computed
+///   from the model's own heads and last match price. The engine publishes it in `SpotNewMarketPrice`
+///   but rests remainders at their limit price, so it does not move orders in the model either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Divergences {
+    pub dust: u64,
+    pub make_price: bool,
+}
+
+/// Order resting in the reference book, identified by its timestamp
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RefOrder {
+    pub timestamp: i64,
+    pub is_bid: bool,
+    pub owner: u8,
+    pub price: u64,
+    pub cqty: u64,
+    pub pqty: u64,
+}
+
+/// Fill of a taker against the maker placed at `maker`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Fill {
+    pub maker: i64,
+    pub price: u64,
+    pub base: u64,
+    pub quote: u64,
+}
+
+/// Outcome of a command.
+/// - `remaining` is the part of the taker's amount that did not fill, in its own terms. It rests on the
+///   book when `rests` is true and is released otherwise.
+/// - `market_price` is the make price, reported with `Divergences::make_price` only.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct Outcome {
+    pub fills: Vec<Fill>,
+    pub remaining: u64,
+    pub rests: bool,
+    pub market_price: Option<u64>,
+}
+
+/// Price-time order book, bids from the highest price and asks from the lowest, earliest first
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReferenceBook {
+    pub bids: Vec<RefOrder>,
+    pub asks: Vec<RefOrder>,
+    pub lmp: Option<u64>,
+    pub divergences: Divergences,
+}
+
+fn notional(price: u64, base: u64, ceil: bool) -> u64 {
+    let product = price as u128 * base as u128;
+    let quote = if ceil { product.div_ceil(SCALE_8 as u128) } else { product / SCALE_8 as u128 };
+    u64::try_from(quote).expect("notional fits u64")
+}
+
+fn quantity(price: u64, quote: u64, ceil: bool) -> u64 {
+    let product = quote as u128 * SCALE_8 as u128;
+    let base = if ceil { product.div_ceil(price as u128) } else { product / price as u128 };
+    u64::try_from(base).expect("quantity fits u64")
+}
+
+/// Decreases an order by a fill, returning whether it leaves the book
+fn decrease(order: &mut RefOrder, amount: u64, dust: u64, clear: bool) -> bool {
+    let left = order.cqty - amount.min(order.cqty);
+    if clear || left <= dust {
+        return true;
+    }
+    order.cqty = left;
+    order.pqty = order.pqty.min(left);
+    false
+}
+
+/// Fill of a limit taker against a maker as the engine matches it: base, quote, whether the taker and
+/// whether the maker are cleared. The taker's side of the fill is what clears the order that is filled
+/// up, priced at the taker's price when the maker is cleared and at the maker's price otherwise, and it
+/// converts to the other side at the taker's price.
+fn limit_fill(taker: &RefOrder, maker: &RefOrder) -> (u64, u64, bool, bool) {
+    if taker.is_bid {
+        let (quote, taker_clear, maker_clear) = if quantity(taker.price, taker.cqty, false) >= maker.cqty {
+            (quantity(taker.price, maker.cqty, true), false, true)
+        } else {
+            (notional(maker.price, taker.cqty, true), true, false)
+        };
+        (quantity(taker.price, quote, false), quote, taker_clear, maker_clear)
+    } else {
+        let (base, taker_clear, maker_clear) = if notional(taker.price, taker.cqty, false) >= maker.cqty {
+            (notional(taker.price, maker.cqty, true), false, true)
+        } else {
+            (quantity(maker.price, taker.cqty, true), true, false)
+        };
+        (base, notional(taker.price, base, false), taker_clear, maker_clear)
+    }
+}
+
+impl ReferenceBook {
+    pub(crate) fn new(divergences: Divergences) -> Self {
+        Self { divergences, ..Self::default() }
+    }
+
+    /// Resting orders in placement order
+    pub(crate) fn orders(&self) -> Vec<RefOrder> {
+        let mut orders: Vec<RefOrder> = self.bids.iter().chain(self.asks.iter()).cloned().collect();
+        orders.sort_by_key(|order| order.timestamp);
+        orders
+    }
+
+    fn side(&mut self, is_bid: bool) -> &mut Vec<RefOrder> {
+        if is_bid { &mut self.bids } else { &mut self.asks }
+    }
+
+    /// Inserts an order behind every order of its side at the same or a better price
+    fn rest(&mut self, order: RefOrder) {
+        let side = self.side(order.is_bid);
+        let at = side
+            .iter()
+            .position(|other| if order.is_bid { other.price < order.price } else { other.price > order.price })
+            .unwrap_or(side.len());
+        side.insert(at, order);
+    }
+
+    fn remove(&mut self, is_bid: bool, timestamp: i64) {
+        self.side(is_bid).retain(|order| order.timestamp != timestamp);
+    }
+
+    fn heads(&self) -> (u64, u64) {
+        (self.bids.first().map_or(0, |o| o.price), self.asks.first().map_or(0, |o| o.price))
+    }
+
+    /// Applies a command, `step` + 1 is the timestamp of its order
+    pub(crate) fn apply(&mut self, command: &Command, step: usize) -> Result<Outcome, OrderBookError> {
+        let timestamp = step as i64 + 1;
+        match command.clone() {
+            Command::Limit { is_bid, owner, price, amnt, iqty, time_in_force } => {
+                let taker = RefOrder { timestamp, is_bid, owner, price, cqty: amnt, pqty: amnt - iqty };
+                self.limit(taker, time_in_force)
+            }
+            Command::Market { is_bid, owner, size, time_in_force } => self.market(is_bid, owner, size, time_in_force, timestamp),
+            Command::Cancel { nth } => {
+                let orders = self.orders();
+                if !orders.is_empty() {
+                    let order = &orders[nth % orders.len()];
+                    self.remove(order.is_bid, order.timestamp);
+                }
+                Ok(Outcome::default())
+            }
+        }
+    }
+
+    /// Whether the opposite side up to `price` can fill `amount`, level by level.
+    /// The engine checks the quantity of each level as a whole, rounding up what it takes to clear it,
+    /// so a fill or kill order may pass and still leave a remainder that is then cancelled.
+    fn can_fill(&self, is_bid: bool, price: u64, amount: u64) -> bool {
+        let opposite = if is_bid { &self.asks } else { &self.bids };
+        let mut remaining = amount;
+        let mut i = 0;
+        while let Some(level) = opposite.get(i).map(|o| o.price) {
+            if if is_bid { level > price } else { level < price } {
+                break;
+            }
+            let orders = opposite[i..].iter().take_while(|o| o.price == level).count();
+            let sum = opposite[i..i + orders].iter().map(|o| o.cqty).sum();
+            let required = if is_bid { notional(level, sum, true) } else { quantity(level, sum, true) };
+            if remaining <= required {
+                return true;
+            }
+            remaining -= required;
+            i += orders;
+        }
+        false
+    }
+
+    fn limit(&mut self, mut taker: RefOrder, time_in_force: TimeInForce) -> Result<Outcome, OrderBookError> {
+        let (is_bid, price, amount) = (taker.is_bid, taker.price, taker.cqty);
+        if time_in_force == TimeInForce::FillOrKill && !self.can_fill(is_bid, price, amount) {
+            return Err(OrderBookError::OrderNotFullyFilled);
+        }
+        let dust = self.divergences.dust;
+        let mut outcome = Outcome::default();
+        let mut spent = 0;
+        let mut open = true;
+        while open {
+            let opposite = self.side(!is_bid);
+            let Some(maker) = opposite.first_mut() else { break };
+            if if is_bid { maker.price > price } else { maker.price < price } {
+                break;
+            }
+            let (base, quote, taker_clear, maker_clear) = limit_fill(&taker, maker);
+            let (taker_amount, maker_amount) = if is_bid { (quote, base) } else { (base, quote) };
+            let fill = Fill { maker: maker.timestamp, price: maker.price, base, quote };
+            if decrease(maker, maker_amount, dust, maker_clear) {
+                opposite.remove(0);
+            }
+            open = !decrease(&mut taker, taker_amount, dust, taker_clear);
+            spent += taker_amount;
+            self.lmp = Some(fill.price);
+            if base > 0 || quote > 0 {
+                outcome.fills.push(fill);
+            }
+        }
+
+        if open {
+            self.rest(taker.clone());
+        }
+        if self.divergences.make_price {
+            let (bid_head, ask_head) = self.heads();
+            let l1 = L1 { lmp: self.lmp, ..L1::default() };
+            outcome.market_price = Some(if is_bid {
+                l1.det_limit_buy_make_price(price, bid_head, ask_head, MAKER_FEE_BPS as u32)
+            } else {
+                l1.det_limit_sell_make_price(price, bid_head, ask_head, MAKER_FEE_BPS as u32)
+            });
+        }
+        if open && time_in_force != TimeInForce::GoodTillCanceled {
+            self.remove(is_bid, taker.timestamp);
+            open = false;
+        }
+        outcome.remaining = amount.saturating_sub(spent);
+        outcome.rests = open;
+        Ok(outcome)
+    }
+
+    fn market(
+        &mut self,
+        is_bid: bool,
+        owner: u8,
+        size: MarketOrderSize,
+        time_in_force: TimeInForce,
+        timestamp: i64,
+    ) -> Result<Outcome, OrderBookError> {
+        let opposite = if is_bid { &self.asks } else { &self.bids };
+        let Some(head) = opposite.first().map(|o| o.price) else {
+            return Err(if is_bid { OrderBookError::NoAskOrdersInOrderbook } else { OrderBookError::NoBidOrdersInOrderbook });
+        };
+        let reference = self.lmp.unwrap_or(head) as u128;
+        let protection = if is_bid {
+            (reference * (BPS_DENOM + MARKET_PROTECTION_BPS) as u128 / BPS_DENOM as u128) as u64
+        } else {
+            (reference * (BPS_DENOM - MARKET_PROTECTION_BPS) as u128).div_ceil(BPS_DENOM as u128) as u64
+        };
+
+        // plan the fills from the best price, maker by maker
+        let mut fills = Vec::new();
+        let mut clears = Vec::new();
+        let mut remaining = size.amount();
+        let (mut exhausted, mut protected) = (false, false);
+        let mut last_price = head;
+        let mut i = 0;
+        loop {
+            let Some(maker) = opposite.get(i) else {
+                exhausted = remaining > 0;
+                break;
+            };
+            if remaining == 0 {
+                break;
+            }
+            if if is_bid { maker.price > protection } else { maker.price < protection } {
+                protected = true;
+                break;
+            }
+            let price = maker.price;
+            last_price = price;
+            let (base, quote, clear) = if is_bid {
+                let base = match size {
+                    MarketOrderSize::Base(_) => remaining.min(maker.cqty),
+                    MarketOrderSize::Quote(_) if remaining >= notional(price, maker.cqty, true) => maker.cqty,
+                    MarketOrderSize::Quote(_) => quantity(price, remaining, false),
+                };
+                (base, notional(price, base, true), base == maker.cqty)
+            } else {
+                let capacity = quantity(price, maker.cqty, false);
+                if capacity == 0 {
+                    // a bid that cannot buy a single base unit is passed over
+                    i += 1;
+                    continue;
+                }
+                let base = match size {
+                    MarketOrderSize::Base(_) => remaining.min(capacity),
+                    MarketOrderSize::Quote(_) => quantity(price, remaining, false).min(capacity),
+                };
+                (base, notional(price, base, false), base == capacity)
+            };
+            if base == 0 || quote == 0 {
+                break;
+            }
+            remaining -= match size {
+                MarketOrderSize::Base(_) => base,
+                MarketOrderSize::Quote(_) => quote,
+            };
+            fills.push(Fill { maker: maker.timestamp, price, base, quote });
+            clears.push(clear);
+            if !clear {
+                break;
+            }
+            i += 1;
+        }
+        if time_in_force == TimeInForce::FillOrKill && remaining > 0 {
+            return Err(OrderBookError::OrderNotFullyFilled);
+        }
+
+        let base_filled: u64 = fills.iter().map(|f| f.base).sum();
+        let quote_filled: u64 = fills.iter().map(|f| f.quote).sum();
+        let amount = match (is_bid, size) {
+            (true, MarketOrderSize::Quote(amount)) | (false, MarketOrderSize::Base(amount)) => amount,
+            // the unfilled part is locked at the last price reached
+            (true, MarketOrderSize::Base(_)) if exhausted || protected => quote_filled + notional(last_price, remaining, true),
+            (true, MarketOrderSize::Base(_)) => quote_filled,
+            (false, MarketOrderSize::Quote(_)) if exhausted || protected => base_filled + quantity(last_price, remaining, true),
+            (false, MarketOrderSize::Quote(_)) => base_filled,
+        };
+
+        let dust = self.divergences.dust;
+        let spent = if is_bid { quote_filled } else { base_filled };
+        for (fill, clear) in fills.iter().zip(clears) {
+            let side = self.side(!is_bid);
+            let at = side.iter().position(|o| o.timestamp == fill.maker).expect("planned maker rests");
+            if decrease(&mut side[at], if is_bid { fill.base } else { fill.quote }, dust, clear) {
+                side.remove(at);
+            }
+        }
+        if let Some(fill) = fills.last() {
+            self.lmp = Some(fill.price);
+        }
+
+        // the rest rests at the last price reached, where it is locked, only when the book ran out of orders
+        let left = amount - spent;
+        let mut rests = exhausted && left > 0;
+        if rests {
+            self.rest(RefOrder { timestamp, is_bid, owner, price: last_price, cqty: left, pqty: left });
+        }
+        let market_price = self.divergences.make_price.then(|| {
+            let (bid_head, ask_head) = self.heads();
+            let l1 = L1 { lmp: self.lmp, ..L1::default() };
+            if is_bid {
+                l1.det_market_buy_make_price(bid_head, ask_head, MAKER_FEE_BPS as u32)
+            } else {
+                l1.det_market_sell_make_price(bid_head, ask_head, MAKER_FEE_BPS as u32)
+            }
+        });
+        if rests && time_in_force != TimeInForce::GoodTillCanceled {
+            self.remove(is_bid, timestamp);
+            rests = false;
+        }
+        Ok(Outcome { fills, remaining: left, rests, market_price })
+    }
+}
+
+#[test]
+fn fills_in_price_then_time_priority() {
+    let mut book = ReferenceBook::new(Divergences::default());
+    let limit = |is_bid, owner, price, amnt| Command::Limit { is_bid, owner, price, amnt, iqty: 0, time_in_force: TimeInForce::GoodTillCanceled };
+    book.apply(&limit(false, 1, 125 * SCALE_8, 2 * SCALE_8), 0).expect("ask");
+    book.apply(&limit(false, 2, 100 * SCALE_8, SCALE_8), 1).expect("ask");
+    book.apply(&limit(false, 3, 125 * SCALE_8, SCALE_8), 2).expect("ask");
+
+    let outcome = book.apply(&limit(true, 4, 125 * SCALE_8, 200 * SCALE_8), 3).expect("bid");
+    assert_eq!(
+        outcome.fills,
+        vec![
+            Fill { maker: 2, price: 100 * SCALE_8, base: 6400, quote: 800000 },
+            Fill { maker: 1, price: 125 * SCALE_8, base: 199_99200000, quote: 24999_00000000 },
+        ]
+    );
+    assert_eq!((outcome.remaining, outcome.rests), (0, false));
+    assert_eq!(book.asks.iter().map(|o| (o.timestamp, o.cqty)).collect::<Vec<_>>(), vec![(3, SCALE_8)]);
+    assert!(book.bids.is_empty());
+}
+
+#[test]
+fn dust_divergence_clears_small_remainders() {
+    let limit = |is_bid, price, amnt| Command::Limit { is_bid, owner: 1, price, amnt, iqty: 0, time_in_force: TimeInForce::GoodTillCanceled };
+    for (dust, rests) in [(0, true), (1000, false)] {
+        let mut book = ReferenceBook::new(Divergences { dust, make_price: false });
+        book.apply(&limit(false, 100 * SCALE_8, SCALE_8 + 1000), 0).expect("ask");
+        let outcome = book.apply(&limit(true, 100 * SCALE_8, SCALE_8), 1).expect("bid");
+        assert_eq!(outcome.fills, vec![Fill { maker: 1, price: 100 * SCALE_8, base: SCALE_8, quote: 100 * SCALE_8 }]);
+        assert_eq!(!book.asks.is_empty(), rests);
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::mem::{discriminant, Discriminant};
+
+use off_grid_primitives::spot::command::{LimitOrder, MarketOrder, SpotCommand};
+use off_grid_primitives::spot::event::{self, SpotEvent};
+use off_grid_primitives::spot::ids::PairId;
+use off_grid_primitives::spot::L2;
+use off_grid_primitives::spot::orderbook::{OrderBook, OrderBookError};
+use off_grid_primitives::spot::orders::OrderRef;
+use off_grid_primitives::spot::runtime::{shard_of, CommandOutput, ShardedEngine};
+use off_grid_primitives::spot::MatchingEngine;
+
+use super::simulation::{generate, open_orders, Command, Rng, MAKER_FEE_BPS, TAKER_FEE_BPS};
+use super::EVENT_MUTEX;
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+const PAIRS: u8 = 6;
+const STEPS: usize = 120;
+
+fn pair_id(pair: u8) -> PairId {
+    PairId::new(&[b'P', pair])
+}
+
+fn add_pair(pair: u8) -> SpotCommand {
+    SpotCommand::AddPair {
+        cid: [1].into(),
+        admin_account_id: [10].into(),
+        fee_account_id: [11].into(),
+        pair_id: pair_id(pair),
+        timestamp: 0,
+    }
+}
+
+/// Translates a simulated command of a pair, orders carry their step as client order id
+/// and a cancel refers to an earlier limit order of the pair by that id
+fn spot_command(pair: u8, command: &Command, step: usize, limits: &mut Vec<(u8, Vec<u8>)>) -> Option<SpotCommand> {
+    let timestamp = step as i64 + 1;
+    let coid = (step as u64).to_be_bytes().to_vec();
+    let command = match command.clone() {
+        Command::Limit { is_bid, owner, price, amnt, iqty, time_in_force } => {
+            limits.push((owner, coid.clone()));
+            let order = LimitOrder {
+                cid: [1].into(),
+                pair_id: pair_id(pair),
+                existing_order_id: None,
+                owner: [owner].into(),
+                coid: Some(coid.into()),
+                price,
+                amnt,
+                iqty,
+                timestamp,
+                expires_at: i64::MAX,
+                maker_fee_bps: MAKER_FEE_BPS,
+                taker_fee_bps: TAKER_FEE_BPS,
+                time_in_force,
+                post_only: false,
+            };
+            if is_bid { SpotCommand::LimitBuy(order) } else { SpotCommand::LimitSell(order) }
+        }
+        Command::Market { is_bid, owner, size, time_in_force } => {
+            let order = MarketOrder {
+                cid: [1].into(),
+                pair_id: pair_id(pair),
+                existing_order_id: None,
+                owner: [owner].into(),
+                coid: Some(coid.into()),
+                size,
+                public_amount: 0,
+                timestamp,
+                expires_at: i64::MAX,
+                maker_fee_bps: MAKER_FEE_BPS,
+                taker_fee_bps: TAKER_FEE_BPS,
+                time_in_force,
+            };
+            if is_bid { SpotCommand::MarketBuy(order) } else { SpotCommand::MarketSell(order) }
+        }
+        Command::Cancel { nth } => {
+            if limits.is_empty() {
+                return None;
+            }
+            let (owner, coid) = limits[nth % limits.len()].clone();
+            SpotCommand::CancelOrder { cid: [1].into(), pair_id: pair_id(pair), order: OrderRef::ClientOrderId(coid), owner: [owner].into() }
+        }
+    };
+    Some(command)
+}
+
+/// Order flow of every pair interleaved round robin, after the commands adding the pairs
+fn interleaved_flow(seed: u64) -> Vec<SpotCommand> {
+    let mut rng = Rng::new(seed);
+    let streams: Vec<Vec<Command>> = (0..PAIRS).map(|_| generate(&mut rng, STEPS)).collect();
+    let mut limits: Vec<Vec<(u8, Vec<u8>)>> = vec![Vec::new(); PAIRS as usize];
+    let mut commands: Vec<SpotCommand> = (0..PAIRS).map(add_pair).collect();
+    for step in 0..STEPS {
+        for pair in 0..PAIRS {
+            let command = &streams[pair as usize][step];
+            commands.extend(spot_command(pair, command, step, &mut limits[pair as usize]));
+        }
+    }
+    commands
+}
+
+/// Kind of an event and the quantities it reports, leaving out order ids and wall clock timestamps
+fn shape(event: &SpotEvent) -> (Discriminant<SpotEvent>, Vec<u64>) {
+    let quantities = match event {
+        SpotEvent::SpotTrade { price, base_volume, quote_volume, .. } => vec![*price, *base_volume, *quote_volume],
+        SpotEvent::SpotPriceLevelChanged { price, pqty, cqty, .. } => vec![*price, *pqty, *cqty],
+        SpotEvent::SpotOrderPartiallyFilled { pqty, cqty, .. } | SpotEvent::SpotOrderFullyFilled { pqty, cqty, .. } => vec![*pqty, *cqty],
+        SpotEvent::SpotOrderPlaced { price, amnt, pqty, cqty, .. } => vec![*price, *amnt, *pqty, *cqty],
+        SpotEvent::SpotOrderCancelled { price, cqty, .. } => vec![*price, *cqty],
+        SpotEvent::SpotNewMarketPrice { price, .. } => vec![*price],
+        _ => Vec::new(),
+    };
+    (discriminant(event), quantities)
+}
+
+type Outcome = Result<Vec<(Discriminant<SpotEvent>, Vec<u64>)>, String>;
+
+/// Resting book of a pair without order ids: the L2 state and the open orders in placement order
+fn book(orderbook: &OrderBook) -> (L2, Vec<String>) {
+    let orders = open_orders(orderbook)
+        .iter()
+        .map(|order| format!("{:?} {:?} {} {} {} {} {}", order.owner, order.coid, order.is_bid, order.price, order.amnt, order.pqty, order.cqty))
+        .collect();
+    (orderbook.l2.clone(), orders)
+}
+
+fn outcomes(outputs: &[CommandOutput]) -> Vec<Outcome> {
+    outputs
+        .iter()
+        .map(|output| match &output.result {
+            Ok(events) => Ok(events.iter().map(|event| shape(&event.event)).collect()),
+            Err(err) => Err(err.to_string()),
+        })
+        .collect()
+}
+
+fn run_sharded(mut runtime: ShardedEngine, commands: &[SpotCommand]) -> (Vec<CommandOutput>, Vec<MatchingEngine>) {
+    for command in commands {
+        runtime.submit(command.clone());
+    }
+    let outputs = runtime.flush();
+    (outputs, runtime.shutdown())
+}
+
+fn pair_book(engines: &[MatchingEngine], pair: u8) -> (L2, Vec<String>) {
+    let shard = shard_of(pair_id(pair), engines.len());
+    book(engines[shard].orderbook(pair_id(pair)).expect("pair on its shard"))
+}
+
+#[test]
+fn sharded_pairs_behave_like_a_single_engine() {
+    let _guard = lock_events();
+    for seed in 0..4 {
+        let commands = interleaved_flow(seed);
+
+        let _ = event::drain_events();
+        let mut engine = MatchingEngine::new();
+        let expected: Vec<Outcome> = commands
+            .iter()
+            .map(|command| match engine.execute(command.clone()) {
+                Ok(events) => Ok(events.iter().map(shape).collect()),
+                Err(err) => Err(err.to_string()),
+            })
+            .collect();
+
+        for runtime in [ShardedEngine::new(3), ShardedEngine::synchronous(3)] {
+            let (outputs, engines) = run_sharded(runtime, &commands);
+            assert_eq!(outputs.len(), commands.len());
+            assert_eq!(outcomes(&outputs), expected, "seed {seed}");
+            for pair in 0..PAIRS {
+                let single = book(engine.orderbook(pair_id(pair)).expect("pair"));
+                assert_eq!(pair_book(&engines, pair), single, "seed {seed} pair {pair}");
+            }
+        }
+    }
+}
+
+#[test]
+fn outputs_carry_deterministic_ordering_metadata() {
+    let commands = interleaved_flow(7);
+    let (outputs, engines) = run_sharded(ShardedEngine::new(4), &commands);
+    assert_eq!(engines.len(), 4);
+
+    let mut pair_seqs: HashMap<PairId, u64> = HashMap::new();
+    for (seq, (output, command)) in outputs.iter().zip(&commands).enumerate() {
+        assert_eq!(output.seq, seq as u64);
+        assert_eq!(output.pair_id, command.pair_id());
+        assert_eq!(output.shard, shard_of(command.pair_id(), 4));
+        for event in output.events() {
+            assert_eq!((event.seq, event.pair_id), (output.seq, output.pair_id));
+            let next = pair_seqs.entry(event.pair_id).or_insert(0);
+            assert_eq!(event.pair_seq, *next, "events of a pair are numbered without gaps");
+            *next += 1;
+        }
+    }
+
+    // the merged stream does not depend on the scheduling of the workers
+    let (again, _) = run_sharded(ShardedEngine::new(4), &commands);
+    assert_eq!(outcomes(&again), outcomes(&outputs));
+}
+
+#[test]
+fn runtime_leaves_the_caller_queue_alone() {
+    let _guard = lock_events();
+    let _ = event::drain_events();
+    let mut runtime = ShardedEngine::synchronous(2);
+    runtime.submit(add_pair(0));
+    let outputs = runtime.flush();
+    assert!(!outputs[0].events().is_empty());
+    assert!(event::drain_events().is_empty());
+}
+
+#[test]
+fn commands_of_unknown_pairs_are_rejected() {
+    let mut runtime = ShardedEngine::new(2);
+    runtime.submit(SpotCommand::SetDust { pair_id: pair_id(9), dust: 10 });
+    runtime.submit(add_pair(9));
+    runtime.submit(SpotCommand::SetDust { pair_id: pair_id(9), dust: 10 });
+    let outputs = runtime.flush();
+    assert_eq!(outputs[0].result, Err(OrderBookError::PairNotFound));
+    assert!(outputs[1].result.is_ok());
+    assert_eq!(outputs[2].result, Ok(Vec::new()));
+    let engines = runtime.shutdown();
+    assert_eq!(engines[shard_of(pair_id(9), 2)].orderbook(pair_id(9)).map(|book| book.dust), Some(10));
+}
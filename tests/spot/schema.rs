@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+
+use off_grid_primitives::spot::event::SpotEvent;
+use off_grid_primitives::spot::schema;
+use serde_json::Value;
+
+use super::simulation::{apply, generate, new_engine, Rng};
+use super::EVENT_MUTEX;
+
+// The checked-in schema files are rewritten with `UPDATE_GOLDEN=1 cargo test --features schema`.
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn assert_checked_in(file: &str, generated: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("schema").join(file);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, generated).unwrap();
+    }
+    let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(checked_in == generated, "schema/{file} drifted from the types, regenerate it with UPDATE_GOLDEN=1");
+}
+
+#[test]
+fn checked_in_schema_matches_the_types() {
+    assert_checked_in("spot.schema.json", &schema::json_schema_string());
+    assert_checked_in("spot.d.ts", &schema::typescript());
+}
+
+#[test]
+fn schema_exports_events_and_dtos() {
+    let schema = schema::json_schema();
+    let definitions = schema["$defs"].as_object().unwrap();
+    for name in schema["x-exported"].as_array().unwrap() {
+        assert!(definitions.contains_key(name.as_str().unwrap()), "{name} is not defined");
+    }
+    for name in ["SpotEvent", "Order", "Level", "L1", "L2Snapshot", "L2Delta", "PairId"] {
+        assert!(definitions.contains_key(name), "{name} is not defined");
+    }
+    let typescript = schema::typescript();
+    assert!(typescript.contains("export type SpotEvent =\n"));
+    assert!(typescript.contains("export type PairId = number[];"));
+}
+
+#[test]
+fn serialized_events_have_the_fields_of_the_schema() {
+    let _guard = lock_events();
+    let schema = schema::json_schema();
+    let variants: Vec<&Value> = schema["$defs"]["SpotEvent"]["oneOf"].as_array().unwrap().iter().collect();
+    let mut engine = new_engine();
+    let events: Vec<SpotEvent> = generate(&mut Rng::new(11), 120)
+        .iter()
+        .enumerate()
+        .filter_map(|(step, command)| apply(&mut engine, command, step).ok())
+        .flat_map(|events| events.into_vec())
+        .collect();
+    assert!(!events.is_empty());
+
+    for event in events {
+        let json = serde_json::to_value(&event).unwrap();
+        let (variant, fields) = json.as_object().unwrap().iter().next().unwrap();
+        let properties = variants
+            .iter()
+            .find_map(|schema| schema["properties"].get(variant))
+            .unwrap_or_else(|| panic!("{variant} is not in the schema"))["properties"]
+            .as_object()
+            .unwrap();
+        let mut names: Vec<&String> = fields.as_object().unwrap().keys().collect();
+        let mut expected: Vec<&String> = properties.keys().collect();
+        names.sort();
+        expected.sort();
+        assert_eq!(names, expected, "fields of {variant}");
+        for (name, value) in fields.as_object().unwrap() {
+            let property = &properties[name];
+            let ty = match value {
+                Value::Null => "null",
+                Value::Bool(_) => "boolean",
+                Value::Number(_) => "integer",
+                Value::String(_) => "string",
+                Value::Array(_) => "array",
+                Value::Object(_) => "object",
+            };
+            assert!(allows(property, ty), "{variant}.{name} is {ty}, the schema says {property}");
+        }
+    }
+}
+
+/// Whether a property schema allows a JSON type, optional ids being `anyOf` an id and null
+fn allows(property: &Value, ty: &str) -> bool {
+    match property.get("type") {
+        Some(Value::String(allowed)) => allowed == ty,
+        Some(Value::Array(allowed)) => allowed.iter().any(|allowed| allowed == ty),
+        // references to ids, which are byte arrays
+        _ if property.get("$ref").is_some() => ty == "array",
+        _ => property["anyOf"].as_array().is_some_and(|any| any.iter().any(|property| allows(property, ty))),
+    }
+}
@@ -0,0 +1,353 @@
+// Randomized order flow against `MatchingEngine`.
+// Commands are generated from a seeded generator so every run of `cargo test` replays the same
+// streams. After each command the book is checked for the invariants below, and a failing stream
+// is shrunk to a minimal list of commands before it is reported.
+
+use off_grid_primitives::spot::command::{LimitOrder, MarketOrder};
+use off_grid_primitives::spot::event::{self, SpotEvent};
+use off_grid_primitives::spot::invariants;
+use off_grid_primitives::spot::market_order::MarketOrderSize;
+use off_grid_primitives::spot::orderbook::{OrderBook, OrderBookError};
+use off_grid_primitives::spot::orders::Order;
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::MatchingEngine;
+
+use super::EVENT_MUTEX;
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+const SCALE_8: u64 = 1_0000_0000;
+pub(crate) const PAIR_ID: [u8; 1] = [1];
+pub(crate) const MAKER_FEE_BPS: i16 = 5;
+pub(crate) const TAKER_FEE_BPS: i16 = 10;
+
+/// splitmix64, small and deterministic across platforms
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// uniform in `low..=high`
+    pub(crate) fn range(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next_u64() % (high - low + 1)
+    }
+
+    /// true with probability `percent` / 100
+    pub(crate) fn chance(&mut self, percent: u64) -> bool {
+        self.next_u64() % 100 < percent
+    }
+}
+
+/// A command of the order flow.
+/// - amounts are in the order's own terms, quote for buys and base for sells.
+/// - `Cancel` picks the `nth` open order in placement order, modulo the number of open orders, so a stream
+///   stays valid when commands before it are removed while shrinking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Command {
+    Limit { is_bid: bool, owner: u8, price: u64, amnt: u64, iqty: u64, time_in_force: TimeInForce },
+    Market { is_bid: bool, owner: u8, size: MarketOrderSize, time_in_force: TimeInForce },
+    Cancel { nth: usize },
+}
+
+fn time_in_force(rng: &mut Rng) -> TimeInForce {
+    match rng.range(0, 9) {
+        0 => TimeInForce::FillOrKill,
+        1 | 2 => TimeInForce::ImmediateOrCancel,
+        _ => TimeInForce::GoodTillCanceled,
+    }
+}
+
+/// Generates a stream of `n` commands around a price of 100.
+/// Prices are on a 0.25 tick between 95 and 105 with a few off-tick prices,
+/// amounts are not round and a quarter of the limit orders are icebergs.
+pub(crate) fn generate(rng: &mut Rng, n: usize) -> Vec<Command> {
+    (0..n)
+        .map(|_| {
+            let is_bid = rng.chance(50);
+            let owner = rng.range(1, 6) as u8;
+            match rng.range(0, 9) {
+                0..=5 => {
+                    let mut price = rng.range(95 * 4, 105 * 4) * SCALE_8 / 4;
+                    if rng.chance(10) {
+                        price += rng.range(1, SCALE_8 / 4 - 1);
+                    }
+                    let amnt = if is_bid { rng.range(SCALE_8, 1000 * SCALE_8) } else { rng.range(SCALE_8 / 100, 10 * SCALE_8) };
+                    let iqty = if rng.chance(25) { rng.range(0, amnt - 1) } else { 0 };
+                    Command::Limit { is_bid, owner, price, amnt, iqty, time_in_force: time_in_force(rng) }
+                }
+                6..=7 => {
+                    let size = if rng.chance(50) {
+                        MarketOrderSize::Base(rng.range(SCALE_8 / 100, 5 * SCALE_8))
+                    } else {
+                        MarketOrderSize::Quote(rng.range(SCALE_8, 500 * SCALE_8))
+                    };
+                    Command::Market { is_bid, owner, size, time_in_force: time_in_force(rng) }
+                }
+                _ => Command::Cancel { nth: rng.range(0, 63) as usize },
+            }
+        })
+        .collect()
+}
+
+/// Open orders of the book in placement order, the step of a command is the timestamp of its order
+pub(crate) fn open_orders(orderbook: &OrderBook) -> Vec<Order> {
+    let mut orders: Vec<Order> = orderbook.l3.orders().cloned().collect();
+    orders.sort_by_key(|order| (order.timestamp, order.id));
+    orders
+}
+
+pub(crate) fn new_engine() -> MatchingEngine {
+    let _ = event::drain_events();
+    let mut engine = MatchingEngine::new();
+    engine.add_pair(vec![1], vec![10], vec![11], PAIR_ID.to_vec(), 0);
+    let _ = event::drain_events();
+    engine
+}
+
+/// Applies a command to the engine, `step` + 1 is used as the order timestamp.
+/// Returns the events of the command, or the error it was rejected with.
+pub(crate) fn apply(engine: &mut MatchingEngine, command: &Command, step: usize) -> Result<event::EventQueue, OrderBookError> {
+    let timestamp = step as i64 + 1;
+    let limit = |owner: u8, price, amnt, iqty, time_in_force| LimitOrder {
+        cid: [1].into(),
+        pair_id: PAIR_ID.into(),
+        existing_order_id: None,
+        owner: [owner].into(),
+        coid: None,
+        price,
+        amnt,
+        iqty,
+        timestamp,
+        expires_at: i64::MAX,
+        maker_fee_bps: MAKER_FEE_BPS,
+        taker_fee_bps: TAKER_FEE_BPS,
+        time_in_force,
+        post_only: false,
+    };
+    let market = |owner: u8, size, time_in_force| MarketOrder {
+        cid: [1].into(),
+        pair_id: PAIR_ID.into(),
+        existing_order_id: None,
+        owner: [owner].into(),
+        coid: None,
+        size,
+        public_amount: 0,
+        timestamp,
+        expires_at: i64::MAX,
+        maker_fee_bps: MAKER_FEE_BPS,
+        taker_fee_bps: TAKER_FEE_BPS,
+        time_in_force,
+    };
+    let result = match command.clone() {
+        Command::Limit { is_bid: true, owner, price, amnt, iqty, time_in_force } => {
+            engine.limit_buy(limit(owner, price, amnt, iqty, time_in_force))
+        }
+        Command::Limit { is_bid: false, owner, price, amnt, iqty, time_in_force } => {
+            engine.limit_sell(limit(owner, price, amnt, iqty, time_in_force))
+        }
+        Command::Market { is_bid: true, owner, size, time_in_force } => engine.market_buy(market(owner, size, time_in_force)),
+        Command::Market { is_bid: false, owner, size, time_in_force } => engine.market_sell(market(owner, size, time_in_force)),
+        Command::Cancel { nth } => {
+            let orders = open_orders(engine.orderbook(&PAIR_ID).expect("pair"));
+            if orders.is_empty() {
+                return Ok(event::EventQueue::new());
+            }
+            let order = &orders[nth % orders.len()];
+            engine.cancel_order(vec![1], PAIR_ID, order.id, order.owner.clone())
+        }
+    };
+    if result.is_err() {
+        // the engine only drains the events of successful commands
+        let _ = event::drain_events();
+    }
+    result
+}
+
+/// Failure of a stream at a step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Failure {
+    pub step: usize,
+    pub reason: String,
+}
+
+/// Errors a command may be rejected with without changing the book
+fn is_rejection(err: &OrderBookError) -> bool {
+    matches!(
+        err,
+        OrderBookError::NoAskOrdersInOrderbook | OrderBookError::NoBidOrdersInOrderbook | OrderBookError::OrderNotFullyFilled
+    )
+}
+
+/// Checks the book at rest after a command:
+/// - the book is not crossed, the best bid is below the best ask.
+/// - orders of a side are in time priority within a level.
+/// - the invariants of `spot::invariants` hold for the command.
+fn check_step(before: &OrderBook, after: &OrderBook, events: &[SpotEvent]) -> Result<(), String> {
+    invariants::check_command(before, after, events).map_err(|err| err.to_string())?;
+    if let (Some(bid), Some(ask)) = (after.l2.bid_head(), after.l2.ask_head()) {
+        if bid >= ask {
+            return Err(format!("crossed book at rest: bid {bid} ask {ask}"));
+        }
+    }
+    for price in after.l3.prices() {
+        for is_bid in [true, false] {
+            let mut timestamps = Vec::new();
+            let mut current = after.l3.head(price);
+            while let Some(id) = current {
+                let order = after.l3.get_order(id).expect("listed order");
+                if order.is_bid == is_bid {
+                    timestamps.push(order.timestamp);
+                }
+                current = after.l3.next(price, id);
+            }
+            if timestamps.windows(2).any(|pair| pair[0] > pair[1]) {
+                return Err(format!("level {price} isBid: {is_bid} is not in time priority: {timestamps:?}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs a stream of commands on a new engine, checking the book after every command
+pub(crate) fn run(commands: &[Command]) -> Result<(), Failure> {
+    let mut engine = new_engine();
+    for (step, command) in commands.iter().enumerate() {
+        let before = engine.orderbook(&PAIR_ID).expect("pair").clone();
+        let fail = |reason: String| Failure { step, reason };
+        let result = apply(&mut engine, command, step);
+        let after = engine.orderbook(&PAIR_ID).expect("pair");
+        match result {
+            Ok(events) => check_step(&before, after, &events).map_err(fail)?,
+            Err(err) if is_rejection(&err) => {
+                if open_orders(&before) != open_orders(after) || before.l2.bid_head() != after.l2.bid_head() || before.l2.ask_head() != after.l2.ask_head() {
+                    return Err(fail(format!("rejected with {err} after changing the book")));
+                }
+            }
+            Err(err) => return Err(fail(format!("failed with {err}"))),
+        }
+    }
+    Ok(())
+}
+
+/// Simpler variants of a command to try while shrinking
+fn simplify(command: &Command) -> Vec<Command> {
+    let mut simpler = Vec::new();
+    match command.clone() {
+        Command::Limit { is_bid, owner, price, amnt, iqty, time_in_force } => {
+            if iqty > 0 {
+                simpler.push(Command::Limit { is_bid, owner, price, amnt, iqty: 0, time_in_force });
+            }
+            if time_in_force != TimeInForce::GoodTillCanceled {
+                simpler.push(Command::Limit { is_bid, owner, price, amnt, iqty, time_in_force: TimeInForce::GoodTillCanceled });
+            }
+            if price % SCALE_8 != 0 {
+                simpler.push(Command::Limit { is_bid, owner, price: price - price % SCALE_8, amnt, iqty: 0, time_in_force });
+            }
+        }
+        Command::Market { is_bid, owner, size, time_in_force } => {
+            if time_in_force != TimeInForce::ImmediateOrCancel {
+                simpler.push(Command::Market { is_bid, owner, size, time_in_force: TimeInForce::ImmediateOrCancel });
+            }
+        }
+        Command::Cancel { nth } => {
+            if nth > 0 {
+                simpler.push(Command::Cancel { nth: 0 });
+            }
+        }
+    }
+    simpler
+}
+
+/// Shrinks a failing stream to a minimal one that still fails `fails`.
+/// Chunks of commands are removed from the largest down to single commands, then each command left
+/// is replaced by simpler variants, until nothing more can be removed or simplified.
+pub(crate) fn shrink(commands: &[Command], fails: impl Fn(&[Command]) -> bool) -> Vec<Command> {
+    let mut current = commands.to_vec();
+    loop {
+        let mut progressed = false;
+        let mut chunk = current.len().max(1);
+        while chunk > 0 {
+            let mut start = 0;
+            while start < current.len() {
+                let mut candidate = current.clone();
+                candidate.drain(start..(start + chunk).min(current.len()));
+                if fails(&candidate) {
+                    current = candidate;
+                    progressed = true;
+                } else {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+        for i in 0..current.len() {
+            for simpler in simplify(&current[i]) {
+                let mut candidate = current.clone();
+                candidate[i] = simpler;
+                if fails(&candidate) {
+                    current = candidate;
+                    progressed = true;
+                    break;
+                }
+            }
+        }
+        if !progressed {
+            return current;
+        }
+    }
+}
+
+/// Runs the streams of the seeds, shrinking and reporting the first failure
+pub(crate) fn check_seeds(seeds: std::ops::Range<u64>, len: usize) {
+    for seed in seeds {
+        let commands = generate(&mut Rng::new(seed), len);
+        if let Err(failure) = run(&commands) {
+            let minimal = shrink(&commands[..=failure.step], |candidate| run(candidate).is_err());
+            let reason = run(&minimal).expect_err("shrunk stream fails").reason;
+            panic!("seed {seed} failed at step {}: {}\nminimal stream ({reason}):\n{minimal:#?}", failure.step, failure.reason);
+        }
+    }
+}
+
+#[test]
+fn random_order_flow_keeps_the_book_consistent() {
+    let _guard = lock_events();
+    check_seeds(0..64, 200);
+}
+
+#[test]
+fn generation_is_deterministic() {
+    let _guard = lock_events();
+    assert_eq!(generate(&mut Rng::new(7), 50), generate(&mut Rng::new(7), 50));
+    assert_ne!(generate(&mut Rng::new(7), 50), generate(&mut Rng::new(8), 50));
+}
+
+#[test]
+fn shrinks_to_a_minimal_failing_stream() {
+    let _guard = lock_events();
+    let commands = generate(&mut Rng::new(3), 120);
+    // fails when an iceberg limit order is followed by a market order
+    let fails = |candidate: &[Command]| {
+        let iceberg = candidate.iter().position(|c| matches!(c, Command::Limit { iqty, .. } if *iqty > 0));
+        iceberg.is_some_and(|i| candidate[i..].iter().any(|c| matches!(c, Command::Market { .. })))
+    };
+    assert!(fails(&commands));
+
+    let minimal = shrink(&commands, fails);
+    assert_eq!(minimal.len(), 2);
+    assert!(matches!(minimal[0], Command::Limit { iqty, time_in_force: TimeInForce::GoodTillCanceled, .. } if iqty > 0));
+    assert!(matches!(minimal[1], Command::Market { time_in_force: TimeInForce::ImmediateOrCancel, .. }));
+}
@@ -0,0 +1,220 @@
+use std::path::PathBuf;
+
+use off_grid_primitives::spot::command::{LimitOrder, SpotCommand};
+use off_grid_primitives::spot::event::{EventQueue, SpotEvent};
+use off_grid_primitives::spot::market::DEFAULT_MARKET_PROTECTION_BPS;
+use off_grid_primitives::spot::orderbook::OrderBook;
+use off_grid_primitives::spot::orders::{L3, Order};
+use off_grid_primitives::spot::prices::{Level, DEFAULT_SCALES, L2};
+use off_grid_primitives::spot::snapshot::{self, SnapshotError, SNAPSHOT_MAGIC, SNAPSHOT_VERSION};
+use off_grid_primitives::spot::time_in_force::TimeInForce;
+use off_grid_primitives::spot::trades::CandleInterval;
+use off_grid_primitives::spot::MatchingEngine;
+
+use super::EVENT_MUTEX;
+
+// Fixtures hold snapshots in hex, one file per snapshot type and version. They were written by the release of
+// their version and are never rewritten: version 0 by 0.1.0, with plain postcard serialization.
+
+fn fixture_path(version: u16, name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("tests/spot/golden/snapshot/v{version}/{name}.hex"))
+}
+
+fn read_fixture(version: u16, name: &str) -> Vec<u8> {
+    let text = std::fs::read_to_string(fixture_path(version, name)).unwrap_or_else(|_| panic!("no fixture {name} of version {version}"));
+    let text = text.trim();
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+}
+
+#[test]
+fn l2_of_version_0_decodes() {
+    let l2: L2 = snapshot::decode(&read_fixture(0, "L2")).expect("decode L2 of 0.1.0");
+    assert_eq!(l2.collect_bid_prices(), vec![99_00000000, 98_00000000]);
+    assert_eq!(l2.collect_ask_prices(), vec![101_00000000, 102_50000000]);
+    assert_eq!(l2.public_bid_level(99_00000000), Some(198_00000000));
+    assert_eq!(l2.public_ask_level(102_50000000), Some(2_00000000));
+    assert_eq!(l2.current_ask_level(102_50000000), Some(3_00000000));
+    assert_eq!(
+        l2.bid_levels(1_00000000),
+        &vec![
+            Level { price: 99_00000000, pqty: 198_00000000, cqty: 198_00000000 },
+            Level { price: 98_00000000, pqty: 98_00000000, cqty: 98_00000000 },
+        ]
+    );
+    assert_eq!(l2.ask_levels(1_00000000).len(), 2);
+    // the scaled depth state did not exist in 0.1.0
+    assert!(l2.scales.is_empty());
+    assert!(l2.depth_seqs.is_empty());
+}
+
+/// Limit order of `client-1` on BTC-USD, `amnt` in quote for buys and base for sells
+fn limit(owner: &str, price: u64, amnt: u64, timestamp: i64, time_in_force: TimeInForce) -> LimitOrder {
+    LimitOrder {
+        cid: "client-1".into(),
+        pair_id: "BTC-USD".into(),
+        existing_order_id: None,
+        owner: owner.into(),
+        coid: None,
+        price,
+        amnt,
+        iqty: 0,
+        timestamp,
+        expires_at: i64::MAX,
+        maker_fee_bps: 10,
+        taker_fee_bps: 20,
+        time_in_force,
+        post_only: false,
+    }
+}
+
+/// Owners of the orders at a price level, in FIFO order
+fn level_owners(l3: &L3, price: u64) -> Vec<Vec<u8>> {
+    l3.get_orders(price, 10).iter().map(|order| order.owner.to_vec()).collect()
+}
+
+/// The book written by the 0.1.0 fixtures: asks of alice and bob at 101, an iceberg of carol at 102.5,
+/// bids of dave at 99 and erin at 98
+fn assert_fixture_book(l3: &L3) {
+    assert_eq!(level_owners(l3, 101_00000000), vec![b"alice".to_vec(), b"bob".to_vec()]);
+    assert_eq!(level_owners(l3, 102_50000000), vec![b"carol".to_vec()]);
+    assert_eq!(level_owners(l3, 99_00000000), vec![b"dave".to_vec()]);
+    assert_eq!(level_owners(l3, 98_00000000), vec![b"erin".to_vec()]);
+    let carol: &Order = &l3.get_orders(102_50000000, 1)[0];
+    assert_eq!((carol.cid.to_vec(), carol.amnt, carol.iqty, carol.pqty), (b"client-2".to_vec(), 3_00000000, 1_00000000, 2_00000000));
+    assert_eq!((carol.timestamp, carol.expires_at, carol.fee_bps), (2_002, i64::MAX, 20));
+    assert_eq!(carol.coid, None);
+}
+
+#[test]
+fn l3_of_version_0_decodes() {
+    let l3: L3 = snapshot::decode(&read_fixture(0, "L3")).expect("decode L3 of 0.1.0");
+    assert_fixture_book(&l3);
+    // the owner index is rebuilt from the orders
+    assert_eq!(l3.get_orders_by_owner(b"alice").len(), 1);
+    assert_eq!(l3.get_orders_by_owner(b"dave")[0].price, 99_00000000);
+}
+
+#[test]
+fn orderbook_of_version_0_decodes() {
+    let orderbook: OrderBook = snapshot::decode(&read_fixture(0, "OrderBook")).expect("decode OrderBook of 0.1.0");
+    assert_fixture_book(&orderbook.l3);
+    assert_eq!(orderbook.l2.collect_ask_prices(), vec![101_00000000, 102_50000000]);
+    assert_eq!(orderbook.l2.public_ask_level(101_00000000), Some(3_00000000));
+    assert_eq!(orderbook.dust, 0);
+    assert_eq!(orderbook.last_trade_id, 0);
+}
+
+#[test]
+fn matching_engine_of_version_0_decodes_and_resumes() {
+    let _guard = EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+    let mut engine: MatchingEngine = snapshot::decode(&read_fixture(0, "MatchingEngine")).expect("decode MatchingEngine of 0.1.0");
+    assert_eq!(engine.pair_count(), 2);
+    let pair = engine.pair("BTC-USD").expect("BTC-USD pair");
+    assert_eq!(pair.clients.len(), 2);
+    assert_eq!(pair.client_fee_account_ids.get(&"client-2".into()).map(|id| id.to_vec()), Some(b"fee-2".to_vec()));
+    assert_fixture_book(&pair.orderbook.l3);
+    assert_eq!(engine.orders_by_owner("ETH-USD", "alice")[0].price, 3_000_00000000);
+    // the 8 decimals slippage limits are kept, market orders are protected by the default band
+    assert_eq!(pair.l1.market_buy_slippage_limit, Some(10000));
+    assert_eq!(pair.l1.market_sell_protection_bps, Some(DEFAULT_MARKET_PROTECTION_BPS));
+    // pairs maintain the default scales from their price levels
+    assert_eq!(pair.orderbook.l2.scales, DEFAULT_SCALES.to_vec());
+    assert_eq!(
+        pair.orderbook.l2.bid_levels(10_00000000),
+        &vec![Level { price: 90_00000000, pqty: 296_00000000, cqty: 296_00000000 }]
+    );
+    assert_eq!(pair.orderbook.l2.ask_levels(1_00000000).iter().map(|level| level.price).collect::<Vec<_>>(), vec![101_00000000, 103_00000000]);
+
+    // the restored book matches: a buy at 101 fills 1 against alice, the oldest order of the level
+    let events = engine
+        .execute(SpotCommand::LimitBuy(limit("frank", 101_00000000, 1_00000000, 3_000, TimeInForce::ImmediateOrCancel)))
+        .expect("buy against the restored book");
+    let trades: Vec<(u64, u64, u64)> = events
+        .into_vec()
+        .into_iter()
+        .filter_map(|event| match event {
+            SpotEvent::SpotTrade { trade_id, price, base_volume, .. } => Some((trade_id, price, base_volume)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(trades, vec![(1, 101_00000000, 1_00000000)]);
+    assert_eq!(engine.orders_by_owner("BTC-USD", "alice")[0].cqty, 1_00000000);
+    assert_eq!(engine.orders_by_owner("BTC-USD", "bob")[0].cqty, 1_00000000);
+}
+
+#[test]
+fn depth_feeds_resume_from_a_snapshot() {
+    let _guard = EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+    let mut engine: MatchingEngine = snapshot::decode(&read_fixture(0, "MatchingEngine")).expect("decode MatchingEngine of 0.1.0");
+    // feeds did not exist in 0.1.0, the first snapshot of a restored pair opens them
+    let depth = engine.depth_snapshot("BTC-USD", 1_00000000).expect("open the depth feed");
+    assert_eq!(depth.seq, 0);
+    assert_eq!(depth.bids.len(), 2);
+
+    engine.execute(SpotCommand::LimitBuy(limit("frank", 100_00000000, 100_00000000, 3_000, TimeInForce::GoodTillCanceled))).expect("place bid");
+    // the changes not published yet and the sequence of the feed are part of the snapshot
+    let mut restored: MatchingEngine = snapshot::decode(&snapshot::encode(&engine).expect("encode engine")).expect("decode engine");
+    let deltas = engine.depth_deltas("BTC-USD").expect("deltas");
+    assert_eq!(deltas.iter().map(|delta| (delta.seq, delta.price)).collect::<Vec<_>>(), vec![(1, 100_00000000)]);
+    assert_eq!(restored.depth_deltas("BTC-USD").expect("restored deltas"), deltas);
+}
+
+/// Ids of the trades of the events of a command
+fn trade_ids(events: EventQueue) -> Vec<u64> {
+    events
+        .into_vec()
+        .into_iter()
+        .filter_map(|event| match event {
+            SpotEvent::SpotTrade { trade_id, .. } => Some(trade_id),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn trade_ids_and_candles_resume_from_a_snapshot() {
+    let _guard = EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+    let mut engine: MatchingEngine = snapshot::decode(&read_fixture(0, "MatchingEngine")).expect("decode MatchingEngine of 0.1.0");
+    assert!(engine.candles("BTC-USD", CandleInterval::OneMinute, 10).expect("candles").is_empty());
+    let buy = || SpotCommand::LimitBuy(limit("frank", 101_00000000, 1_00000000, 3_000, TimeInForce::ImmediateOrCancel));
+    assert_eq!(trade_ids(engine.execute(buy()).expect("first buy")), vec![1]);
+
+    let mut restored: MatchingEngine = snapshot::decode(&snapshot::encode(&engine).expect("encode engine")).expect("decode engine");
+    assert_eq!(restored.orderbook("BTC-USD").map(|orderbook| orderbook.last_trade_id), Some(1));
+    assert_eq!(trade_ids(restored.execute(buy()).expect("second buy")), vec![2]);
+    let candles = restored.candles("BTC-USD", CandleInterval::OneMinute, 10).expect("candles");
+    assert_eq!(candles.iter().map(|candle| candle.trades).sum::<u64>(), 2);
+    assert_eq!(candles.iter().map(|candle| candle.base_volume).sum::<u64>(), 2_00000000);
+}
+
+#[test]
+fn tickers_resume_from_a_snapshot() {
+    let _guard = EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+    let mut engine: MatchingEngine = snapshot::decode(&read_fixture(0, "MatchingEngine")).expect("decode MatchingEngine of 0.1.0");
+    assert_eq!(engine.ticker("BTC-USD", 3_000).expect("ticker").trades, 0);
+    let buy = || SpotCommand::LimitBuy(limit("frank", 101_00000000, 1_00000000, 3_000, TimeInForce::ImmediateOrCancel));
+    engine.execute(buy()).expect("first buy");
+
+    let mut restored: MatchingEngine = snapshot::decode(&snapshot::encode(&engine).expect("encode engine")).expect("decode engine");
+    restored.execute(buy()).expect("second buy");
+    let ticker = restored.ticker("BTC-USD", 3_000).expect("ticker");
+    assert_eq!((ticker.trades, ticker.base_volume, ticker.last), (2, 2_00000000, Some(101_00000000)));
+}
+
+#[test]
+fn snapshots_round_trip_in_the_current_version() {
+    let mut l2: L2 = snapshot::decode(&read_fixture(0, "L2")).expect("decode L2 of 0.1.0");
+    l2.set_scales(&[1_00000000, 10_00000000]);
+
+    let encoded = snapshot::encode(&l2).expect("encode L2");
+    assert_eq!(encoded[..2], SNAPSHOT_MAGIC);
+    assert_eq!(snapshot::decode::<L2>(&encoded), Ok(l2));
+}
+
+#[test]
+fn newer_snapshot_versions_are_rejected() {
+    let mut encoded = SNAPSHOT_MAGIC.to_vec();
+    encoded.extend(postcard::to_allocvec(&(SNAPSHOT_VERSION + 1)).unwrap());
+    encoded.extend(postcard::to_allocvec(&L2::new()).unwrap());
+    assert_eq!(snapshot::decode::<L2>(&encoded), Err(SnapshotError::UnsupportedVersion(SNAPSHOT_VERSION + 1)));
+}
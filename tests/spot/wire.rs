@@ -0,0 +1,361 @@
+use std::path::PathBuf;
+
+use off_grid_primitives::spot::event::SpotEvent;
+use off_grid_primitives::spot::event_backends::read_postcard;
+use off_grid_primitives::spot::wire::{self, EventEnvelope, EventType, WireError, ENVELOPE_MAGIC, SCHEMA_VERSION};
+
+use super::simulation::{apply, generate, new_engine, Rng};
+use super::EVENT_MUTEX;
+
+// Golden files hold the encoded bytes of `sample_events` in hex, one file per variant and schema version.
+// `UPDATE_GOLDEN=1 cargo test` rewrites the files of the current version, the files of older versions
+// are never rewritten: they were written by the release of that version.
+
+fn lock_events() -> std::sync::MutexGuard<'static, ()> {
+    EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn golden_path(version: u16, event_type: EventType) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("tests/spot/golden/v{version}/{event_type:?}.hex"))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(text: &str) -> Vec<u8> {
+    let text = text.trim();
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+}
+
+fn read_golden(version: u16, event_type: EventType) -> Option<Vec<u8>> {
+    std::fs::read_to_string(golden_path(version, event_type)).ok().map(|text| from_hex(&text))
+}
+
+/// One event of every variant, with every optional field set
+fn sample_events() -> Vec<SpotEvent> {
+    let order_id = |n: u8| vec![0x01, 0x8f, 0x3a, 0x5c, 0x7e, 0x00, 0x42, 0x11, 0x90, 0xab, 0xcd, 0xef, 0x10, 0x20, 0x30, n];
+    vec![
+        SpotEvent::SpotNewMarketPrice {
+            client_ids: vec![b"client-1".into(), b"client-2".into()],
+            pair_id: b"BTC-USD".into(),
+            base_asset_id: b"BTC".into(),
+            quote_asset_id: b"USD".into(),
+            price: 65_000_0000_0000,
+            is_make_price: true,
+            timestamp: 1_700_000_000_000,
+        },
+        SpotEvent::SpotPairRebate {
+            pair_id: b"BTC-USD".into(),
+            base_asset_id: b"BTC".into(),
+            quote_asset_id: b"USD".into(),
+            price: 65_000_0000_0000,
+            is_bid: true,
+            account_id: b"maker".into(),
+            is_maker: true,
+            fee_bps: -2,
+            base_volume: 5000_0000,
+            quote_volume: 32_500_0000_0000,
+            timestamp: 1_700_000_000_001,
+        },
+        SpotEvent::SpotPairClientAccountChanged {
+            pair_id: b"BTC-USD".into(),
+            cid: Some(b"client-1".into()),
+            admin_account_id: Some(b"admin".into()),
+            fee_account_id: Some(b"fees".into()),
+            timestamp: 1_700_000_000_002,
+        },
+        SpotEvent::SpotPairAdded { cid: b"client-1".into(), pair_id: b"BTC-USD".into(), timestamp: 1_700_000_000_003 },
+        SpotEvent::Transfer {
+            cid: b"client-1".into(),
+            from: b"maker".into(),
+            to: b"taker".into(),
+            asset: b"USD".into(),
+            amnt: 1_000_0000_0000,
+            timestamp: 1_700_000_000_004,
+        },
+        SpotEvent::SpotPriceLevelChanged {
+            pair_id: b"BTC-USD".into(),
+            is_bid: false,
+            price: 65_010_0000_0000,
+            pqty: 2_0000_0000,
+            cqty: 7_0000_0000,
+            timestamp: 1_700_000_000_005,
+        },
+        SpotEvent::SpotOrderPlaced {
+            cid: b"client-1".into(),
+            pair_id: b"BTC-USD".into(),
+            base_asset_id: b"BTC".into(),
+            quote_asset_id: b"USD".into(),
+            order_id: order_id(1),
+            maker_account_id: b"maker".into(),
+            coid: Some(b"bid-1".into()),
+            is_bid: true,
+            price: 64_990_0000_0000,
+            amnt: 3_0000_0000,
+            iqty: 1_0000_0000,
+            cqty: 3_0000_0000,
+            pqty: 1_0000_0000,
+            fee_bps: 5,
+            timestamp: 1_700_000_000_006,
+            expires_at: i64::MAX,
+        },
+        SpotEvent::SpotOrderPartiallyFilled {
+            is_taker_event: true,
+            taker_cid: b"client-2".into(),
+            maker_cid: b"client-1".into(),
+            taker_order_id: order_id(2),
+            maker_order_id: order_id(1),
+            taker_account_id: b"taker".into(),
+            maker_account_id: b"maker".into(),
+            taker_order_is_bid: false,
+            maker_order_is_bid: true,
+            price: 64_990_0000_0000,
+            pair_id: b"BTC-USD".into(),
+            base_asset_id: b"BTC".into(),
+            quote_asset_id: b"USD".into(),
+            base_volume: 5000_0000,
+            quote_volume: 32_495_0000_0000,
+            base_fee: 0,
+            quote_fee: 32_4950_0000,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            amnt: 2_0000_0000,
+            iqty: 0,
+            pqty: 1_5000_0000,
+            cqty: 1_5000_0000,
+            timestamp: 1_700_000_000_007,
+            expires_at: 1_700_000_060_000,
+        },
+        SpotEvent::SpotOrderFullyFilled {
+            is_taker_event: false,
+            taker_cid: b"client-2".into(),
+            maker_cid: b"client-1".into(),
+            taker_order_id: order_id(2),
+            maker_order_id: order_id(1),
+            maker_account_id: b"maker".into(),
+            taker_account_id: b"taker".into(),
+            taker_order_is_bid: false,
+            maker_order_is_bid: true,
+            price: 64_990_0000_0000,
+            pair_id: b"BTC-USD".into(),
+            base_asset_id: b"BTC".into(),
+            quote_asset_id: b"USD".into(),
+            base_volume: 3_0000_0000,
+            quote_volume: 194_970_0000_0000,
+            base_fee: 15_0000,
+            quote_fee: 0,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            amnt: 3_0000_0000,
+            iqty: 1_0000_0000,
+            pqty: 0,
+            cqty: 0,
+            timestamp: 1_700_000_000_008,
+            expires_at: i64::MAX,
+        },
+        SpotEvent::SpotTrade {
+            trade_id: 42,
+            pair_id: b"BTC-USD".into(),
+            base_asset_id: b"BTC".into(),
+            quote_asset_id: b"USD".into(),
+            taker_order_id: order_id(2),
+            maker_order_id: order_id(1),
+            taker_is_bid: false,
+            price: 64_990_0000_0000,
+            base_volume: 5000_0000,
+            quote_volume: 32_495_0000_0000,
+            timestamp: 1_700_000_000_009,
+        },
+        SpotEvent::SpotMarketOrderProtected {
+            cid: b"client-2".into(),
+            pair_id: b"BTC-USD".into(),
+            base_asset_id: b"BTC".into(),
+            quote_asset_id: b"USD".into(),
+            order_id: order_id(3),
+            maker_account_id: b"taker".into(),
+            is_bid: true,
+            protection_price: 66_950_0000_0000,
+            is_quote_size: true,
+            unfilled: 12_000_0000_0000,
+            timestamp: 1_700_000_000_010,
+        },
+        SpotEvent::SpotOrderCancelled {
+            cid: b"client-1".into(),
+            pair_id: Some(b"BTC-USD".into()),
+            order_id: order_id(4),
+            maker_account_id: b"maker".into(),
+            is_bid: false,
+            price: 65_010_0000_0000,
+            amnt: 2_0000_0000,
+            iqty: 0,
+            pqty: 2_0000_0000,
+            cqty: 2_0000_0000,
+            timestamp: 1_700_000_000_011,
+            expires_at: i64::MAX,
+        },
+        SpotEvent::SpotOrderExpired {
+            cid: b"client-1".into(),
+            pair_id: Some(b"BTC-USD".into()),
+            order_id: order_id(5),
+            maker_account_id: b"maker".into(),
+            is_bid: true,
+            price: 64_000_0000_0000,
+            amnt: 1_0000_0000,
+            iqty: 0,
+            pqty: 1_0000_0000,
+            cqty: 1_0000_0000,
+            timestamp: 1_700_000_000_012,
+            expires_at: 1_700_000_000_000,
+        },
+        SpotEvent::SpotOrderIcebergQuantityChanged {
+            cid: b"client-1".into(),
+            pair_id: Some(b"BTC-USD".into()),
+            order_id: order_id(1),
+            amnt: 3_0000_0000,
+            iqty: 5000_0000,
+            pqty: 5000_0000,
+            cqty: 3_0000_0000,
+            timestamp: 1_700_000_000_013,
+            expires_at: i64::MAX,
+        },
+    ]
+}
+
+/// The sample event as read from version 1, with the fields added in version 2 at their defaults
+fn as_version_1(mut event: SpotEvent) -> SpotEvent {
+    match &mut event {
+        SpotEvent::SpotNewMarketPrice { is_make_price, .. } => *is_make_price = false,
+        SpotEvent::SpotOrderPlaced { coid, fee_bps, .. } => (*coid, *fee_bps) = (None, 0),
+        SpotEvent::SpotOrderCancelled { pair_id, .. }
+        | SpotEvent::SpotOrderExpired { pair_id, .. }
+        | SpotEvent::SpotOrderIcebergQuantityChanged { pair_id, .. } => *pair_id = None,
+        _ => {}
+    }
+    event
+}
+
+#[test]
+fn samples_cover_every_event_type() {
+    let types: Vec<EventType> = sample_events().iter().map(EventType::of).collect();
+    let mut tags: Vec<u16> = types.iter().map(|event_type| event_type.tag()).collect();
+    tags.sort_unstable();
+    assert_eq!(tags, (1..=EventType::ALL.len() as u16).collect::<Vec<_>>());
+    for event_type in EventType::ALL {
+        assert_eq!(EventType::from_tag(event_type.tag()), Some(event_type));
+    }
+    assert_eq!(EventType::from_tag(0), None);
+}
+
+#[test]
+fn variants_are_appended_in_the_order_of_their_tags() {
+    // the plain serde encoding of an event starts with the index of its variant, which never moves
+    for event in sample_events() {
+        let event_type = EventType::of(&event);
+        assert_eq!(u16::from(postcard::to_allocvec(&event).unwrap()[0]), event_type.tag() - 1, "{event_type:?}");
+    }
+}
+
+#[test]
+fn encoding_matches_the_golden_files() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    for event in sample_events() {
+        let event_type = EventType::of(&event);
+        let bytes = wire::encode(&event).unwrap();
+        assert_eq!(bytes[0], ENVELOPE_MAGIC);
+        if update {
+            let path = golden_path(SCHEMA_VERSION, event_type);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, to_hex(&bytes) + "\n").unwrap();
+        }
+        let golden = read_golden(SCHEMA_VERSION, event_type).unwrap_or_else(|| panic!("no golden file of {event_type:?}"));
+        assert_eq!(to_hex(&bytes), to_hex(&golden), "encoding of {event_type:?} changed, add a schema version instead");
+        assert_eq!(wire::decode(&golden).unwrap(), event);
+    }
+}
+
+#[test]
+fn events_of_every_version_decode() {
+    for event in sample_events() {
+        let event_type = EventType::of(&event);
+        for version in 1..=SCHEMA_VERSION {
+            let Some(golden) = read_golden(version, event_type) else {
+                assert!(version < event_type.since(), "no golden file of {event_type:?} in version {version}");
+                continue;
+            };
+            let expected = if version == 1 { as_version_1(event.clone()) } else { event.clone() };
+            assert_eq!(wire::decode(&golden).unwrap(), expected, "{event_type:?} of version {version}");
+        }
+    }
+}
+
+#[test]
+fn version_1_layouts_are_a_prefix_of_the_current_ones() {
+    for event in sample_events() {
+        let event_type = EventType::of(&event);
+        let Some(legacy) = read_golden(1, event_type) else { continue };
+        let current = EventEnvelope::new(&as_version_1(event)).unwrap().payload;
+        assert!(current.starts_with(&legacy[1..]), "fields of {event_type:?} were not appended");
+    }
+}
+
+#[test]
+fn version_1_frames_decode_with_or_without_an_envelope() {
+    for event in sample_events() {
+        let event_type = EventType::of(&event);
+        let Some(legacy) = read_golden(1, event_type) else { continue };
+        // 0.1.0 wrote the variant index of its enum, in the order of the tags
+        assert_eq!(u16::from(legacy[0]) + 1, event_type.tag());
+        let envelope = EventEnvelope { version: 1, event_type: event_type.tag(), payload: legacy[1..].to_vec() };
+        assert_eq!(envelope.decode().unwrap(), as_version_1(event));
+    }
+    // variants added after version 1 cannot be in a version 1 event
+    let trade = EventEnvelope { version: 1, event_type: EventType::SpotTrade.tag(), payload: Vec::new() };
+    assert_eq!(trade.decode(), Err(WireError::UnknownEventType { tag: EventType::SpotTrade.tag(), version: 1 }));
+    assert!(matches!(wire::decode(&[12]), Err(WireError::UnknownEventType { tag: 13, version: 1 })));
+    assert_eq!(wire::decode(&[]), Err(WireError::Empty));
+}
+
+#[test]
+fn events_of_newer_versions_decode_what_is_known() {
+    for event in sample_events() {
+        let mut envelope = EventEnvelope::new(&event).unwrap();
+        envelope.version = SCHEMA_VERSION + 1;
+        // a field appended by the newer version
+        envelope.payload.extend([0x2a, 0x01]);
+        assert_eq!(envelope.decode().unwrap(), event);
+    }
+
+    let unknown = EventEnvelope { version: SCHEMA_VERSION + 1, event_type: 1000, payload: vec![1, 2, 3] };
+    let mut bytes = vec![ENVELOPE_MAGIC];
+    bytes.extend(postcard::to_allocvec(&unknown).unwrap());
+    assert_eq!(wire::decode(&bytes), Err(WireError::UnknownEventType { tag: 1000, version: SCHEMA_VERSION + 1 }));
+    let version_0 = EventEnvelope { version: 0, ..unknown };
+    assert_eq!(version_0.decode(), Err(WireError::UnsupportedVersion(0)));
+}
+
+#[test]
+fn postcard_files_skip_unknown_event_types() {
+    let _guard = lock_events();
+    let mut engine = new_engine();
+    let events: Vec<SpotEvent> = generate(&mut Rng::new(7), 40)
+        .iter()
+        .enumerate()
+        .filter_map(|(step, command)| apply(&mut engine, command, step).ok())
+        .flat_map(|events| events.into_vec())
+        .collect();
+    let unknown = EventEnvelope { version: SCHEMA_VERSION + 1, event_type: 1000, payload: vec![0, 1, 2] };
+
+    let mut file = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        file.extend(wire::encode_cobs(event).unwrap());
+        if i % 5 == 0 {
+            file.extend(postcard::to_allocvec_cobs(&(ENVELOPE_MAGIC, &unknown)).unwrap());
+        }
+    }
+    assert_eq!(read_postcard(file.as_slice()).unwrap(), events);
+
+    // frames without an envelope are not events of a postcard file
+    let mut frame = postcard::to_allocvec_cobs(&events[0]).unwrap();
+    assert!(matches!(wire::decode_cobs(&mut frame), Err(WireError::MissingEnvelope | WireError::Postcard(_))));
+}
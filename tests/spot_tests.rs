@@ -1,20 +1,67 @@
-// Spot tests share a global event queue (event::drain_events / emit_event).
-// All spot tests that use the event queue must use this single mutex so events
-// are not drained by another test running in parallel.
-// Run with `--test-threads=1` to avoid flaky failures from cross-test event pollution:
-//   cargo test -p off-grid-primitives --test spot_tests -- --test-threads=1
+// Spot tests collect events in the queue of their own thread (event::drain_events / emit_event),
+// but share the process-wide event bus (event::publish_events / register_backend).
+// Spot tests that use events take this single mutex so a backend registered by one test
+// does not see the events published by another test running in parallel.
+
+// Tests publishing to the bus hold a `PublishGuard`, which hands its single producer back when they end.
 
 use once_cell::sync::Lazy;
-use std::sync::Mutex;
+use std::sync::{Mutex, MutexGuard};
+
+use off_grid_primitives::spot::event;
 
 pub(crate) static EVENT_MUTEX: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
+pub(crate) struct PublishGuard(#[allow(dead_code)] MutexGuard<'static, ()>);
+
+impl PublishGuard {
+    pub(crate) fn lock() -> Self {
+        PublishGuard(EVENT_MUTEX.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl Drop for PublishGuard {
+    // runs before the mutex is unlocked, so the next test can take the producer
+    fn drop(&mut self) {
+        event::release_event_producer();
+    }
+}
+
 #[path = "spot/l1.rs"]
 mod l1;
 #[path = "spot/l2.rs"]
 mod l2;
 #[path = "spot/l3.rs"]
 mod l3;
+#[path = "spot/fixed_point.rs"]
+mod fixed_point;
+#[path = "spot/ids.rs"]
+mod ids;
+#[path = "spot/invariants.rs"]
+mod invariants;
+#[path = "spot/simulation.rs"]
+mod simulation;
+#[path = "spot/reference.rs"]
+mod reference;
+#[path = "spot/differential.rs"]
+mod differential;
+#[path = "spot/runtime.rs"]
+mod runtime;
+#[path = "spot/event_bus.rs"]
+mod event_bus;
+#[path = "spot/event_backends.rs"]
+mod event_backends;
+#[path = "spot/projection.rs"]
+mod projection;
+#[path = "spot/wire.rs"]
+mod wire;
+#[path = "spot/snapshot.rs"]
+mod snapshot;
+#[path = "spot/fix.rs"]
+mod fix;
+#[cfg(feature = "schema")]
+#[path = "spot/schema.rs"]
+mod schema;
 #[path = "spot/orderbook/mod.rs"]
 mod orderbook;
 #[path = "spot/pair/mod.rs"]
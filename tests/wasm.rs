@@ -0,0 +1,130 @@
+// Tests of the wasm-bindgen wrappers, run natively by `cargo test --features wasm` and in wasm with
+// `cargo test --target wasm32-unknown-unknown --features wasm --test wasm` (see README).
+#![cfg(feature = "wasm")]
+
+use off_grid_primitives::spot::event::{EventQueue, SpotEvent};
+use off_grid_primitives::spot::intern::BOUNDED_LEN;
+use off_grid_primitives::spot::{snapshot, AccountId, MatchingEngine};
+use off_grid_primitives::spot::wasm::{events_from_json, events_to_json, WasmEngine, WasmError};
+use off_grid_primitives::spot::orderbook::OrderBookError;
+use off_grid_primitives::spot::query::QueryError;
+use serde_json::{json, Value};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_test::wasm_bindgen_test as test;
+
+const PAIR: &str = "BTC-USD";
+const SCALE_8: u64 = 1_0000_0000;
+
+fn add_pair() -> String {
+    json!({ "AddPair": { "cid": "web", "admin_account_id": "admin", "fee_account_id": "fees", "pair_id": PAIR, "timestamp": 1 } })
+        .to_string()
+}
+
+fn limit(side: &str, owner: &str, coid: Option<&str>, price: u64, amnt: u64) -> String {
+    json!({ side: {
+        "cid": "web", "pair_id": PAIR, "existing_order_id": null, "owner": owner, "coid": coid.map(str::as_bytes),
+        "price": price * SCALE_8, "amnt": amnt * SCALE_8, "iqty": 0, "timestamp": 2, "expires_at": i64::MAX,
+        "maker_fee_bps": 5, "taker_fee_bps": 10, "time_in_force": "GoodTillCanceled"
+    } })
+    .to_string()
+}
+
+fn engine_with_book() -> WasmEngine {
+    let mut engine = WasmEngine::new();
+    engine.execute(&add_pair()).unwrap();
+    engine.execute(&limit("LimitBuy", "alice", Some("bid-1"), 99, 990)).unwrap();
+    engine.execute(&limit("LimitSell", "bob", None, 101, 5)).unwrap();
+    engine
+}
+
+fn event_names(events: &str) -> Vec<String> {
+    let events: Vec<Value> = serde_json::from_str(events).unwrap();
+    events.iter().map(|event| event.as_object().unwrap().keys().next().unwrap().clone()).collect()
+}
+
+#[test]
+fn commands_and_queries_use_json() {
+    let mut engine = engine_with_book();
+    assert!(engine.has_pair(PAIR));
+    let depth: Value = serde_json::from_str(&engine.depth(PAIR, 10).unwrap()).unwrap();
+    // bids rest their quote amount
+    assert_eq!(depth["bids"][0], json!({ "price": 99 * SCALE_8, "qty": 990 * SCALE_8 }));
+    assert_eq!(depth["asks"][0], json!({ "price": 101 * SCALE_8, "qty": 5 * SCALE_8 }));
+
+    let events = engine.execute(&limit("LimitSell", "carol", None, 99, 4)).unwrap();
+    assert!(event_names(&events).contains(&"SpotTrade".to_string()));
+    assert_eq!(engine.last_price(PAIR).unwrap(), Some(99 * SCALE_8));
+
+    let page: Value = serde_json::from_str(&engine.open_orders(PAIR, "alice", None, 10).unwrap()).unwrap();
+    let order_id = page["orders"][0]["id"].as_str().unwrap().to_string();
+    let order: Value = serde_json::from_str(&engine.order(PAIR, &order_id).unwrap()).unwrap();
+    assert_eq!(order["cqty"], json!(985_99999905u64));
+    let ticker: Value = serde_json::from_str(&engine.ticker(PAIR, 10).unwrap()).unwrap();
+    assert_eq!(ticker["trades"], json!(1));
+    let candles: Value = serde_json::from_str(&engine.candles(PAIR, "OneMinute", 10).unwrap()).unwrap();
+    assert_eq!(candles.as_array().unwrap().len(), 1);
+    let snapshot: Value = serde_json::from_str(&engine.depth_snapshot(PAIR, SCALE_8).unwrap()).unwrap();
+    assert_eq!(snapshot["asks"][0]["price"], json!(101 * SCALE_8));
+
+    let cancel = json!({ "CancelOrder": { "cid": "web", "pair_id": PAIR, "order": { "ClientOrderId": b"bid-1" }, "owner": "alice" } });
+    assert_eq!(event_names(&engine.execute(&cancel.to_string()).unwrap()), ["SpotOrderCancelled", "SpotPriceLevelChanged"]);
+}
+
+#[test]
+fn previews_leave_the_engine_untouched() {
+    let engine = engine_with_book();
+    let before = engine.snapshot().unwrap();
+    let events = engine.preview(&limit("LimitBuy", "carol", None, 101, 303)).unwrap();
+    assert!(event_names(&events).contains(&"SpotOrderFullyFilled".to_string()));
+    assert_eq!(engine.snapshot().unwrap(), before);
+    assert_eq!(engine.best_bid_ask(PAIR).unwrap(), engine_with_book().best_bid_ask(PAIR).unwrap());
+}
+
+#[test]
+fn events_convert_between_postcard_and_json() {
+    let mut engine = WasmEngine::new();
+    let command = postcard::to_allocvec(&serde_json::from_str::<off_grid_primitives::spot::command::SpotCommand>(&add_pair()).unwrap()).unwrap();
+    let events = engine.execute_postcard(&command).unwrap();
+    let queue: EventQueue = postcard::from_bytes(&events).unwrap();
+    assert!(queue.iter().any(|event| matches!(event, SpotEvent::SpotPairAdded { .. })));
+
+    let json = events_to_json(&events).unwrap();
+    assert!(event_names(&json).contains(&"SpotPairAdded".to_string()));
+    assert_eq!(events_from_json(&json).unwrap(), events);
+}
+
+#[test]
+fn snapshots_restore_the_engine() {
+    let engine = engine_with_book();
+    let restored = WasmEngine::restore(&engine.snapshot().unwrap()).unwrap();
+    assert_eq!(restored.depth(PAIR, 10).unwrap(), engine.depth(PAIR, 10).unwrap());
+    assert!(matches!(WasmEngine::restore(&[0xff]), Err(WasmError::Snapshot(_))));
+
+    // snapshots are untrusted input, ids past the interning bounds are rejected
+    let (long, unknown) = (vec![b'w'; BOUNDED_LEN + 1], vec![b'x'; BOUNDED_LEN + 1]);
+    let mut trusted = MatchingEngine::new();
+    trusted.add_pair("web", long.as_slice(), "fees", "wasm-long-pair", 1);
+    let mut bytes = snapshot::encode(&trusted).unwrap();
+    let at = bytes.windows(long.len()).position(|window| window == long).expect("long id in the snapshot");
+    bytes[at..at + long.len()].copy_from_slice(&unknown);
+    assert!(matches!(WasmEngine::restore(&bytes), Err(WasmError::Snapshot(_))));
+    assert_eq!(AccountId::lookup(&unknown), None);
+}
+
+#[test]
+fn errors_carry_the_engine_error() {
+    let mut engine = engine_with_book();
+    // a pair id never registered does not decode, it is not interned
+    let unknown = limit("LimitBuy", "alice", None, 99, 1).replace(PAIR, "wasm-unknown-pair");
+    assert!(matches!(engine.execute(&unknown), Err(WasmError::Json(err)) if err.to_string().contains("unknown id")));
+    assert!(!engine.has_pair("wasm-unknown-pair"));
+    assert!(matches!(engine.depth("wasm-unknown-pair", 1), Err(WasmError::Query(QueryError::PairNotFound))));
+    // a pair registered on another engine decodes but is not found
+    WasmEngine::new().execute(&add_pair().replace(PAIR, "ETH-USD")).unwrap();
+    let missing = limit("LimitBuy", "alice", None, 99, 1).replace(PAIR, "ETH-USD");
+    assert!(matches!(engine.execute(&missing), Err(WasmError::OrderBook(OrderBookError::PairNotFound))));
+    assert!(matches!(engine.execute("{"), Err(WasmError::Json(_))));
+    assert!(matches!(engine.order(PAIR, "not an id"), Err(WasmError::InvalidOrderId(_))));
+    assert!(matches!(engine.candles(PAIR, "OneWeek", 1), Err(WasmError::Json(_))));
+}